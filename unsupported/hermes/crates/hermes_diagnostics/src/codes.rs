@@ -0,0 +1,38 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Codes of the diagnostics reported by the Hermes crates. The names are stable, since
+//! configurations refer to them.
+
+use crate::DiagnosticCode;
+
+/// The parser rejected the input.
+pub const SYNTAX_ERROR: DiagnosticCode = DiagnosticCode("syntax-error");
+
+/// A node of the parser's AST doesn't have the kind the ESTree conversion expects.
+pub const UNEXPECTED_NODE_KIND: DiagnosticCode = DiagnosticCode("unexpected-node-kind");
+
+/// A reference to a variable which isn't declared or a known global.
+pub const UNDEFINED_VARIABLE: DiagnosticCode = DiagnosticCode("undefined-variable");
+
+/// A name declared twice in the same scope.
+pub const DUPLICATE_DECLARATION: DiagnosticCode = DiagnosticCode("duplicate-declaration");
+
+/// An `import` declaration which isn't at the top-level of a module.
+pub const IMPORT_NOT_AT_TOP_LEVEL: DiagnosticCode = DiagnosticCode("import-not-at-top-level");
+
+/// An assignment to something which can't be assigned.
+pub const INVALID_ASSIGNMENT_TARGET: DiagnosticCode = DiagnosticCode("invalid-assignment-target");
+
+/// A `break` without a target.
+pub const INVALID_BREAK: DiagnosticCode = DiagnosticCode("invalid-break");
+
+/// A `continue` without a target, or whose label isn't a loop.
+pub const INVALID_CONTINUE: DiagnosticCode = DiagnosticCode("invalid-continue");
+
+/// A JSX element with an empty name.
+pub const EMPTY_JSX_NAME: DiagnosticCode = DiagnosticCode("empty-jsx-name");
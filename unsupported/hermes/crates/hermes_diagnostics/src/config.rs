@@ -0,0 +1,62 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashMap;
+
+use crate::Diagnostic;
+use crate::DiagnosticSeverity;
+use crate::Diagnostics;
+
+/// Overrides of the severity of the diagnostics by code, e.g. to promote the warnings
+/// of a code to errors, or to suppress the diagnostics of a code.
+/// Diagnostics without a code are never overridden.
+#[derive(Clone, Debug, Default)]
+pub struct DiagnosticConfig {
+    /// The severity of every overridden code, `None` if the code is suppressed.
+    overrides: HashMap<String, Option<DiagnosticSeverity>>,
+}
+
+impl DiagnosticConfig {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Reports the diagnostics of `code` with `severity`.
+    pub fn set_severity(&mut self, code: &str, severity: DiagnosticSeverity) -> &mut Self {
+        self.overrides.insert(code.to_string(), Some(severity));
+        self
+    }
+
+    /// Doesn't report the diagnostics of `code`.
+    pub fn suppress(&mut self, code: &str) -> &mut Self {
+        self.overrides.insert(code.to_string(), None);
+        self
+    }
+
+    /// The severity to report `diagnostic` with, or `None` if it is suppressed.
+    pub fn severity(&self, diagnostic: &Diagnostic) -> Option<DiagnosticSeverity> {
+        match diagnostic
+            .code()
+            .and_then(|code| self.overrides.get(code.as_str()))
+        {
+            Some(severity) => *severity,
+            None => Some(diagnostic.severity()),
+        }
+    }
+
+    /// Applies the overrides to `diagnostics`, dropping the suppressed ones.
+    pub fn apply(&self, diagnostics: Diagnostics) -> Diagnostics {
+        diagnostics
+            .into_iter()
+            .filter_map(|mut diagnostic| {
+                let severity = self.severity(&diagnostic)?;
+                diagnostic.set_severity(severity);
+                Some(diagnostic)
+            })
+            .collect()
+    }
+}
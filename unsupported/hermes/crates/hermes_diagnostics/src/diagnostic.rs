@@ -9,6 +9,7 @@ use std::error::Error;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Write;
+use std::num::NonZeroU32;
 
 use hermes_estree::SourceRange;
 use miette::SourceSpan;
@@ -26,19 +27,17 @@ pub struct WithDiagnostics<T> {
 
 impl<T> From<WithDiagnostics<T>> for Result<T, Diagnostics> {
     fn from(s: WithDiagnostics<T>) -> Result<T, Diagnostics> {
-        if s.diagnostics.is_empty() {
-            Ok(s.item)
-        } else {
-            Err(s.diagnostics)
-        }
+        diagnostics_result(s.item, s.diagnostics)
     }
 }
 
+/// Returns `result`, or all the `diagnostics` if any of them is an error.
+/// Diagnostics which aren't errors, such as warnings, don't fail the result.
 pub fn diagnostics_result<T>(result: T, diagnostics: Diagnostics) -> DiagnosticsResult<T> {
-    if diagnostics.is_empty() {
-        Ok(result)
-    } else {
+    if diagnostics.iter().any(|d| d.severity().is_error()) {
         Err(diagnostics)
+    } else {
+        Ok(result)
     }
 }
 
@@ -59,6 +58,70 @@ pub enum DiagnosticSeverity {
     /// Internal compiler error (ICE)
     #[error("Internal error")]
     Invariant,
+
+    /// A hint about a possible improvement, e.g. a more idiomatic construct
+    #[error("Hint")]
+    Hint,
+
+    /// Information which doesn't indicate a problem
+    #[error("Info")]
+    Info,
+
+    /// A problem which doesn't prevent compilation
+    #[error("Warning")]
+    Warning,
+}
+
+impl DiagnosticSeverity {
+    /// Whether diagnostics of this severity prevent compilation.
+    pub fn is_error(self) -> bool {
+        !matches!(
+            self,
+            DiagnosticSeverity::Hint | DiagnosticSeverity::Info | DiagnosticSeverity::Warning
+        )
+    }
+
+    /// The severity reported to LSP clients. Every category of error is an LSP error.
+    pub fn lsp_severity(self) -> LspSeverity {
+        match self {
+            DiagnosticSeverity::Hint => LspSeverity::Hint,
+            DiagnosticSeverity::Info => LspSeverity::Information,
+            DiagnosticSeverity::Warning => LspSeverity::Warning,
+            DiagnosticSeverity::Todo
+            | DiagnosticSeverity::Unsupported
+            | DiagnosticSeverity::InvalidSyntax
+            | DiagnosticSeverity::Invariant => LspSeverity::Error,
+        }
+    }
+}
+
+/// The `DiagnosticSeverity` of the LSP specification, with its numeric values:
+/// https://microsoft.github.io/language-server-protocol/specification#diagnosticSeverity
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[repr(u8)]
+pub enum LspSeverity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+    Hint = 4,
+}
+
+/// A stable identifier of a kind of diagnostic message, e.g. `undefined-variable`,
+/// which configurations use to promote or suppress the diagnostics of that kind.
+/// The codes of this crate's users are in [`crate::codes`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct DiagnosticCode(pub &'static str);
+
+impl DiagnosticCode {
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+impl Display for DiagnosticCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
 }
 
 /// A diagnostic message as a result of validating some code. This struct is
@@ -71,7 +134,8 @@ pub enum DiagnosticSeverity {
 /// - Unused fields are omitted.
 /// - Severity is a custom enum that represents various categories of error.
 ///   The translation to an LSP error/warning/etc depends on compiler settings and
-///   invocation context.
+///   invocation context: a `DiagnosticConfig` can change the severity of a code, and
+///   `DiagnosticSeverity::lsp_severity` translates the result.
 #[derive(Debug)]
 pub struct Diagnostic(Box<DiagnosticData>);
 
@@ -86,10 +150,30 @@ impl Diagnostic {
             span: source_span_from_range(range),
             related_information: Vec::new(),
             severity,
+            code: None,
+            fixes: Vec::new(),
             data: Vec::new(),
         }))
     }
 
+    /// Creates a new Hint Diagnostic.
+    /// Additional locations can be added with the `.annotate()` function.
+    pub fn hint<T: 'static + DiagnosticDisplay>(message: T, range: SourceRange) -> Self {
+        Diagnostic::with_severity(DiagnosticSeverity::Hint, message, range)
+    }
+
+    /// Creates a new Info Diagnostic.
+    /// Additional locations can be added with the `.annotate()` function.
+    pub fn info<T: 'static + DiagnosticDisplay>(message: T, range: SourceRange) -> Self {
+        Diagnostic::with_severity(DiagnosticSeverity::Info, message, range)
+    }
+
+    /// Creates a new Warning Diagnostic.
+    /// Additional locations can be added with the `.annotate()` function.
+    pub fn warning<T: 'static + DiagnosticDisplay>(message: T, range: SourceRange) -> Self {
+        Diagnostic::with_severity(DiagnosticSeverity::Warning, message, range)
+    }
+
     /// Creates a new Todo Diagnostic.
     /// Additional locations can be added with the `.annotate()` function.
     pub fn todo<T: 'static + DiagnosticDisplay>(message: T, range: SourceRange) -> Self {
@@ -129,6 +213,26 @@ impl Diagnostic {
         self
     }
 
+    /// Sets the code identifying the kind of this diagnostic.
+    pub fn with_code(mut self, code: DiagnosticCode) -> Self {
+        self.0.code = Some(code);
+        self
+    }
+
+    /// Adds a fix which corrects this diagnostic, made of `edits` of its source.
+    pub fn with_fix<S: Into<String>>(mut self, message: S, edits: Vec<TextEdit>) -> Self {
+        self.0.fixes.push(DiagnosticFix {
+            message: message.into(),
+            edits,
+        });
+        self
+    }
+
+    /// Changes the severity of this diagnostic, e.g. to apply a configuration.
+    pub fn set_severity(&mut self, severity: DiagnosticSeverity) {
+        self.0.severity = severity;
+    }
+
     pub fn message(&self) -> &impl DiagnosticDisplay {
         &self.0.message
     }
@@ -141,6 +245,11 @@ impl Diagnostic {
         self.0.span
     }
 
+    /// The range of the span, which is at least one byte long.
+    pub fn range(&self) -> SourceRange {
+        range_from_span(self.0.span)
+    }
+
     pub fn get_data(&self) -> &[impl DiagnosticDisplay] {
         &self.0.data
     }
@@ -149,10 +258,18 @@ impl Diagnostic {
         self.0.severity
     }
 
+    pub fn code(&self) -> Option<DiagnosticCode> {
+        self.0.code
+    }
+
     pub fn related_information(&self) -> &[DiagnosticRelatedInformation] {
         &self.0.related_information
     }
 
+    pub fn fixes(&self) -> &[DiagnosticFix] {
+        &self.0.fixes
+    }
+
     pub fn print_without_source(&self) -> String {
         let mut result = String::new();
         writeln!(
@@ -187,6 +304,20 @@ impl Display for Diagnostic {
 impl Error for Diagnostic {}
 
 impl miette::Diagnostic for Diagnostic {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.0
+            .code
+            .map(|code| Box::new(code) as Box<dyn Display + 'a>)
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(match self.0.severity {
+            DiagnosticSeverity::Hint | DiagnosticSeverity::Info => miette::Severity::Advice,
+            DiagnosticSeverity::Warning => miette::Severity::Warning,
+            _ => miette::Severity::Error,
+        })
+    }
+
     fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
         Some(Box::new(self.0.message.to_string()))
     }
@@ -227,6 +358,12 @@ struct DiagnosticData {
 
     severity: DiagnosticSeverity,
 
+    /// The kind of the diagnostic, if it has one.
+    code: Option<DiagnosticCode>,
+
+    /// Machine-applicable fixes of the problem, any of which can be applied.
+    fixes: Vec<DiagnosticFix>,
+
     /// A list with data that can be passed to the code actions
     /// `data` is used in the LSP protocol:
     /// @see https://microsoft.github.io/language-server-protocol/specifications/specification-current/#diagnostic
@@ -243,6 +380,73 @@ pub struct DiagnosticRelatedInformation {
     pub span: SourceSpan,
 }
 
+/// A fix of a diagnostic, made of edits of its source which must all be applied together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiagnosticFix {
+    /// Description of the fix, e.g. "Remove the unused label".
+    pub message: String,
+
+    pub edits: Vec<TextEdit>,
+}
+
+/// An edit replacing the text of `range` with `text`.
+pub fn replace_range<S: Into<String>>(range: SourceRange, text: S) -> TextEdit {
+    TextEdit {
+        start: range.start as usize,
+        end: range.end.get() as usize,
+        text: text.into(),
+    }
+}
+
+/// Replacement of the bytes `start..end` of a source with `text`.
+/// An edit with `start == end` inserts `text`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Applies the fixes of `diagnostics` to `source`, the source they were reported about,
+/// skipping the fixes which conflict with a previous one.
+/// A fix is skipped if any of its edits is outside `source`, splits a character, or
+/// overlaps an edit of the same fix or of a fix which was applied.
+/// Returns the new source and the number of applied fixes.
+pub fn apply_fixes(source: &str, diagnostics: &[Diagnostic]) -> (String, usize) {
+    // Two edits conflict if they overlap, or insert at the same position.
+    let conflict = |a: &TextEdit, b: &TextEdit| {
+        (a.start < b.end && b.start < a.end) || (a.start == b.start && a.end == b.end)
+    };
+    let mut applied: Vec<&TextEdit> = Vec::new();
+    let mut count = 0;
+    for fix in diagnostics.iter().flat_map(|diagnostic| diagnostic.fixes()) {
+        let edits = &fix.edits;
+        let valid = edits.iter().enumerate().all(|(i, edit)| {
+            edit.start <= edit.end
+                && source.is_char_boundary(edit.start)
+                && source.is_char_boundary(edit.end)
+                && !edits[..i].iter().any(|other| conflict(edit, other))
+                && !applied.iter().any(|other| conflict(edit, other))
+        });
+        if valid {
+            applied.extend(edits);
+            count += 1;
+        }
+    }
+
+    // Insertions come before the replacements which start at the same position.
+    applied.sort_by_key(|edit| (edit.start, edit.end));
+    let mut result = String::with_capacity(source.len());
+    let mut pos = 0;
+    for edit in applied {
+        result.push_str(&source[pos..edit.start]);
+        result.push_str(&edit.text);
+        pos = edit.end;
+    }
+    result.push_str(&source[pos..]);
+    (result, count)
+}
+
 /// Trait for diagnostic messages to allow structs that capture
 /// some data and can lazily convert it to a message.
 #[typetag::serialize(tag = "type")]
@@ -265,3 +469,11 @@ fn source_span_from_range(range: SourceRange) -> SourceSpan {
         ((u32::from(range.end) - range.start) as usize).into(),
     )
 }
+
+pub(crate) fn range_from_span(span: SourceSpan) -> SourceRange {
+    let start = span.offset() as u32;
+    SourceRange {
+        start,
+        end: NonZeroU32::new(start + (span.len() as u32).max(1)).unwrap(),
+    }
+}
@@ -0,0 +1,172 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Conversions between [`Diagnostic`]s, which have byte ranges, and the diagnostics of
+//! the juno [`SourceManager`], which have line and column ranges, so that tools using
+//! both can report them the same way.
+//!
+//! A [`Diagnostic`] doesn't record its file, so the file is passed separately, and
+//! related information is always in the file of the diagnostic. The severity and code
+//! of a [`Diagnostic`] are kept by the juno diagnostic, so they survive a round-trip.
+//!
+//! This module needs the `juno` feature, since `juno_support` builds the Hermes
+//! compiler with cmake.
+
+use std::collections::BTreeSet;
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+
+use hermes_estree::SourceRange;
+use juno_support::diagnostics::DiagKind;
+use juno_support::diagnostics::Diagnostic as JunoDiagnostic;
+use juno_support::diagnostics::Fix;
+use juno_support::diagnostics::TextEdit as JunoEdit;
+use juno_support::source_manager::SourceId;
+use juno_support::source_manager::SourceManager;
+use juno_support::source_manager::SourceRange as JunoRange;
+
+use crate::diagnostic::range_from_span;
+use crate::Diagnostic;
+use crate::DiagnosticCode;
+use crate::DiagnosticSeverity;
+use crate::Diagnostics;
+use crate::TextEdit;
+
+const SEVERITIES: [DiagnosticSeverity; 7] = [
+    DiagnosticSeverity::Todo,
+    DiagnosticSeverity::Unsupported,
+    DiagnosticSeverity::InvalidSyntax,
+    DiagnosticSeverity::Invariant,
+    DiagnosticSeverity::Hint,
+    DiagnosticSeverity::Info,
+    DiagnosticSeverity::Warning,
+];
+
+/// The code named `name`. Codes are `'static`, so the name of a code which wasn't
+/// converted before is leaked, once.
+fn code(name: &str) -> DiagnosticCode {
+    static CODES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+    let mut codes = CODES.lock().unwrap();
+    if let Some(name) = codes.get(name) {
+        return DiagnosticCode(name);
+    }
+    let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+    codes.insert(name);
+    DiagnosticCode(name)
+}
+
+/// Convert a byte range of `file`, with an exclusive end, to a line and column range
+/// with an inclusive end.
+pub fn to_juno_range(sm: &SourceManager, file: SourceId, range: SourceRange) -> JunoRange {
+    let table = sm.line_table(file);
+    let start = range.start as usize;
+    let end = (u32::from(range.end) as usize).max(start + 1) - 1;
+    JunoRange {
+        file,
+        start: table.loc(start),
+        end: table.loc(end),
+    }
+}
+
+/// Convert a line and column range with an inclusive end to a byte range with an
+/// exclusive end, or `None` if the range isn't in its file.
+pub fn from_juno_range(sm: &SourceManager, range: JunoRange) -> Option<SourceRange> {
+    let table = sm.line_table(range.file);
+    let start = table.offset(range.start)?;
+    let end = table.offset(range.end)?.max(start) + 1;
+    Some(SourceRange {
+        start: start as u32,
+        end: NonZeroU32::new(end as u32).unwrap(),
+    })
+}
+
+/// Report `diagnostic` about `file` to `sm`, followed by its related information as
+/// notes. Warnings are reported as warnings, infos and hints as notes, and the other
+/// severities as errors, all with their severity and code.
+pub fn report(sm: &SourceManager, file: SourceId, diagnostic: &Diagnostic) {
+    let kind = match diagnostic.severity() {
+        DiagnosticSeverity::Hint | DiagnosticSeverity::Info => DiagKind::Note,
+        DiagnosticSeverity::Warning => DiagKind::Warning,
+        _ => DiagKind::Error,
+    };
+    sm.report(JunoDiagnostic {
+        kind,
+        range: to_juno_range(sm, file, diagnostic.range()),
+        message: diagnostic.message().to_string(),
+        fixes: diagnostic
+            .fixes()
+            .iter()
+            .map(|fix| {
+                let edits = fix.edits.iter().map(|edit| JunoEdit {
+                    start: edit.start,
+                    end: edit.end,
+                    text: edit.text.clone(),
+                });
+                Fix::new(fix.message.clone(), file, edits.collect())
+            })
+            .collect(),
+        code: diagnostic.code().map(|code| code.0.to_string()),
+        severity: Some(diagnostic.severity().to_string()),
+    });
+    for related in diagnostic.related_information() {
+        sm.note(
+            to_juno_range(sm, file, range_from_span(related.span)),
+            related.message.to_string(),
+        );
+    }
+}
+
+/// Convert the diagnostics reported to a juno source manager, e.g. collected by a
+/// `CollectingHandler`, to [`Diagnostic`]s, the notes without a severity becoming
+/// related information of the diagnostic they follow.
+/// Diagnostics keep their severity if they have one, otherwise errors are
+/// `InvalidSyntax` and notes are infos. Diagnostics whose range isn't in its file are
+/// dropped, as are fixes of other files.
+pub fn from_juno(sm: &SourceManager, diagnostics: &[JunoDiagnostic]) -> Diagnostics {
+    let mut result: Diagnostics = Vec::new();
+    for diagnostic in diagnostics {
+        let range = match from_juno_range(sm, diagnostic.range) {
+            Some(range) => range,
+            None => continue,
+        };
+        let message = diagnostic.message.clone();
+        match (diagnostic.kind, &diagnostic.severity, result.pop()) {
+            (DiagKind::Note, None, Some(last)) => result.push(last.annotate(message, range)),
+            (kind, severity, last) => {
+                result.extend(last);
+                let severity = severity
+                    .as_ref()
+                    .and_then(|name| SEVERITIES.into_iter().find(|s| s.to_string() == *name))
+                    .unwrap_or(match kind {
+                        DiagKind::Error => DiagnosticSeverity::InvalidSyntax,
+                        DiagKind::Warning => DiagnosticSeverity::Warning,
+                        DiagKind::Note => DiagnosticSeverity::Info,
+                    });
+                let mut converted = Diagnostic::invalid_syntax(message, range);
+                converted.set_severity(severity);
+                if let Some(name) = &diagnostic.code {
+                    converted = converted.with_code(code(name));
+                }
+                result.push(
+                    diagnostic
+                        .fixes
+                        .iter()
+                        .filter(|fix| fix.file == diagnostic.range.file)
+                        .fold(converted, |converted, fix| {
+                            let edits = fix.edits.iter().map(|edit| TextEdit {
+                                start: edit.start,
+                                end: edit.end,
+                                text: edit.text.clone(),
+                            });
+                            converted.with_fix(fix.message.clone(), edits.collect())
+                        }),
+                );
+            }
+        }
+    }
+    result
+}
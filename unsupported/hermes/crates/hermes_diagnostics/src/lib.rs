@@ -5,8 +5,13 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+pub mod codes;
+mod config;
 mod diagnostic;
+#[cfg(feature = "juno")]
+pub mod juno;
 
+pub use config::*;
 pub use diagnostic::*;
 
 /// Returns Ok(()) if the condition is true, otherwise returns Err()
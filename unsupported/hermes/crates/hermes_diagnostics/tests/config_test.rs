@@ -0,0 +1,76 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::num::NonZeroU32;
+
+use hermes_diagnostics::codes;
+use hermes_diagnostics::diagnostics_result;
+use hermes_diagnostics::Diagnostic;
+use hermes_diagnostics::DiagnosticConfig;
+use hermes_diagnostics::DiagnosticSeverity;
+use hermes_diagnostics::LspSeverity;
+use hermes_estree::SourceRange;
+
+fn range(start: u32, end: u32) -> SourceRange {
+    SourceRange {
+        start,
+        end: NonZeroU32::new(end).unwrap(),
+    }
+}
+
+#[test]
+fn promote_and_suppress() {
+    let mut config = DiagnosticConfig::new();
+    config
+        .set_severity(
+            codes::UNDEFINED_VARIABLE.as_str(),
+            DiagnosticSeverity::InvalidSyntax,
+        )
+        .suppress(codes::DUPLICATE_DECLARATION.as_str());
+
+    let diagnostics = config.apply(vec![
+        Diagnostic::warning("Undefined variable", range(0, 1)).with_code(codes::UNDEFINED_VARIABLE),
+        Diagnostic::invalid_syntax("Duplicate declaration", range(2, 3))
+            .with_code(codes::DUPLICATE_DECLARATION),
+        Diagnostic::hint("Prefer `const`", range(4, 5)),
+    ]);
+
+    assert_eq!(2, diagnostics.len());
+    assert_eq!(Some(codes::UNDEFINED_VARIABLE), diagnostics[0].code());
+    assert_eq!(DiagnosticSeverity::InvalidSyntax, diagnostics[0].severity());
+    assert_eq!(None, diagnostics[1].code());
+    assert_eq!(DiagnosticSeverity::Hint, diagnostics[1].severity());
+}
+
+#[test]
+fn lsp_severity() {
+    assert_eq!(LspSeverity::Hint, DiagnosticSeverity::Hint.lsp_severity());
+    assert_eq!(
+        LspSeverity::Information,
+        DiagnosticSeverity::Info.lsp_severity()
+    );
+    assert_eq!(
+        LspSeverity::Warning,
+        DiagnosticSeverity::Warning.lsp_severity()
+    );
+    assert_eq!(
+        LspSeverity::Error,
+        DiagnosticSeverity::Invariant.lsp_severity()
+    );
+    assert_eq!(1, LspSeverity::Error as u8);
+    assert_eq!(4, LspSeverity::Hint as u8);
+    assert!(!DiagnosticSeverity::Warning.is_error());
+    assert!(DiagnosticSeverity::Todo.is_error());
+}
+
+#[test]
+fn only_errors_fail() {
+    let warning = || Diagnostic::warning("unused", range(0, 1));
+    assert_eq!(1, diagnostics_result(1, vec![warning()]).unwrap());
+    let error = Diagnostic::invalid_syntax("unexpected token", range(0, 1));
+    assert_eq!(2, diagnostics_result(1, vec![warning(), error]).unwrap_err().len());
+}
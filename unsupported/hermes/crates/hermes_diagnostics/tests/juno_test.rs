@@ -0,0 +1,153 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::num::NonZeroU32;
+
+use hermes_diagnostics::apply_fixes;
+use hermes_diagnostics::codes;
+use hermes_diagnostics::juno::from_juno;
+use hermes_diagnostics::juno::report;
+use hermes_diagnostics::juno::to_juno_range;
+use hermes_diagnostics::replace_range;
+use hermes_diagnostics::Diagnostic;
+use hermes_diagnostics::DiagnosticCode;
+use hermes_diagnostics::DiagnosticSeverity;
+use hermes_estree::SourceRange;
+use juno_support::diagnostics::CollectingHandler;
+use juno_support::diagnostics::DiagKind;
+use juno_support::source_manager::SourceLoc;
+use juno_support::source_manager::SourceManager;
+use juno_support::NullTerminatedBuf;
+
+fn range(start: u32, end: u32) -> SourceRange {
+    SourceRange {
+        start,
+        end: NonZeroU32::new(end).unwrap(),
+    }
+}
+
+#[test]
+fn round_trip() {
+    let mut sm = SourceManager::new();
+    let file = sm.add_source("a.js", NullTerminatedBuf::from_str_copy("let x;\nlet x;\n"));
+    let handler = CollectingHandler::new();
+    sm.set_handler(Box::new(handler.clone()));
+
+    let juno_range = to_juno_range(&sm, file, range(11, 12));
+    assert_eq!(SourceLoc { line: 2, col: 5 }, juno_range.start);
+    assert_eq!(SourceLoc { line: 2, col: 5 }, juno_range.end);
+
+    let diagnostic = Diagnostic::invalid_syntax("Duplicate declaration", range(11, 12))
+        .annotate("Previous declaration", range(0, 5));
+    report(&sm, file, &diagnostic);
+    let collected = handler.take();
+    assert_eq!(
+        vec![DiagKind::Error, DiagKind::Note],
+        collected.iter().map(|d| d.kind).collect::<Vec<_>>()
+    );
+    assert_eq!(SourceLoc { line: 1, col: 5 }, collected[1].range.end);
+    assert_eq!(1, sm.num_errors());
+
+    let converted = from_juno(&sm, &collected);
+    assert_eq!(1, converted.len());
+    assert_eq!(DiagnosticSeverity::InvalidSyntax, converted[0].severity());
+    assert_eq!(diagnostic.span(), converted[0].span());
+    assert_eq!("Duplicate declaration", converted[0].to_string());
+    let related = converted[0].related_information();
+    assert_eq!(1, related.len());
+    assert_eq!(diagnostic.related_information()[0].span, related[0].span);
+    assert_eq!("Previous declaration", related[0].message.to_string());
+}
+
+#[test]
+fn warnings() {
+    let mut sm = SourceManager::new();
+    let file = sm.add_source("a.js", NullTerminatedBuf::from_str_copy("x;"));
+    let handler = CollectingHandler::new();
+    sm.set_handler(Box::new(handler.clone()));
+
+    report(&sm, file, &Diagnostic::warning("Unused", range(0, 1)));
+    let collected = handler.take();
+    assert_eq!(DiagKind::Warning, collected[0].kind);
+    assert_eq!(1, sm.num_warnings());
+    assert_eq!(
+        DiagnosticSeverity::Warning,
+        from_juno(&sm, &collected)[0].severity()
+    );
+}
+
+#[test]
+fn fixes() {
+    let source = "const x = 1;\nx = 2;\n";
+    let mut sm = SourceManager::new();
+    let file = sm.add_source("a.js", NullTerminatedBuf::from_str_copy(source));
+    let handler = CollectingHandler::new();
+    sm.set_handler(Box::new(handler.clone()));
+
+    let diagnostic = Diagnostic::invalid_syntax("Assignment to constant", range(13, 14)).with_fix(
+        "Declare `x` with `let`",
+        vec![replace_range(range(0, 5), "let")],
+    );
+    report(&sm, file, &diagnostic);
+    let collected = handler.take();
+    assert_eq!(1, collected[0].fixes.len());
+    assert_eq!(file, collected[0].fixes[0].file);
+
+    let converted = from_juno(&sm, &collected);
+    assert_eq!(diagnostic.fixes(), converted[0].fixes());
+    assert_eq!(
+        ("let x = 1;\nx = 2;\n".to_string(), 1),
+        apply_fixes(source, &converted)
+    );
+}
+
+#[test]
+fn severities_and_codes() {
+    let mut sm = SourceManager::new();
+    let file = sm.add_source("a.js", NullTerminatedBuf::from_str_copy("let x;\nlet x;\n"));
+    let handler = CollectingHandler::new();
+    sm.set_handler(Box::new(handler.clone()));
+
+    let diagnostics = vec![
+        Diagnostic::invalid_syntax("Duplicate declaration", range(11, 12))
+            .with_code(codes::DUPLICATE_DECLARATION)
+            .annotate("Previous declaration", range(4, 5)),
+        Diagnostic::hint("Prefer `const`", range(0, 3))
+            .with_code(DiagnosticCode("prefer-const"))
+            .with_fix("Use `const`", vec![replace_range(range(0, 3), "const")]),
+        Diagnostic::todo("Not supported yet", range(7, 10)),
+        Diagnostic::info("Declared twice", range(7, 13)),
+    ];
+    for diagnostic in &diagnostics {
+        report(&sm, file, diagnostic);
+    }
+    let collected = handler.take();
+    assert_eq!(
+        vec![
+            DiagKind::Error,
+            DiagKind::Note,
+            DiagKind::Note,
+            DiagKind::Error,
+            DiagKind::Note
+        ],
+        collected.iter().map(|d| d.kind).collect::<Vec<_>>()
+    );
+    assert_eq!(2, sm.num_errors());
+
+    let converted = from_juno(&sm, &collected);
+    assert_eq!(diagnostics.len(), converted.len());
+    for (diagnostic, converted) in diagnostics.iter().zip(&converted) {
+        assert_eq!(diagnostic.severity(), converted.severity());
+        assert_eq!(diagnostic.code(), converted.code());
+        assert_eq!(diagnostic.span(), converted.span());
+        assert_eq!(diagnostic.fixes(), converted.fixes());
+        assert_eq!(
+            diagnostic.related_information().len(),
+            converted.related_information().len()
+        );
+    }
+}
@@ -553,7 +553,7 @@ impl Node {
                     let node_ref = node.as_ref();
                     let range = convert_range(cx, node);
                     if node_ref.kind != NodeKind::#type_ {
-                        return Err(vec![Diagnostic::invariant(format!("Expected node kind {:?}, got node kind {:?}.", NodeKind::#type_, node_ref.kind), range)]);
+                        return Err(vec![Diagnostic::invariant(format!("Expected node kind {:?}, got node kind {:?}.", NodeKind::#type_, node_ref.kind), range).with_code(hermes_diagnostics::codes::UNEXPECTED_NODE_KIND)]);
                     }
                     #(#fields)*
                     Ok(Self {
@@ -901,7 +901,7 @@ impl Enum {
                         _ => {
                             let range = convert_range(cx, node);
                             Err(vec![
-                                Diagnostic::invariant(format!("Unexpected node kind `{:?}` for `{}`", node_ref.kind, #name_str), range)
+                                Diagnostic::invariant(format!("Unexpected node kind `{:?}` for `{}`", node_ref.kind, #name_str), range).with_code(hermes_diagnostics::codes::UNEXPECTED_NODE_KIND)
                             ])
                         }
                     }
@@ -0,0 +1,121 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashSet;
+
+use hermes_diagnostics::DiagnosticConfig;
+use hermes_diagnostics::DiagnosticSeverity;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::rules::rules;
+
+#[derive(Debug, Error)]
+pub enum LintConfigError {
+    #[error("Invalid lint configuration: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Expected the lint configuration to be an object, with an object of `rules`")]
+    InvalidShape,
+
+    #[error("Unknown lint rule `{0}`")]
+    UnknownRule(String),
+
+    #[error("Invalid severity {severity} for lint rule `{rule}`")]
+    InvalidSeverity { rule: String, severity: String },
+}
+
+/// The enabled rules and the severity of their diagnostics. Every rule is enabled by default,
+/// with the severity the rule reports.
+#[derive(Clone, Debug, Default)]
+pub struct LintConfig {
+    disabled: HashSet<String>,
+    diagnostics: DiagnosticConfig,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Parses a configuration of the form `{"rules": {"no-shadow": "off"}}`, where the severity of
+    /// a rule is one of `"off"`, `"hint"`, `"info"`, `"warn"` and `"error"`, or `0` (off),
+    /// `1` (warn) and `2` (error). Errors are reported as invalid syntax.
+    pub fn from_json(json: &str) -> Result<Self, LintConfigError> {
+        Self::from_value(&serde_json::from_str(json)?)
+    }
+
+    pub fn from_value(value: &Value) -> Result<Self, LintConfigError> {
+        let object = value.as_object().ok_or(LintConfigError::InvalidShape)?;
+        let names: Vec<&str> = rules().iter().map(|rule| rule.name()).collect();
+        let mut config = Self::new();
+        let Some(severities) = object.get("rules") else {
+            return Ok(config);
+        };
+        for (rule, severity) in severities
+            .as_object()
+            .ok_or(LintConfigError::InvalidShape)?
+        {
+            if !names.contains(&rule.as_str()) {
+                return Err(LintConfigError::UnknownRule(rule.clone()));
+            }
+            let severity =
+                parse_severity(severity).ok_or_else(|| LintConfigError::InvalidSeverity {
+                    rule: rule.clone(),
+                    severity: severity.to_string(),
+                })?;
+            config.set_rule(rule, severity);
+        }
+        Ok(config)
+    }
+
+    /// Reports the diagnostics of `rule` with `severity`, or disables the rule if `severity`
+    /// is `None`.
+    pub fn set_rule(&mut self, rule: &str, severity: Option<DiagnosticSeverity>) -> &mut Self {
+        match severity {
+            Some(severity) => {
+                self.disabled.remove(rule);
+                self.diagnostics.set_severity(rule, severity);
+            }
+            None => {
+                self.disabled.insert(rule.to_string());
+                self.diagnostics.suppress(rule);
+            }
+        }
+        self
+    }
+
+    pub fn is_enabled(&self, rule: &str) -> bool {
+        !self.disabled.contains(rule)
+    }
+
+    /// The severity overrides of the diagnostics of the rules.
+    pub fn diagnostics(&self) -> &DiagnosticConfig {
+        &self.diagnostics
+    }
+}
+
+/// The severity of a rule, `Some(None)` if it's off.
+fn parse_severity(value: &Value) -> Option<Option<DiagnosticSeverity>> {
+    match value {
+        Value::String(severity) => match severity.as_str() {
+            "off" => Some(None),
+            "hint" => Some(Some(DiagnosticSeverity::Hint)),
+            "info" => Some(Some(DiagnosticSeverity::Info)),
+            "warn" | "warning" => Some(Some(DiagnosticSeverity::Warning)),
+            "error" => Some(Some(DiagnosticSeverity::InvalidSyntax)),
+            _ => None,
+        },
+        Value::Number(severity) => match severity.as_u64()? {
+            0 => Some(None),
+            1 => Some(Some(DiagnosticSeverity::Warning)),
+            2 => Some(Some(DiagnosticSeverity::InvalidSyntax)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
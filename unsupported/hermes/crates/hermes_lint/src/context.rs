@@ -0,0 +1,267 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::num::NonZeroU32;
+
+use hermes_diagnostics::Diagnostic;
+use hermes_estree::ClassExpression;
+use hermes_estree::ExportDefaultDeclaration;
+use hermes_estree::ExportNamedDeclaration;
+use hermes_estree::FunctionExpression;
+use hermes_estree::Identifier;
+use hermes_estree::JSXIdentifier;
+use hermes_estree::Program;
+use hermes_estree::SourceRange;
+use hermes_estree::VariableDeclaration;
+use hermes_estree::VariableDeclarationKind;
+use hermes_estree::Visitor;
+use hermes_semantic_analysis::DeclarationId;
+use hermes_semantic_analysis::DeclarationKind;
+use hermes_semantic_analysis::ReferenceId;
+use hermes_semantic_analysis::Scope;
+use hermes_semantic_analysis::ScopeManager;
+
+/// What rules know about the linted program: its AST, its analysis, and the location of the
+/// declarations and references which the `ScopeManager` doesn't record.
+pub struct LintContext<'a> {
+    pub program: &'a Program,
+    pub manager: &'a ScopeManager,
+    /// Every scope, parents before their children.
+    scopes: Vec<&'a Scope>,
+    /// Ranges of the identifiers declaring each declaration, in source order.
+    declarations: HashMap<DeclarationId, Vec<SourceRange>>,
+    /// Ranges of the identifiers of each reference, in source order.
+    references: Vec<(SourceRange, ReferenceId)>,
+    /// References to undeclared variables, with their name.
+    undefined: Vec<(SourceRange, String)>,
+    exported: HashSet<DeclarationId>,
+    /// Names of function and class expressions, which are only visible in their own body.
+    expression_names: HashSet<DeclarationId>,
+    /// Range of the `const` keyword of the declarations of a `const` statement.
+    const_keywords: HashMap<DeclarationId, SourceRange>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> LintContext<'a> {
+    /// Indexes `program`. `undefined` are the ranges of the references which the analysis
+    /// couldn't resolve.
+    pub fn new(program: &'a Program, manager: &'a ScopeManager, undefined: &[SourceRange]) -> Self {
+        let mut scopes = vec![manager.root()];
+        let mut index = 0;
+        while index < scopes.len() {
+            let scope = scopes[index];
+            scopes.extend(scope.children.iter().map(|child| manager.scope(*child)));
+            index += 1;
+        }
+
+        let mut indexer = Indexer {
+            manager,
+            declarations: HashMap::new(),
+            references: Vec::new(),
+            unresolved: HashMap::new(),
+            exported: HashSet::new(),
+            expression_names: HashSet::new(),
+            const_keywords: HashMap::new(),
+            exporting: false,
+            const_keyword: None,
+        };
+        indexer.visit_program(program);
+        let undefined = undefined
+            .iter()
+            .filter_map(|range| {
+                let name = indexer.unresolved.get(&range.start)?;
+                Some((*range, name.clone()))
+            })
+            .collect();
+        Self {
+            program,
+            manager,
+            scopes,
+            declarations: indexer.declarations,
+            references: indexer.references,
+            undefined,
+            exported: indexer.exported,
+            expression_names: indexer.expression_names,
+            const_keywords: indexer.const_keywords,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    /// Every scope of the program, parents before their children.
+    pub fn scopes(&self) -> &[&'a Scope] {
+        &self.scopes
+    }
+
+    /// Ranges of the identifiers declaring `declaration`, in source order. Declarations can be
+    /// declared several times, e.g. by `var`.
+    pub fn declaration_ranges(&self, declaration: DeclarationId) -> &[SourceRange] {
+        self.declarations
+            .get(&declaration)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Ranges of the identifiers of every reference, in source order.
+    pub fn references(&self) -> &[(SourceRange, ReferenceId)] {
+        &self.references
+    }
+
+    /// Ranges and names of the references to undeclared variables.
+    pub fn undefined(&self) -> &[(SourceRange, String)] {
+        &self.undefined
+    }
+
+    /// Whether `declaration` is a top-level declaration of an `export` statement.
+    pub fn is_exported(&self, declaration: DeclarationId) -> bool {
+        self.exported.contains(&declaration)
+    }
+
+    /// Whether `declaration` is the name of a function or class expression.
+    pub fn is_expression_name(&self, declaration: DeclarationId) -> bool {
+        self.expression_names.contains(&declaration)
+    }
+
+    /// The `const` keyword declaring `declaration`, if it's declared by a `const` statement.
+    pub fn const_keyword(&self, declaration: DeclarationId) -> Option<SourceRange> {
+        self.const_keywords.get(&declaration).copied()
+    }
+}
+
+struct Indexer<'a> {
+    manager: &'a ScopeManager,
+    declarations: HashMap<DeclarationId, Vec<SourceRange>>,
+    references: Vec<(SourceRange, ReferenceId)>,
+    /// Names of the identifiers which are neither declarations nor resolved references,
+    /// by offset.
+    unresolved: HashMap<u32, String>,
+    exported: HashSet<DeclarationId>,
+    expression_names: HashSet<DeclarationId>,
+    const_keywords: HashMap<DeclarationId, SourceRange>,
+    /// Whether the visited declaration is exported.
+    exporting: bool,
+    /// The `const` keyword of the visited `const` statement.
+    const_keyword: Option<SourceRange>,
+}
+
+impl Indexer<'_> {
+    fn record(&mut self, name: &str, range: SourceRange, declaration: Option<DeclarationId>) {
+        // Identifier ranges include their type annotation
+        let range = SourceRange {
+            start: range.start,
+            end: NonZeroU32::new(range.start + name.len() as u32).unwrap_or(range.end),
+        };
+        let Some(id) = declaration else {
+            self.unresolved.insert(range.start, name.to_string());
+            return;
+        };
+        self.declarations.entry(id).or_default().push(range);
+        let declaration = self.manager.declaration(id);
+        if self.exporting && declaration.scope == self.manager.root().id {
+            self.exported.insert(id);
+        }
+        if let Some(keyword) = self.const_keyword {
+            if declaration.kind == DeclarationKind::Const {
+                self.const_keywords.entry(id).or_insert(keyword);
+            }
+        }
+    }
+
+    fn record_reference(&mut self, name: &str, range: SourceRange, reference: ReferenceId) {
+        let range = SourceRange {
+            start: range.start,
+            end: NonZeroU32::new(range.start + name.len() as u32).unwrap_or(range.end),
+        };
+        self.references.push((range, reference));
+    }
+}
+
+impl<'ast> Visitor<'ast> for Indexer<'_> {
+    fn visit_identifier(&mut self, ast: &'ast Identifier) {
+        if let Some(reference) = self.manager.node_reference(ast) {
+            self.record_reference(&ast.name, ast.range, reference.id);
+        } else {
+            let declaration = self.manager.node_declaration(ast).map(|d| d.id);
+            self.record(&ast.name, ast.range, declaration);
+        }
+        if let Some(type_annotation) = &ast.type_annotation {
+            self.visit_type_annotation(type_annotation);
+        }
+    }
+
+    fn visit_jsxidentifier(&mut self, ast: &'ast JSXIdentifier) {
+        if let Some(reference) = self.manager.node_reference(ast) {
+            self.record_reference(&ast.name, ast.range, reference.id);
+        } else {
+            self.unresolved.insert(ast.range.start, ast.name.clone());
+        }
+    }
+
+    fn visit_export_named_declaration(&mut self, ast: &'ast ExportNamedDeclaration) {
+        if let Some(declaration) = &ast.declaration {
+            self.exporting = true;
+            self.visit_declaration(declaration);
+            self.exporting = false;
+        }
+        for specifier in &ast.specifiers {
+            self.visit_export_specifier(specifier);
+        }
+    }
+
+    fn visit_export_default_declaration(&mut self, ast: &'ast ExportDefaultDeclaration) {
+        self.exporting = true;
+        self.visit_declaration_or_expression(&ast.declaration);
+        self.exporting = false;
+    }
+
+    fn visit_function_expression(&mut self, ast: &'ast FunctionExpression) {
+        if let Some(declaration) = ast
+            .function
+            .id
+            .as_ref()
+            .and_then(|id| self.manager.node_declaration(id))
+        {
+            self.expression_names.insert(declaration.id);
+        }
+        self.visit_function(&ast.function);
+    }
+
+    fn visit_class_expression(&mut self, ast: &'ast ClassExpression) {
+        if let Some(declaration) = ast
+            .class
+            .id
+            .as_ref()
+            .and_then(|id| self.manager.node_declaration(id))
+        {
+            self.expression_names.insert(declaration.id);
+        }
+        self.visit_class(&ast.class);
+    }
+
+    fn visit_variable_declaration(&mut self, ast: &'ast VariableDeclaration) {
+        let outer = self.const_keyword;
+        if ast.kind == VariableDeclarationKind::Const {
+            self.const_keyword = Some(SourceRange {
+                start: ast.range.start,
+                end: NonZeroU32::new(ast.range.start + "const".len() as u32).unwrap(),
+            });
+        }
+        for declarator in &ast.declarations {
+            self.visit_variable_declarator(declarator);
+        }
+        self.const_keyword = outer;
+    }
+}
@@ -0,0 +1,16 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+mod config;
+mod context;
+mod linter;
+mod rules;
+
+pub use config::*;
+pub use context::*;
+pub use linter::*;
+pub use rules::*;
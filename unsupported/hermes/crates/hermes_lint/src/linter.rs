@@ -0,0 +1,36 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use hermes_diagnostics::codes;
+use hermes_diagnostics::Diagnostic;
+use hermes_estree::Program;
+use hermes_semantic_analysis::ScopeManager;
+
+use crate::rules::rules;
+use crate::LintConfig;
+use crate::LintContext;
+
+/// Runs the rules enabled by `config` over `program`, whose analysis is `manager`.
+/// Returns the diagnostics of the rules along with the other diagnostics of the analysis, ordered
+/// by position. The "Undefined variable" diagnostics of the analysis are reported by `no-undef`.
+pub fn lint(program: &Program, manager: &mut ScopeManager, config: &LintConfig) -> Vec<Diagnostic> {
+    let (undefined, mut diagnostics): (Vec<_>, Vec<_>) = manager
+        .diagnostics()
+        .into_iter()
+        .partition(|diagnostic| diagnostic.code() == Some(codes::UNDEFINED_VARIABLE));
+    let undefined: Vec<_> = undefined.iter().map(Diagnostic::range).collect();
+
+    let mut cx = LintContext::new(program, manager, &undefined);
+    for rule in rules() {
+        if config.is_enabled(rule.name()) {
+            rule.check(&mut cx);
+        }
+    }
+    diagnostics.extend(config.diagnostics().apply(cx.into_diagnostics()));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span().offset());
+    diagnostics
+}
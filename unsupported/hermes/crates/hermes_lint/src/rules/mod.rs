@@ -0,0 +1,42 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+mod no_const_assign;
+mod no_redeclare;
+mod no_shadow;
+mod no_undef;
+mod no_unreachable;
+mod no_unused_vars;
+
+pub use no_const_assign::NoConstAssign;
+pub use no_redeclare::NoRedeclare;
+pub use no_shadow::NoShadow;
+pub use no_undef::NoUndef;
+pub use no_unreachable::NoUnreachable;
+pub use no_unused_vars::NoUnusedVars;
+
+use crate::LintContext;
+
+/// A check of the linted program, reporting its findings to the `LintContext`.
+pub trait Rule {
+    /// The name of the rule in the configuration, which is also the code of its diagnostics.
+    fn name(&self) -> &'static str;
+
+    fn check(&self, cx: &mut LintContext<'_>);
+}
+
+/// Every rule, in the order they run.
+pub fn rules() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(NoUnusedVars),
+        Box::new(NoShadow),
+        Box::new(NoUndef),
+        Box::new(NoRedeclare),
+        Box::new(NoConstAssign),
+        Box::new(NoUnreachable),
+    ]
+}
@@ -0,0 +1,54 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use hermes_diagnostics::replace_range;
+use hermes_diagnostics::Diagnostic;
+use hermes_diagnostics::DiagnosticCode;
+use hermes_semantic_analysis::DeclarationKind;
+use hermes_semantic_analysis::ReferenceKind;
+
+use crate::LintContext;
+use crate::Rule;
+
+const CODE: DiagnosticCode = DiagnosticCode("no-const-assign");
+
+/// Reports assignments to `const` declarations, which throw at runtime. The fix declares the
+/// variable with `let` instead.
+pub struct NoConstAssign;
+
+impl Rule for NoConstAssign {
+    fn name(&self) -> &'static str {
+        CODE.as_str()
+    }
+
+    fn check(&self, cx: &mut LintContext<'_>) {
+        let mut diagnostics = Vec::new();
+        for (range, reference) in cx.references() {
+            let reference = cx.manager.reference(*reference);
+            let declaration = cx.manager.declaration(reference.declaration);
+            if reference.kind == ReferenceKind::Read || declaration.kind != DeclarationKind::Const {
+                continue;
+            }
+            let name = &declaration.name;
+            let mut diagnostic =
+                Diagnostic::invalid_syntax(format!("'{name}' is constant"), *range).with_code(CODE);
+            if let Some(declared) = cx.declaration_ranges(declaration.id).first() {
+                diagnostic = diagnostic.annotate(format!("'{name}' is declared here"), *declared);
+            }
+            if let Some(keyword) = cx.const_keyword(declaration.id) {
+                diagnostic = diagnostic.with_fix(
+                    format!("declare '{name}' with 'let'"),
+                    vec![replace_range(keyword, "let")],
+                );
+            }
+            diagnostics.push(diagnostic);
+        }
+        for diagnostic in diagnostics {
+            cx.report(diagnostic);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use hermes_diagnostics::Diagnostic;
+use hermes_diagnostics::DiagnosticCode;
+
+use crate::LintContext;
+use crate::Rule;
+
+const CODE: DiagnosticCode = DiagnosticCode("no-redeclare");
+
+/// Reports `var` declarations of a variable which is already declared in the same scope.
+/// The other redeclarations are syntax errors reported by the analysis.
+pub struct NoRedeclare;
+
+impl Rule for NoRedeclare {
+    fn name(&self) -> &'static str {
+        CODE.as_str()
+    }
+
+    fn check(&self, cx: &mut LintContext<'_>) {
+        let mut diagnostics = Vec::new();
+        for scope in cx.scopes() {
+            for (name, id) in &scope.declarations {
+                let ranges = cx.declaration_ranges(*id);
+                for range in ranges.iter().skip(1) {
+                    diagnostics.push(
+                        Diagnostic::warning(format!("'{name}' is already declared"), *range)
+                            .with_code(CODE)
+                            .annotate(format!("'{name}' is first declared here"), ranges[0]),
+                    );
+                }
+            }
+        }
+        for diagnostic in diagnostics {
+            cx.report(diagnostic);
+        }
+    }
+}
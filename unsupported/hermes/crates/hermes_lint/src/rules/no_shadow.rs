@@ -0,0 +1,60 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use hermes_diagnostics::Diagnostic;
+use hermes_diagnostics::DiagnosticCode;
+
+use crate::LintContext;
+use crate::Rule;
+
+const CODE: DiagnosticCode = DiagnosticCode("no-shadow");
+
+/// Reports declarations with the same name as a declaration of an enclosing scope, which they
+/// make inaccessible. Globals are not considered.
+pub struct NoShadow;
+
+impl Rule for NoShadow {
+    fn name(&self) -> &'static str {
+        CODE.as_str()
+    }
+
+    fn check(&self, cx: &mut LintContext<'_>) {
+        let mut diagnostics = Vec::new();
+        for scope in cx.scopes() {
+            for (name, id) in &scope.declarations {
+                // `const f = function f() {}` is idiomatic
+                if cx.is_expression_name(*id) {
+                    continue;
+                }
+                let Some(range) = cx.declaration_ranges(*id).first() else {
+                    continue;
+                };
+                let mut parent = scope.parent;
+                while let Some(ancestor) = parent {
+                    let ancestor = cx.manager.scope(ancestor);
+                    if let Some(shadowed) = ancestor.declarations.get(name) {
+                        let mut diagnostic = Diagnostic::warning(
+                            format!("'{name}' is already declared in an outer scope"),
+                            *range,
+                        )
+                        .with_code(CODE);
+                        if let Some(shadowed) = cx.declaration_ranges(*shadowed).first() {
+                            diagnostic = diagnostic
+                                .annotate(format!("'{name}' is declared here"), *shadowed);
+                        }
+                        diagnostics.push(diagnostic);
+                        break;
+                    }
+                    parent = ancestor.parent;
+                }
+            }
+        }
+        for diagnostic in diagnostics {
+            cx.report(diagnostic);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use hermes_diagnostics::Diagnostic;
+use hermes_diagnostics::DiagnosticCode;
+
+use crate::LintContext;
+use crate::Rule;
+
+const CODE: DiagnosticCode = DiagnosticCode("no-undef");
+
+/// Reports references to variables which are neither declared nor globals. Replaces the
+/// "Undefined variable" diagnostics of the analysis.
+pub struct NoUndef;
+
+impl Rule for NoUndef {
+    fn name(&self) -> &'static str {
+        CODE.as_str()
+    }
+
+    fn check(&self, cx: &mut LintContext<'_>) {
+        let diagnostics: Vec<_> = cx
+            .undefined()
+            .iter()
+            .map(|(range, name)| {
+                Diagnostic::warning(format!("'{name}' is not defined"), *range).with_code(CODE)
+            })
+            .collect();
+        for diagnostic in diagnostics {
+            cx.report(diagnostic);
+        }
+    }
+}
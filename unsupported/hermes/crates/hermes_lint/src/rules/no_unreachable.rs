@@ -0,0 +1,148 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use hermes_diagnostics::replace_range;
+use hermes_diagnostics::Diagnostic;
+use hermes_diagnostics::DiagnosticCode;
+use hermes_estree::BlockStatement;
+use hermes_estree::ModuleItem;
+use hermes_estree::Program;
+use hermes_estree::Range;
+use hermes_estree::SourceRange;
+use hermes_estree::Statement;
+use hermes_estree::StaticBlock;
+use hermes_estree::SwitchCase;
+use hermes_estree::VariableDeclarationKind;
+use hermes_estree::Visitor;
+
+use crate::LintContext;
+use crate::Rule;
+
+const CODE: DiagnosticCode = DiagnosticCode("no-unreachable");
+
+/// Reports the statements following a `return`, `throw`, `break` or `continue` in the same
+/// block. Function declarations and `var` declarations without initializer are hoisted, so they
+/// aren't unreachable. The fix removes the unreachable statements.
+pub struct NoUnreachable;
+
+impl Rule for NoUnreachable {
+    fn name(&self) -> &'static str {
+        CODE.as_str()
+    }
+
+    fn check(&self, cx: &mut LintContext<'_>) {
+        let mut visitor = UnreachableVisitor { ranges: Vec::new() };
+        visitor.visit_program(cx.program);
+        for range in visitor.ranges {
+            cx.report(
+                Diagnostic::warning("Unreachable code", range)
+                    .with_code(CODE)
+                    .with_fix(
+                        "remove the unreachable code",
+                        vec![replace_range(range, "")],
+                    ),
+            );
+        }
+    }
+}
+
+struct UnreachableVisitor {
+    ranges: Vec<SourceRange>,
+}
+
+impl UnreachableVisitor {
+    /// Records the ranges of the unreachable statements of a statement list, where `None` stands
+    /// for items which are not statements.
+    fn check<'a, I>(&mut self, statements: I)
+    where
+        I: IntoIterator<Item = Option<&'a Statement>>,
+    {
+        let mut statements = statements.into_iter();
+        if !statements.by_ref().any(|statement| {
+            matches!(
+                statement,
+                Some(
+                    Statement::ReturnStatement(_)
+                        | Statement::ThrowStatement(_)
+                        | Statement::BreakStatement(_)
+                        | Statement::ContinueStatement(_)
+                )
+            )
+        }) {
+            return;
+        }
+        // Report every run of consecutive unreachable statements, to not remove the hoisted
+        // declarations between them
+        let mut run: Option<SourceRange> = None;
+        for statement in statements {
+            match statement {
+                Some(statement) if !is_hoisted(statement) => {
+                    let range = statement.range();
+                    run = Some(match run {
+                        Some(run) => SourceRange {
+                            start: run.start,
+                            end: range.end,
+                        },
+                        None => range,
+                    });
+                }
+                _ => self.ranges.extend(run.take()),
+            }
+        }
+        self.ranges.extend(run);
+    }
+}
+
+fn is_hoisted(statement: &Statement) -> bool {
+    match statement {
+        Statement::FunctionDeclaration(_) | Statement::EmptyStatement(_) => true,
+        Statement::VariableDeclaration(declaration) => {
+            declaration.kind == VariableDeclarationKind::Var
+                && declaration
+                    .declarations
+                    .iter()
+                    .all(|declarator| declarator.init.is_none())
+        }
+        _ => false,
+    }
+}
+
+impl<'ast> Visitor<'ast> for UnreachableVisitor {
+    fn visit_program(&mut self, ast: &'ast Program) {
+        self.check(ast.body.iter().map(|item| match item {
+            ModuleItem::Statement(statement) => Some(statement),
+            ModuleItem::ImportOrExportDeclaration(_) => None,
+        }));
+        for item in &ast.body {
+            self.visit_module_item(item);
+        }
+    }
+
+    fn visit_block_statement(&mut self, ast: &'ast BlockStatement) {
+        self.check(ast.body.iter().map(Some));
+        for statement in &ast.body {
+            self.visit_statement(statement);
+        }
+    }
+
+    fn visit_switch_case(&mut self, ast: &'ast SwitchCase) {
+        self.check(ast.consequent.iter().map(Some));
+        if let Some(test) = &ast.test {
+            self.visit_expression(test);
+        }
+        for statement in &ast.consequent {
+            self.visit_statement(statement);
+        }
+    }
+
+    fn visit_static_block(&mut self, ast: &'ast StaticBlock) {
+        self.check(ast.body.iter().map(Some));
+        for statement in &ast.body {
+            self.visit_statement(statement);
+        }
+    }
+}
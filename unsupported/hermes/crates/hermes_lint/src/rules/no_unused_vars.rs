@@ -0,0 +1,56 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashSet;
+
+use hermes_diagnostics::Diagnostic;
+use hermes_diagnostics::DiagnosticCode;
+
+use crate::LintContext;
+use crate::Rule;
+
+const CODE: DiagnosticCode = DiagnosticCode("no-unused-vars");
+
+/// Reports declarations which are never referenced. Exported declarations, names of function
+/// and class expressions, and names starting with `_` are ignored.
+pub struct NoUnusedVars;
+
+impl Rule for NoUnusedVars {
+    fn name(&self) -> &'static str {
+        CODE.as_str()
+    }
+
+    fn check(&self, cx: &mut LintContext<'_>) {
+        let referenced: HashSet<_> = cx
+            .scopes()
+            .iter()
+            .flat_map(|scope| &scope.references)
+            .map(|reference| cx.manager.reference(*reference).declaration)
+            .collect();
+        let mut diagnostics = Vec::new();
+        for scope in cx.scopes() {
+            for (name, id) in &scope.declarations {
+                if referenced.contains(id)
+                    || name.starts_with('_')
+                    || cx.is_exported(*id)
+                    || cx.is_expression_name(*id)
+                {
+                    continue;
+                }
+                if let Some(range) = cx.declaration_ranges(*id).first() {
+                    diagnostics.push(
+                        Diagnostic::warning(format!("'{name}' is declared but never used"), *range)
+                            .with_code(CODE),
+                    );
+                }
+            }
+        }
+        for diagnostic in diagnostics {
+            cx.report(diagnostic);
+        }
+    }
+}
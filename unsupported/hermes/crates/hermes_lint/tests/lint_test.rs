@@ -0,0 +1,152 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use hermes_diagnostics::apply_fixes;
+use hermes_diagnostics::Diagnostic;
+use hermes_diagnostics::DiagnosticSeverity;
+use hermes_lint::lint;
+use hermes_lint::LintConfig;
+use hermes_lint::LintConfigError;
+use hermes_parser::parse;
+use hermes_parser::ParserFlags;
+use hermes_semantic_analysis::analyze;
+use hermes_semantic_analysis::AnalyzeOptions;
+
+fn run(source: &str, config: &LintConfig) -> Vec<Diagnostic> {
+    let result = parse(source, "test.js", ParserFlags::default()).unwrap();
+    let mut manager = analyze(
+        &result.ast,
+        AnalyzeOptions {
+            globals: vec!["console".to_string()],
+        },
+    );
+    lint(&result.ast, &mut manager, config)
+}
+
+fn messages(diagnostics: &[Diagnostic]) -> Vec<(&'static str, String)> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.code().unwrap().as_str(),
+                diagnostic.message().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn no_unused_vars() {
+    let diagnostics = run(
+        "const used = 1;\nconst unused = 2;\nconst _ignored = 3;\nconsole.log(used);\n",
+        &LintConfig::new(),
+    );
+    assert_eq!(
+        vec![(
+            "no-unused-vars",
+            "'unused' is declared but never used".to_string()
+        )],
+        messages(&diagnostics)
+    );
+    assert_eq!(DiagnosticSeverity::Warning, diagnostics[0].severity());
+}
+
+#[test]
+fn no_shadow() {
+    let diagnostics = run(
+        "const x = 1;\nfunction f(x) {\n  return x;\n}\nf(x);\n",
+        &LintConfig::new(),
+    );
+    assert_eq!(
+        vec![(
+            "no-shadow",
+            "'x' is already declared in an outer scope".to_string()
+        )],
+        messages(&diagnostics)
+    );
+    assert_eq!(1, diagnostics[0].related_information().len());
+}
+
+#[test]
+fn no_undef() {
+    let diagnostics = run("missing();\n", &LintConfig::new());
+    assert_eq!(
+        vec![("no-undef", "'missing' is not defined".to_string())],
+        messages(&diagnostics)
+    );
+}
+
+#[test]
+fn no_redeclare() {
+    let diagnostics = run(
+        "var a = 1;\nvar a = 2;\nconsole.log(a);\n",
+        &LintConfig::new(),
+    );
+    assert_eq!(
+        vec![("no-redeclare", "'a' is already declared".to_string())],
+        messages(&diagnostics)
+    );
+    assert_eq!(15, diagnostics[0].span().offset());
+}
+
+#[test]
+fn no_const_assign() {
+    let source = "const a = 1;\na = 2;\nconsole.log(a);\n";
+    let diagnostics = run(source, &LintConfig::new());
+    assert_eq!(
+        vec![("no-const-assign", "'a' is constant".to_string())],
+        messages(&diagnostics)
+    );
+    assert!(diagnostics[0].severity().is_error());
+    assert_eq!(
+        ("let a = 1;\na = 2;\nconsole.log(a);\n".to_string(), 1),
+        apply_fixes(source, &diagnostics)
+    );
+}
+
+#[test]
+fn no_unreachable() {
+    let source = "function f() {\n  return 1;\n  console.log(2);\n}\nf();\n";
+    let diagnostics = run(source, &LintConfig::new());
+    assert_eq!(
+        vec![("no-unreachable", "Unreachable code".to_string())],
+        messages(&diagnostics)
+    );
+    assert_eq!(
+        ("function f() {\n  return 1;\n  \n}\nf();\n".to_string(), 1),
+        apply_fixes(source, &diagnostics)
+    );
+}
+
+#[test]
+fn config() {
+    let config =
+        LintConfig::from_json(r#"{"rules": {"no-undef": "off", "no-unused-vars": 2}}"#).unwrap();
+    assert!(!config.is_enabled("no-undef"));
+    let diagnostics = run("missing();\nlet x;\n", &config);
+    assert_eq!(
+        vec![(
+            "no-unused-vars",
+            "'x' is declared but never used".to_string()
+        )],
+        messages(&diagnostics)
+    );
+    assert_eq!(DiagnosticSeverity::InvalidSyntax, diagnostics[0].severity());
+
+    assert!(matches!(
+        LintConfig::from_json(r#"{"rules": {"no-such-rule": "warn"}}"#),
+        Err(LintConfigError::UnknownRule(rule)) if rule == "no-such-rule"
+    ));
+    assert!(matches!(
+        LintConfig::from_json(r#"{"rules": {"no-shadow": "loud"}}"#),
+        Err(LintConfigError::InvalidSeverity { .. })
+    ));
+    assert!(matches!(
+        LintConfig::from_json("[]"),
+        Err(LintConfigError::InvalidShape)
+    ));
+}
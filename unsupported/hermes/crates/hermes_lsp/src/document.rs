@@ -0,0 +1,448 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashMap;
+
+use hermes_diagnostics::Diagnostic;
+use hermes_estree::AssignmentProperty;
+use hermes_estree::ClassDeclaration;
+use hermes_estree::ESTreeNode;
+use hermes_estree::ExportSpecifier;
+use hermes_estree::FunctionDeclaration;
+use hermes_estree::Identifier;
+use hermes_estree::ImportSpecifier;
+use hermes_estree::JSXElement;
+use hermes_estree::JSXElementName;
+use hermes_estree::JSXIdentifier;
+use hermes_estree::Property;
+use hermes_estree::SourceRange;
+use hermes_estree::Visitor;
+use hermes_parser::parse;
+use hermes_parser::ParserDialect;
+use hermes_parser::ParserFlags;
+use hermes_semantic_analysis::analyze;
+use hermes_semantic_analysis::AnalyzeOptions;
+use hermes_semantic_analysis::DeclarationId;
+use hermes_semantic_analysis::DeclarationKind;
+use hermes_semantic_analysis::Scope;
+use hermes_semantic_analysis::ScopeId;
+use hermes_semantic_analysis::ScopeKind;
+use hermes_semantic_analysis::ScopeManager;
+use serde_json::json;
+use serde_json::Value;
+
+/// LSP `SymbolKind`s of the document symbols.
+const SYMBOL_CLASS: u8 = 5;
+const SYMBOL_FUNCTION: u8 = 12;
+const SYMBOL_VARIABLE: u8 = 13;
+const SYMBOL_CONSTANT: u8 = 14;
+
+/// A document opened by the client, along with the result of its analysis.
+pub struct Document {
+    text: String,
+    /// Byte offset of the start of each line.
+    line_starts: Vec<usize>,
+    diagnostics: Vec<Diagnostic>,
+    /// Identifiers resolved to a declaration, ordered by position.
+    occurrences: Vec<Occurrence>,
+    symbols: Vec<Symbol>,
+    /// The result of the analysis, if the document was parsed.
+    manager: Option<ScopeManager>,
+}
+
+/// An identifier which declares or references a declaration.
+#[derive(Debug)]
+pub struct Occurrence {
+    pub start: usize,
+    pub end: usize,
+    pub declaration: DeclarationId,
+    pub is_declaration: bool,
+    /// The scope of the reference, or of the declaration.
+    pub scope: ScopeId,
+    /// Whether the declaration is one of the globals, which aren't declared in the document.
+    pub is_global: bool,
+    shorthand: Option<Shorthand>,
+}
+
+/// Identifiers which stand for two names at once, and must be expanded when renamed.
+#[derive(Clone, Copy, Debug)]
+enum Shorthand {
+    /// `{x}` in an object literal or pattern.
+    Property,
+    /// `import {x} from '...'`.
+    Import,
+    /// `export {x}`.
+    Export,
+}
+
+#[derive(Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: u8,
+    /// The range of the whole declaration.
+    pub range: (usize, usize),
+    /// The range of the declared identifier.
+    pub selection: (usize, usize),
+    pub children: Vec<Symbol>,
+}
+
+impl Document {
+    /// Parses and analyzes `text`, resolving references to undeclared `globals`.
+    pub fn new(text: String, globals: Vec<String>) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(index, _)| index + 1));
+        let mut document = Self {
+            text,
+            line_starts,
+            diagnostics: Vec::new(),
+            occurrences: Vec::new(),
+            symbols: Vec::new(),
+            manager: None,
+        };
+        let flags = ParserFlags {
+            strict_mode: true,
+            enable_jsx: true,
+            dialect: ParserDialect::Flow,
+            store_doc_block: false,
+            store_comments: false,
+        };
+        let result = match parse(&document.text, "", flags) {
+            Ok(result) => result,
+            Err(diagnostics) => {
+                document.diagnostics = diagnostics;
+                return document;
+            }
+        };
+        let mut manager = analyze(&result.ast, AnalyzeOptions { globals });
+        let mut indexer = Indexer {
+            manager: &manager,
+            occurrences: Vec::new(),
+            shorthands: HashMap::new(),
+            containers: HashMap::new(),
+        };
+        indexer.visit_program(&result.ast);
+        let Indexer {
+            mut occurrences,
+            containers,
+            ..
+        } = indexer;
+        occurrences.sort_by_key(|occurrence| occurrence.start);
+        let mut declared = HashMap::new();
+        for occurrence in occurrences.iter().filter(|o| o.is_declaration) {
+            declared
+                .entry(occurrence.declaration)
+                .or_insert((occurrence.start, occurrence.end));
+        }
+        document.symbols = symbols(&manager, manager.root(), &declared, &containers);
+        document.occurrences = occurrences;
+        document.diagnostics = manager.diagnostics();
+        document.manager = Some(manager);
+        document
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// The LSP `Position` of a byte offset, whose character is counted in UTF-16 code units.
+    pub fn position(&self, offset: usize) -> Value {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let character = self.text[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+        json!({ "line": line, "character": character })
+    }
+
+    pub fn range(&self, start: usize, end: usize) -> Value {
+        json!({ "start": self.position(start), "end": self.position(end) })
+    }
+
+    /// The byte offset of an LSP `Position`, clamped to the end of its line.
+    pub fn offset(&self, position: &Value) -> Option<usize> {
+        let line = position.get("line")?.as_u64()? as usize;
+        let character = position.get("character")?.as_u64()? as usize;
+        let start = *self.line_starts.get(line)?;
+        let mut units = 0;
+        for (index, c) in self.text[start..].char_indices() {
+            if units >= character || c == '\n' || c == '\r' {
+                return Some(start + index);
+            }
+            units += c.len_utf16();
+        }
+        Some(self.text.len())
+    }
+
+    /// The occurrence which contains `offset`, or ends at it.
+    pub fn occurrence_at(&self, offset: usize) -> Option<&Occurrence> {
+        let index = self
+            .occurrences
+            .partition_point(|occurrence| occurrence.start <= offset);
+        self.occurrences[..index]
+            .last()
+            .filter(|occurrence| offset <= occurrence.end)
+    }
+
+    pub fn occurrences_of(
+        &self,
+        declaration: DeclarationId,
+    ) -> impl Iterator<Item = &Occurrence> + '_ {
+        self.occurrences
+            .iter()
+            .filter(move |occurrence| occurrence.declaration == declaration)
+    }
+
+    /// The edits renaming every occurrence of `declaration` to `name`, as (start, end, text),
+    /// or an error if `name` would then refer to another declaration somewhere.
+    pub fn rename(
+        &self,
+        declaration: DeclarationId,
+        name: &str,
+    ) -> Result<Vec<(usize, usize, String)>, String> {
+        if let Some(manager) = &self.manager {
+            check_rename(manager, &self.text, &self.occurrences, declaration, name)?;
+        }
+        Ok(self
+            .occurrences_of(declaration)
+            .map(|occurrence| {
+                let old = &self.text[occurrence.start..occurrence.end];
+                let text = match occurrence.shorthand {
+                    None => name.to_string(),
+                    Some(Shorthand::Property) => format!("{old}: {name}"),
+                    Some(Shorthand::Import) => format!("{old} as {name}"),
+                    Some(Shorthand::Export) => format!("{name} as {old}"),
+                };
+                (occurrence.start, occurrence.end, text)
+            })
+            .collect())
+    }
+}
+
+/// Checks that renaming `declaration` to `name` keeps every occurrence resolving to the
+/// same declaration: no other `name` may be declared between an occurrence of
+/// `declaration` and its scope, and no reference to another `name` may be in that scope.
+fn check_rename(
+    manager: &ScopeManager,
+    text: &str,
+    occurrences: &[Occurrence],
+    declaration: DeclarationId,
+    name: &str,
+) -> Result<(), String> {
+    let scope = manager.declaration(declaration).scope;
+    for occurrence in occurrences {
+        if occurrence.declaration == declaration {
+            // The scopes from the occurrence up to the declaration must not declare `name`.
+            let mut current = Some(occurrence.scope);
+            while let Some(id) = current {
+                let other = manager.scope(id).declarations.get(name);
+                if matches!(other, Some(other) if *other != declaration) {
+                    return Err(format!("`{name}` is already declared in this scope"));
+                }
+                current = manager.scope(id).parent.filter(|_| id != scope);
+            }
+        } else if !occurrence.is_declaration
+            && &text[occurrence.start..occurrence.end] == name
+            && manager.is_descendant_of(occurrence.scope, scope)
+        {
+            // A reference to an outer `name` would be captured by the renamed declaration.
+            let other = manager.declaration(occurrence.declaration);
+            if other.kind == DeclarationKind::Global
+                || !manager.is_descendant_of(other.scope, scope)
+            {
+                return Err(format!(
+                    "`{name}` is referenced in the scope of the declaration"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects the identifiers which the `ScopeManager` resolved, and the scopes of the
+/// function and class declarations.
+struct Indexer<'a> {
+    manager: &'a ScopeManager,
+    occurrences: Vec<Occurrence>,
+    /// Shorthands by the offset of their identifier.
+    shorthands: HashMap<usize, Shorthand>,
+    /// Scope and range of function and class declarations.
+    containers: HashMap<DeclarationId, (ScopeId, SourceRange)>,
+}
+
+impl Indexer<'_> {
+    fn resolve<T: ESTreeNode>(&self, node: &T) -> Option<(DeclarationId, bool, ScopeId)> {
+        if let Some(declaration) = self.manager.node_declaration(node) {
+            Some((declaration.id, true, declaration.scope))
+        } else {
+            self.manager
+                .node_reference(node)
+                .map(|reference| (reference.declaration, false, reference.scope))
+        }
+    }
+
+    fn record(
+        &mut self,
+        name: &str,
+        range: SourceRange,
+        resolved: Option<(DeclarationId, bool, ScopeId)>,
+    ) {
+        if let Some((declaration, is_declaration, scope)) = resolved {
+            let start = range.start as usize;
+            self.occurrences.push(Occurrence {
+                start,
+                end: start + name.len(),
+                declaration,
+                is_declaration,
+                scope,
+                is_global: self.manager.declaration(declaration).kind == DeclarationKind::Global,
+                shorthand: self.shorthands.get(&start).copied(),
+            });
+        }
+    }
+
+    fn record_container<T: ESTreeNode>(
+        &mut self,
+        id: &Option<Identifier>,
+        node: &T,
+        range: SourceRange,
+    ) {
+        if let (Some(declaration), Some(scope)) = (
+            id.as_ref().and_then(|id| self.manager.node_declaration(id)),
+            self.manager.node_scope(node),
+        ) {
+            self.containers.insert(declaration.id, (scope.id, range));
+        }
+    }
+}
+
+impl<'ast> Visitor<'ast> for Indexer<'_> {
+    fn visit_identifier(&mut self, ast: &'ast Identifier) {
+        self.record(&ast.name, ast.range, self.resolve(ast));
+        if let Some(type_annotation) = &ast.type_annotation {
+            self.visit_type_annotation(type_annotation);
+        }
+    }
+
+    fn visit_jsxidentifier(&mut self, ast: &'ast JSXIdentifier) {
+        self.record(&ast.name, ast.range, self.resolve(ast));
+    }
+
+    fn visit_jsxelement(&mut self, ast: &'ast JSXElement) {
+        self.visit_jsxopening_element(&ast.opening_element);
+        for child in &ast.children {
+            self.visit_jsxchild_item(child);
+        }
+        // The analysis doesn't visit closing elements: resolve their name like the opening one
+        if let (JSXElementName::JSXIdentifier(opening), Some(closing)) =
+            (&ast.opening_element.name, &ast.closing_element)
+        {
+            if let JSXElementName::JSXIdentifier(name) = &closing.name {
+                let resolved = self
+                    .manager
+                    .node_reference(&**opening)
+                    .map(|reference| (reference.declaration, false, reference.scope));
+                self.record(&name.name, name.range, resolved);
+            }
+        }
+    }
+
+    fn visit_property(&mut self, ast: &'ast Property) {
+        if ast.is_shorthand {
+            self.shorthands
+                .insert(ast.range.start as usize, Shorthand::Property);
+        }
+        self.visit_expression(&ast.key);
+        self.visit_expression(&ast.value);
+    }
+
+    fn visit_assignment_property(&mut self, ast: &'ast AssignmentProperty) {
+        if ast.is_shorthand {
+            self.shorthands
+                .insert(ast.range.start as usize, Shorthand::Property);
+        }
+        self.visit_expression(&ast.key);
+        self.visit_pattern(&ast.value);
+    }
+
+    fn visit_import_specifier(&mut self, ast: &'ast ImportSpecifier) {
+        if ast.imported.range.start == ast.local.range.start {
+            self.shorthands
+                .insert(ast.local.range.start as usize, Shorthand::Import);
+        }
+        self.visit_identifier(&ast.imported);
+        self.visit_identifier(&ast.local);
+    }
+
+    fn visit_export_specifier(&mut self, ast: &'ast ExportSpecifier) {
+        self.shorthands
+            .insert(ast.exported.range.start as usize, Shorthand::Export);
+        self.visit_identifier(&ast.exported);
+    }
+
+    fn visit_function_declaration(&mut self, ast: &'ast FunctionDeclaration) {
+        self.record_container(&ast.function.id, &ast.function, ast.range);
+        self.visit_function(&ast.function);
+    }
+
+    fn visit_class_declaration(&mut self, ast: &'ast ClassDeclaration) {
+        self.record_container(&ast.class.id, &ast.class, ast.range);
+        self.visit_class(&ast.class);
+    }
+}
+
+/// The symbols of the declarations of `scope` and of its nested blocks, ordered by position.
+/// Function and class declarations contain the symbols of their own scope.
+fn symbols(
+    manager: &ScopeManager,
+    scope: &Scope,
+    declared: &HashMap<DeclarationId, (usize, usize)>,
+    containers: &HashMap<DeclarationId, (ScopeId, SourceRange)>,
+) -> Vec<Symbol> {
+    let mut result = Vec::new();
+    for id in scope.declarations.values() {
+        let declaration = manager.declaration(*id);
+        let Some(selection) = declared.get(id).copied() else {
+            continue;
+        };
+        let container = containers.get(id);
+        let kind = match (declaration.kind, container) {
+            (DeclarationKind::Class, _) => SYMBOL_CLASS,
+            (DeclarationKind::Function, Some(_)) => SYMBOL_FUNCTION,
+            (DeclarationKind::Const, _) => SYMBOL_CONSTANT,
+            _ => SYMBOL_VARIABLE,
+        };
+        let (range, children) = match container {
+            Some((scope, range)) => (
+                (range.start as usize, range.end.get() as usize),
+                symbols(manager, manager.scope(*scope), declared, containers),
+            ),
+            None => (selection, Vec::new()),
+        };
+        result.push(Symbol {
+            name: declaration.name.clone(),
+            kind,
+            range,
+            selection,
+            children,
+        });
+    }
+    for child in &scope.children {
+        let child = manager.scope(*child);
+        if !matches!(child.kind, ScopeKind::Function | ScopeKind::Class) {
+            result.extend(symbols(manager, child, declared, containers));
+        }
+    }
+    result.sort_by_key(|symbol| symbol.range.0);
+    result
+}
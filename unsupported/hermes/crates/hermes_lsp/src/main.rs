@@ -0,0 +1,30 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! A Language Server Protocol server for JavaScript, communicating over stdio.
+
+mod document;
+mod protocol;
+mod server;
+
+use std::io;
+use std::process;
+
+use server::Server;
+
+fn main() {
+    let stdout = io::stdout();
+    let mut server = Server::new(stdout.lock());
+    let code = match server.run(&mut io::stdin().lock()) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("hermes_lsp: {error}");
+            1
+        }
+    };
+    process::exit(code);
+}
@@ -0,0 +1,453 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+
+use hermes_diagnostics::Diagnostic;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::document::Document;
+use crate::document::Symbol;
+use crate::protocol::error_response;
+use crate::protocol::notification;
+use crate::protocol::read_message;
+use crate::protocol::response;
+use crate::protocol::write_message;
+use crate::protocol::ResponseError;
+use crate::protocol::INVALID_REQUEST;
+use crate::protocol::METHOD_NOT_FOUND;
+use crate::protocol::PARSE_ERROR;
+use crate::protocol::REQUEST_FAILED;
+use crate::protocol::SERVER_NOT_INITIALIZED;
+
+/// Globals of every JavaScript environment, used unless the client passes its own
+/// in the `globals` of the `initializationOptions`.
+const DEFAULT_GLOBALS: &[&str] = &[
+    "Array",
+    "ArrayBuffer",
+    "BigInt",
+    "Boolean",
+    "console",
+    "Date",
+    "decodeURIComponent",
+    "encodeURIComponent",
+    "Error",
+    "globalThis",
+    "Infinity",
+    "isFinite",
+    "isNaN",
+    "JSON",
+    "Map",
+    "Math",
+    "NaN",
+    "Number",
+    "Object",
+    "parseFloat",
+    "parseInt",
+    "Promise",
+    "Proxy",
+    "RangeError",
+    "Reflect",
+    "RegExp",
+    "Set",
+    "String",
+    "Symbol",
+    "SyntaxError",
+    "TypeError",
+    "undefined",
+    "WeakMap",
+    "WeakSet",
+];
+
+/// Words which can't be the name of a binding.
+const RESERVED_WORDS: &[&str] = &[
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// A language server answering the messages of a single client.
+pub struct Server<W> {
+    writer: W,
+    documents: HashMap<String, Document>,
+    globals: Vec<String>,
+    initialized: bool,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            documents: HashMap::new(),
+            globals: DEFAULT_GLOBALS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            initialized: false,
+            shutdown: false,
+        }
+    }
+
+    /// Serves the messages of `reader` until the `exit` notification, and returns the exit code
+    /// of the server: 0 if the client requested a shutdown first, 1 otherwise.
+    pub fn run<R: BufRead>(&mut self, reader: &mut R) -> io::Result<i32> {
+        while let Some(content) = read_message(reader)? {
+            let message: Value = match serde_json::from_slice(&content) {
+                Ok(message) => message,
+                Err(error) => {
+                    let error = ResponseError::new(PARSE_ERROR, error.to_string());
+                    self.send(error_response(Value::Null, error))?;
+                    continue;
+                }
+            };
+            let params = message.get("params").cloned().unwrap_or(Value::Null);
+            match (
+                message.get("id"),
+                message.get("method").and_then(Value::as_str),
+            ) {
+                (Some(id), Some(method)) => {
+                    let message = match self.request(method, &params) {
+                        Ok(result) => response(id.clone(), result),
+                        Err(error) => error_response(id.clone(), error),
+                    };
+                    self.send(message)?;
+                }
+                (None, Some("exit")) => return Ok(if self.shutdown { 0 } else { 1 }),
+                (None, Some(method)) => self.notification(method, &params)?,
+                // A response, but the server doesn't send requests
+                (Some(_), None) => {}
+                (None, None) => {
+                    let error = ResponseError::new(INVALID_REQUEST, "Expected a method");
+                    self.send(error_response(Value::Null, error))?;
+                }
+            }
+        }
+        Ok(1)
+    }
+
+    fn send(&mut self, message: Value) -> io::Result<()> {
+        write_message(&mut self.writer, &message)
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, ResponseError> {
+        if method == "initialize" {
+            return self.initialize(params);
+        }
+        if !self.initialized {
+            return Err(ResponseError::new(
+                SERVER_NOT_INITIALIZED,
+                "The server is not initialized",
+            ));
+        }
+        if self.shutdown {
+            return Err(ResponseError::new(
+                INVALID_REQUEST,
+                "The server is shutting down",
+            ));
+        }
+        match method {
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/documentSymbol" => self.document_symbol(params),
+            "textDocument/rename" => self.rename(params),
+            _ => Err(ResponseError::new(
+                METHOD_NOT_FOUND,
+                format!("Unsupported request `{method}`"),
+            )),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> io::Result<()> {
+        if !self.initialized || self.shutdown {
+            return Ok(());
+        }
+        let uri = params
+            .pointer("/textDocument/uri")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        match (method, uri) {
+            ("textDocument/didOpen", Some(uri)) => {
+                if let Some(text) = params.pointer("/textDocument/text").and_then(Value::as_str) {
+                    self.update(uri, text.to_string())?;
+                }
+            }
+            ("textDocument/didChange", Some(uri)) => {
+                // With full synchronization, the last change is the whole new text
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Value::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Value::as_str);
+                if let Some(text) = text {
+                    self.update(uri, text.to_string())?;
+                }
+            }
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(&uri);
+                self.send(notification(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                ))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn initialize(&mut self, params: &Value) -> Result<Value, ResponseError> {
+        if self.initialized {
+            return Err(ResponseError::new(
+                INVALID_REQUEST,
+                "The server is already initialized",
+            ));
+        }
+        if let Some(globals) = params
+            .pointer("/initializationOptions/globals")
+            .and_then(Value::as_array)
+        {
+            self.globals = globals
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect();
+        }
+        self.initialized = true;
+        Ok(json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "definitionProvider": true,
+                "referencesProvider": true,
+                "documentSymbolProvider": true,
+                "renameProvider": true,
+            },
+            "serverInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        }))
+    }
+
+    /// Analyzes the new text of a document and publishes its diagnostics.
+    fn update(&mut self, uri: String, text: String) -> io::Result<()> {
+        let document = Document::new(text, self.globals.clone());
+        let diagnostics: Vec<Value> = document
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(&document, &uri, diagnostic))
+            .collect();
+        self.documents.insert(uri.clone(), document);
+        self.send(notification(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        ))
+    }
+
+    /// The URI and document of a `TextDocumentIdentifier` param.
+    fn document<'a>(&'a self, params: &'a Value) -> Result<(&'a str, &'a Document), ResponseError> {
+        let uri = params
+            .pointer("/textDocument/uri")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ResponseError::invalid_params("Expected a `textDocument`"))?;
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| ResponseError::invalid_params(format!("Unknown document `{uri}`")))?;
+        Ok((uri, document))
+    }
+
+    /// The URI, document and byte offset of a `TextDocumentPositionParams`.
+    fn position<'a>(
+        &'a self,
+        params: &'a Value,
+    ) -> Result<(&'a str, &'a Document, usize), ResponseError> {
+        let (uri, document) = self.document(params)?;
+        let offset = params
+            .get("position")
+            .and_then(|position| document.offset(position))
+            .ok_or_else(|| ResponseError::invalid_params("Expected a valid `position`"))?;
+        Ok((uri, document, offset))
+    }
+
+    fn definition(&self, params: &Value) -> Result<Value, ResponseError> {
+        let (uri, document, offset) = self.position(params)?;
+        let Some(occurrence) = document.occurrence_at(offset) else {
+            return Ok(Value::Null);
+        };
+        let locations: Vec<Value> = document
+            .occurrences_of(occurrence.declaration)
+            .filter(|occurrence| occurrence.is_declaration)
+            .map(|occurrence| location(document, uri, occurrence.start, occurrence.end))
+            .collect();
+        Ok(if locations.is_empty() {
+            Value::Null
+        } else {
+            Value::Array(locations)
+        })
+    }
+
+    fn references(&self, params: &Value) -> Result<Value, ResponseError> {
+        let (uri, document, offset) = self.position(params)?;
+        let include_declaration = params
+            .pointer("/context/includeDeclaration")
+            .and_then(Value::as_bool)
+            .unwrap_or(true);
+        let Some(occurrence) = document.occurrence_at(offset) else {
+            return Ok(Value::Null);
+        };
+        Ok(Value::Array(
+            document
+                .occurrences_of(occurrence.declaration)
+                .filter(|occurrence| include_declaration || !occurrence.is_declaration)
+                .map(|occurrence| location(document, uri, occurrence.start, occurrence.end))
+                .collect(),
+        ))
+    }
+
+    fn document_symbol(&self, params: &Value) -> Result<Value, ResponseError> {
+        let (_, document) = self.document(params)?;
+        Ok(document_symbols(document, document.symbols()))
+    }
+
+    fn rename(&self, params: &Value) -> Result<Value, ResponseError> {
+        let (uri, document, offset) = self.position(params)?;
+        let name = params
+            .get("newName")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ResponseError::invalid_params("Expected a `newName`"))?;
+        if !is_identifier(name) {
+            return Err(ResponseError::invalid_params(format!(
+                "`{name}` is not a valid identifier"
+            )));
+        }
+        let Some(occurrence) = document.occurrence_at(offset) else {
+            return Ok(Value::Null);
+        };
+        if occurrence.is_global {
+            return Err(ResponseError::new(
+                REQUEST_FAILED,
+                "Globals can't be renamed",
+            ));
+        }
+        let edits: Vec<Value> = document
+            .rename(occurrence.declaration, name)
+            .map_err(|message| ResponseError::new(REQUEST_FAILED, message))?
+            .into_iter()
+            .map(|(start, end, text)| json!({ "range": document.range(start, end), "newText": text }))
+            .collect();
+        Ok(json!({ "changes": { uri: edits } }))
+    }
+}
+
+fn location(document: &Document, uri: &str, start: usize, end: usize) -> Value {
+    json!({ "uri": uri, "range": document.range(start, end) })
+}
+
+fn document_symbols(document: &Document, symbols: &[Symbol]) -> Value {
+    symbols
+        .iter()
+        .map(|symbol| {
+            json!({
+                "name": symbol.name,
+                "kind": symbol.kind,
+                "range": document.range(symbol.range.0, symbol.range.1),
+                "selectionRange": document.range(symbol.selection.0, symbol.selection.1),
+                "children": document_symbols(document, &symbol.children),
+            })
+        })
+        .collect()
+}
+
+fn lsp_diagnostic(document: &Document, uri: &str, diagnostic: &Diagnostic) -> Value {
+    let span = diagnostic.span();
+    let mut result = json!({
+        "range": document.range(span.offset(), span.offset() + span.len()),
+        "severity": diagnostic.severity().lsp_severity() as u8,
+        "source": "hermes",
+        "message": diagnostic.message().to_string(),
+    });
+    if let Some(code) = diagnostic.code() {
+        result["code"] = json!(code.as_str());
+    }
+    let related: Vec<Value> = diagnostic
+        .related_information()
+        .iter()
+        .map(|related| {
+            let (start, len) = (related.span.offset(), related.span.len());
+            json!({
+                "location": location(document, uri, start, start + len),
+                "message": related.message.to_string(),
+            })
+        })
+        .collect();
+    if !related.is_empty() {
+        result["relatedInformation"] = Value::Array(related);
+    }
+    result
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    (first.is_alphabetic() || first == '_' || first == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        && !RESERVED_WORDS.contains(&name)
+}
@@ -0,0 +1,306 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::ChildStdout;
+use std::process::Command;
+use std::process::Stdio;
+
+use serde_json::json;
+use serde_json::Value;
+
+const URI: &str = "file:///test.js";
+
+/// A client scripting a session with the server over its stdio.
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_hermes_lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Self {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let content = message.to_string();
+        write!(
+            self.stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )
+        .unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut content = vec![0; length];
+        self.stdout.read_exact(&mut content).unwrap();
+        serde_json::from_slice(&content).unwrap()
+    }
+
+    /// Sends a request and returns its response.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        let response = self.receive();
+        assert_eq!(json!(id), response["id"]);
+        response
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn initialize(&mut self) {
+        let response = self.request(
+            "initialize",
+            json!({
+                "capabilities": {},
+                "initializationOptions": { "globals": ["console"] },
+            }),
+        );
+        assert_eq!(
+            json!(true),
+            response["result"]["capabilities"]["renameProvider"]
+        );
+        self.notify("initialized", json!({}));
+    }
+
+    /// Opens a document and returns its published diagnostics.
+    fn open(&mut self, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "javascript", "version": 1, "text": text },
+            }),
+        );
+        let notification = self.receive();
+        assert_eq!(
+            json!("textDocument/publishDiagnostics"),
+            notification["method"]
+        );
+        assert_eq!(json!(URI), notification["params"]["uri"]);
+        notification["params"]["diagnostics"].clone()
+    }
+
+    /// Sends the `exit` notification and returns the exit code of the server.
+    fn exit(mut self) -> i32 {
+        self.notify("exit", Value::Null);
+        self.child.wait().unwrap().code().unwrap()
+    }
+}
+
+fn position(line: u32, character: u32) -> Value {
+    json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+}
+
+fn range(line: u32, start: u32, end: u32) -> Value {
+    json!({
+        "start": { "line": line, "character": start },
+        "end": { "line": line, "character": end },
+    })
+}
+
+#[test]
+fn navigation() {
+    let mut client = Client::start();
+    client.initialize();
+    let diagnostics = client.open(
+        "function add(a, b) {\n  return a + b;\n}\nconst total = add(1, 2);\nconsole.log(total, missing);\n",
+    );
+    assert_eq!(
+        json!([{
+            "range": range(4, 19, 26),
+            "severity": 2,
+            "code": "undefined-variable",
+            "source": "hermes",
+            "message": "Undefined variable",
+        }]),
+        diagnostics
+    );
+
+    let definition = client.request("textDocument/definition", position(3, 15));
+    assert_eq!(
+        json!([{ "uri": URI, "range": range(0, 9, 12) }]),
+        definition["result"]
+    );
+
+    let mut params = position(4, 12);
+    params["context"] = json!({ "includeDeclaration": true });
+    let references = client.request("textDocument/references", params);
+    assert_eq!(
+        json!([
+            { "uri": URI, "range": range(3, 6, 11) },
+            { "uri": URI, "range": range(4, 12, 17) },
+        ]),
+        references["result"]
+    );
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let names: Vec<(&str, u64, usize)> = symbols["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            (
+                symbol["name"].as_str().unwrap(),
+                symbol["kind"].as_u64().unwrap(),
+                symbol["children"].as_array().unwrap().len(),
+            )
+        })
+        .collect();
+    assert_eq!(vec![("add", 12, 2), ("total", 14, 0)], names);
+    assert_eq!(
+        json!({
+            "start": { "line": 0, "character": 0 },
+            "end": { "line": 2, "character": 1 },
+        }),
+        symbols["result"][0]["range"]
+    );
+
+    let mut params = position(1, 9);
+    params["newName"] = json!("x");
+    let rename = client.request("textDocument/rename", params);
+    assert_eq!(
+        json!({ "changes": { URI: [
+            { "range": range(0, 13, 14), "newText": "x" },
+            { "range": range(1, 9, 10), "newText": "x" },
+        ] } }),
+        rename["result"]
+    );
+
+    let mut params = position(4, 2);
+    params["newName"] = json!("log");
+    let rename = client.request("textDocument/rename", params);
+    assert_eq!(json!(-32803), rename["error"]["code"]);
+
+    let shutdown = client.request("shutdown", Value::Null);
+    assert_eq!(Value::Null, shutdown["result"]);
+    assert_eq!(0, client.exit());
+}
+
+#[test]
+fn rename_shorthand() {
+    let mut client = Client::start();
+    client.initialize();
+    let diagnostics = client.open("const value = 1;\nconst object = {value};\n");
+    assert_eq!(json!([]), diagnostics);
+
+    let mut params = position(0, 6);
+    params["newName"] = json!("count");
+    let rename = client.request("textDocument/rename", params);
+    assert_eq!(
+        json!({ "changes": { URI: [
+            { "range": range(0, 6, 11), "newText": "count" },
+            { "range": range(1, 16, 21), "newText": "value: count" },
+        ] } }),
+        rename["result"]
+    );
+
+    let mut params = position(0, 6);
+    params["newName"] = json!("class");
+    let rename = client.request("textDocument/rename", params);
+    assert_eq!(json!(-32602), rename["error"]["code"]);
+
+    let shutdown = client.request("shutdown", Value::Null);
+    assert_eq!(Value::Null, shutdown["result"]);
+    assert_eq!(0, client.exit());
+}
+
+#[test]
+fn rename_conflicts() {
+    let mut client = Client::start();
+    client.initialize();
+    let diagnostics =
+        client.open("let a = 1;\nlet b = 2;\nfunction f() {\n  let c = 3;\n  return a + c;\n}\n");
+    assert_eq!(json!([]), diagnostics);
+
+    // `b` is declared in the same scope, and `c` in the scope of a reference to `a`
+    for name in ["b", "c"] {
+        let mut params = position(0, 4);
+        params["newName"] = json!(name);
+        let rename = client.request("textDocument/rename", params);
+        assert_eq!(json!(-32803), rename["error"]["code"]);
+    }
+
+    // The reference to `a` would refer to the renamed `c`
+    let mut params = position(3, 6);
+    params["newName"] = json!("a");
+    let rename = client.request("textDocument/rename", params);
+    assert_eq!(json!(-32803), rename["error"]["code"]);
+
+    let mut params = position(3, 6);
+    params["newName"] = json!("b");
+    let rename = client.request("textDocument/rename", params);
+    assert_eq!(
+        json!({ "changes": { URI: [
+            { "range": range(3, 6, 7), "newText": "b" },
+            { "range": range(4, 13, 14), "newText": "b" },
+        ] } }),
+        rename["result"]
+    );
+
+    let shutdown = client.request("shutdown", Value::Null);
+    assert_eq!(Value::Null, shutdown["result"]);
+    assert_eq!(0, client.exit());
+}
+
+#[test]
+fn lifecycle() {
+    let mut client = Client::start();
+    let response = client.request("shutdown", Value::Null);
+    assert_eq!(json!(-32002), response["error"]["code"]);
+
+    client.initialize();
+    let diagnostics = client.open("let x = ;\n");
+    assert_eq!(json!(1), diagnostics[0]["severity"]);
+    assert_eq!(json!("syntax-error"), diagnostics[0]["code"]);
+    assert_eq!(json!(0), diagnostics[0]["range"]["start"]["line"]);
+
+    let response = client.request("workspace/symbol", json!({ "query": "" }));
+    assert_eq!(json!(-32601), response["error"]["code"]);
+
+    // Exiting without a shutdown request is an error
+    assert_eq!(1, client.exit());
+}
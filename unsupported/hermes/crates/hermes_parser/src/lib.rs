@@ -13,10 +13,12 @@ use generated_extension::convert_smloc;
 pub use generated_extension::Comment;
 use generated_extension::Context;
 use generated_extension::FromHermes;
+use hermes::parser::DiagKind;
 use hermes::parser::HermesParser;
 pub use hermes::parser::ParserDialect;
 pub use hermes::parser::ParserFlags;
 use hermes::utf::utf8_with_surrogates_to_string;
+use hermes_diagnostics::codes;
 use hermes_diagnostics::Diagnostic;
 use hermes_estree::Program;
 use hermes_estree::SourceRange;
@@ -42,13 +44,17 @@ pub fn parse(
             .map(|diag| {
                 let message = utf8_with_surrogates_to_string(diag.message.as_slice()).unwrap();
                 let start = convert_smloc(&cx, diag.loc) as u32;
-                Diagnostic::invalid_syntax(
-                    message,
-                    SourceRange {
-                        start,
-                        end: std::num::NonZeroU32::new(start + 1).unwrap(),
-                    },
-                )
+                let range = SourceRange {
+                    start,
+                    end: std::num::NonZeroU32::new(start + 1).unwrap(),
+                };
+                match diag.diag_kind {
+                    DiagKind::Error => {
+                        Diagnostic::invalid_syntax(message, range).with_code(codes::SYNTAX_ERROR)
+                    }
+                    DiagKind::Warning => Diagnostic::warning(message, range),
+                    DiagKind::Remark | DiagKind::Note => Diagnostic::info(message, range),
+                }
             })
             .collect());
     }
@@ -5,6 +5,7 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use hermes_diagnostics::codes;
 use hermes_diagnostics::Diagnostic;
 use hermes_estree::AssignmentOperator;
 use hermes_estree::AssignmentPropertyOrRestElement;
@@ -96,10 +97,10 @@ impl Analyzer {
                         .add_reference(reference.scope, reference.kind, declaration.id);
                 self.manager.node_references.insert(reference.ast, id);
             } else {
-                self.manager.diagnostics.push(Diagnostic::invalid_syntax(
-                    "Undefined variable",
-                    reference.range,
-                ));
+                self.manager.diagnostics.push(
+                    Diagnostic::warning("Undefined variable", reference.range)
+                        .with_code(codes::UNDEFINED_VARIABLE),
+                );
             }
         }
         self.manager
@@ -342,10 +343,13 @@ impl Visitor<'_> for Analyzer {
     ) {
         let kind = self.manager.scope(self.current).kind;
         if kind != ScopeKind::Module {
-            self.manager.diagnostics.push(Diagnostic::invalid_syntax(
-                "`import` declarations are only allowed at the top-level of a module",
-                ast.range(),
-            ))
+            self.manager.diagnostics.push(
+                Diagnostic::invalid_syntax(
+                    "`import` declarations are only allowed at the top-level of a module",
+                    ast.range(),
+                )
+                .with_code(codes::IMPORT_NOT_AT_TOP_LEVEL),
+            )
         }
         match ast {
             ImportDeclarationSpecifier::ImportDefaultSpecifier(specifier) => {
@@ -545,7 +549,7 @@ impl Visitor<'_> for Analyzer {
                         self.manager.diagnostics.push(Diagnostic::invalid_syntax(
                             "Invalid AssignmentExpression, expected left-hand side to be a Pattern or MemberExpression",
                             ast.range
-                        ));
+                        ).with_code(codes::INVALID_ASSIGNMENT_TARGET));
                     }
                 },
             }
@@ -561,16 +565,19 @@ impl Visitor<'_> for Analyzer {
                     self.manager.diagnostics.push(Diagnostic::invalid_syntax(
                         "Expected AssignmentExpression.left to be an Identifier when using operator {}",
                         pat.range()
-                    ));
+                    ).with_code(codes::INVALID_ASSIGNMENT_TARGET));
                     // Visit the right-hand side anyway to find any errors there
                     self.visit_expression(&ast.right);
                     return;
                 }
             } else {
-                self.manager.diagnostics.push(Diagnostic::invalid_syntax(
-                    "Expected AssignmentExpression.left to be an Identifier when using operator {}",
-                    ast.range,
-                ));
+                self.manager.diagnostics.push(
+                    Diagnostic::invalid_syntax(
+                        "Expected AssignmentExpression.left to be an Identifier when using operator {}",
+                        ast.range,
+                    )
+                    .with_code(codes::INVALID_ASSIGNMENT_TARGET),
+                );
                 // Visit the right-hand side anyway to find any errors there
                 self.visit_expression(&ast.right);
                 return;
@@ -608,10 +615,13 @@ impl Visitor<'_> for Analyzer {
                     .insert(AstNode::from(label_node), id);
             }
         } else {
-            self.manager.diagnostics.push(Diagnostic::invalid_syntax(
-                "Non-syntactic break, could not resolve break target",
-                ast.range,
-            ));
+            self.manager.diagnostics.push(
+                Diagnostic::invalid_syntax(
+                    "Non-syntactic break, could not resolve break target",
+                    ast.range,
+                )
+                .with_code(codes::INVALID_BREAK),
+            );
         }
     }
 
@@ -643,10 +653,13 @@ impl Visitor<'_> for Analyzer {
         {
             let id = label.id;
             if label.kind != LabelKind::Loop {
-                self.manager.diagnostics.push(Diagnostic::invalid_syntax(
-                    "Invalid continue statement, the named label must be for a loop",
-                    range,
-                ));
+                self.manager.diagnostics.push(
+                    Diagnostic::invalid_syntax(
+                        "Invalid continue statement, the named label must be for a loop",
+                        range,
+                    )
+                    .with_code(codes::INVALID_CONTINUE),
+                );
             }
             self.manager.node_labels.insert(AstNode::from(ast), id);
             if let Some(label_node) = &ast.label {
@@ -655,10 +668,13 @@ impl Visitor<'_> for Analyzer {
                     .insert(AstNode::from(label_node), id);
             }
         } else {
-            self.manager.diagnostics.push(Diagnostic::invalid_syntax(
-                "Non-syntactic continue, could not resolve continue target",
-                range,
-            ));
+            self.manager.diagnostics.push(
+                Diagnostic::invalid_syntax(
+                    "Non-syntactic continue, could not resolve continue target",
+                    range,
+                )
+                .with_code(codes::INVALID_CONTINUE),
+            );
         }
     }
 
@@ -867,10 +883,13 @@ impl Visitor<'_> for Analyzer {
                     // TODO: this likely indicates a parse error, since a valid parse
                     // should never result in an empty JSXIdentifier node. but just in
                     // case we report this rather than silently fail
-                    self.manager.diagnostics.push(Diagnostic::invalid_syntax(
-                        "Expected JSXOpeningElement.name to be non-empty",
-                        name.range,
-                    ));
+                    self.manager.diagnostics.push(
+                        Diagnostic::invalid_syntax(
+                            "Expected JSXOpeningElement.name to be non-empty",
+                            name.range,
+                        )
+                        .with_code(codes::EMPTY_JSX_NAME),
+                    );
                 }
             }
             JSXElementName::JSXMemberExpression(name) => {
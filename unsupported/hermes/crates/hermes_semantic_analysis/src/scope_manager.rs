@@ -5,6 +5,7 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use hermes_diagnostics::codes;
 use hermes_diagnostics::Diagnostic;
 use hermes_estree::BreakStatement;
 use hermes_estree::ContinueStatement;
@@ -313,8 +314,10 @@ impl ScopeManager {
                     let declaration = self.declaration(*declaration);
                     if is_block_scoped_declaration(declaration.kind) {
                         // Var cannot be declared in the same scope as let/const/class/import/etc
-                        self.diagnostics
-                            .push(Diagnostic::invalid_syntax("Duplicate declaration", range));
+                        self.diagnostics.push(
+                            Diagnostic::invalid_syntax("Duplicate declaration", range)
+                                .with_code(codes::DUPLICATE_DECLARATION),
+                        );
                     }
                 } else if hoisted_scope_id != scope_id {
                     if let Some(declaration) = self.scope(hoisted_scope_id).declarations.get(&name)
@@ -322,8 +325,10 @@ impl ScopeManager {
                         let declaration = self.declaration(*declaration);
                         if is_block_scoped_declaration(declaration.kind) {
                             // Var cannot *hoist* to the same scope as let/const/class/import/etc
-                            self.diagnostics
-                                .push(Diagnostic::invalid_syntax("Duplicate declaration", range));
+                            self.diagnostics.push(
+                                Diagnostic::invalid_syntax("Duplicate declaration", range)
+                                    .with_code(codes::DUPLICATE_DECLARATION),
+                            );
                         }
                     }
                 }
@@ -350,8 +355,10 @@ impl ScopeManager {
                 // not want to report a "cannot find declaration for `x`" reference error just because there
                 // were duplicate declarations of `x`.
                 if let Some(_declaration) = scope.declarations.get(&name) {
-                    self.diagnostics
-                        .push(Diagnostic::invalid_syntax("Duplicate declaration", range));
+                    self.diagnostics.push(
+                        Diagnostic::invalid_syntax("Duplicate declaration", range)
+                            .with_code(codes::DUPLICATE_DECLARATION),
+                    );
                 }
             }
             DeclarationKind::Global => {
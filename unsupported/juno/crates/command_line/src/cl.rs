@@ -9,9 +9,13 @@ use std::fmt::Write;
 use std::process::exit;
 use std::rc::Rc;
 
+use crate::completion::completion_script;
+use crate::completion::Shell;
 use crate::opt::*;
+use crate::parser::program_name;
 use crate::parser::CommandLineIntent;
 use crate::parser::Parser;
+use crate::response_file::expand_response_files;
 
 #[derive(Debug)]
 struct OptionCategory {
@@ -27,6 +31,7 @@ pub struct CommandLine {
 
     help: Option<Opt<bool>>,
     help_hidden: Option<Opt<bool>>,
+    completion: Option<Opt<Shell>>,
 }
 
 impl CommandLine {
@@ -37,6 +42,7 @@ impl CommandLine {
             categories: Default::default(),
             help: None,
             help_hidden: None,
+            completion: None,
         };
 
         res.add_category("Generic Options", None);
@@ -58,6 +64,17 @@ impl CommandLine {
                 ..Default::default()
             },
         ));
+        res.completion = Some(Opt::new_enum(
+            &mut res,
+            OptDesc {
+                long: Some("completion"),
+                desc: Some("Print a completion script for the shell"),
+                values: Some(&[("bash", Shell::Bash, "Bash"), ("zsh", Shell::Zsh, "Zsh")]),
+                value_desc: Some("shell"),
+                hidden: Hidden::Yes,
+                ..Default::default()
+            },
+        ));
 
         res
     }
@@ -70,9 +87,27 @@ impl CommandLine {
         self.categories.len() - 1
     }
 
-    /// Parse the specified arguments.
+    /// Set the value of the option named `name`, as if "--name=value" was
+    /// specified before the arguments, e.g. from a configuration file. Enum
+    /// options are seeded by the name of the alternative without a value.
+    /// Seeded values don't count as occurrences, and are replaced by the
+    /// values specified in the arguments. Must be called before parsing.
+    pub fn seed(&self, name: &str, value: Option<&str>) -> Result<(), String> {
+        let long = name.len() > 1;
+        let (opt, eq) = match self.find_option(name, long) {
+            Some(found) => found,
+            None => return Err(format!("Unknown option '{}'", name)),
+        };
+        opt.seed_value(value, eq)
+            .map_err(|e| format!("for the {}{} option: {}", cond!(long, "--", "-"), name, e))
+    }
+
+    /// Parse the specified arguments, after replacing the "@file" arguments
+    /// with the arguments in the file.
     pub fn parse(self, args: &[String]) -> Result<CommandLineIntent, String> {
-        let parser = Parser::new(self, args);
+        let args =
+            expand_response_files(args).map_err(|e| format!("{}: {}", program_name(args), e))?;
+        let parser = Parser::new(self, &args);
         parser.parse()
     }
 
@@ -88,6 +123,10 @@ impl CommandLine {
                 println!("{}", s);
                 exit(0);
             }
+            Ok(CommandLineIntent::Completion(s)) => {
+                print!("{}", s);
+                exit(0);
+            }
             Ok(CommandLineIntent::Normal) => {}
         }
     }
@@ -116,6 +155,23 @@ impl CommandLine {
         None
     }
 
+    /// Find the subcommand option, if there is one.
+    pub(crate) fn find_subcommand_option(&self) -> Option<&Rc<dyn CLOption>> {
+        self.opts.iter().find(|opt| opt.info().subcommand)
+    }
+
+    /// Find the subcommand with the specified name.
+    pub(crate) fn find_subcommand(&self, name: &str) -> Option<(&Rc<dyn CLOption>, EqName)> {
+        let opt = self.find_subcommand_option()?;
+        let index = opt
+            .info()
+            .values_desc
+            .unwrap()
+            .iter()
+            .position(|p| p.0 == name)?;
+        Some((opt, EqName::EnumValue(index)))
+    }
+
     /// Find the next positional option that can still accept values.
     pub(crate) fn next_positional(&self) -> Option<(&Rc<dyn CLOption>, EqName)> {
         self.opts
@@ -142,6 +198,20 @@ impl CommandLine {
         None
     }
 
+    /// Check whether a completion script has been requested, and for which
+    /// shell.
+    pub(crate) fn is_completion_requested(&self) -> Option<Shell> {
+        match &self.completion {
+            Some(c) if c.occurrences() != 0 => Some(**c),
+            _ => None,
+        }
+    }
+
+    /// Build and return the completion script for the shell.
+    pub(crate) fn build_completion(&self, prog_name: &str, shell: Shell) -> String {
+        completion_script(self, prog_name, shell)
+    }
+
     /// Build and return the help string.
     pub(crate) fn build_help(&self, prog_name: &str, show_hidden: bool) -> String {
         self.build_help_impl(prog_name, show_hidden).unwrap()
@@ -164,6 +234,8 @@ impl CommandLine {
         let mut cats: Vec<Vec<(String, Option<String>)>> = Vec::new();
         // Positional argument description.
         let mut pos_descs = String::new();
+        // Names and descriptions of the subcommands.
+        let mut subcommands: &[(String, String)] = &[];
 
         cats.resize(self.categories.len(), Vec::new());
 
@@ -180,6 +252,10 @@ impl CommandLine {
         for opt in opts {
             let info = opt.info();
 
+            if info.subcommand {
+                subcommands = info.values_desc.unwrap();
+                continue;
+            }
             if info.is_positional() {
                 if !pos_descs.is_empty() {
                     pos_descs.push(' ');
@@ -260,9 +336,14 @@ impl CommandLine {
                     }
                 }
             }
+            // Mention the environment variable providing the default.
+            let desc = match info.env {
+                Some(env) => format!("{} [env: {}]", info.desc.unwrap_or(""), env),
+                None => info.desc.unwrap_or("").to_string(),
+            };
             if info.is_enum_value() {
                 // An enum value generates descriptions for all alternatives.
-                cats[cat_index].push((left, Some(info.desc.unwrap_or("").to_string())));
+                cats[cat_index].push((left, Some(desc)));
                 for alt in info.values_desc.unwrap() {
                     cats[cat_index].push((format!("    ={}", alt.0), Some(format!("  {}", alt.1))));
                 }
@@ -281,7 +362,7 @@ impl CommandLine {
                     ));
                 }
             } else {
-                cats[cat_index].push((left, Some(info.desc.unwrap_or("").to_string())));
+                cats[cat_index].push((left, Some(desc)));
             }
         }
 
@@ -296,6 +377,9 @@ impl CommandLine {
         let mut out = String::new();
         writeln!(out, "OVERVIEW: {}\n", self.desc)?;
         write!(out, "USAGE: {}", prog_name)?;
+        if !subcommands.is_empty() {
+            write!(out, " [subcommand]")?;
+        }
         if have_options {
             write!(out, " [options]")?;
         }
@@ -303,6 +387,14 @@ impl CommandLine {
             write!(out, " {}", pos_descs)?;
         }
         writeln!(out, "\n")?;
+        if !subcommands.is_empty() {
+            writeln!(out, "SUBCOMMANDS:\n")?;
+            let name_width = subcommands.iter().fold(0, |w, p| w.max(p.0.len()));
+            for (name, desc) in subcommands {
+                writeln!(out, "  {0:1$} - {2}", name, name_width, desc)?;
+            }
+            writeln!(out)?;
+        }
         writeln!(out, "OPTIONS:\n")?;
 
         for (left, right) in &descs {
@@ -521,6 +613,58 @@ Special options:
 
   -m, --memory=<number> - Set maximum memory amount
   --port=<port number>  - The port
+";
+        assert_eq!(options.build_help("test", false), OUTPUT);
+    }
+
+    #[test]
+    fn test_subcommands_help() {
+        let mut options = CommandLine::new("juno");
+
+        Opt::<TestEnum>::new_subcommand(
+            &mut options,
+            OptDesc {
+                values: Some(&[
+                    ("js", TestEnum::GenJS, "Generate JS"),
+                    ("ast", TestEnum::GenAST, "Generate AST"),
+                    ("ir", TestEnum::GenIR, "Generate IR"),
+                ]),
+                ..Default::default()
+            },
+        );
+        Opt::<u32>::new(
+            &mut options,
+            OptDesc {
+                long: Some("memory"),
+                short: Some("m"),
+                desc: Some("Set maximum memory amount"),
+                value_desc: Some("number"),
+                env: Some("JUNO_MEMORY"),
+                ..Default::default()
+            },
+        );
+        Opt::<String>::new_list(
+            &mut options,
+            OptDesc {
+                value_desc: Some("file"),
+                ..Default::default()
+            },
+        );
+
+        static OUTPUT: &str = r"OVERVIEW: juno
+
+USAGE: test [subcommand] [options] <file>*
+
+SUBCOMMANDS:
+
+  js  - Generate JS
+  ast - Generate AST
+  ir  - Generate IR
+
+OPTIONS:
+
+  -h, --help            - Display available options (-help-hidden for more)
+  -m, --memory=<number> - Set maximum memory amount [env: JUNO_MEMORY]
 ";
         assert_eq!(options.build_help("test", false), OUTPUT);
     }
@@ -0,0 +1,522 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::fmt::Write;
+
+use crate::cl::CommandLine;
+use crate::opt::*;
+
+/// A shell for which a completion script can be generated.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+}
+
+/// How the value of an option is completed.
+enum ValueCompletion<'a> {
+    /// One of the listed words.
+    Words(Vec<&'a str>),
+    /// A file name.
+    File,
+}
+
+/// The completion of a single visible option.
+struct OptCompletion<'a> {
+    /// The names with their dashes, e.g. "-o" and "--out".
+    names: Vec<String>,
+    desc: &'a str,
+    /// How the value is completed, `None` if a value is disallowed.
+    value: Option<ValueCompletion<'a>>,
+    /// Whether the value can be omitted.
+    optional: bool,
+    value_desc: &'a str,
+    /// Whether the option can be specified more than once.
+    repeated: bool,
+}
+
+/// The information needed to generate completion scripts.
+struct Completions<'a> {
+    opts: Vec<OptCompletion<'a>>,
+    /// The alternatives of the enum options, which exclude each other.
+    enum_opts: Vec<Vec<(String, &'a str)>>,
+    /// Names and descriptions of the subcommands.
+    subcommands: &'a [(String, String)],
+    /// Whether there are positional arguments, which are completed as files.
+    positional: bool,
+}
+
+/// Prefix an option name with "-" or "--" depending on its length.
+fn dashed(name: &str) -> String {
+    format!("{}{}", cond!(name.len() > 1, "--", "-"), name)
+}
+
+impl<'a> Completions<'a> {
+    fn new(cl: &'a CommandLine) -> Completions<'a> {
+        let mut res = Completions {
+            opts: Vec::new(),
+            enum_opts: Vec::new(),
+            subcommands: &[],
+            positional: false,
+        };
+        for opt in cl.as_slice() {
+            let info = opt.info();
+            if info.subcommand {
+                res.subcommands = info.values_desc.unwrap();
+            } else if info.is_positional() {
+                res.positional = true;
+            } else if info.hidden != Hidden::No {
+                // Hidden options are not completed.
+            } else if info.is_enum_option() {
+                res.enum_opts.push(
+                    info.values_desc
+                        .unwrap()
+                        .iter()
+                        .map(|p| (dashed(&p.0), p.1.as_str()))
+                        .collect(),
+                );
+            } else {
+                let value = if info.expected_value == ExpectedValue::Disallowed {
+                    None
+                } else if let Some(vd) = info.values_desc {
+                    Some(ValueCompletion::Words(
+                        vd.iter().map(|p| p.0.as_str()).collect(),
+                    ))
+                } else if info.value_desc == Some("bool") {
+                    Some(ValueCompletion::Words(vec!["true", "false"]))
+                } else {
+                    Some(ValueCompletion::File)
+                };
+                res.opts.push(OptCompletion {
+                    names: info
+                        .short
+                        .iter()
+                        .chain(info.long.iter())
+                        .map(|n| dashed(n))
+                        .collect(),
+                    desc: info.desc.unwrap_or(""),
+                    value,
+                    optional: info.expected_value == ExpectedValue::Optional,
+                    value_desc: info.value_desc.unwrap_or("value"),
+                    repeated: info.max_count > 1,
+                });
+            }
+        }
+        res
+    }
+}
+
+/// Return the name of the shell function completing `prog_name`.
+fn function_name(prog_name: &str) -> String {
+    let name: String = prog_name
+        .chars()
+        .map(|c| cond!(c.is_ascii_alphanumeric(), c, '_'))
+        .collect();
+    format!("_{}", name)
+}
+
+/// Build the script completing the options of `cl` for the shell.
+pub(crate) fn completion_script(cl: &CommandLine, prog_name: &str, shell: Shell) -> String {
+    let completions = Completions::new(cl);
+    let result = match shell {
+        Shell::Bash => bash_script(&completions, prog_name),
+        Shell::Zsh => zsh_script(&completions, prog_name),
+    };
+    // Writing to a String can't fail.
+    result.unwrap()
+}
+
+/// Build a bash script, which completes the names of options and subcommands,
+/// the values of options and file names.
+fn bash_script(c: &Completions, prog_name: &str) -> Result<String, std::fmt::Error> {
+    let func = function_name(prog_name);
+    let mut words = Vec::new();
+    for opt in &c.opts {
+        for name in &opt.names {
+            // Long options take their value after "=".
+            if name.starts_with("--") && opt.value.is_some() && !opt.optional {
+                words.push(format!("{}=", name));
+            } else {
+                words.push(name.clone());
+            }
+        }
+    }
+    for alts in &c.enum_opts {
+        words.extend(alts.iter().map(|p| p.0.clone()));
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "# bash completion for {0}, generated by `{0} --completion=bash`.",
+        prog_name
+    )?;
+    writeln!(out, "{}() {{", func)?;
+    writeln!(out, "    local cur=${{COMP_WORDS[COMP_CWORD]}}")?;
+    writeln!(out, "    local prev=")?;
+    writeln!(out, "    if ((COMP_CWORD > 0)); then")?;
+    writeln!(out, "        prev=${{COMP_WORDS[COMP_CWORD-1]}}")?;
+    writeln!(out, "    fi")?;
+    writeln!(
+        out,
+        "    # COMP_WORDBREAKS splits \"--opt=value\" into \"--opt\", \"=\" and \"value\"."
+    )?;
+    writeln!(out, "    local opt=")?;
+    writeln!(out, "    if [[ $cur == \"=\" ]]; then")?;
+    writeln!(out, "        opt=$prev")?;
+    writeln!(out, "        cur=")?;
+    writeln!(
+        out,
+        "    elif [[ $prev == \"=\" ]] && ((COMP_CWORD > 1)); then"
+    )?;
+    writeln!(out, "        opt=${{COMP_WORDS[COMP_CWORD-2]}}")?;
+    writeln!(out, "    elif [[ $prev == -? ]]; then")?;
+    writeln!(out, "        opt=$prev")?;
+    writeln!(out, "    fi")?;
+    writeln!(out, "    case $opt in")?;
+    for opt in &c.opts {
+        let value = match &opt.value {
+            Some(value) => value,
+            None => continue,
+        };
+        // Short options may take their value in the next argument.
+        let names: Vec<&str> = opt
+            .names
+            .iter()
+            .map(|n| n.as_str())
+            .filter(|n| n.starts_with("--") || !opt.optional)
+            .collect();
+        if names.is_empty() {
+            continue;
+        }
+        let compgen = match value {
+            ValueCompletion::Words(words) => format!("-W \"{}\"", words.join(" ")),
+            ValueCompletion::File => String::from("-f"),
+        };
+        writeln!(
+            out,
+            "        {})\n            COMPREPLY=($(compgen {} -- \"$cur\"))\n            return\n            ;;",
+            names.join("|"),
+            compgen
+        )?;
+    }
+    writeln!(out, "    esac")?;
+    writeln!(out, "    if [[ $cur == -* ]]; then")?;
+    writeln!(
+        out,
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+        words.join(" ")
+    )?;
+    writeln!(
+        out,
+        "        if [[ ${{#COMPREPLY[@]}} == 1 && ${{COMPREPLY[0]}} == *= ]]; then"
+    )?;
+    writeln!(out, "            compopt -o nospace")?;
+    writeln!(out, "        fi")?;
+    writeln!(out, "        return")?;
+    writeln!(out, "    fi")?;
+    if !c.subcommands.is_empty() {
+        let names: Vec<&str> = c.subcommands.iter().map(|p| p.0.as_str()).collect();
+        writeln!(out, "    if ((COMP_CWORD == 1)); then")?;
+        writeln!(
+            out,
+            "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+            names.join(" ")
+        )?;
+        writeln!(out, "    fi")?;
+    }
+    if c.positional {
+        writeln!(out, "    COMPREPLY+=($(compgen -f -- \"$cur\"))")?;
+    }
+    writeln!(out, "}}")?;
+    writeln!(out, "complete -o filenames -F {} {}", func, prog_name)?;
+    Ok(out)
+}
+
+/// Quote `s` for the shell with single quotes.
+fn zsh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Escape a description or message in an `_arguments` specification.
+fn zsh_escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '[' | ']' | ':') {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// Build a zsh script using `_arguments`, which also shows the descriptions.
+fn zsh_script(c: &Completions, prog_name: &str) -> Result<String, std::fmt::Error> {
+    let func = function_name(prog_name);
+    let mut specs = Vec::new();
+    for opt in &c.opts {
+        // The value follows "=" in long options, and may follow short options
+        // in the same or the next argument.
+        let names: Vec<String> = opt
+            .names
+            .iter()
+            .map(|n| match (&opt.value, n.starts_with("--")) {
+                (None, _) => n.clone(),
+                (Some(_), true) => format!("{}=-", n),
+                (Some(_), false) => format!("{}+", n),
+            })
+            .collect();
+        // Repeatable options can't exclude their other names.
+        let prefix = if opt.repeated {
+            String::from("*")
+        } else if opt.names.len() > 1 {
+            format!("({})", opt.names.join(" "))
+        } else {
+            String::new()
+        };
+        let mut rest = format!("[{}]", zsh_escape(opt.desc));
+        if let Some(value) = &opt.value {
+            let action = match value {
+                ValueCompletion::Words(words) => format!("({})", words.join(" ")),
+                ValueCompletion::File => String::from("_files"),
+            };
+            write!(
+                rest,
+                "{}{}:{}",
+                cond!(opt.optional, "::", ":"),
+                zsh_escape(opt.value_desc),
+                action
+            )?;
+        }
+        // Several names are listed with a brace expansion.
+        specs.push(if names.len() > 1 {
+            format!(
+                "{}{{{}}}{}",
+                zsh_quote(&prefix),
+                names.join(","),
+                zsh_quote(&rest)
+            )
+        } else {
+            zsh_quote(&format!("{}{}{}", prefix, names[0], rest))
+        });
+    }
+    for alts in &c.enum_opts {
+        let names: Vec<&str> = alts.iter().map(|p| p.0.as_str()).collect();
+        for (name, desc) in alts {
+            specs.push(zsh_quote(&format!(
+                "({}){}[{}]",
+                names.join(" "),
+                name,
+                zsh_escape(desc)
+            )));
+        }
+    }
+    if !c.subcommands.is_empty() {
+        specs.push(zsh_quote("1: :->first"));
+    }
+    if c.positional {
+        specs.push(zsh_quote("*:file:_files"));
+    }
+
+    let mut out = String::new();
+    writeln!(out, "#compdef {}", prog_name)?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "# zsh completion for {0}, generated by `{0} --completion=zsh`.",
+        prog_name
+    )?;
+    writeln!(out, "{}() {{", func)?;
+    writeln!(out, "  local state")?;
+    writeln!(out, "  _arguments -s \\")?;
+    for (i, spec) in specs.iter().enumerate() {
+        writeln!(out, "    {}{}", spec, cond!(i + 1 < specs.len(), " \\", ""))?;
+    }
+    if !c.subcommands.is_empty() {
+        let alts: Vec<String> = c
+            .subcommands
+            .iter()
+            .map(|(name, desc)| {
+                format!(
+                    "{}\\:\"{}\"",
+                    name,
+                    desc.replace('\\', "\\\\").replace('"', "\\\"")
+                )
+            })
+            .collect();
+        writeln!(out, "  case $state in")?;
+        writeln!(out, "    first)")?;
+        writeln!(out, "      _alternative \\")?;
+        writeln!(
+            out,
+            "        {}{}",
+            zsh_quote(&format!("subcommands:subcommand:(({}))", alts.join(" "))),
+            cond!(c.positional, " \\", "")
+        )?;
+        if c.positional {
+            writeln!(out, "        {}", zsh_quote("files:file:_files"))?;
+        }
+        writeln!(out, "      ;;")?;
+        writeln!(out, "  esac")?;
+    }
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+    writeln!(out, "{} \"$@\"", func)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum TestEnum {
+        GenJS,
+        GenAST,
+    }
+
+    fn options() -> CommandLine {
+        let mut options = CommandLine::new("juno");
+
+        Opt::<TestEnum>::new_subcommand(
+            &mut options,
+            OptDesc {
+                values: Some(&[
+                    ("build", TestEnum::GenJS, "Build the inputs"),
+                    ("dump", TestEnum::GenAST, "Dump the \"AST\""),
+                ]),
+                ..Default::default()
+            },
+        );
+        Opt::<String>::new(
+            &mut options,
+            OptDesc {
+                long: Some("out"),
+                short: Some("o"),
+                desc: Some("Output path"),
+                value_desc: Some("path"),
+                ..Default::default()
+            },
+        );
+        Opt::new_bool(
+            &mut options,
+            OptDesc {
+                long: Some("pretty"),
+                desc: Some("Pretty print [default: on]"),
+                ..Default::default()
+            },
+        );
+        Opt::<TestEnum>::new_enum(
+            &mut options,
+            OptDesc {
+                values: Some(&[
+                    ("gen-js", TestEnum::GenJS, "Generate JS"),
+                    ("gen-ast", TestEnum::GenAST, "Generate AST"),
+                ]),
+                ..Default::default()
+            },
+        );
+        Opt::<String>::new_list(
+            &mut options,
+            OptDesc {
+                long: Some("include"),
+                short: Some("I"),
+                desc: Some("Add the user's directory"),
+                value_desc: Some("dir"),
+                ..Default::default()
+            },
+        );
+        Opt::<String>::new_list(
+            &mut options,
+            OptDesc {
+                desc: Some("files"),
+                ..Default::default()
+            },
+        );
+        options
+    }
+
+    #[test]
+    fn test_bash() {
+        static OUTPUT: &str = r##"# bash completion for juno, generated by `juno --completion=bash`.
+_juno() {
+    local cur=${COMP_WORDS[COMP_CWORD]}
+    local prev=
+    if ((COMP_CWORD > 0)); then
+        prev=${COMP_WORDS[COMP_CWORD-1]}
+    fi
+    # COMP_WORDBREAKS splits "--opt=value" into "--opt", "=" and "value".
+    local opt=
+    if [[ $cur == "=" ]]; then
+        opt=$prev
+        cur=
+    elif [[ $prev == "=" ]] && ((COMP_CWORD > 1)); then
+        opt=${COMP_WORDS[COMP_CWORD-2]}
+    elif [[ $prev == -? ]]; then
+        opt=$prev
+    fi
+    case $opt in
+        -o|--out)
+            COMPREPLY=($(compgen -f -- "$cur"))
+            return
+            ;;
+        --pretty)
+            COMPREPLY=($(compgen -W "true false" -- "$cur"))
+            return
+            ;;
+        -I|--include)
+            COMPREPLY=($(compgen -f -- "$cur"))
+            return
+            ;;
+    esac
+    if [[ $cur == -* ]]; then
+        COMPREPLY=($(compgen -W "-h --help -o --out= --pretty -I --include= --gen-js --gen-ast" -- "$cur"))
+        if [[ ${#COMPREPLY[@]} == 1 && ${COMPREPLY[0]} == *= ]]; then
+            compopt -o nospace
+        fi
+        return
+    fi
+    if ((COMP_CWORD == 1)); then
+        COMPREPLY=($(compgen -W "build dump" -- "$cur"))
+    fi
+    COMPREPLY+=($(compgen -f -- "$cur"))
+}
+complete -o filenames -F _juno juno
+"##;
+        assert_eq!(completion_script(&options(), "juno", Shell::Bash), OUTPUT);
+    }
+
+    #[test]
+    fn test_zsh() {
+        static OUTPUT: &str = r##"#compdef juno
+
+# zsh completion for juno, generated by `juno --completion=zsh`.
+_juno() {
+  local state
+  _arguments -s \
+    '(-h --help)'{-h,--help}'[Display available options (-help-hidden for more)]' \
+    '(-o --out)'{-o+,--out=-}'[Output path]:path:_files' \
+    '--pretty=-[Pretty print \[default\: on\]]::bool:(true false)' \
+    '*'{-I+,--include=-}'[Add the user'\''s directory]:dir:_files' \
+    '(--gen-js --gen-ast)--gen-js[Generate JS]' \
+    '(--gen-js --gen-ast)--gen-ast[Generate AST]' \
+    '1: :->first' \
+    '*:file:_files'
+  case $state in
+    first)
+      _alternative \
+        'subcommands:subcommand:((build\:"Build the inputs" dump\:"Dump the \"AST\""))' \
+        'files:file:_files'
+      ;;
+  esac
+}
+
+_juno "$@"
+"##;
+        assert_eq!(completion_script(&options(), "juno", Shell::Zsh), OUTPUT);
+    }
+}
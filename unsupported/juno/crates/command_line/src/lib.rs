@@ -13,12 +13,15 @@ macro_rules! cond {
 
 #[allow(dead_code)]
 mod cl;
+mod completion;
 #[allow(dead_code)]
 mod opt;
 #[allow(dead_code)]
 mod parser;
+mod response_file;
 
 pub use cl::CommandLine;
+pub use completion::Shell;
 pub use opt::parse_bool;
 pub use opt::parse_disallowed;
 pub use opt::EnumDesc;
@@ -8,6 +8,7 @@
 use std::cell::Cell;
 use std::cell::UnsafeCell;
 use std::collections::HashSet;
+use std::env::VarError;
 use std::ops::Deref;
 use std::rc::Rc;
 use std::str::FromStr;
@@ -72,6 +73,16 @@ impl<T> OptValue<T> {
         }
     }
 
+    /// Remove all values.
+    /// This method may only be called before parsing has finished.
+    fn clear(&self) {
+        assert!(
+            !self.finished_parsing.get(),
+            "value cannot be modified after parsing"
+        );
+        unsafe { &mut *self.value.get() }.clear();
+    }
+
     /// Mark the value is no longer changeable, which enables borrowing.
     fn finish(&self) {
         self.finished_parsing.set(true);
@@ -132,6 +143,12 @@ pub struct OptDesc<'a, T, S: Into<String>> {
     pub opt_value: Option<Rc<OptValue<T>>>,
     /// The category this option belongs to. The default 0 is "Generic Options".
     pub category: usize,
+    /// Name of an environment variable providing a default value, which the
+    /// command line overrides.
+    pub env: Option<S>,
+    /// Whether this enum option is selected by the first argument naming one
+    /// of its [`values`], like "juno build", instead of "--build".
+    pub subcommand: bool,
 }
 
 impl<T, S: Into<String>> Default for OptDesc<'_, T, S> {
@@ -151,6 +168,8 @@ impl<T, S: Into<String>> Default for OptDesc<'_, T, S> {
             hidden: Hidden::No,
             opt_value: None,
             category: 0,
+            env: None,
+            subcommand: false,
         }
     }
 }
@@ -193,6 +212,11 @@ impl<T> Opt<T> {
     pub fn occurrences(&self) -> usize {
         self.0.count.get()
     }
+    /// Return true if the value was seeded with [`CommandLine::seed()`] and
+    /// the command line didn't override it.
+    pub fn is_seeded(&self) -> bool {
+        self.0.seeded.get()
+    }
 }
 
 pub struct OptHolder<T> {
@@ -230,11 +254,17 @@ pub struct OptHolder<T> {
     opt_value: Rc<OptValue<T>>,
     /// The category this option belongs to. The default 0 is "Generic Options".
     pub category: usize,
+    /// Name of an environment variable providing a default value.
+    env: Option<String>,
+    /// Whether this enum option is selected by the first argument.
+    subcommand: bool,
 
     /// Callback to parse a string into T.
     parser: Box<dyn Fn(&str) -> Result<T, String>>,
     /// How many times has the option been specified.
     count: Cell<usize>,
+    /// Whether the value was seeded, and not yet specified on the command line.
+    seeded: Cell<bool>,
 }
 
 pub(crate) struct OptInfo<'a> {
@@ -250,6 +280,8 @@ pub(crate) struct OptInfo<'a> {
     pub value_desc: Option<&'a str>,
     pub hidden: Hidden,
     pub category: usize,
+    pub env: Option<&'a str>,
+    pub subcommand: bool,
 }
 
 impl OptInfo<'_> {
@@ -260,7 +292,10 @@ impl OptInfo<'_> {
     /// An enum option is a set of mutually exclusive options encoded as an
     /// enum.
     pub fn is_enum_option(&self) -> bool {
-        self.long.is_none() && self.short.is_none() && self.values_desc.is_some()
+        self.long.is_none()
+            && self.short.is_none()
+            && self.values_desc.is_some()
+            && !self.subcommand
     }
     /// Is this an option whose value is an enum?
     pub fn is_enum_value(&self) -> bool {
@@ -284,6 +319,12 @@ pub(crate) trait CLOption {
     fn is_accepting_positional(&self) -> bool;
     /// Parse a value, and if successful, store it.
     fn parse_value(&self, s: Option<&str>, eq: EqName) -> Result<(), String>;
+    /// Parse a value, and if successful, store it as a seeded value which
+    /// doesn't count as an occurrence and is replaced by the first occurrence.
+    fn seed_value(&self, s: Option<&str>, eq: EqName) -> Result<(), String>;
+    /// Seed the value from the environment variable of the option, if it is
+    /// set. It replaces the values seeded before.
+    fn seed_env(&self) -> Result<(), String>;
     /// At the end of CLI parsing, perform finish actions like setting a init
     /// value and validate the option by checking [`Opt::min_count`].
     fn finish(&self) -> Result<(), String>;
@@ -324,27 +365,19 @@ impl<T: Clone> OptHolder<T> {
 
     //noinspection RsSelfConvention
     fn set_opt_value(&self, v: Option<T>) {
+        // The first occurrence replaces the seeded values.
+        if self.seeded.replace(false) && self.list {
+            self.opt_value.clear();
+        }
         if let Some(value) = v {
             self.opt_value.update_value(self.list, value);
         }
         self.count.set(self.count.get() + 1);
     }
-}
-
-impl<T: 'static + Clone> CLOption for OptHolder<T> {
-    fn is_accepting_positional(&self) -> bool {
-        self.is_positional() && self.count.get() < self.max_count
-    }
-
-    fn parse_value(&self, s: Option<&str>, eq: EqName) -> Result<(), String> {
-        if self.count.get() >= self.max_count {
-            return Err(format!(
-                "option may not occur more than {} time{}",
-                self.max_count,
-                plural(self.max_count)
-            ));
-        }
 
+    /// Parse the value specified with the option, returning the value to be
+    /// stored, if any.
+    fn parse_opt_value(&self, s: Option<&str>, eq: EqName) -> Result<Option<T>, String> {
         let enum_opt_value = if let EqName::EnumValue(index) = eq {
             Some(self.values.as_ref().unwrap()[index].clone())
         } else {
@@ -361,31 +394,97 @@ impl<T: 'static + Clone> CLOption for OptHolder<T> {
             }
 
             (ExpectedValue::Disallowed, d, None) | (ExpectedValue::Optional, d, None) => {
-                self.set_opt_value(d.cloned());
-                Ok(())
+                Ok(d.cloned())
             }
 
             (ExpectedValue::Required, _, None) => Err(String::from("option requires a value")),
 
-            (ExpectedValue::Optional, _, Some(v)) | (ExpectedValue::Required, _, Some(v)) => {
-                match cond!(
-                    self.is_enum_value(),
-                    self.parse_enum_value(v),
-                    (self.parser)(v)
-                ) {
-                    Ok(o) => {
-                        self.set_opt_value(Some(o));
-                        Ok(())
-                    }
-                    Err(e) => Err(e),
-                }
+            (ExpectedValue::Optional, _, Some(v)) | (ExpectedValue::Required, _, Some(v)) => cond!(
+                self.is_enum_value(),
+                self.parse_enum_value(v),
+                (self.parser)(v)
+            )
+            .map(Some),
+        }
+    }
+}
+
+impl<T: 'static + Clone> CLOption for OptHolder<T> {
+    fn is_accepting_positional(&self) -> bool {
+        self.is_positional() && self.count.get() < self.max_count
+    }
+
+    fn parse_value(&self, s: Option<&str>, eq: EqName) -> Result<(), String> {
+        if self.count.get() >= self.max_count {
+            return Err(format!(
+                "option may not occur more than {} time{}",
+                self.max_count,
+                plural(self.max_count)
+            ));
+        }
+
+        let value = self.parse_opt_value(s, eq)?;
+        self.set_opt_value(value);
+        Ok(())
+    }
+
+    fn seed_value(&self, s: Option<&str>, eq: EqName) -> Result<(), String> {
+        assert_eq!(self.count.get(), 0, "options must be seeded before parsing");
+        if let Some(value) = self.parse_opt_value(s, eq)? {
+            self.opt_value.update_value(self.list, value);
+        }
+        self.seeded.set(true);
+        Ok(())
+    }
+
+    fn seed_env(&self) -> Result<(), String> {
+        let name = match &self.env {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        let value = match std::env::var(name) {
+            Ok(value) => value,
+            Err(VarError::NotPresent) => return Ok(()),
+            Err(VarError::NotUnicode(_)) => {
+                return Err(format!(
+                    "environment variable {} is not valid Unicode",
+                    name
+                ));
             }
+        };
+        if self.seeded.get() && self.list {
+            self.opt_value.clear();
         }
+
+        let result = if self.is_enum_option() {
+            // The variable names one of the alternatives.
+            match self
+                .values_desc
+                .as_ref()
+                .unwrap()
+                .iter()
+                .position(|p| p.0 == value)
+            {
+                Some(index) => self.seed_value(None, EqName::EnumValue(index)),
+                None => Err(format!("cannot find option named '{}'", value)),
+            }
+        } else if self.expected_value == ExpectedValue::Disallowed {
+            // A flag is specified by a true value.
+            match parse_bool(&value) {
+                Ok(true) => self.seed_value(None, EqName::Yes),
+                Ok(false) => Ok(()),
+                Err(e) => Err(e),
+            }
+        } else {
+            self.seed_value(Some(&value), EqName::Yes)
+        };
+        result.map_err(|e| format!("in environment variable {}: {}", name, e))
     }
 
     fn finish(&self) -> Result<(), String> {
         self.opt_value.finish();
-        if self.count.get() < self.min_count {
+        // A seeded value satisfies the minimum, since it was specified elsewhere.
+        if self.count.get() < self.min_count && !self.seeded.get() {
             if self.min_count == self.max_count {
                 if self.min_count == 1 {
                     Err(String::from("a value must be supplied"))
@@ -410,7 +509,10 @@ impl<T: 'static + Clone> CLOption for OptHolder<T> {
     }
 
     fn eq_name(&self, name: &str, long: bool) -> EqName {
-        if self.is_enum_option() {
+        if self.subcommand {
+            // Subcommands aren't options.
+            EqName::No
+        } else if self.is_enum_option() {
             self.values_desc
                 .as_ref()
                 .unwrap()
@@ -451,6 +553,8 @@ impl<T: 'static + Clone> CLOption for OptHolder<T> {
             value_desc: self.value_desc.as_deref(),
             hidden: self.hidden,
             category: self.category,
+            env: self.env.as_deref(),
+            subcommand: self.subcommand,
         }
     }
 }
@@ -512,7 +616,12 @@ impl<T: 'static + Clone> Opt<T> {
                 );
 
                 // Check for duplicate option names.
-                if cfg!(debug_assertions) {
+                if desc.subcommand {
+                    debug_assert!(
+                        opts.find_subcommand_option().is_none(),
+                        "Duplicated subcommand option"
+                    );
+                } else if cfg!(debug_assertions) {
                     for nd in d.iter() {
                         let name = nd.0.as_str();
                         debug_assert!(
@@ -524,6 +633,7 @@ impl<T: 'static + Clone> Opt<T> {
                     }
                 }
             } else {
+                assert!(!desc.subcommand, "Subcommand options can't have a name");
                 // The value cannot be disallowed in options of the form "--opt=enum_val".
                 assert_ne!(
                     desc.expected_value,
@@ -581,8 +691,11 @@ impl<T: 'static + Clone> Opt<T> {
                 .opt_value
                 .unwrap_or_else(|| Rc::new(Default::default())),
             category: desc.category,
+            env: desc.env.map(|s| s.into()),
+            subcommand: desc.subcommand,
             parser,
             count: Default::default(),
+            seeded: Default::default(),
         }));
 
         if !desc.list {
@@ -683,6 +796,16 @@ impl<T: 'static + Clone> Opt<T> {
         }
         Self::with_parser(opts, desc, parse_disallowed::<T>)
     }
+
+    /// Create the subcommand option, an enum option selected by the first
+    /// argument. If no subcommand is specified, the init value is used.
+    pub fn new_subcommand<S: Into<String> + Clone>(
+        opts: &mut CommandLine,
+        mut desc: OptDesc<T, S>,
+    ) -> Opt<T> {
+        desc.subcommand = true;
+        Self::new_enum(opts, desc)
+    }
 }
 
 impl<U: 'static + Clone> Opt<Option<U>> {
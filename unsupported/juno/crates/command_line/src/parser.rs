@@ -16,6 +16,17 @@ pub enum CommandLineIntent {
     Normal,
     /// Help was requested. The help text is attached.
     Help(String),
+    /// A shell completion script was requested. The script is attached.
+    Completion(String),
+}
+
+/// Extract the name of the program from the first argument.
+pub(crate) fn program_name(args: &[String]) -> String {
+    args.first()
+        .map(|s| AsRef::<Path>::as_ref(s.as_str()))
+        .and_then(|p| p.file_name())
+        .map(|os| os.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("<unknown>"))
 }
 
 pub(crate) struct Parser<'a> {
@@ -28,16 +39,8 @@ pub(crate) struct Parser<'a> {
 
 impl Parser<'_> {
     pub fn new(opts: CommandLine, args: &[String]) -> Parser {
-        // Extract the name from the first arg.
-        let name = args
-            .first()
-            .map(|s| AsRef::<Path>::as_ref(s.as_str()))
-            .and_then(|p| p.file_name())
-            .map(|os| os.to_string_lossy().to_string())
-            .unwrap_or_else(|| String::from("<unknown>"));
-
         Parser {
-            prog_name: name,
+            prog_name: program_name(args),
             opts,
             args,
             cur_arg: 0,
@@ -45,7 +48,30 @@ impl Parser<'_> {
     }
 
     pub(crate) fn parse(mut self) -> Result<CommandLineIntent, String> {
+        // Seed the options from the environment, so that the arguments override
+        // them.
+        for opt in self.opts.as_slice() {
+            if let Err(e) = opt.seed_env() {
+                return Err(format!(
+                    "{}: for the {} option: {}",
+                    self.prog_name,
+                    opt.name(),
+                    e
+                ));
+            }
+        }
+
         self.cur_arg = 1;
+        // The first argument may select the subcommand.
+        if let Some(arg) = self.args.get(1) {
+            if let Some((opt, eq)) = self.opts.find_subcommand(arg) {
+                opt.parse_value(None, eq)
+                    .map_err(|e| format!("{}: {}", self.prog_name, e))?;
+                self.prog_name = format!("{} {}", self.prog_name, arg);
+                self.cur_arg = 2;
+            }
+        }
+
         while self.cur_arg < self.args.len() {
             let arg = self.args[self.cur_arg].as_str();
             if arg == "--" {
@@ -84,30 +110,42 @@ impl Parser<'_> {
         }
 
         // Validate all options in the end.
+        let mut error = None;
         for opt in self.opts.as_slice() {
             if let Err(e) = opt.finish() {
-                let info = opt.info();
-                return if info.is_positional() {
-                    if let Some(vd) = info.desc.or(info.value_desc) {
-                        Err(format!(
-                            "{}: for positional argument {}: {}",
-                            self.prog_name, vd, e
-                        ))
-                    } else {
-                        Err(format!(
-                            "{}: for positional argument: {}",
-                            self.prog_name, e
-                        ))
-                    }
+                error.get_or_insert((opt, e));
+            }
+        }
+
+        // A completion script doesn't need valid options.
+        if let Some(shell) = self.opts.is_completion_requested() {
+            return Ok(CommandLineIntent::Completion(
+                self.opts.build_completion(&self.prog_name, shell),
+            ));
+        }
+
+        if let Some((opt, e)) = error {
+            let info = opt.info();
+            return if info.is_positional() {
+                if let Some(vd) = info.desc.or(info.value_desc) {
+                    Err(format!(
+                        "{}: for positional argument {}: {}",
+                        self.prog_name, vd, e
+                    ))
                 } else {
                     Err(format!(
-                        "{}: for the {} option: {}",
-                        self.prog_name,
-                        opt.name(),
-                        e
+                        "{}: for positional argument: {}",
+                        self.prog_name, e
                     ))
-                };
-            }
+                }
+            } else {
+                Err(format!(
+                    "{}: for the {} option: {}",
+                    self.prog_name,
+                    opt.name(),
+                    e
+                ))
+            };
         }
 
         Ok(CommandLineIntent::Normal)
@@ -452,4 +490,293 @@ mod tests {
         assert_eq!(gen[0], TestEnum::GenAST);
         assert_eq!(gen[1], TestEnum::GenJS);
     }
+
+    #[test]
+    fn test_seed() {
+        let mut options = CommandLine::new("juno");
+
+        let max_heap = Opt::<u32>::new(
+            &mut options,
+            OptDesc {
+                long: Some("max-heap"),
+                ..Default::default()
+            },
+        );
+        let pretty = Opt::new_bool(
+            &mut options,
+            OptDesc {
+                long: Some("pretty"),
+                ..Default::default()
+            },
+        );
+        let gen = Opt::<TestEnum>::new_enum(
+            &mut options,
+            OptDesc {
+                values: Some(&[
+                    ("gen-js", TestEnum::GenJS, "Generate JS"),
+                    ("gen-ast", TestEnum::GenAST, "Generate AST"),
+                    ("gen-ir", TestEnum::GenIR, "Generate IR"),
+                ]),
+                desc: Some("Choose output"),
+                ..Default::default()
+            },
+        );
+        let include = Opt::<String>::new_list(
+            &mut options,
+            OptDesc {
+                long: Some("include"),
+                short: Some("I"),
+                ..Default::default()
+            },
+        );
+        let define = Opt::<String>::new_list(
+            &mut options,
+            OptDesc {
+                long: Some("define"),
+                ..Default::default()
+            },
+        );
+
+        options.seed("max-heap", Some("10")).unwrap();
+        options.seed("pretty", None).unwrap();
+        options.seed("gen-ir", None).unwrap();
+        options.seed("I", Some("a")).unwrap();
+        options.seed("include", Some("b")).unwrap();
+        options.seed("define", Some("X")).unwrap();
+        assert_eq!(
+            options.seed("max-heap", Some("ten")).unwrap_err(),
+            "for the --max-heap option: invalid digit found in string"
+        );
+        assert_eq!(
+            options.seed("min-heap", Some("10")).unwrap_err(),
+            "Unknown option 'min-heap'"
+        );
+
+        let vec1: Vec<String> = "test --max-heap=20 --gen-ast --include=c"
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect();
+        let p = Parser::new(options, &vec1);
+        p.parse().expect("parse should succeed");
+
+        assert_eq!(*max_heap, 20);
+        assert!(!max_heap.is_seeded());
+        assert!(*pretty);
+        assert!(pretty.is_seeded());
+        assert_eq!(pretty.occurrences(), 0);
+        assert_eq!(*gen, TestEnum::GenAST);
+        assert_eq!(include.values(), &["c"]);
+        assert_eq!(define.values(), &["X"]);
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum Command {
+        Build,
+        Lint,
+    }
+
+    #[test]
+    fn test_subcommand() {
+        let mut options = CommandLine::new("juno");
+
+        let command = Opt::<Command>::new_subcommand(
+            &mut options,
+            OptDesc {
+                values: Some(&[
+                    ("build", Command::Build, "Build"),
+                    ("lint", Command::Lint, "Lint"),
+                ]),
+                ..Default::default()
+            },
+        );
+        let files = Opt::<String>::new_list(
+            &mut options,
+            OptDesc {
+                desc: Some("files"),
+                ..Default::default()
+            },
+        );
+
+        let vec1: Vec<String> = "test lint build"
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect();
+        let p = Parser::new(options, &vec1);
+        p.parse().expect("parse should succeed");
+
+        assert_eq!(*command, Command::Lint);
+        assert_eq!(files.values(), &["build"]);
+    }
+
+    #[test]
+    fn test_subcommand_default() {
+        let mut options = CommandLine::new("juno");
+
+        let command = Opt::<Command>::new_subcommand(
+            &mut options,
+            OptDesc {
+                values: Some(&[
+                    ("build", Command::Build, "Build"),
+                    ("lint", Command::Lint, "Lint"),
+                ]),
+                ..Default::default()
+            },
+        );
+        let files = Opt::<String>::new_list(
+            &mut options,
+            OptDesc {
+                desc: Some("files"),
+                ..Default::default()
+            },
+        );
+
+        let vec1: Vec<String> = "test file"
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect();
+        let p = Parser::new(options, &vec1);
+        p.parse().expect("parse should succeed");
+
+        assert_eq!(*command, Command::Build);
+        assert_eq!(files.values(), &["file"]);
+    }
+
+    #[test]
+    fn test_subcommand_err() {
+        let mut options = CommandLine::new("juno");
+
+        let _command = Opt::<Command>::new_subcommand(
+            &mut options,
+            OptDesc {
+                values: Some(&[
+                    ("build", Command::Build, "Build"),
+                    ("lint", Command::Lint, "Lint"),
+                ]),
+                ..Default::default()
+            },
+        );
+        let _files = Opt::<String>::new_list(
+            &mut options,
+            OptDesc {
+                desc: Some("files"),
+                ..Default::default()
+            },
+        );
+
+        let vec1: Vec<String> = "test --lint file"
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect();
+        let p = Parser::new(options, &vec1);
+        assert_eq!(
+            p.parse().expect_err("parse should fail"),
+            "test: Unknown command line argument --lint"
+        );
+    }
+
+    #[test]
+    fn test_env() {
+        let mut options = CommandLine::new("juno");
+
+        let max_heap = Opt::<u32>::new(
+            &mut options,
+            OptDesc {
+                long: Some("max-heap"),
+                env: Some("COMMAND_LINE_TEST_MAX_HEAP"),
+                ..Default::default()
+            },
+        );
+        let verbose = Opt::new_flag(
+            &mut options,
+            OptDesc {
+                long: Some("verbose"),
+                env: Some("COMMAND_LINE_TEST_VERBOSE"),
+                ..Default::default()
+            },
+        );
+        let include = Opt::<String>::new_list(
+            &mut options,
+            OptDesc {
+                long: Some("include"),
+                env: Some("COMMAND_LINE_TEST_INCLUDE"),
+                ..Default::default()
+            },
+        );
+
+        std::env::set_var("COMMAND_LINE_TEST_MAX_HEAP", "10");
+        std::env::set_var("COMMAND_LINE_TEST_VERBOSE", "yes");
+        std::env::set_var("COMMAND_LINE_TEST_INCLUDE", "b");
+        options.seed("include", Some("a")).unwrap();
+
+        let vec1: Vec<String> = "test --max-heap=20"
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect();
+        let p = Parser::new(options, &vec1);
+        p.parse().expect("parse should succeed");
+
+        assert_eq!(*max_heap, 20);
+        assert!(*verbose);
+        assert_eq!(verbose.occurrences(), 0);
+        // The environment replaces the seeded values.
+        assert_eq!(include.values(), &["b"]);
+    }
+
+    #[test]
+    fn test_env_err() {
+        let mut options = CommandLine::new("juno");
+
+        let _max_heap = Opt::<u32>::new(
+            &mut options,
+            OptDesc {
+                long: Some("max-heap"),
+                env: Some("COMMAND_LINE_TEST_BAD_MAX_HEAP"),
+                ..Default::default()
+            },
+        );
+
+        std::env::set_var("COMMAND_LINE_TEST_BAD_MAX_HEAP", "ten");
+        let vec1: Vec<String> = vec!["test".to_string()];
+        let p = Parser::new(options, &vec1);
+        assert_eq!(
+            p.parse().expect_err("parse should fail"),
+            "test: for the --max-heap option: in environment variable \
+            COMMAND_LINE_TEST_BAD_MAX_HEAP: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn test_response_file() {
+        let path =
+            std::env::temp_dir().join(format!("command_line_test_{}.rsp", std::process::id()));
+        std::fs::write(&path, "--max-heap=10 'file 1'\nfile2\n").unwrap();
+
+        let mut options = CommandLine::new("juno");
+        let max_heap = Opt::<u32>::new(
+            &mut options,
+            OptDesc {
+                long: Some("max-heap"),
+                ..Default::default()
+            },
+        );
+        let files = Opt::<String>::new_list(
+            &mut options,
+            OptDesc {
+                desc: Some("files"),
+                ..Default::default()
+            },
+        );
+
+        let args = vec![
+            "test".to_string(),
+            format!("@{}", path.display()),
+            "file3".to_string(),
+        ];
+        let result = options.parse(&args);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(result, Ok(CommandLineIntent::Normal));
+
+        assert_eq!(*max_heap, 10);
+        assert_eq!(files.values(), &["file 1", "file2", "file3"]);
+    }
 }
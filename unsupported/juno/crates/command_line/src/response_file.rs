@@ -0,0 +1,165 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Replace every "@file" argument with the arguments in the file, which may
+/// contain other "@file" arguments. This allows passing more arguments than
+/// the operating system permits. Arguments after "--" are not expanded.
+pub(crate) fn expand_response_files(args: &[String]) -> Result<Vec<String>, String> {
+    let mut result = Vec::with_capacity(args.len());
+    let mut iter = args.iter().cloned();
+    // The first argument is the program name.
+    result.extend(iter.next());
+    let mut verbatim = false;
+    expand_into(iter, &mut Vec::new(), &mut verbatim, &mut result)?;
+    Ok(result)
+}
+
+/// Append `args` to `result`, expanding the response files. `open` contains the
+/// files being expanded, to detect cycles. `verbatim` is set after "--".
+fn expand_into(
+    args: impl Iterator<Item = String>,
+    open: &mut Vec<PathBuf>,
+    verbatim: &mut bool,
+    result: &mut Vec<String>,
+) -> Result<(), String> {
+    for arg in args {
+        let path = match arg.strip_prefix('@') {
+            Some(path) if !*verbatim && !path.is_empty() => Path::new(path),
+            _ => {
+                *verbatim |= arg == "--";
+                result.push(arg);
+                continue;
+            }
+        };
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read response file '{}': {}", path.display(), e))?;
+        let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if open.contains(&canonical) {
+            return Err(format!(
+                "response file '{}' includes itself",
+                path.display()
+            ));
+        }
+        let file_args =
+            tokenize(&text).map_err(|e| format!("in response file '{}': {}", path.display(), e))?;
+        open.push(canonical);
+        expand_into(file_args.into_iter(), open, verbatim, result)?;
+        open.pop();
+    }
+    Ok(())
+}
+
+/// Split the contents of a response file into arguments like a POSIX shell:
+/// arguments are separated by whitespace, which can be quoted with single or
+/// double quotes, or escaped with a backslash. Backslashes are literal inside
+/// single quotes.
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    // Whether an argument has started, which may be empty, like "".
+    let mut in_arg = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            '\\' => {
+                in_arg = true;
+                // A trailing backslash is kept.
+                arg.push(chars.next().unwrap_or('\\'));
+            }
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(String::from("missing closing quote")),
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => arg.push(c),
+                            None => return Err(String::from("missing closing quote")),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(String::from("missing closing quote")),
+                    }
+                }
+            }
+            c => {
+                in_arg = true;
+                arg.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("a  b\n\t'c d' \"e \\\"f\\\"\" g\\ h '' 'i\\j'\\").unwrap(),
+            strings(&["a", "b", "c d", "e \"f\"", "g h", "", "i\\j\\"])
+        );
+        assert_eq!(tokenize("a 'b").unwrap_err(), "missing closing quote");
+    }
+
+    #[test]
+    fn test_expand() {
+        let dir = std::env::temp_dir().join(format!("command_line_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let inner = dir.join("inner.rsp");
+        let outer = dir.join("outer.rsp");
+        let cycle = dir.join("cycle.rsp");
+        std::fs::write(&inner, "b 'c d'\n").unwrap();
+        std::fs::write(&outer, format!("a @{}\ne", inner.display())).unwrap();
+        std::fs::write(&cycle, format!("@{}", cycle.display())).unwrap();
+
+        let outer_arg = format!("@{}", outer.display());
+        assert_eq!(
+            expand_response_files(&strings(&["test", &outer_arg, "f", "@", "--", &outer_arg]))
+                .unwrap(),
+            strings(&["test", "a", "b", "c d", "e", "f", "@", "--", &outer_arg])
+        );
+        assert_eq!(
+            expand_response_files(&strings(&["test", &format!("@{}", cycle.display())]))
+                .unwrap_err(),
+            format!("response file '{}' includes itself", cycle.display())
+        );
+        assert!(
+            expand_response_files(&strings(&["test", "@/nonexistent/file.rsp"]))
+                .unwrap_err()
+                .starts_with("cannot read response file '/nonexistent/file.rsp'")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
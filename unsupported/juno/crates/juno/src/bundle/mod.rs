@@ -0,0 +1,1098 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Bundling of modules into a single script.
+//!
+//! Starting from an entry module, the modules it depends on through `require`, `import`
+//! and `export ... from` are collected into a [`ModuleGraph`]. Every module is converted
+//! to CommonJS and wrapped in a factory function which is registered with `__d`, the
+//! module registry at the top of the bundle, and the entry module is then run with `__r`.
+//!
+//! Imported bindings stay live: every reference to an imported binding is replaced with
+//! a property access on the exports of the imported module, and the exports of an ES
+//! module are defined as getters on its `exports` object.
+//!
+//! Alternatively, [`bundle_scope_hoisted`] inlines the ES modules of a bundle into its
+//! top-level scope instead of wrapping them in factories.
+//!
+//! Before bundling, [`tree_shake`] can remove the exports that no module uses, and
+//! the code only they need.
+
+mod scope_hoist;
+mod tree_shake;
+
+pub use scope_hoist::bundle_scope_hoisted;
+pub use tree_shake::package_side_effects;
+pub use tree_shake::tree_shake;
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::io;
+
+use juno_support::source_manager::SourceId;
+
+use crate::ast::*;
+use crate::comments::CommentMap;
+use crate::gen_js;
+use crate::sema::DeclId;
+use crate::sema::Resolution;
+use crate::sema::SemContext;
+use crate::sourcemap::FullSourceMap;
+use crate::sourcemap::compose_sourcemaps;
+use crate::sourcemap::concat_sourcemaps;
+
+/// The module registry, emitted at the top of every bundle.
+///
+/// `__d(factory, id, dependencies, name)` registers a module and `__r(id)` runs it
+/// on first use and returns its exports. Factories are called with
+/// `(global, require, module, exports)`, where `require` is `__r`.
+/// Modules outside the bundle are required by name, which `__r` passes on to the
+/// `require` of the host, if there is one.
+const RUNTIME: &str = r#"var __d, __r;
+(function (global) {
+  'use strict';
+  var modules = Object.create(null);
+  __d = function (factory, id, dependencies, name) {
+    modules[id] = {factory: factory, name: name, module: null};
+  };
+  __r = function (id) {
+    var entry = modules[id];
+    if (entry === undefined) {
+      if (typeof id === 'string' && typeof require === 'function') {
+        return require(id);
+      }
+      throw new Error('Unknown module: ' + id);
+    }
+    if (entry.module === null) {
+      var module = (entry.module = {exports: {}});
+      entry.factory.call(module.exports, global, __r, module, module.exports);
+    }
+    return entry.module.exports;
+  };
+  __r.defineExports = function (exports, getters) {
+    Object.defineProperty(exports, '__esModule', {value: true});
+    Object.keys(getters).forEach(function (key) {
+      Object.defineProperty(exports, key, {enumerable: true, get: getters[key]});
+    });
+  };
+  __r.exportAll = function (exports, id) {
+    var source = __r(id);
+    Object.keys(source).forEach(function (key) {
+      if (key !== 'default' && !Object.prototype.hasOwnProperty.call(exports, key)) {
+        Object.defineProperty(exports, key, {
+          enumerable: true,
+          get: function () {
+            return source[key];
+          },
+        });
+      }
+    });
+  };
+})(typeof globalThis !== 'undefined' ? globalThis : this);
+"#;
+
+/// The modules reachable from an entry module.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleGraph {
+    /// Every module in the graph in breadth-first order, starting with the entry.
+    /// The index of a module in this list is its ID in the bundle.
+    pub modules: Vec<SourceId>,
+
+    /// The dependencies of every module, in the order they first appear in its source.
+    pub deps: HashMap<SourceId, Vec<SourceId>>,
+}
+
+impl ModuleGraph {
+    /// Collect the modules reachable from `entry` through the dependencies resolved in
+    /// their semantic information, as returned by `sem_of`.
+    /// Modules without semantic information are left out of the graph.
+    pub fn build<'s>(
+        gc: &GCLock,
+        entry: SourceId,
+        sem_of: impl Fn(SourceId) -> Option<&'s SemContext>,
+    ) -> ModuleGraph {
+        let mut graph = ModuleGraph::default();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(entry);
+        queue.push_back(entry);
+        while let Some(id) = queue.pop_front() {
+            let sem = match sem_of(id) {
+                Some(sem) => sem,
+                None => continue,
+            };
+            let deps: Vec<SourceId> = dependencies(gc, sem)
+                .into_iter()
+                .filter(|&dep| sem_of(dep).is_some())
+                .collect();
+            for &dep in &deps {
+                if seen.insert(dep) {
+                    queue.push_back(dep);
+                }
+            }
+            graph.modules.push(id);
+            graph.deps.insert(id, deps);
+        }
+        graph
+    }
+}
+
+/// The modules `sem` depends on at runtime, in the order they first appear in the source.
+fn dependencies(gc: &GCLock, sem: &SemContext) -> Vec<SourceId> {
+    let mut requires: Vec<((u32, u32), SourceId)> = sem
+        .all_requires()
+        .iter()
+        .filter_map(|(node, &id)| {
+            let node = node.node(gc);
+            if !id.is_valid() || is_type_only(node) {
+                return None;
+            }
+            let start = node.range().start;
+            Some(((start.line, start.col), id))
+        })
+        .collect();
+    requires.sort_by_key(|&(loc, _)| loc);
+    let mut seen = HashSet::new();
+    requires
+        .into_iter()
+        .map(|(_, id)| id)
+        .filter(|&id| seen.insert(id))
+        .collect()
+}
+
+/// The module which `node`, a `require` call or a declaration importing from another
+/// module, refers to in `sem`.
+/// Declarations are also found by their source, so they may have been rebuilt since
+/// `sem` was created, e.g. to remove some of their specifiers.
+fn required_module<'gc>(
+    gc: &'gc GCLock,
+    sem: &SemContext,
+    node: &'gc Node<'gc>,
+) -> Option<SourceId> {
+    if let Some(&id) = sem.all_requires().get(&NodeRc::from_node(gc, node)) {
+        return Some(id);
+    }
+    let source = declaration_source(node)?;
+    sem.all_requires()
+        .iter()
+        .find_map(|(decl, &id)| match declaration_source(decl.node(gc)) {
+            Some(decl_source) if std::ptr::eq(decl_source, source) => Some(id),
+            _ => None,
+        })
+}
+
+/// The source of `node` if it is a declaration importing from another module.
+fn declaration_source<'gc>(node: &'gc Node<'gc>) -> Option<&'gc Node<'gc>> {
+    match node {
+        Node::ImportDeclaration(ImportDeclaration { source, .. })
+        | Node::ExportAllDeclaration(ExportAllDeclaration { source, .. })
+        | Node::ExportNamedDeclaration(ExportNamedDeclaration {
+            source: Some(source),
+            ..
+        }) => Some(source),
+        _ => None,
+    }
+}
+
+/// Whether `node` only imports or exports types, so it doesn't exist at runtime.
+fn is_type_only(node: &Node) -> bool {
+    match node {
+        Node::ImportDeclaration(ImportDeclaration { import_kind, .. }) => {
+            *import_kind != ImportKind::Value
+        }
+        Node::ExportNamedDeclaration(ExportNamedDeclaration { export_kind, .. })
+        | Node::ExportAllDeclaration(ExportAllDeclaration { export_kind, .. }) => {
+            *export_kind != ExportKind::Value
+        }
+        _ => false,
+    }
+}
+
+/// Whether `module` has any ES `import` or `export` declaration.
+fn is_es_module(module: &Node) -> bool {
+    match module {
+        Node::Module(Module { body, .. }) => body.iter().any(is_module_declaration),
+        _ => false,
+    }
+}
+
+/// Whether `stmt` is an ES `import` or `export` declaration.
+fn is_module_declaration(stmt: &Node) -> bool {
+    matches!(
+        stmt,
+        Node::ImportDeclaration(_)
+            | Node::ExportNamedDeclaration(_)
+            | Node::ExportDefaultDeclaration(_)
+            | Node::ExportAllDeclaration(_)
+    )
+}
+
+/// A module to bundle.
+pub struct BundleModule<'s> {
+    /// The AST of the module, which must be a `Module`.
+    pub ast: NodeRc,
+
+    /// Semantic information of `ast`, as returned by [`crate::sema::resolve_module`].
+    pub sem: &'s SemContext,
+
+    /// Map from the source of the module to its original sources, if it has one.
+    pub source_map: Option<&'s FullSourceMap>,
+
+    /// Comments attached to `ast`, if they are preserved.
+    pub comments: Option<&'s CommentMap>,
+}
+
+/// A script running an entry module and all the modules it depends on.
+pub struct Bundle {
+    /// The JS source of the bundle.
+    pub code: String,
+
+    /// Map from `code` to the sources of all the modules in the bundle.
+    pub source_map: FullSourceMap,
+
+    /// The modules in the bundle.
+    pub graph: ModuleGraph,
+}
+
+/// Bundle `entry` and the modules it depends on, which must all be in `modules`.
+/// Dependencies which aren't in `modules` are left to be required by name at runtime,
+/// with the `require` of the host.
+/// Every module is generated with `opt`, except for its comments, doc block and annotation.
+pub fn bundle<'s>(
+    ctx: &mut Context,
+    entry: SourceId,
+    modules: &HashMap<SourceId, BundleModule<'s>>,
+    opt: gen_js::Opt<'s>,
+) -> io::Result<Bundle> {
+    let linker = Linker::new(ctx, entry, modules);
+    let mut out = Output::default();
+    out.push(RUNTIME);
+    for &id in &linker.graph.modules {
+        linker.define(ctx, &mut out, id, &opt)?;
+    }
+    out.push("__r(0);\n");
+    Ok(out.finish(linker.graph))
+}
+
+/// The modules of a bundle.
+struct Linker<'a, 's> {
+    modules: &'a HashMap<SourceId, BundleModule<'s>>,
+    graph: ModuleGraph,
+    /// The ID of every module in the bundle.
+    ids: HashMap<SourceId, u32>,
+    /// The modules in the bundle which are ES modules.
+    es_modules: HashSet<SourceId>,
+}
+
+impl<'a, 's> Linker<'a, 's> {
+    fn new(
+        ctx: &mut Context,
+        entry: SourceId,
+        modules: &'a HashMap<SourceId, BundleModule<'s>>,
+    ) -> Self {
+        let gc = GCLock::new(ctx);
+        let graph = ModuleGraph::build(&gc, entry, |id| modules.get(&id).map(|m| m.sem));
+        let es_modules: HashSet<SourceId> = graph
+            .modules
+            .iter()
+            .copied()
+            .filter(|id| is_es_module(modules[id].ast.node(&gc)))
+            .collect();
+        let ids: HashMap<SourceId, u32> = graph
+            .modules
+            .iter()
+            .enumerate()
+            .map(|(index, &id)| (id, index as u32))
+            .collect();
+        Linker {
+            modules,
+            graph,
+            ids,
+            es_modules,
+        }
+    }
+
+    /// Convert the module `id` to CommonJS and register it with `__d` at the end of `out`.
+    fn define(
+        &self,
+        ctx: &mut Context,
+        out: &mut Output,
+        id: SourceId,
+        opt: &gen_js::Opt,
+    ) -> io::Result<()> {
+        let module = &self.modules[&id];
+        let ast = {
+            let gc = GCLock::new(ctx);
+            let mut converter = ToCommonJS {
+                sem: module.sem,
+                ids: &self.ids,
+                es_modules: &self.es_modules,
+                imports: HashMap::new(),
+            };
+            let converted = converter.convert(&gc, module.ast.node(&gc));
+            NodeRc::from_node(&gc, converted)
+        };
+
+        out.push("__d(function (global, require, module, exports) {\n");
+        if self.es_modules.contains(&id) {
+            out.push("'use strict';\n");
+        }
+        out.push_code(ctx, &ast, id, module, opt)?;
+        out.push("\n");
+        self.push_registration(ctx, out, id)
+    }
+
+    /// Close the factory of the module `id` at the end of `out` and register it.
+    fn push_registration(&self, ctx: &Context, out: &mut Output, id: SourceId) -> io::Result<()> {
+        let deps: Vec<String> = self.graph.deps[&id]
+            .iter()
+            .map(|dep| self.ids[dep].to_string())
+            .collect();
+        let name = serde_json::to_string(ctx.sm().source_name(id))?;
+        out.push(&format!(
+            "}}, {}, [{}], {});\n",
+            self.ids[&id],
+            deps.join(", "),
+            name
+        ));
+        Ok(())
+    }
+}
+
+/// The code of a bundle being generated, with its number of lines and the source maps
+/// of its parts.
+#[derive(Default)]
+struct Output {
+    code: String,
+    line: u32,
+    /// The line each part of the code generated from a module starts on, and its map.
+    sections: Vec<(u32, FullSourceMap)>,
+}
+
+impl Output {
+    fn push(&mut self, text: &str) {
+        self.code.push_str(text);
+        self.line += text.matches('\n').count() as u32;
+    }
+
+    /// Generate `ast`, which is all or part of `module` with the ID `id`, without its
+    /// trailing newline, and add its source map to the sections.
+    fn push_code(
+        &mut self,
+        ctx: &mut Context,
+        ast: &NodeRc,
+        id: SourceId,
+        module: &BundleModule,
+        opt: &gen_js::Opt,
+    ) -> io::Result<()> {
+        let start_line = self.line;
+        let mut buf: Vec<u8> = vec![];
+        let map = gen_js::generate(
+            &mut buf,
+            ctx,
+            ast,
+            gen_js::Opt {
+                annotation: gen_js::Annotation::No,
+                doc_block: None,
+                comments: module.comments,
+                ..opt.clone()
+            },
+        )?;
+        self.push(String::from_utf8_lossy(&buf).trim_end());
+
+        let map = FullSourceMap::from(map);
+        let map = match module.source_map {
+            None => map,
+            Some(input_map) => {
+                // Only the source of this module has an input map.
+                let mut inputs = vec![None; map.map.get_source_count() as usize];
+                if let Some(input) = inputs.get_mut(id.0 as usize) {
+                    *input = Some(input_map);
+                }
+                compose_sourcemaps(&map, &inputs)
+            }
+        };
+        self.sections.push((start_line, map));
+        Ok(())
+    }
+
+    fn finish(self, graph: ModuleGraph) -> Bundle {
+        let sections: Vec<(u32, &FullSourceMap)> = self
+            .sections
+            .iter()
+            .map(|(line, map)| (*line, map))
+            .collect();
+        Bundle {
+            source_map: concat_sourcemaps(None, &sections),
+            code: self.code,
+            graph,
+        }
+    }
+}
+
+/// The module that a `require` refers to.
+#[derive(Copy, Clone)]
+enum ModuleRef<'gc> {
+    /// A module in the bundle, by its ID, and whether it is an ES module.
+    Bundled(u32, bool),
+    /// A module outside of the bundle, by the original string literal.
+    External(&'gc Node<'gc>),
+}
+
+/// The value of an imported binding.
+#[derive(Copy, Clone)]
+struct ImportBinding<'gc> {
+    /// The module the binding is imported from.
+    source: ModuleRef<'gc>,
+    /// The name of the export, or `None` for the whole exports object.
+    name: Option<NodeLabel>,
+}
+
+/// Converts a module to the body of a CommonJS factory function.
+struct ToCommonJS<'s, 'gc> {
+    sem: &'s SemContext,
+    /// The ID of every module in the bundle.
+    ids: &'s HashMap<SourceId, u32>,
+    /// The modules in the bundle which are ES modules.
+    es_modules: &'s HashSet<SourceId>,
+    /// The declarations of imported bindings in the module being converted.
+    imports: HashMap<DeclId, ImportBinding<'gc>>,
+}
+
+impl<'gc> ToCommonJS<'_, 'gc> {
+    fn convert(&mut self, gc: &'gc GCLock, module: &'gc Node<'gc>) -> &'gc Node<'gc> {
+        let body = match module {
+            Node::Module(Module { body, .. }) => *body,
+            _ => return module,
+        };
+        let default_name = unique_name(gc, module, "_default");
+
+        // Getters of the exports, by exported name.
+        let mut getters: Vec<(NodeLabel, &'gc Node<'gc>)> = vec![];
+        // Statements running the dependencies, in the order of the declarations.
+        let mut requires: Vec<&'gc Node<'gc>> = vec![];
+        let mut stmts: Vec<&'gc Node<'gc>> = vec![];
+
+        for stmt in body.iter() {
+            if is_type_only(stmt) {
+                continue;
+            }
+            match stmt {
+                Node::ImportDeclaration(ImportDeclaration { specifiers, .. }) => {
+                    let source = self.module_ref(gc, stmt);
+                    for spec in specifiers.iter() {
+                        self.add_import(gc, source, spec);
+                    }
+                    requires.push(expression_statement(gc, require(gc, source, *stmt.range())));
+                }
+                Node::ExportNamedDeclaration(ExportNamedDeclaration {
+                    declaration: Some(decl),
+                    ..
+                }) => {
+                    for id in declared_names(decl) {
+                        getters.push((id_name(id), copy_identifier(gc, id)));
+                    }
+                    stmts.push(decl);
+                }
+                Node::ExportNamedDeclaration(ExportNamedDeclaration {
+                    declaration: None,
+                    specifiers,
+                    source,
+                    ..
+                }) => {
+                    let source = source.map(|_| self.module_ref(gc, stmt));
+                    if let Some(source) = source {
+                        requires.push(expression_statement(gc, require(gc, source, *stmt.range())));
+                    }
+                    for spec in specifiers.iter() {
+                        match (spec, source) {
+                            (
+                                Node::ExportSpecifier(ExportSpecifier {
+                                    exported, local, ..
+                                }),
+                                None,
+                            ) => {
+                                getters.push((id_name(exported), local));
+                            }
+                            (
+                                Node::ExportSpecifier(ExportSpecifier {
+                                    exported, local, ..
+                                }),
+                                Some(source),
+                            ) => {
+                                let binding = self.binding(gc, source, id_name(local));
+                                getters.push((
+                                    id_name(exported),
+                                    binding_value(gc, binding, NO_RANGE),
+                                ));
+                            }
+                            (
+                                Node::ExportNamespaceSpecifier(ExportNamespaceSpecifier {
+                                    exported,
+                                    ..
+                                }),
+                                Some(source),
+                            ) => {
+                                getters.push((id_name(exported), require(gc, source, NO_RANGE)));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Node::ExportDefaultDeclaration(ExportDefaultDeclaration {
+                    declaration, ..
+                }) => {
+                    let default = gc.atom("default");
+                    match declaration {
+                        Node::FunctionDeclaration(FunctionDeclaration { id: Some(id), .. })
+                        | Node::ClassDeclaration(ClassDeclaration { id: Some(id), .. }) => {
+                            getters.push((default, copy_identifier(gc, id)));
+                            stmts.push(declaration);
+                        }
+                        Node::FunctionDeclaration(decl @ FunctionDeclaration { id: None, .. }) => {
+                            let id = identifier(gc, default_name, NO_RANGE);
+                            let mut builder = builder::FunctionDeclaration::from_node(decl);
+                            builder.id(Some(id));
+                            getters.push((default, copy_identifier(gc, id)));
+                            stmts.push(builder.build_forced(gc));
+                        }
+                        Node::ClassDeclaration(decl @ ClassDeclaration { id: None, .. }) => {
+                            let id = identifier(gc, default_name, NO_RANGE);
+                            let mut builder = builder::ClassDeclaration::from_node(decl);
+                            builder.id(Some(id));
+                            getters.push((default, copy_identifier(gc, id)));
+                            stmts.push(builder.build_forced(gc));
+                        }
+                        expr => {
+                            let id = identifier(gc, default_name, NO_RANGE);
+                            getters.push((default, copy_identifier(gc, id)));
+                            stmts.push(var_declaration(gc, id, expr, *stmt.range()));
+                        }
+                    }
+                }
+                Node::ExportAllDeclaration(_) => {
+                    let source = self.module_ref(gc, stmt);
+                    let export_all = call(
+                        gc,
+                        member(
+                            gc,
+                            identifier(gc, gc.atom("require"), NO_RANGE),
+                            "exportAll",
+                            NO_RANGE,
+                        ),
+                        &[
+                            identifier(gc, gc.atom("exports"), NO_RANGE),
+                            module_ref_value(gc, source),
+                        ],
+                        *stmt.range(),
+                    );
+                    requires.push(expression_statement(gc, export_all));
+                }
+                _ => stmts.push(stmt),
+            }
+        }
+
+        let mut result = vec![];
+        if !getters.is_empty() {
+            let properties = getters.into_iter().map(|(name, value)| {
+                builder::Property::build_template(
+                    gc,
+                    template::Property {
+                        metadata: Default::default(),
+                        key: string_literal(gc, gc.str(name)),
+                        value: getter(gc, value),
+                        kind: PropertyKind::Init,
+                        computed: false,
+                        method: false,
+                        shorthand: false,
+                    },
+                )
+            });
+            let properties = NodeList::from_iter(gc, properties);
+            let getters = builder::ObjectExpression::build_template(
+                gc,
+                template::ObjectExpression {
+                    metadata: Default::default(),
+                    properties,
+                },
+            );
+            let define = call(
+                gc,
+                member(
+                    gc,
+                    identifier(gc, gc.atom("require"), NO_RANGE),
+                    "defineExports",
+                    NO_RANGE,
+                ),
+                &[identifier(gc, gc.atom("exports"), NO_RANGE), getters],
+                NO_RANGE,
+            );
+            result.push(expression_statement(gc, define));
+        }
+        result.extend(requires);
+        result.extend(stmts);
+
+        let module = builder::Module::build_template(
+            gc,
+            template::Module {
+                metadata: TemplateMetadata {
+                    phantom: Default::default(),
+                    range: *module.range(),
+                },
+                body: NodeList::from_iter(gc, result),
+            },
+        );
+        module.visit_mut(gc, self, None).unwrap_or(module)
+    }
+
+    /// The module required by `node`, which must be a `require` call or a declaration
+    /// importing from another module.
+    fn module_ref(&self, gc: &'gc GCLock, node: &'gc Node<'gc>) -> ModuleRef<'gc> {
+        let module = required_module(gc, self.sem, node)
+            .and_then(|module| Some((self.ids.get(&module)?, module)));
+        match (module, node) {
+            (Some((&id, module)), _) => ModuleRef::Bundled(id, self.es_modules.contains(&module)),
+            (None, Node::CallExpression(CallExpression { arguments, .. })) => {
+                ModuleRef::External(arguments.head().unwrap())
+            }
+            (None, Node::ImportDeclaration(ImportDeclaration { source, .. }))
+            | (None, Node::ExportAllDeclaration(ExportAllDeclaration { source, .. }))
+            | (
+                None,
+                Node::ExportNamedDeclaration(ExportNamedDeclaration {
+                    source: Some(source),
+                    ..
+                }),
+            ) => ModuleRef::External(source),
+            _ => unreachable!("{} doesn't refer to a module", node.name()),
+        }
+    }
+
+    /// The binding of the export `name` of `source`.
+    /// `default` refers to the whole exports object of CommonJS modules.
+    fn binding(&self, gc: &GCLock, source: ModuleRef<'gc>, name: NodeLabel) -> ImportBinding<'gc> {
+        let is_es_module = matches!(source, ModuleRef::Bundled(_, true));
+        ImportBinding {
+            source,
+            name: if !is_es_module && gc.str(name) == "default" {
+                None
+            } else {
+                Some(name)
+            },
+        }
+    }
+
+    /// Record the binding declared by the import specifier `spec`.
+    fn add_import(&mut self, gc: &'gc GCLock, source: ModuleRef<'gc>, spec: &'gc Node<'gc>) {
+        let (local, binding) = match spec {
+            Node::ImportSpecifier(ImportSpecifier {
+                imported,
+                local,
+                import_kind: ImportKind::Value,
+                ..
+            }) => (*local, self.binding(gc, source, id_name(imported))),
+            Node::ImportDefaultSpecifier(ImportDefaultSpecifier { local, .. }) => {
+                (*local, self.binding(gc, source, gc.atom("default")))
+            }
+            Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier { local, .. }) => {
+                (*local, ImportBinding { source, name: None })
+            }
+            _ => return,
+        };
+        if let Some(Resolution::Decl(decl)) = self.sem.ident_decl(&NodeRc::from_node(gc, local)) {
+            self.imports.insert(decl, binding);
+        }
+    }
+
+    /// The imported binding that the identifier `node` refers to, if any.
+    fn imported(&self, gc: &'gc GCLock, node: &'gc Node<'gc>) -> Option<ImportBinding<'gc>> {
+        match self.sem.ident_decl(&NodeRc::from_node(gc, node)) {
+            Some(Resolution::Decl(decl)) => self.imports.get(&decl).copied(),
+            _ => None,
+        }
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for ToCommonJS<'_, 'gc> {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        match node {
+            Node::Identifier(_) => {
+                let binding = match self.imported(gc, node) {
+                    Some(binding) => binding,
+                    None => return TransformResult::Unchanged,
+                };
+                let mut value = binding_value(gc, binding, *node.range());
+                if binding.name.is_some() {
+                    // Don't pass the exports object as `this` to calls.
+                    if let Some(Path {
+                        parent:
+                            Node::CallExpression(_)
+                            | Node::OptionalCallExpression(_)
+                            | Node::NewExpression(_),
+                        field: NodeField::callee,
+                    })
+                    | Some(Path {
+                        parent: Node::TaggedTemplateExpression(_),
+                        field: NodeField::tag,
+                    }) = path
+                    {
+                        value = builder::SequenceExpression::build_template(
+                            gc,
+                            template::SequenceExpression {
+                                metadata: TemplateMetadata {
+                                    phantom: Default::default(),
+                                    range: *node.range(),
+                                },
+                                expressions: NodeList::from_iter(
+                                    gc,
+                                    [number_literal(gc, 0.0, NO_RANGE), value],
+                                ),
+                            },
+                        );
+                    }
+                }
+                TransformResult::Changed(value)
+            }
+            Node::Property(
+                prop @ Property {
+                    shorthand: true,
+                    value: value @ Node::Identifier(_),
+                    ..
+                },
+            ) if self.imported(gc, value).is_some() => {
+                let mut builder = builder::Property::from_node(prop);
+                builder.shorthand(false);
+                node.replace_with_new(builder::Builder::Property(builder), gc, self)
+            }
+            Node::CallExpression(CallExpression { arguments, .. })
+                if self
+                    .sem
+                    .all_requires()
+                    .contains_key(&NodeRc::from_node(gc, node)) =>
+            {
+                match self.module_ref(gc, node) {
+                    ModuleRef::Bundled(id, _) => {
+                        let arg = arguments.head().unwrap();
+                        let mut builder = builder::CallExpression::from_node(node_cast!(
+                            Node::CallExpression,
+                            node
+                        ));
+                        builder.arguments(NodeList::from_iter(
+                            gc,
+                            [number_literal(gc, id as f64, *arg.range())],
+                        ));
+                        TransformResult::Changed(builder.build_forced(gc))
+                    }
+                    ModuleRef::External(_) => node.visit_children_mut(gc, self),
+                }
+            }
+            _ => node.visit_children_mut(gc, self),
+        }
+    }
+}
+
+/// Range of the nodes which don't correspond to any source.
+const NO_RANGE: SourceRange = SourceRange {
+    file: SourceId::INVALID,
+    start: SourceLoc { line: 0, col: 0 },
+    end: SourceLoc { line: 0, col: 0 },
+};
+
+/// Return `base`, or `base` with a numeric suffix if `base` is already an identifier
+/// in `root`.
+fn unique_name<'gc>(gc: &'gc GCLock, root: &'gc Node<'gc>, base: &str) -> NodeLabel {
+    struct Names<'gc> {
+        names: HashSet<&'gc str>,
+    }
+    impl<'gc> Visitor<'gc> for Names<'gc> {
+        fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+            if let Node::Identifier(Identifier { name, .. }) = node {
+                self.names.insert(gc.str(*name));
+            }
+            node.visit_children(gc, self);
+        }
+    }
+    let mut names = Names {
+        names: HashSet::new(),
+    };
+    root.visit(gc, &mut names, None);
+    let mut name = base.to_string();
+    let mut suffix = 1;
+    while names.names.contains(name.as_str()) {
+        name = format!("{}{}", base, suffix);
+        suffix += 1;
+    }
+    gc.atom(name)
+}
+
+/// The identifiers declared by the declaration `decl`.
+fn declared_names<'gc>(decl: &'gc Node<'gc>) -> Vec<&'gc Node<'gc>> {
+    let mut result = vec![];
+    match decl {
+        Node::VariableDeclaration(VariableDeclaration { declarations, .. }) => {
+            for declarator in declarations.iter() {
+                if let Node::VariableDeclarator(VariableDeclarator { id, .. }) = declarator {
+                    pattern_names(id, &mut result);
+                }
+            }
+        }
+        Node::FunctionDeclaration(FunctionDeclaration { id: Some(id), .. })
+        | Node::ClassDeclaration(ClassDeclaration { id: Some(id), .. }) => result.push(*id),
+        _ => {}
+    }
+    result
+}
+
+/// Append the identifiers bound by `pattern` to `result`.
+fn pattern_names<'gc>(pattern: &'gc Node<'gc>, result: &mut Vec<&'gc Node<'gc>>) {
+    match pattern {
+        Node::Identifier(_) => result.push(pattern),
+        Node::ObjectPattern(ObjectPattern { properties, .. }) => {
+            for prop in properties.iter() {
+                match prop {
+                    Node::Property(Property { value, .. }) => pattern_names(value, result),
+                    Node::RestElement(RestElement { argument, .. }) => {
+                        pattern_names(argument, result)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Node::ArrayPattern(ArrayPattern { elements, .. }) => {
+            for element in elements.iter() {
+                pattern_names(element, result);
+            }
+        }
+        Node::RestElement(RestElement { argument, .. }) => pattern_names(argument, result),
+        Node::AssignmentPattern(AssignmentPattern { left, .. }) => pattern_names(left, result),
+        _ => {}
+    }
+}
+
+fn id_name(node: &Node) -> NodeLabel {
+    node_cast!(Node::Identifier, node).name
+}
+
+fn identifier<'gc>(gc: &'gc GCLock, name: NodeLabel, range: SourceRange) -> &'gc Node<'gc> {
+    builder::Identifier::build_template(
+        gc,
+        template::Identifier {
+            metadata: TemplateMetadata {
+                phantom: Default::default(),
+                range,
+            },
+            name,
+            type_annotation: None,
+            optional: false,
+        },
+    )
+}
+
+/// A new identifier with the name and range of the identifier `node`, without its type.
+fn copy_identifier<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> &'gc Node<'gc> {
+    identifier(gc, id_name(node), *node.range())
+}
+
+fn string_literal<'gc>(gc: &'gc GCLock, value: &str) -> &'gc Node<'gc> {
+    builder::StringLiteral::build_template(
+        gc,
+        template::StringLiteral {
+            metadata: Default::default(),
+            value: gc.atom_u16(value.encode_utf16().collect::<Vec<u16>>()),
+        },
+    )
+}
+
+fn number_literal<'gc>(gc: &'gc GCLock, value: f64, range: SourceRange) -> &'gc Node<'gc> {
+    builder::NumericLiteral::build_template(
+        gc,
+        template::NumericLiteral {
+            metadata: TemplateMetadata {
+                phantom: Default::default(),
+                range,
+            },
+            value,
+        },
+    )
+}
+
+/// `object.property`
+fn member<'gc>(
+    gc: &'gc GCLock,
+    object: &'gc Node<'gc>,
+    property: &str,
+    range: SourceRange,
+) -> &'gc Node<'gc> {
+    builder::MemberExpression::build_template(
+        gc,
+        template::MemberExpression {
+            metadata: TemplateMetadata {
+                phantom: Default::default(),
+                range,
+            },
+            object,
+            property: identifier(gc, gc.atom(property), NO_RANGE),
+            computed: false,
+        },
+    )
+}
+
+fn call<'gc>(
+    gc: &'gc GCLock,
+    callee: &'gc Node<'gc>,
+    arguments: &[&'gc Node<'gc>],
+    range: SourceRange,
+) -> &'gc Node<'gc> {
+    builder::CallExpression::build_template(
+        gc,
+        template::CallExpression {
+            metadata: TemplateMetadata {
+                phantom: Default::default(),
+                range,
+            },
+            callee,
+            type_arguments: None,
+            arguments: NodeList::from_iter(gc, arguments.iter().copied()),
+        },
+    )
+}
+
+/// The argument of `require` for `source`.
+fn module_ref_value<'gc>(gc: &'gc GCLock, source: ModuleRef<'gc>) -> &'gc Node<'gc> {
+    match source {
+        ModuleRef::Bundled(id, _) => number_literal(gc, id as f64, NO_RANGE),
+        ModuleRef::External(literal) => literal,
+    }
+}
+
+/// `require(source)`
+fn require<'gc>(gc: &'gc GCLock, source: ModuleRef<'gc>, range: SourceRange) -> &'gc Node<'gc> {
+    call(
+        gc,
+        identifier(gc, gc.atom("require"), NO_RANGE),
+        &[module_ref_value(gc, source)],
+        range,
+    )
+}
+
+/// The expression reading the current value of `binding`, mapped to `range`.
+fn binding_value<'gc>(
+    gc: &'gc GCLock,
+    binding: ImportBinding<'gc>,
+    range: SourceRange,
+) -> &'gc Node<'gc> {
+    match binding.name {
+        None => require(gc, binding.source, range),
+        Some(name) => member(
+            gc,
+            require(gc, binding.source, NO_RANGE),
+            gc.str(name),
+            range,
+        ),
+    }
+}
+
+/// `function () { return value; }`
+fn getter<'gc>(gc: &'gc GCLock, value: &'gc Node<'gc>) -> &'gc Node<'gc> {
+    let ret = builder::ReturnStatement::build_template(
+        gc,
+        template::ReturnStatement {
+            metadata: Default::default(),
+            argument: Some(value),
+        },
+    );
+    let body = builder::BlockStatement::build_template(
+        gc,
+        template::BlockStatement {
+            metadata: Default::default(),
+            body: NodeList::from_iter(gc, [ret]),
+        },
+    );
+    builder::FunctionExpression::build_template(
+        gc,
+        template::FunctionExpression {
+            metadata: Default::default(),
+            id: None,
+            params: NodeList::new(gc),
+            body,
+            type_parameters: None,
+            return_type: None,
+            predicate: None,
+            generator: false,
+            is_async: false,
+        },
+    )
+}
+
+fn expression_statement<'gc>(gc: &'gc GCLock, expression: &'gc Node<'gc>) -> &'gc Node<'gc> {
+    builder::ExpressionStatement::build_template(
+        gc,
+        template::ExpressionStatement {
+            metadata: TemplateMetadata {
+                phantom: Default::default(),
+                range: *expression.range(),
+            },
+            expression,
+            directive: None,
+        },
+    )
+}
+
+/// `import {specifiers} from source;`
+fn import_declaration<'gc>(
+    gc: &'gc GCLock,
+    specifiers: &[&'gc Node<'gc>],
+    source: &'gc Node<'gc>,
+) -> &'gc Node<'gc> {
+    builder::ImportDeclaration::build_template(
+        gc,
+        template::ImportDeclaration {
+            metadata: TemplateMetadata {
+                phantom: Default::default(),
+                range: *source.range(),
+            },
+            specifiers: NodeList::from_iter(gc, specifiers.iter().copied()),
+            source,
+            assertions: None,
+            import_kind: ImportKind::Value,
+        },
+    )
+}
+
+/// `var id = init;`
+fn var_declaration<'gc>(
+    gc: &'gc GCLock,
+    id: &'gc Node<'gc>,
+    init: &'gc Node<'gc>,
+    range: SourceRange,
+) -> &'gc Node<'gc> {
+    let declarator = builder::VariableDeclarator::build_template(
+        gc,
+        template::VariableDeclarator {
+            metadata: Default::default(),
+            init: Some(init),
+            id,
+        },
+    );
+    builder::VariableDeclaration::build_template(
+        gc,
+        template::VariableDeclaration {
+            metadata: TemplateMetadata {
+                phantom: Default::default(),
+                range,
+            },
+            kind: VariableDeclarationKind::Var,
+            declarations: NodeList::from_iter(gc, [declarator]),
+        },
+    )
+}
@@ -0,0 +1,927 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Scope hoisting, which concatenates the ES modules of a bundle into a single scope.
+//!
+//! Instead of being wrapped in a factory, the top-level code of every ES module is
+//! inlined into the top-level scope of the bundle, which is itself an ES module exporting
+//! the exports of the entry module. A module is placed after the modules it imports, so
+//! modules run in the same order as if they were loaded separately.
+//!
+//! Top-level declarations whose names conflict with those of another module, or with
+//! names that the code of any module refers to in another scope, are renamed with
+//! [`SemContext::rename`]. References to imported bindings are renamed to the declaration
+//! they are bound to in the exporting module, so they stay live, and cyclic imports work
+//! just as they did without bundling.
+//!
+//! CommonJS modules, and ES modules loaded with `require`, are still registered with the
+//! module registry. Imports of modules outside of the bundle are kept as `import`
+//! declarations at the top of the bundle.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path as FilePath;
+
+use juno_support::source_manager::SourceId;
+
+use super::*;
+use crate::sema::LexicalScopeId;
+
+/// Bundle `entry` and the modules it depends on like [`bundle`], but hoist the top-level
+/// scopes of the ES modules into the scope of the bundle.
+/// The result is an ES module with the exports of `entry`.
+pub fn bundle_scope_hoisted<'s>(
+    ctx: &mut Context,
+    entry: SourceId,
+    modules: &HashMap<SourceId, BundleModule<'s>>,
+    opt: gen_js::Opt<'s>,
+) -> io::Result<Bundle> {
+    let linker = Linker::new(ctx, entry, modules);
+    let parts = {
+        let gc = GCLock::new(ctx);
+        let mut hoister = ScopeHoister::new(&gc, &linker);
+        let order = hoister.order(&gc, entry);
+        for &id in &order {
+            let module = hoister.hoist(&gc, id);
+            hoister.hoisted.insert(id, module);
+        }
+        let mut parts = vec![];
+        for &id in &order {
+            parts.push(hoister.link(&gc, id)?);
+        }
+        let exports = if linker.es_modules.contains(&entry) {
+            hoister.entry_exports(&gc, entry)
+        } else {
+            String::new()
+        };
+        Parts {
+            modules: parts,
+            required: hoister.required_namespaces(&gc),
+            exports,
+        }
+    };
+
+    let mut out = Output::default();
+    for part in &parts.modules {
+        if let Some(imports) = &part.imports {
+            out.push_code(ctx, imports, part.id, &modules[&part.id], &opt)?;
+            out.push("\n");
+        }
+    }
+    let registered: Vec<SourceId> = linker
+        .graph
+        .modules
+        .iter()
+        .copied()
+        .filter(|id| !linker.es_modules.contains(id) || parts.required.contains_key(id))
+        .collect();
+    if !registered.is_empty() {
+        out.push(RUNTIME);
+    }
+    for &id in &registered {
+        match parts.required.get(&id) {
+            // The exports of a hoisted module are its namespace object.
+            Some(namespace) => {
+                out.push("__d(function (global, require, module, exports) {\n");
+                out.push(&format!("module.exports = {};\n", namespace));
+                linker.push_registration(ctx, &mut out, id)?;
+            }
+            None => linker.define(ctx, &mut out, id, &opt)?,
+        }
+    }
+    for part in &parts.modules {
+        if let Some(body) = &part.body {
+            out.push_code(ctx, body, part.id, &modules[&part.id], &opt)?;
+            out.push("\n");
+        }
+    }
+    if linker.es_modules.contains(&entry) {
+        out.push(&parts.exports);
+    } else {
+        out.push(&format!("__r({});\n", linker.ids[&entry]));
+    }
+    Ok(out.finish(linker.graph))
+}
+
+/// The code of a bundle to generate.
+struct Parts {
+    modules: Vec<HoistedPart>,
+    /// The name of the namespace object of every hoisted module loaded with `require`.
+    required: HashMap<SourceId, String>,
+    /// The `export` declaration of the exports of the entry module.
+    exports: String,
+}
+
+/// The code of a hoisted module, converted to a `Module` to generate.
+struct HoistedPart {
+    id: SourceId,
+    /// Imports from outside of the bundle, which go at the top of the bundle.
+    imports: Option<NodeRc>,
+    /// The rest of the module.
+    body: Option<NodeRc>,
+}
+
+/// A binding exported by a hoisted module.
+#[derive(Copy, Clone)]
+enum Export {
+    /// A top-level declaration of the module, which may be an import.
+    Decl(DeclId),
+    /// A variable added to the module, by its name in the bundle.
+    Var(NodeLabel),
+    /// An export of another hoisted module.
+    Reexport(SourceId, NodeLabel),
+    /// The namespace object of another hoisted module.
+    Namespace(SourceId),
+}
+
+/// The module that an import, export or `require` refers to.
+enum Target<'gc> {
+    /// An ES module hoisted into the bundle.
+    Hoisted(SourceId),
+    /// A module in the module registry, by its ID.
+    Registered(u32),
+    /// A module outside of the bundle, by the original string literal.
+    External(&'gc Node<'gc>),
+}
+
+/// An ES module whose imports and exports have been removed.
+struct HoistedModule<'gc> {
+    /// Declarations importing from modules outside of the bundle.
+    imports: Vec<&'gc Node<'gc>>,
+    /// The remaining statements of the module.
+    body: Vec<&'gc Node<'gc>>,
+    /// The exports of the module, in order.
+    exports: Vec<(NodeLabel, Export)>,
+    /// The hoisted modules whose exports are all re-exported with `export *`.
+    star_exports: Vec<SourceId>,
+    /// The bindings imported from hoisted modules, by the module and the name of the
+    /// export, or `None` for the namespace object of the module.
+    bindings: HashMap<DeclId, (SourceId, Option<NodeLabel>)>,
+    /// The new names of the top-level declarations which are renamed.
+    names: HashMap<DeclId, NodeLabel>,
+}
+
+struct ScopeHoister<'l, 'gc> {
+    linker: &'l Linker<'l, 'l>,
+    /// Every name used in any module of the bundle, which new names must not conflict with.
+    used: HashSet<NodeLabel>,
+    /// Names referred to outside of the top-level scope of hoisted modules,
+    /// which top-level declarations must not have.
+    reserved: HashSet<NodeLabel>,
+    /// The names of the declarations in the scope of the bundle.
+    top_level: HashSet<NodeLabel>,
+    /// The name of the namespace object of every hoisted module which needs one.
+    namespaces: HashMap<SourceId, NodeLabel>,
+    /// The modules converted so far.
+    hoisted: HashMap<SourceId, HoistedModule<'gc>>,
+    /// The name of default exports.
+    default: NodeLabel,
+}
+
+impl<'l, 'gc> ScopeHoister<'l, 'gc> {
+    fn new(gc: &'gc GCLock, linker: &'l Linker<'l, 'l>) -> Self {
+        let mut hoister = ScopeHoister {
+            linker,
+            used: HashSet::new(),
+            reserved: HashSet::new(),
+            top_level: HashSet::new(),
+            namespaces: HashMap::new(),
+            hoisted: HashMap::new(),
+            default: gc.atom("default"),
+        };
+        // The names used by the module registry and namespace objects.
+        for name in ["__d", "__r", "Object"] {
+            hoister.reserved.insert(gc.atom(name));
+        }
+        for id in &linker.graph.modules {
+            let module = &linker.modules[id];
+            let sem = module.sem;
+            let scope = linker
+                .es_modules
+                .contains(id)
+                .then(|| module_scope(gc, module));
+            for decl in sem.all_decls() {
+                hoister.used.insert(decl.name);
+                // Globals are referred to from anywhere, and other declarations may
+                // shadow top-level declarations of the bundle.
+                if decl.scope.is_global() || matches!(scope, Some(s) if s != decl.scope) {
+                    hoister.reserved.insert(decl.name);
+                }
+            }
+        }
+        hoister.used.extend(hoister.reserved.iter().copied());
+        hoister
+    }
+
+    /// The hoisted modules in the order they run: every module after the modules it
+    /// depends on, and the modules loaded with `require` before the entry.
+    fn order(&mut self, gc: &GCLock, entry: SourceId) -> Vec<SourceId> {
+        fn visit(
+            linker: &Linker,
+            id: SourceId,
+            seen: &mut HashSet<SourceId>,
+            result: &mut Vec<SourceId>,
+        ) {
+            if !linker.es_modules.contains(&id) || !seen.insert(id) {
+                return;
+            }
+            for &dep in &linker.graph.deps[&id] {
+                visit(linker, dep, seen, result);
+            }
+            result.push(id);
+        }
+        let mut seen = HashSet::new();
+        let mut result = vec![];
+        let required: Vec<SourceId> = self.required_modules(gc);
+        for &id in &required {
+            self.namespace(gc, id);
+        }
+        for id in required.into_iter().chain([entry]) {
+            visit(self.linker, id, &mut seen, &mut result);
+        }
+        result
+    }
+
+    /// The ES modules which are loaded with `require`, in the order of the graph.
+    fn required_modules(&self, gc: &GCLock) -> Vec<SourceId> {
+        let required: HashSet<SourceId> = self
+            .linker
+            .graph
+            .modules
+            .iter()
+            .flat_map(|id| self.linker.modules[id].sem.all_requires().iter())
+            .filter(|(node, id)| {
+                self.linker.es_modules.contains(id)
+                    && matches!(node.node(gc), Node::CallExpression(_))
+            })
+            .map(|(_, &id)| id)
+            .collect();
+        self.linker
+            .graph
+            .modules
+            .iter()
+            .copied()
+            .filter(|id| required.contains(id))
+            .collect()
+    }
+
+    /// A new name based on `base`, which doesn't conflict with any other name.
+    fn fresh_name(&mut self, gc: &GCLock, base: &str) -> NodeLabel {
+        let mut name = gc.atom(base);
+        let mut suffix = 1;
+        while self.used.contains(&name) {
+            name = gc.atom(format!("{}${}", base, suffix));
+            suffix += 1;
+        }
+        self.used.insert(name);
+        self.top_level.insert(name);
+        name
+    }
+
+    /// The name of the namespace object of the hoisted module `id`, creating one if needed.
+    fn namespace(&mut self, gc: &GCLock, id: SourceId) -> NodeLabel {
+        if let Some(&name) = self.namespaces.get(&id) {
+            return name;
+        }
+        let name = self.fresh_name(gc, &base_name(gc.sm().source_name(id)));
+        self.namespaces.insert(id, name);
+        name
+    }
+
+    /// The module that `node` imports from or requires.
+    fn target(
+        &self,
+        gc: &'gc GCLock,
+        sem: &SemContext,
+        node: &'gc Node<'gc>,
+        source: &'gc Node<'gc>,
+    ) -> Target<'gc> {
+        match required_module(gc, sem, node).as_ref() {
+            Some(&id)
+                if self.linker.es_modules.contains(&id)
+                    && !matches!(node, Node::CallExpression(_)) =>
+            {
+                Target::Hoisted(id)
+            }
+            Some(id) => match self.linker.ids.get(id) {
+                Some(&index) => Target::Registered(index),
+                None => Target::External(source),
+            },
+            None => Target::External(source),
+        }
+    }
+
+    /// Remove the imports and exports of the ES module `id` and name its top-level
+    /// declarations.
+    fn hoist(&mut self, gc: &'gc GCLock, id: SourceId) -> HoistedModule<'gc> {
+        let linker = self.linker;
+        let module = &linker.modules[&id];
+        let sem = module.sem;
+        let mut result = HoistedModule {
+            imports: vec![],
+            body: vec![],
+            exports: vec![],
+            star_exports: vec![],
+            bindings: HashMap::new(),
+            names: HashMap::new(),
+        };
+        let ast = module.ast.node(gc);
+        let body = match ast {
+            Node::Module(Module { body, .. }) => *body,
+            _ => return result,
+        };
+        let decl_of = |node: &'gc Node<'gc>| match sem.ident_decl(&NodeRc::from_node(gc, node)) {
+            Some(Resolution::Decl(decl)) => Some(decl),
+            _ => None,
+        };
+        let is_entry = id == self.linker.graph.modules[0];
+        let stem = base_name(gc.sm().source_name(id));
+
+        for stmt in body.iter() {
+            if is_type_only(stmt) {
+                continue;
+            }
+            match stmt {
+                Node::ImportDeclaration(ImportDeclaration {
+                    specifiers, source, ..
+                }) => match self.target(gc, sem, stmt, source) {
+                    Target::Hoisted(target) => {
+                        for spec in specifiers.iter() {
+                            let (local, name) = match spec {
+                                Node::ImportSpecifier(ImportSpecifier {
+                                    imported,
+                                    local,
+                                    import_kind: ImportKind::Value,
+                                    ..
+                                }) => (local, Some(id_name(imported))),
+                                Node::ImportDefaultSpecifier(ImportDefaultSpecifier {
+                                    local,
+                                    ..
+                                }) => (local, Some(self.default)),
+                                Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier {
+                                    local,
+                                    ..
+                                }) => {
+                                    self.namespace(gc, target);
+                                    (local, None)
+                                }
+                                _ => continue,
+                            };
+                            if let Some(decl) = decl_of(local) {
+                                result.bindings.insert(decl, (target, name));
+                            }
+                        }
+                    }
+                    Target::Registered(index) => {
+                        if specifiers.is_empty() {
+                            let exports = registry_require(gc, index, *stmt.range());
+                            result.body.push(expression_statement(gc, exports));
+                        }
+                        for spec in specifiers.iter() {
+                            let exports = registry_require(gc, index, *spec.range());
+                            // The exports object of a CommonJS module is its default export.
+                            let (local, init) = match spec {
+                                Node::ImportSpecifier(ImportSpecifier {
+                                    imported,
+                                    local,
+                                    import_kind: ImportKind::Value,
+                                    ..
+                                }) if gc.str(id_name(imported)) != "default" => (
+                                    local,
+                                    member(gc, exports, gc.str(id_name(imported)), NO_RANGE),
+                                ),
+                                Node::ImportSpecifier(ImportSpecifier {
+                                    local,
+                                    import_kind: ImportKind::Value,
+                                    ..
+                                })
+                                | Node::ImportDefaultSpecifier(ImportDefaultSpecifier {
+                                    local,
+                                    ..
+                                })
+                                | Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier {
+                                    local,
+                                    ..
+                                }) => (local, exports),
+                                _ => continue,
+                            };
+                            result
+                                .body
+                                .push(var_declaration(gc, local, init, *spec.range()));
+                        }
+                    }
+                    Target::External(_) => result.imports.push(stmt),
+                },
+                Node::ExportNamedDeclaration(ExportNamedDeclaration {
+                    declaration: Some(decl),
+                    ..
+                }) => {
+                    for name in declared_names(decl) {
+                        if let Some(d) = decl_of(name) {
+                            result.exports.push((id_name(name), Export::Decl(d)));
+                        }
+                    }
+                    result.body.push(decl);
+                }
+                Node::ExportNamedDeclaration(ExportNamedDeclaration {
+                    declaration: None,
+                    specifiers,
+                    source: None,
+                    ..
+                }) => {
+                    for spec in specifiers.iter() {
+                        if let Node::ExportSpecifier(ExportSpecifier {
+                            exported, local, ..
+                        }) = spec
+                        {
+                            if let Some(d) = decl_of(local) {
+                                result.exports.push((id_name(exported), Export::Decl(d)));
+                            }
+                        }
+                    }
+                }
+                Node::ExportNamedDeclaration(ExportNamedDeclaration {
+                    declaration: None,
+                    specifiers,
+                    source: Some(source),
+                    ..
+                }) => {
+                    let target = self.target(gc, sem, stmt, source);
+                    if specifiers.is_empty() {
+                        match target {
+                            Target::Registered(index) => result.body.push(expression_statement(
+                                gc,
+                                registry_require(gc, index, *stmt.range()),
+                            )),
+                            Target::External(source) => {
+                                result.imports.push(import_declaration(gc, &[], source))
+                            }
+                            Target::Hoisted(_) => {}
+                        }
+                    }
+                    for spec in specifiers.iter() {
+                        let (exported, name) = match spec {
+                            Node::ExportSpecifier(ExportSpecifier {
+                                exported, local, ..
+                            }) => (id_name(exported), Some(id_name(local))),
+                            Node::ExportNamespaceSpecifier(ExportNamespaceSpecifier {
+                                exported,
+                                ..
+                            }) => (id_name(exported), None),
+                            _ => continue,
+                        };
+                        let export = match &target {
+                            Target::Hoisted(target) => match name {
+                                Some(name) => Export::Reexport(*target, name),
+                                None => Export::Namespace(*target),
+                            },
+                            Target::Registered(index) => {
+                                let var = self.fresh_name(gc, gc.str(exported));
+                                let exports = registry_require(gc, *index, *spec.range());
+                                let init = match name {
+                                    Some(name) if gc.str(name) != "default" => {
+                                        member(gc, exports, gc.str(name), NO_RANGE)
+                                    }
+                                    _ => exports,
+                                };
+                                let id = identifier(gc, var, NO_RANGE);
+                                result
+                                    .body
+                                    .push(var_declaration(gc, id, init, *spec.range()));
+                                Export::Var(var)
+                            }
+                            Target::External(source) => {
+                                let var = self.fresh_name(gc, gc.str(exported));
+                                let spec = import_specifier(gc, name, var, *spec.range());
+                                result.imports.push(import_declaration(gc, &[spec], source));
+                                Export::Var(var)
+                            }
+                        };
+                        if let Export::Namespace(target) = export {
+                            self.namespace(gc, target);
+                        }
+                        result.exports.push((exported, export));
+                    }
+                }
+                Node::ExportDefaultDeclaration(ExportDefaultDeclaration {
+                    declaration, ..
+                }) => {
+                    let default = self.default;
+                    match declaration {
+                        Node::FunctionDeclaration(FunctionDeclaration { id: Some(id), .. })
+                        | Node::ClassDeclaration(ClassDeclaration { id: Some(id), .. }) => {
+                            if let Some(d) = decl_of(id) {
+                                result.exports.push((default, Export::Decl(d)));
+                            }
+                            result.body.push(declaration);
+                        }
+                        Node::FunctionDeclaration(decl @ FunctionDeclaration { id: None, .. }) => {
+                            let var = self.fresh_name(gc, &format!("{}_default", stem));
+                            let mut builder = builder::FunctionDeclaration::from_node(decl);
+                            builder.id(Some(identifier(gc, var, NO_RANGE)));
+                            result.exports.push((default, Export::Var(var)));
+                            result.body.push(builder.build_forced(gc));
+                        }
+                        Node::ClassDeclaration(decl @ ClassDeclaration { id: None, .. }) => {
+                            let var = self.fresh_name(gc, &format!("{}_default", stem));
+                            let mut builder = builder::ClassDeclaration::from_node(decl);
+                            builder.id(Some(identifier(gc, var, NO_RANGE)));
+                            result.exports.push((default, Export::Var(var)));
+                            result.body.push(builder.build_forced(gc));
+                        }
+                        expr => {
+                            let var = self.fresh_name(gc, &format!("{}_default", stem));
+                            let id = identifier(gc, var, NO_RANGE);
+                            result.exports.push((default, Export::Var(var)));
+                            result
+                                .body
+                                .push(var_declaration(gc, id, expr, *stmt.range()));
+                        }
+                    }
+                }
+                Node::ExportAllDeclaration(ExportAllDeclaration { source, .. }) => {
+                    match self.target(gc, sem, stmt, source) {
+                        Target::Hoisted(target) => result.star_exports.push(target),
+                        // The names exported by a CommonJS module aren't known statically.
+                        Target::Registered(index) => result.body.push(expression_statement(
+                            gc,
+                            registry_require(gc, index, *stmt.range()),
+                        )),
+                        // Only the bundle itself can re-export the names of an external module.
+                        Target::External(source) => {
+                            if is_entry {
+                                result.imports.push(stmt);
+                            } else {
+                                result.imports.push(import_declaration(gc, &[], source));
+                            }
+                        }
+                    }
+                }
+                _ => result.body.push(stmt),
+            }
+        }
+
+        // Name the top-level declarations, except for the bindings which are renamed to
+        // the declarations they are bound to.
+        let scope = module_scope(gc, module);
+        for &decl in &sem.scope(scope).decls {
+            if result.bindings.contains_key(&decl) {
+                continue;
+            }
+            let name = sem.decl(decl).name;
+            if self.reserved.contains(&name) || !self.top_level.insert(name) {
+                let new_name = self.fresh_name(gc, gc.str(name));
+                result.names.insert(decl, new_name);
+            }
+        }
+        result
+    }
+
+    /// The name in the bundle of the export `name` of the hoisted module `id`, or `None`
+    /// if it isn't exported.
+    fn resolve_export(
+        &self,
+        id: SourceId,
+        name: NodeLabel,
+        seen: &mut HashSet<(SourceId, NodeLabel)>,
+    ) -> Option<NodeLabel> {
+        if !seen.insert((id, name)) {
+            return None;
+        }
+        let module = &self.hoisted[&id];
+        if let Some(&(_, export)) = module.exports.iter().find(|(n, _)| *n == name) {
+            return match export {
+                Export::Decl(decl) => self.resolve_decl(id, decl, seen),
+                Export::Var(var) => Some(var),
+                Export::Reexport(target, name) => self.resolve_export(target, name, seen),
+                Export::Namespace(target) => Some(self.namespaces[&target]),
+            };
+        }
+        // `export *` doesn't re-export the default export.
+        if name == self.default {
+            return None;
+        }
+        module
+            .star_exports
+            .iter()
+            .find_map(|&target| self.resolve_export(target, name, seen))
+    }
+
+    /// The name in the bundle of the top-level declaration `decl` of the module `id`.
+    fn resolve_decl(
+        &self,
+        id: SourceId,
+        decl: DeclId,
+        seen: &mut HashSet<(SourceId, NodeLabel)>,
+    ) -> Option<NodeLabel> {
+        let module = &self.hoisted[&id];
+        match module.bindings.get(&decl) {
+            Some(&(target, Some(name))) => self.resolve_export(target, name, seen),
+            Some(&(target, None)) => Some(self.namespaces[&target]),
+            None => Some(
+                module
+                    .names
+                    .get(&decl)
+                    .copied()
+                    .unwrap_or_else(|| self.linker.modules[&id].sem.decl(decl).name),
+            ),
+        }
+    }
+
+    /// The names exported by the hoisted module `id`, in order, including those
+    /// re-exported with `export *`.
+    fn export_names(&self, id: SourceId, seen: &mut HashSet<SourceId>) -> Vec<NodeLabel> {
+        let mut result = vec![];
+        if !seen.insert(id) {
+            return result;
+        }
+        let module = &self.hoisted[&id];
+        result.extend(module.exports.iter().map(|&(name, _)| name));
+        for &target in &module.star_exports {
+            for name in self.export_names(target, seen) {
+                if name != self.default && !result.contains(&name) {
+                    result.push(name);
+                }
+            }
+        }
+        result
+    }
+
+    /// The exports of the hoisted module `id` with their names in the bundle.
+    fn resolved_exports(&self, id: SourceId) -> Vec<(NodeLabel, NodeLabel)> {
+        self.export_names(id, &mut HashSet::new())
+            .into_iter()
+            .filter_map(|name| Some((name, self.resolve_export(id, name, &mut HashSet::new())?)))
+            .collect()
+    }
+
+    /// Rename the bindings and top-level declarations of the hoisted module `id`
+    /// and return its code.
+    fn link(&self, gc: &'gc GCLock, id: SourceId) -> io::Result<HoistedPart> {
+        let module = &self.linker.modules[&id];
+        let sem = module.sem;
+        let hoisted = &self.hoisted[&id];
+
+        let mut names = hoisted.names.clone();
+        for (&decl, &(target, name)) in &hoisted.bindings {
+            let resolved = match name {
+                Some(name) => self.resolve_export(target, name, &mut HashSet::new()),
+                None => Some(self.namespaces[&target]),
+            };
+            match resolved {
+                Some(resolved) => {
+                    names.insert(decl, resolved);
+                }
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "{}: '{}' is not exported by {}",
+                            gc.sm().source_name(id),
+                            gc.str(name.unwrap()),
+                            gc.sm().source_name(target)
+                        ),
+                    ));
+                }
+            }
+        }
+        let renamed = names
+            .iter()
+            .any(|(&decl, &name)| sem.decl(decl).name != name);
+        if renamed && sem.scope(module_scope(gc, module)).local_eval {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{}: can't rename the declarations of a module using local eval",
+                    gc.sm().source_name(id)
+                ),
+            ));
+        }
+
+        let range = *module.ast.node(gc).range();
+        let part = |stmts: Vec<&'gc Node<'gc>>| -> Option<NodeRc> {
+            if stmts.is_empty() {
+                return None;
+            }
+            let ast = builder::Module::build_template(
+                gc,
+                template::Module {
+                    metadata: TemplateMetadata {
+                        phantom: Default::default(),
+                        range,
+                    },
+                    body: NodeList::from_iter(gc, stmts),
+                },
+            );
+            let mut requires = RequireToRegistry {
+                sem,
+                ids: &self.linker.ids,
+            };
+            let ast = ast.visit_mut(gc, &mut requires, None).unwrap_or(ast);
+            Some(NodeRc::from_node(gc, sem.rename(gc, ast, &names)))
+        };
+
+        let mut body = hoisted.body.clone();
+        if let Some(&namespace) = self.namespaces.get(&id) {
+            body.push(namespace_declaration(
+                gc,
+                namespace,
+                &self.resolved_exports(id),
+            ));
+        }
+        Ok(HoistedPart {
+            id,
+            imports: part(hoisted.imports.clone()),
+            body: part(body),
+        })
+    }
+
+    /// The `export` declaration of the exports of the entry module `id`.
+    fn entry_exports(&self, gc: &GCLock, id: SourceId) -> String {
+        let specifiers: Vec<String> = self
+            .resolved_exports(id)
+            .into_iter()
+            .map(|(exported, local)| {
+                if exported == local {
+                    gc.str(local).to_string()
+                } else {
+                    format!("{} as {}", gc.str(local), gc.str(exported))
+                }
+            })
+            .collect();
+        if specifiers.is_empty() {
+            String::new()
+        } else {
+            format!("export {{{}}};\n", specifiers.join(", "))
+        }
+    }
+
+    /// The names of the namespace objects of the hoisted modules loaded with `require`.
+    fn required_namespaces(&self, gc: &GCLock) -> HashMap<SourceId, String> {
+        self.required_modules(gc)
+            .into_iter()
+            .filter_map(|id| Some((id, gc.str(*self.namespaces.get(&id)?).to_string())))
+            .collect()
+    }
+}
+
+/// The scope of the top-level declarations of `module`.
+fn module_scope(gc: &GCLock, module: &BundleModule) -> LexicalScopeId {
+    module
+        .sem
+        .module_scope()
+        .unwrap_or_else(|| unreachable!("{} has no scope", module.ast.node(gc).name()))
+}
+
+/// A valid identifier based on the file name of `path`.
+fn base_name(path: &str) -> String {
+    let stem = FilePath::new(path)
+        .file_stem()
+        .map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+    let mut result: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '$' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !result.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$') {
+        result.insert(0, '_');
+    }
+    result
+}
+
+/// Replaces the `require` calls of modules in the bundle with calls to `__r`.
+struct RequireToRegistry<'a> {
+    sem: &'a SemContext,
+    ids: &'a HashMap<SourceId, u32>,
+}
+
+impl<'gc> VisitorMut<'gc> for RequireToRegistry<'_> {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        if let Node::CallExpression(CallExpression { arguments, .. }) = node {
+            let index = self
+                .sem
+                .all_requires()
+                .get(&NodeRc::from_node(gc, node))
+                .and_then(|id| self.ids.get(id));
+            if let Some(&index) = index {
+                let arg = arguments.head().unwrap();
+                return TransformResult::Changed(call(
+                    gc,
+                    identifier(gc, gc.atom("__r"), NO_RANGE),
+                    &[number_literal(gc, index as f64, *arg.range())],
+                    *node.range(),
+                ));
+            }
+        }
+        node.visit_children_mut(gc, self)
+    }
+}
+
+/// `__r(index)`
+fn registry_require<'gc>(gc: &'gc GCLock, index: u32, range: SourceRange) -> &'gc Node<'gc> {
+    call(
+        gc,
+        identifier(gc, gc.atom("__r"), NO_RANGE),
+        &[number_literal(gc, index as f64, NO_RANGE)],
+        range,
+    )
+}
+
+/// `import {imported as local}`, or `import * as local` if `imported` is `None`.
+fn import_specifier<'gc>(
+    gc: &'gc GCLock,
+    imported: Option<NodeLabel>,
+    local: NodeLabel,
+    range: SourceRange,
+) -> &'gc Node<'gc> {
+    let metadata = TemplateMetadata {
+        phantom: Default::default(),
+        range,
+    };
+    let local = identifier(gc, local, NO_RANGE);
+    match imported {
+        Some(imported) => builder::ImportSpecifier::build_template(
+            gc,
+            template::ImportSpecifier {
+                metadata,
+                imported: identifier(gc, imported, NO_RANGE),
+                local,
+                import_kind: ImportKind::Value,
+            },
+        ),
+        None => builder::ImportNamespaceSpecifier::build_template(
+            gc,
+            template::ImportNamespaceSpecifier { metadata, local },
+        ),
+    }
+}
+
+/// `var name = Object.freeze({__proto__: null, get export() { return local; }, ...});`
+fn namespace_declaration<'gc>(
+    gc: &'gc GCLock,
+    name: NodeLabel,
+    exports: &[(NodeLabel, NodeLabel)],
+) -> &'gc Node<'gc> {
+    let property = |key: &str, value: &'gc Node<'gc>, kind: PropertyKind| {
+        builder::Property::build_template(
+            gc,
+            template::Property {
+                metadata: Default::default(),
+                key: identifier(gc, gc.atom(key), NO_RANGE),
+                value,
+                kind,
+                computed: false,
+                method: false,
+                shorthand: false,
+            },
+        )
+    };
+    let null = builder::NullLiteral::build_template(
+        gc,
+        template::NullLiteral {
+            metadata: Default::default(),
+        },
+    );
+    let mut properties = vec![property("__proto__", null, PropertyKind::Init)];
+    for &(exported, local) in exports {
+        properties.push(property(
+            gc.str(exported),
+            getter(gc, identifier(gc, local, NO_RANGE)),
+            PropertyKind::Get,
+        ));
+    }
+    let object = builder::ObjectExpression::build_template(
+        gc,
+        template::ObjectExpression {
+            metadata: Default::default(),
+            properties: NodeList::from_iter(gc, properties),
+        },
+    );
+    let freeze = call(
+        gc,
+        member(
+            gc,
+            identifier(gc, gc.atom("Object"), NO_RANGE),
+            "freeze",
+            NO_RANGE,
+        ),
+        &[object],
+        NO_RANGE,
+    );
+    var_declaration(gc, identifier(gc, name, NO_RANGE), freeze, NO_RANGE)
+}
@@ -0,0 +1,908 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Tree shaking, which removes the code of a bundle that running its entry module
+//! doesn't need.
+//!
+//! Starting from the entry module and all of its exports, [`tree_shake`] marks the
+//! top-level statements of ES modules which are needed: those with side effects, and
+//! those declaring the bindings that needed code refers to. Imported bindings are
+//! followed to the declaration exported by the other module. Exports which no module
+//! imports are removed, along with the declarations only they need, and modules of
+//! which nothing is needed are left out of the bundle.
+//!
+//! A module is assumed to have side effects unless told otherwise, e.g. by the
+//! `sideEffects` field of its `package.json` (see [`package_side_effects`]). A module
+//! without side effects is only kept if some of its exports are used.
+//! A call or `new` expression annotated with a leading `/*#__PURE__*/` or
+//! `/*@__PURE__*/` comment is assumed not to have side effects, so it is removed when
+//! its result isn't used.
+//!
+//! CommonJS modules are kept whole, along with all the exports of the modules they
+//! depend on.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path as FilePath;
+
+use juno_support::source_manager::SourceId;
+
+use super::*;
+use crate::comments::CommentKind;
+use crate::sema::LexicalScopeId;
+
+/// Remove the code of `modules` which isn't needed to run `entry` and to use its
+/// exports, and return the new AST of every module that is still needed.
+/// The new ASTs keep the semantic information, source maps and comments of the
+/// original modules. Modules which aren't needed are left out of the result.
+///
+/// `side_effects` returns whether loading a module may have side effects.
+/// Pure annotations are only found in the modules whose comments are given.
+pub fn tree_shake<'s>(
+    ctx: &mut Context,
+    entry: SourceId,
+    modules: &HashMap<SourceId, BundleModule<'s>>,
+    side_effects: impl Fn(SourceId) -> bool,
+) -> HashMap<SourceId, NodeRc> {
+    let gc = GCLock::new(ctx);
+    let graph = ModuleGraph::build(&gc, entry, |id| modules.get(&id).map(|m| m.sem));
+    let es_modules: HashSet<SourceId> = graph
+        .modules
+        .iter()
+        .copied()
+        .filter(|id| is_es_module(modules[id].ast.node(&gc)))
+        .collect();
+    let mut shaker = TreeShaker {
+        modules,
+        side_effects: &side_effects,
+        default: gc.atom("default"),
+        infos: HashMap::new(),
+        es_modules,
+        queue: vec![Work::Include(entry), Work::AllExports(entry)],
+        included: HashSet::new(),
+        live: HashSet::new(),
+        live_decls: HashSet::new(),
+        used: HashMap::new(),
+        all_exports: HashSet::new(),
+    };
+    for &id in &graph.modules {
+        let info = shaker.analyze(&gc, id);
+        shaker.infos.insert(id, info);
+    }
+    shaker.run(&graph);
+    graph
+        .modules
+        .iter()
+        .filter(|id| shaker.included.contains(id))
+        .map(|&id| (id, NodeRc::from_node(&gc, shaker.rewrite(&gc, id))))
+        .collect()
+}
+
+/// Whether loading the module at `path` may have side effects, according to the
+/// `sideEffects` field of the closest `package.json` above it.
+///
+/// The field is either a boolean, or an array of glob patterns matching the files of
+/// the package which have side effects, relative to the package. A pattern without a
+/// `/` matches files with that name in any directory.
+/// Modules have side effects unless their package declares otherwise.
+pub fn package_side_effects(path: &FilePath) -> bool {
+    for dir in path.ancestors().skip(1) {
+        let text = match fs::read_to_string(dir.join("package.json")) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        let package: serde_json::Value = match serde_json::from_str(&text) {
+            Ok(package) => package,
+            Err(_) => return true,
+        };
+        return match package.get("sideEffects") {
+            Some(serde_json::Value::Bool(side_effects)) => *side_effects,
+            Some(serde_json::Value::Array(patterns)) => {
+                let relative: Vec<String> = path
+                    .strip_prefix(dir)
+                    .unwrap_or(path)
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                let relative = relative.join("/");
+                patterns
+                    .iter()
+                    .filter_map(|pattern| pattern.as_str())
+                    .any(|pattern| matches_pattern(pattern, &relative))
+            }
+            _ => true,
+        };
+    }
+    true
+}
+
+/// Whether `path`, relative to its package, matches the `sideEffects` pattern `pattern`.
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+    if pattern.contains('/') {
+        glob_match(pattern.as_bytes(), path.as_bytes())
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        glob_match(pattern.as_bytes(), name.as_bytes())
+    }
+}
+
+/// Match `text` against `pattern`, in which `**` matches any string, `*` any string
+/// without a `/`, and `?` any character but `/`.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `**/` also matches no directory at all.
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+                || matches!(rest, [b'/', rest @ ..] if glob_match(rest, text))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob_match(rest, &text[i..])),
+        [b'?', rest @ ..] => matches!(text, [c, tail @ ..] if *c != b'/' && glob_match(rest, tail)),
+        [c, rest @ ..] => matches!(text, [t, tail @ ..] if t == c && glob_match(rest, tail)),
+    }
+}
+
+/// Something which is needed to run the entry module.
+#[derive(Debug, Copy, Clone)]
+enum Work {
+    /// Loading a module.
+    Include(SourceId),
+    /// A top-level statement of an ES module, by its index.
+    Statement(SourceId, usize),
+    /// A top-level declaration of an ES module.
+    Decl(SourceId, DeclId),
+    /// An export of an ES module, by its name.
+    Export(SourceId, NodeLabel),
+    /// All the exports of a module, e.g. for its namespace object.
+    AllExports(SourceId),
+}
+
+/// A top-level statement of an ES module.
+#[derive(Default)]
+struct Statement {
+    /// The top-level declarations that the statement refers to.
+    refs: Vec<DeclId>,
+    /// The modules in the bundle that the statement loads with `require`.
+    requires: Vec<SourceId>,
+    /// Whether running the statement may have side effects.
+    side_effects: bool,
+}
+
+/// What the statements of an ES module declare, import and export.
+#[derive(Default)]
+struct ModuleInfo {
+    statements: Vec<Statement>,
+    /// The statements declaring every top-level declaration.
+    declared_by: HashMap<DeclId, Vec<usize>>,
+    /// What every imported binding needs from the module in the bundle it imports.
+    imports: HashMap<DeclId, Work>,
+    /// The statement declaring every export, and what the export needs other than the
+    /// statement.
+    exports: HashMap<NodeLabel, (usize, Option<Work>)>,
+    /// The ES modules in the bundle whose exports are all re-exported with `export *`.
+    star_exports: Vec<SourceId>,
+    /// The modules in the bundle that the module imports from.
+    imported: Vec<SourceId>,
+}
+
+struct TreeShaker<'m, 's, 'f> {
+    modules: &'m HashMap<SourceId, BundleModule<'s>>,
+    side_effects: &'f dyn Fn(SourceId) -> bool,
+    default: NodeLabel,
+    infos: HashMap<SourceId, ModuleInfo>,
+    es_modules: HashSet<SourceId>,
+    queue: Vec<Work>,
+    /// The modules which are needed.
+    included: HashSet<SourceId>,
+    /// The statements which are needed.
+    live: HashSet<(SourceId, usize)>,
+    /// The top-level declarations which are needed.
+    live_decls: HashSet<(SourceId, DeclId)>,
+    /// The names of the exports of every module which are used.
+    used: HashMap<SourceId, HashSet<NodeLabel>>,
+    /// The modules whose exports are all used.
+    all_exports: HashSet<SourceId>,
+}
+
+impl TreeShaker<'_, '_, '_> {
+    /// The module in the bundle that `node` imports from or exports from.
+    fn target<'gc>(
+        &self,
+        gc: &'gc GCLock,
+        sem: &SemContext,
+        node: &'gc Node<'gc>,
+    ) -> Option<SourceId> {
+        required_module(gc, sem, node).filter(|id| self.infos.contains_key(id))
+    }
+
+    /// What an import of `name` from `target` needs, or of its namespace object if
+    /// `name` is `None`.
+    fn import_work(&self, target: SourceId, name: Option<NodeLabel>) -> Work {
+        match name {
+            _ if !self.es_modules.contains(&target) => Work::Include(target),
+            Some(name) => Work::Export(target, name),
+            None => Work::AllExports(target),
+        }
+    }
+
+    fn analyze<'gc>(&self, gc: &'gc GCLock, id: SourceId) -> ModuleInfo {
+        let mut info = ModuleInfo::default();
+        let module = &self.modules[&id];
+        let sem = module.sem;
+        let body = match module.ast.node(gc) {
+            Node::Module(Module { body, .. }) if self.es_modules.contains(&id) => *body,
+            _ => return info,
+        };
+        let purity = Purity {
+            sem,
+            comments: module.comments,
+        };
+        let decl_of = |node: &'gc Node<'gc>| match sem.ident_decl(&NodeRc::from_node(gc, node)) {
+            Some(Resolution::Decl(decl)) => Some(decl),
+            _ => None,
+        };
+        let mut refs = References {
+            sem,
+            modules: self.modules,
+            scope: sem.module_scope(),
+            refs: vec![],
+            requires: vec![],
+            unresolvable: false,
+        };
+
+        for (index, stmt) in body.iter().enumerate() {
+            let mut statement = Statement::default();
+            if is_type_only(stmt) {
+                info.statements.push(statement);
+                continue;
+            }
+            // The part of the statement which runs as the statement itself.
+            let code = match stmt {
+                Node::ImportDeclaration(ImportDeclaration { specifiers, .. }) => {
+                    let target = self.target(gc, sem, stmt);
+                    info.imported.extend(target);
+                    for spec in specifiers.iter() {
+                        let (local, name) = match spec {
+                            Node::ImportSpecifier(ImportSpecifier {
+                                imported,
+                                local,
+                                import_kind: ImportKind::Value,
+                                ..
+                            }) => (local, Some(id_name(imported))),
+                            Node::ImportDefaultSpecifier(ImportDefaultSpecifier {
+                                local, ..
+                            }) => (local, Some(self.default)),
+                            Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier {
+                                local,
+                                ..
+                            }) => (local, None),
+                            _ => continue,
+                        };
+                        if let Some(decl) = decl_of(local) {
+                            info.declared_by.entry(decl).or_default().push(index);
+                            if let Some(target) = target {
+                                info.imports.insert(decl, self.import_work(target, name));
+                            }
+                        }
+                    }
+                    None
+                }
+                Node::ExportNamedDeclaration(ExportNamedDeclaration {
+                    declaration: Some(decl),
+                    ..
+                }) => {
+                    for name in declared_names(decl) {
+                        info.exports.insert(id_name(name), (index, None));
+                    }
+                    Some(*decl)
+                }
+                Node::ExportNamedDeclaration(ExportNamedDeclaration {
+                    declaration: None,
+                    specifiers,
+                    source,
+                    ..
+                }) => {
+                    let target = source.and_then(|_| self.target(gc, sem, stmt));
+                    info.imported.extend(target);
+                    for spec in specifiers.iter() {
+                        let (exported, work) = match (spec, target) {
+                            (
+                                Node::ExportSpecifier(ExportSpecifier {
+                                    exported, local, ..
+                                }),
+                                _,
+                            ) if source.is_none() => {
+                                (exported, decl_of(local).map(|d| Work::Decl(id, d)))
+                            }
+                            (
+                                Node::ExportSpecifier(ExportSpecifier {
+                                    exported, local, ..
+                                }),
+                                Some(target),
+                            ) => (
+                                exported,
+                                Some(self.import_work(target, Some(id_name(local)))),
+                            ),
+                            (
+                                Node::ExportNamespaceSpecifier(ExportNamespaceSpecifier {
+                                    exported,
+                                    ..
+                                }),
+                                Some(target),
+                            ) => (exported, Some(self.import_work(target, None))),
+                            (
+                                Node::ExportSpecifier(ExportSpecifier { exported, .. })
+                                | Node::ExportNamespaceSpecifier(ExportNamespaceSpecifier {
+                                    exported,
+                                    ..
+                                }),
+                                None,
+                            ) => (exported, None),
+                            _ => continue,
+                        };
+                        info.exports.insert(id_name(exported), (index, work));
+                    }
+                    None
+                }
+                Node::ExportDefaultDeclaration(ExportDefaultDeclaration {
+                    declaration, ..
+                }) => {
+                    let work = match declaration {
+                        Node::FunctionDeclaration(FunctionDeclaration {
+                            id: Some(name), ..
+                        })
+                        | Node::ClassDeclaration(ClassDeclaration { id: Some(name), .. }) => {
+                            decl_of(name).map(|d| Work::Decl(id, d))
+                        }
+                        _ => None,
+                    };
+                    info.exports.insert(self.default, (index, work));
+                    Some(*declaration)
+                }
+                Node::ExportAllDeclaration(_) => {
+                    let target = self.target(gc, sem, stmt);
+                    match target {
+                        Some(target) if self.es_modules.contains(&target) => {
+                            info.star_exports.push(target);
+                            info.imported.push(target);
+                        }
+                        // The names exported by a CommonJS module aren't known statically,
+                        // so all of them are needed.
+                        Some(target) => {
+                            statement.requires.push(target);
+                            statement.side_effects = true;
+                        }
+                        None => {}
+                    }
+                    None
+                }
+                _ => Some(stmt),
+            };
+            if let Some(code) = code {
+                for name in declared_names(code) {
+                    if let Some(decl) = decl_of(name) {
+                        info.declared_by.entry(decl).or_default().push(index);
+                    }
+                }
+                code.visit(gc, &mut refs, None);
+                statement.refs = std::mem::take(&mut refs.refs);
+                statement.requires = std::mem::take(&mut refs.requires);
+                statement.side_effects = !purity.is_pure_statement(gc, stmt);
+            }
+            info.statements.push(statement);
+        }
+        // Any declaration may be used by a local `eval`.
+        if refs.unresolvable {
+            let decls: Vec<DeclId> = info.declared_by.keys().copied().collect();
+            for statement in &mut info.statements {
+                statement.side_effects = true;
+                statement.refs.extend(&decls);
+            }
+        }
+        info
+    }
+
+    /// Mark everything the work in the queue needs, until there is nothing left to do.
+    fn run(&mut self, graph: &ModuleGraph) {
+        while let Some(work) = self.queue.pop() {
+            match work {
+                Work::Include(id) => {
+                    if !self.included.insert(id) {
+                        continue;
+                    }
+                    if !self.es_modules.contains(&id) {
+                        for &dep in &graph.deps[&id] {
+                            self.queue.push(Work::AllExports(dep));
+                        }
+                        continue;
+                    }
+                    let info = &self.infos[&id];
+                    for (index, statement) in info.statements.iter().enumerate() {
+                        if statement.side_effects {
+                            self.queue.push(Work::Statement(id, index));
+                        }
+                    }
+                    for &dep in &info.imported {
+                        if (self.side_effects)(dep) {
+                            self.queue.push(Work::Include(dep));
+                        }
+                    }
+                }
+                Work::Statement(id, index) => {
+                    if !self.live.insert((id, index)) {
+                        continue;
+                    }
+                    self.queue.push(Work::Include(id));
+                    let statement = &self.infos[&id].statements[index];
+                    for &decl in &statement.refs {
+                        self.queue.push(Work::Decl(id, decl));
+                    }
+                    for &dep in &statement.requires {
+                        self.queue.push(Work::AllExports(dep));
+                    }
+                }
+                Work::Decl(id, decl) => {
+                    if !self.live_decls.insert((id, decl)) {
+                        continue;
+                    }
+                    let info = &self.infos[&id];
+                    for &index in info.declared_by.get(&decl).into_iter().flatten() {
+                        self.queue.push(Work::Statement(id, index));
+                    }
+                    self.queue.extend(info.imports.get(&decl).copied());
+                }
+                Work::Export(id, name) => {
+                    if !self.used.entry(id).or_default().insert(name) {
+                        continue;
+                    }
+                    self.queue.push(Work::Include(id));
+                    let info = &self.infos[&id];
+                    match info.exports.get(&name) {
+                        Some(&(index, work)) => {
+                            self.queue.push(Work::Statement(id, index));
+                            self.queue.extend(work);
+                        }
+                        // `export *` doesn't re-export default exports.
+                        None if name != self.default => {
+                            for &star in &info.star_exports {
+                                if self.export_names(star).contains(&name) {
+                                    self.queue.push(Work::Export(star, name));
+                                }
+                            }
+                        }
+                        None => {}
+                    }
+                }
+                Work::AllExports(id) => {
+                    if !self.all_exports.insert(id) {
+                        continue;
+                    }
+                    self.queue.push(Work::Include(id));
+                    for name in self.export_names(id) {
+                        self.queue.push(Work::Export(id, name));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The names exported by the module `id`, including those of its star exports.
+    /// CommonJS modules have no known exports.
+    fn export_names(&self, id: SourceId) -> HashSet<NodeLabel> {
+        let mut result = HashSet::new();
+        let mut seen = HashSet::new();
+        let mut stack = vec![(id, true)];
+        while let Some((id, with_default)) = stack.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let info = &self.infos[&id];
+            result.extend(
+                info.exports
+                    .keys()
+                    .copied()
+                    .filter(|&name| with_default || name != self.default),
+            );
+            stack.extend(info.star_exports.iter().map(|&star| (star, false)));
+        }
+        result
+    }
+
+    /// The AST of the module `id` without the statements, imports and exports which
+    /// aren't needed.
+    fn rewrite<'gc>(&self, gc: &'gc GCLock, id: SourceId) -> &'gc Node<'gc> {
+        let ast = self.modules[&id].ast.node(gc);
+        let module = match ast {
+            Node::Module(module) if self.es_modules.contains(&id) => module,
+            _ => return ast,
+        };
+        let mut body = vec![];
+        for (index, stmt) in module.body.iter().enumerate() {
+            body.extend(self.rewrite_statement(gc, id, index, stmt));
+        }
+        if body.len() == module.body.len()
+            && body
+                .iter()
+                .zip(module.body.iter())
+                .all(|(a, b)| a.ptr_eq(b))
+        {
+            return ast;
+        }
+        // Keep an empty `export {}` so that the module is still an ES module, even if all
+        // of its imports and exports were removed.
+        if !body.iter().any(|stmt| is_module_declaration(stmt)) {
+            body.push(builder::ExportNamedDeclaration::build_template(
+                gc,
+                template::ExportNamedDeclaration {
+                    metadata: Default::default(),
+                    declaration: None,
+                    specifiers: NodeList::new(gc),
+                    source: None,
+                    export_kind: ExportKind::Value,
+                },
+            ));
+        }
+        let mut builder = builder::Module::from_node(module);
+        builder.body(NodeList::from_iter(gc, body));
+        builder.build_forced(gc)
+    }
+
+    /// What remains of the statement `stmt` of the module `id`, if anything.
+    fn rewrite_statement<'gc>(
+        &self,
+        gc: &'gc GCLock,
+        id: SourceId,
+        index: usize,
+        stmt: &'gc Node<'gc>,
+    ) -> Option<&'gc Node<'gc>> {
+        let sem = self.modules[&id].sem;
+        let live = self.live.contains(&(id, index));
+        let is_used =
+            |name: NodeLabel| matches!(self.used.get(&id), Some(used) if used.contains(&name));
+        // Whether a declaration which imports from `source` but whose bindings aren't
+        // needed must still load it.
+        let loads = || match self.target(gc, sem, stmt) {
+            Some(target) => self.included.contains(&target) && (self.side_effects)(target),
+            None => true,
+        };
+        if is_type_only(stmt) {
+            return Some(stmt);
+        }
+        match stmt {
+            Node::ImportDeclaration(decl @ ImportDeclaration { specifiers, .. }) => {
+                if let Some(target) = self.target(gc, sem, stmt) {
+                    if !self.included.contains(&target) {
+                        return None;
+                    }
+                }
+                let kept: Vec<&Node> = specifiers
+                    .iter()
+                    .filter(|spec| {
+                        let local = match spec {
+                            Node::ImportSpecifier(ImportSpecifier { local, .. })
+                            | Node::ImportDefaultSpecifier(ImportDefaultSpecifier {
+                                local, ..
+                            })
+                            | Node::ImportNamespaceSpecifier(ImportNamespaceSpecifier {
+                                local,
+                                ..
+                            }) => local,
+                            _ => return true,
+                        };
+                        match sem.ident_decl(&NodeRc::from_node(gc, local)) {
+                            Some(Resolution::Decl(d)) => self.live_decls.contains(&(id, d)),
+                            _ => true,
+                        }
+                    })
+                    .collect();
+                if kept.len() == specifiers.len() {
+                    Some(stmt)
+                } else if kept.is_empty() && !loads() {
+                    None
+                } else {
+                    let mut builder = builder::ImportDeclaration::from_node(decl);
+                    builder.specifiers(NodeList::from_iter(gc, kept));
+                    Some(builder.build_forced(gc))
+                }
+            }
+            Node::ExportNamedDeclaration(ExportNamedDeclaration {
+                declaration: Some(decl),
+                ..
+            }) => {
+                if !live {
+                    None
+                } else if declared_names(decl)
+                    .into_iter()
+                    .any(|name| is_used(id_name(name)))
+                {
+                    Some(stmt)
+                } else {
+                    Some(decl)
+                }
+            }
+            Node::ExportNamedDeclaration(
+                decl @ ExportNamedDeclaration {
+                    declaration: None,
+                    specifiers,
+                    source,
+                    ..
+                },
+            ) => {
+                let kept: Vec<&Node> = specifiers
+                    .iter()
+                    .filter(|spec| match spec {
+                        Node::ExportSpecifier(ExportSpecifier { exported, .. })
+                        | Node::ExportNamespaceSpecifier(ExportNamespaceSpecifier {
+                            exported,
+                            ..
+                        }) => is_used(id_name(exported)),
+                        _ => true,
+                    })
+                    .collect();
+                match source {
+                    _ if kept.len() == specifiers.len() && !kept.is_empty() => Some(stmt),
+                    Some(source) if kept.is_empty() => {
+                        loads().then(|| import_declaration(gc, &[], source))
+                    }
+                    None if kept.is_empty() => None,
+                    _ => {
+                        let mut builder = builder::ExportNamedDeclaration::from_node(decl);
+                        builder.specifiers(NodeList::from_iter(gc, kept));
+                        Some(builder.build_forced(gc))
+                    }
+                }
+            }
+            Node::ExportAllDeclaration(_) => match self.target(gc, sem, stmt) {
+                Some(target) if !self.included.contains(&target) => None,
+                _ => Some(stmt),
+            },
+            Node::ExportDefaultDeclaration(ExportDefaultDeclaration { declaration, .. }) => {
+                if is_used(self.default) {
+                    return Some(stmt);
+                }
+                if !live {
+                    return None;
+                }
+                match declaration {
+                    Node::FunctionDeclaration(FunctionDeclaration { id: Some(_), .. })
+                    | Node::ClassDeclaration(ClassDeclaration { id: Some(_), .. }) => {
+                        Some(declaration)
+                    }
+                    Node::FunctionDeclaration(_) | Node::ClassDeclaration(_) => Some(stmt),
+                    expr => Some(expression_statement(gc, expr)),
+                }
+            }
+            _ => live.then_some(stmt),
+        }
+    }
+}
+
+/// Collects the top-level declarations that a statement refers to, and the modules in
+/// the bundle it requires.
+struct References<'a, 's> {
+    sem: &'a SemContext,
+    modules: &'a HashMap<SourceId, BundleModule<'s>>,
+    /// The scope of the top-level declarations.
+    scope: Option<LexicalScopeId>,
+    refs: Vec<DeclId>,
+    requires: Vec<SourceId>,
+    /// Whether any identifier couldn't be resolved because of a local `eval`.
+    unresolvable: bool,
+}
+
+impl<'gc> Visitor<'gc> for References<'_, '_> {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        match node {
+            Node::Identifier(_) => match self.sem.ident_decl(&NodeRc::from_node(gc, node)) {
+                Some(Resolution::Decl(decl)) if Some(self.sem.decl(decl).scope) == self.scope => {
+                    self.refs.push(decl);
+                }
+                Some(Resolution::Unresolvable) => self.unresolvable = true,
+                _ => {}
+            },
+            Node::CallExpression(_) => {
+                if let Some(&id) = self.sem.all_requires().get(&NodeRc::from_node(gc, node)) {
+                    if self.modules.contains_key(&id) {
+                        self.requires.push(id);
+                    }
+                }
+            }
+            _ => {}
+        }
+        node.visit_children(gc, self);
+    }
+}
+
+/// Decides whether code may have side effects.
+struct Purity<'a> {
+    sem: &'a SemContext,
+    comments: Option<&'a CommentMap>,
+}
+
+impl Purity<'_> {
+    fn is_pure_statement<'gc>(&self, gc: &'gc GCLock, stmt: &'gc Node<'gc>) -> bool {
+        match stmt {
+            Node::EmptyStatement(_) | Node::FunctionDeclaration(_) => true,
+            Node::ClassDeclaration(_) => self.is_pure_class(gc, stmt),
+            Node::VariableDeclaration(VariableDeclaration { declarations, .. }) => {
+                declarations.iter().all(|declarator| match declarator {
+                    // Destructuring may run getters and iterators.
+                    Node::VariableDeclarator(VariableDeclarator {
+                        id: Node::Identifier(_),
+                        init,
+                        ..
+                    }) => init.iter().all(|&init| self.is_pure(gc, init)),
+                    _ => false,
+                })
+            }
+            Node::ExpressionStatement(ExpressionStatement { expression, .. }) => {
+                // An annotation at the start of the statement is attached to it.
+                match expression {
+                    Node::CallExpression(CallExpression { arguments, .. })
+                    | Node::NewExpression(NewExpression { arguments, .. })
+                        if self.is_annotated(stmt) =>
+                    {
+                        self.are_pure_arguments(gc, arguments)
+                    }
+                    _ => self.is_pure(gc, expression),
+                }
+            }
+            Node::ExportNamedDeclaration(ExportNamedDeclaration {
+                declaration: Some(decl),
+                ..
+            }) => self.is_pure_statement(gc, decl),
+            Node::ExportDefaultDeclaration(ExportDefaultDeclaration { declaration, .. }) => {
+                match declaration {
+                    Node::FunctionDeclaration(_) | Node::ClassDeclaration(_) => {
+                        self.is_pure_statement(gc, declaration)
+                    }
+                    _ => self.is_pure(gc, declaration),
+                }
+            }
+            _ => stmt.variant().parent() == Some(NodeVariant::FlowDeclaration),
+        }
+    }
+
+    /// Whether evaluating the expression `node` has no side effects.
+    fn is_pure<'gc>(&self, gc: &'gc GCLock, node: &'gc Node<'gc>) -> bool {
+        match node {
+            Node::NullLiteral(_)
+            | Node::BooleanLiteral(_)
+            | Node::StringLiteral(_)
+            | Node::NumericLiteral(_)
+            | Node::BigIntLiteral(_)
+            | Node::RegExpLiteral(_)
+            | Node::ThisExpression(_)
+            | Node::FunctionExpression(_)
+            | Node::ArrowFunctionExpression(_) => true,
+            // Reading a global which doesn't exist throws.
+            Node::Identifier(Identifier { name, .. }) => {
+                match self.sem.ident_decl(&NodeRc::from_node(gc, node)) {
+                    Some(Resolution::Decl(decl)) => {
+                        !self.sem.decl(decl).kind.is_global() || gc.str(*name) == "undefined"
+                    }
+                    _ => false,
+                }
+            }
+            Node::TemplateLiteral(TemplateLiteral { expressions, .. })
+            | Node::SequenceExpression(SequenceExpression { expressions, .. }) => {
+                expressions.iter().all(|e| self.is_pure(gc, e))
+            }
+            Node::ObjectExpression(ObjectExpression { properties, .. }) => {
+                properties.iter().all(|prop| match prop {
+                    Node::Property(Property {
+                        key,
+                        value,
+                        computed,
+                        ..
+                    }) => (!computed || self.is_pure(gc, key)) && self.is_pure(gc, value),
+                    _ => false,
+                })
+            }
+            Node::ArrayExpression(ArrayExpression { elements, .. }) => {
+                elements.iter().all(|e| match e {
+                    Node::Empty(_) => true,
+                    Node::SpreadElement(_) => false,
+                    _ => self.is_pure(gc, e),
+                })
+            }
+            Node::UnaryExpression(UnaryExpression {
+                operator, argument, ..
+            }) => *operator != UnaryExpressionOperator::Delete && self.is_pure(gc, argument),
+            Node::BinaryExpression(BinaryExpression { left, right, .. })
+            | Node::LogicalExpression(LogicalExpression { left, right, .. }) => {
+                self.is_pure(gc, left) && self.is_pure(gc, right)
+            }
+            Node::ConditionalExpression(ConditionalExpression {
+                test,
+                alternate,
+                consequent,
+                ..
+            }) => {
+                self.is_pure(gc, test)
+                    && self.is_pure(gc, consequent)
+                    && self.is_pure(gc, alternate)
+            }
+            Node::TypeCastExpression(TypeCastExpression { expression, .. }) => {
+                self.is_pure(gc, expression)
+            }
+            Node::ClassExpression(_) => self.is_pure_class(gc, node),
+            Node::CallExpression(CallExpression { arguments, .. })
+            | Node::NewExpression(NewExpression { arguments, .. }) => {
+                self.is_annotated(node) && self.are_pure_arguments(gc, arguments)
+            }
+            _ => false,
+        }
+    }
+
+    fn are_pure_arguments<'gc>(&self, gc: &'gc GCLock, arguments: &NodeList<'gc>) -> bool {
+        arguments
+            .iter()
+            .all(|arg| !matches!(arg, Node::SpreadElement(_)) && self.is_pure(gc, arg))
+    }
+
+    /// Whether defining the class `node` has no side effects: it has no decorators and
+    /// its superclass, computed keys and static fields have none.
+    fn is_pure_class<'gc>(&self, gc: &'gc GCLock, node: &'gc Node<'gc>) -> bool {
+        let (super_class, decorators, body) = match node {
+            Node::ClassDeclaration(ClassDeclaration {
+                super_class,
+                decorators,
+                body,
+                ..
+            })
+            | Node::ClassExpression(ClassExpression {
+                super_class,
+                decorators,
+                body,
+                ..
+            }) => (super_class, decorators, body),
+            _ => return false,
+        };
+        let members = match body {
+            Node::ClassBody(ClassBody { body, .. }) => body,
+            _ => return false,
+        };
+        decorators.is_empty()
+            && super_class.iter().all(|&s| self.is_pure(gc, s))
+            && members.iter().all(|member| match member {
+                Node::ClassProperty(ClassProperty {
+                    key,
+                    value,
+                    computed,
+                    is_static,
+                    ..
+                }) => {
+                    (!computed || self.is_pure(gc, key))
+                        && (!is_static || value.iter().all(|&v| self.is_pure(gc, v)))
+                }
+                Node::ClassPrivateProperty(ClassPrivateProperty {
+                    value, is_static, ..
+                }) => !is_static || value.iter().all(|&v| self.is_pure(gc, v)),
+                Node::MethodDefinition(MethodDefinition { key, computed, .. }) => {
+                    !computed || self.is_pure(gc, key)
+                }
+                _ => false,
+            })
+    }
+
+    /// Whether `node` has a leading `/*#__PURE__*/` or `/*@__PURE__*/` comment.
+    fn is_annotated(&self, node: &Node) -> bool {
+        let comments = match self.comments.and_then(|comments| comments.get(node)) {
+            Some(comments) => comments,
+            None => return false,
+        };
+        comments.leading.iter().any(|comment| {
+            comment.kind == CommentKind::Block
+                && matches!(comment.text.trim(), "#__PURE__" | "@__PURE__")
+        })
+    }
+}
@@ -0,0 +1,738 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Source-preserving rewriting of JS files.
+//!
+//! After passes have transformed an AST, the original and transformed trees are compared
+//! and only the source ranges of the nodes which changed are regenerated with [`gen_js`].
+//! All other text, including formatting and comments, is kept byte-for-byte.
+//!
+//! Nodes are matched by identity: a node which is shared by both trees is unchanged,
+//! and a rebuilt node with the same kind, range and scalar fields as the original
+//! is descended into, so only its changed children are regenerated.
+//! Elements of lists are aligned on the nodes they share, so inserting or removing
+//! statements only touches the lines of those statements.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use juno_support::source_manager::LineTable;
+
+use crate::ast::*;
+use crate::gen_js;
+
+/// Replacement of the bytes `start..end` of a source buffer with `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Compute the edits which turn `source`, the text that `original` was parsed from,
+/// into the text of `transformed`.
+/// The returned edits are sorted and don't overlap.
+pub fn compute_edits<'gc>(
+    gc: &'gc GCLock,
+    source: &str,
+    original: &'gc Node<'gc>,
+    transformed: &'gc Node<'gc>,
+    opt: gen_js::Opt,
+) -> Vec<TextEdit> {
+    let mut collector = NodeCollector {
+        nodes: HashSet::new(),
+    };
+    original.visit(gc, &mut collector, None);
+    let mut differ = Differ {
+        source,
+        lines: LineTable::new(source.as_bytes()),
+        file: original.range().file,
+        original: collector.nodes,
+        opt: gen_js::Opt {
+            doc_block: None,
+            ..opt
+        },
+        edits: vec![],
+    };
+    if !differ.diff_node(gc, original, transformed, None) {
+        // The root doesn't map to the source, so the whole file has to be regenerated.
+        let mut text = differ.generate(gc, transformed, "");
+        text.push('\n');
+        differ.edits = vec![TextEdit {
+            start: 0,
+            end: source.len(),
+            text,
+        }];
+    }
+    differ.edits.sort_by_key(|edit| edit.start);
+    differ.edits
+}
+
+/// Apply `edits`, which must not overlap, to `source`.
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> String {
+    let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+    sorted.sort_by_key(|edit| edit.start);
+    let mut result = String::with_capacity(source.len());
+    let mut pos = 0;
+    for edit in sorted {
+        assert!(edit.start >= pos, "overlapping edits");
+        result.push_str(&source[pos..edit.start]);
+        result.push_str(&edit.text);
+        pos = edit.end;
+    }
+    result.push_str(&source[pos..]);
+    result
+}
+
+/// Number of unchanged lines shown around every change in a unified diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// Maximum edit distance for which a minimal line diff is computed.
+/// Beyond it, the changed region is reported as a single replacement.
+const MAX_DIFF_DISTANCE: usize = 2000;
+
+/// Operation on a single line in a diff.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LineOp {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Return a unified diff from `old` to `new`, or an empty string if they are equal.
+/// `old_name` and `new_name` are used in the file header lines.
+pub fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    // Every operation is paired with the line it applies to.
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    let mut result = String::new();
+    writeln!(result, "--- {}", old_name).unwrap();
+    writeln!(result, "+++ {}", new_name).unwrap();
+
+    let changes: Vec<usize> = (0..ops.len())
+        .filter(|&i| ops[i].0 != LineOp::Equal)
+        .collect();
+    let mut i = 0;
+    while i < changes.len() {
+        // Merge changes whose contexts would touch or overlap.
+        let mut j = i;
+        while j + 1 < changes.len() && changes[j + 1] - changes[j] <= 2 * DIFF_CONTEXT + 1 {
+            j += 1;
+        }
+        let start = changes[i].saturating_sub(DIFF_CONTEXT);
+        let end = (changes[j] + DIFF_CONTEXT + 1).min(ops.len());
+
+        let old_before = ops[..start]
+            .iter()
+            .filter(|(op, _)| *op != LineOp::Insert)
+            .count();
+        let new_before = ops[..start]
+            .iter()
+            .filter(|(op, _)| *op != LineOp::Delete)
+            .count();
+        let hunk = &ops[start..end];
+        let old_count = hunk.iter().filter(|(op, _)| *op != LineOp::Insert).count();
+        let new_count = hunk.iter().filter(|(op, _)| *op != LineOp::Delete).count();
+        writeln!(
+            result,
+            "@@ -{} +{} @@",
+            hunk_range(old_before, old_count),
+            hunk_range(new_before, new_count)
+        )
+        .unwrap();
+        for (op, line) in hunk {
+            result.push(match op {
+                LineOp::Equal => ' ',
+                LineOp::Delete => '-',
+                LineOp::Insert => '+',
+            });
+            result.push_str(line);
+            if !line.ends_with('\n') {
+                result.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        i = j + 1;
+    }
+    result
+}
+
+/// Format the range of a hunk which starts after `before` lines and has `count` lines.
+fn hunk_range(before: usize, count: usize) -> String {
+    match count {
+        // An empty range refers to the line preceding it.
+        0 => format!("{},0", before),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{}", before + 1, count),
+    }
+}
+
+/// Compute a minimal sequence of line operations turning `old` into `new`,
+/// using Myers' algorithm.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(LineOp, &'a str)> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<(LineOp, &str)> = old[..prefix]
+        .iter()
+        .map(|line| (LineOp::Equal, *line))
+        .collect();
+    match myers(a, b) {
+        Some(middle) => ops.extend(middle),
+        None => {
+            ops.extend(a.iter().map(|line| (LineOp::Delete, *line)));
+            ops.extend(b.iter().map(|line| (LineOp::Insert, *line)));
+        }
+    }
+    ops.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| (LineOp::Equal, *line)),
+    );
+    ops
+}
+
+/// Myers' diff of `a` and `b`.
+/// Return `None` if the edit distance exceeds [`MAX_DIFF_DISTANCE`].
+fn myers<'a>(a: &[&'a str], b: &[&'a str]) -> Option<Vec<(LineOp, &'a str)>> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (n + m) as usize;
+    if max == 0 {
+        return Some(vec![]);
+    }
+    let max_d = max.min(MAX_DIFF_DISTANCE) as isize;
+    // Furthest reaching `x` on every diagonal `k = x - y` within reach of `max_d` steps,
+    // offset by `max_d + 1`.
+    let mut v = vec![0isize; 2 * max_d as usize + 3];
+    let idx = |k: isize| (k + max_d + 1) as usize;
+    // Snapshots of the diagonals `-d - 1..=d + 1` of `v` before every step `d`, which
+    // are all the diagonals that step reads, used to recover the path.
+    let mut trace: Vec<Vec<isize>> = vec![];
+
+    'search: for d in 0..=max_d {
+        trace.push(v[idx(-d - 1)..=idx(d + 1)].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[idx(k - 1)] < v[idx(k + 1)]) {
+                v[idx(k + 1)]
+            } else {
+                v[idx(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+        if d as usize == MAX_DIFF_DISTANCE {
+            return None;
+        }
+    }
+
+    let mut ops = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, snapshot) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let v = |k: isize| snapshot[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v(k - 1) < v(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push((LineOp::Equal, a[x as usize]));
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push((LineOp::Insert, b[prev_y as usize]));
+            } else {
+                ops.push((LineOp::Delete, a[prev_x as usize]));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+    Some(ops)
+}
+
+/// Collect every node in a tree.
+struct NodeCollector<'gc> {
+    nodes: HashSet<NodePtr<'gc>>,
+}
+
+impl<'gc> Visitor<'gc> for NodeCollector<'gc> {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        self.nodes.insert(NodePtr::from_node(node));
+        node.visit_children(gc, self);
+    }
+}
+
+/// Collect the children of a node, grouped by the field they are stored in.
+struct FieldCollector<'gc> {
+    fields: Vec<(NodeField, Vec<&'gc Node<'gc>>)>,
+}
+
+impl<'gc> Visitor<'gc> for FieldCollector<'gc> {
+    fn call(&mut self, _gc: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        let field = path.expect("children must have a parent").field;
+        match self.fields.last_mut() {
+            Some((last, nodes)) if *last == field => nodes.push(node),
+            _ => self.fields.push((field, vec![node])),
+        }
+    }
+}
+
+/// Return the children of `node` grouped by field.
+fn children_by_field<'gc>(
+    gc: &'gc GCLock,
+    node: &'gc Node<'gc>,
+) -> Vec<(NodeField, Vec<&'gc Node<'gc>>)> {
+    let mut collector = FieldCollector { fields: vec![] };
+    node.visit_children(gc, &mut collector);
+    collector.fields
+}
+
+/// Whether the elements of a list in `field` are statements on their own lines,
+/// as opposed to comma separated.
+fn is_statement_list(field: NodeField) -> bool {
+    matches!(field, NodeField::body | NodeField::consequent)
+}
+
+/// Whether `node` is an expression.
+fn is_expression(node: &Node) -> bool {
+    instanceof(node.variant(), NodeVariant::Expression)
+}
+
+struct Differ<'gc, 's> {
+    /// The source of the original tree.
+    source: &'s str,
+    lines: LineTable,
+    /// The file of the original tree.
+    file: SourceId,
+    /// All the nodes in the original tree.
+    original: HashSet<NodePtr<'gc>>,
+    opt: gen_js::Opt<'s>,
+    edits: Vec<TextEdit>,
+}
+
+impl<'gc> Differ<'gc, '_> {
+    /// Produce the edits which turn the text of `old` into `new`.
+    /// `field` is the field of the parent `old` is stored in.
+    /// Return false if no edits could be produced because `old` doesn't map to the source,
+    /// in which case the caller must regenerate its own parent.
+    fn diff_node(
+        &mut self,
+        gc: &'gc GCLock,
+        old: &'gc Node<'gc>,
+        new: &'gc Node<'gc>,
+        field: Option<NodeField>,
+    ) -> bool {
+        if old.ptr_eq(new) {
+            return true;
+        }
+        if old.variant() == new.variant() && old.range() == new.range() && old.shallow_eq(new) {
+            // Likely a copy of `old` with some changed children.
+            let mark = self.edits.len();
+            if self.diff_children(gc, old, new) {
+                return true;
+            }
+            self.edits.truncate(mark);
+        }
+        let (start, end) = match self.byte_range(old.range()) {
+            Some(range) => range,
+            None => return false,
+        };
+        let indent = self.indent_at(start).to_string();
+        let mut text = self.text(gc, new, field, &indent);
+        if is_expression(old)
+            && is_expression(new)
+            && gen_js::binds_less_tightly(new, old, self.opt.pretty)
+        {
+            text = format!("({})", text);
+        }
+        self.edits.push(TextEdit { start, end, text });
+        true
+    }
+
+    /// Produce the edits for the children of `old` and `new`, which are of the same kind.
+    fn diff_children(&mut self, gc: &'gc GCLock, old: &'gc Node<'gc>, new: &'gc Node<'gc>) -> bool {
+        let old_fields = children_by_field(gc, old);
+        let mut new_fields = children_by_field(gc, new);
+        for (field, old_children) in old_fields {
+            let new_children = match new_fields.iter().position(|(f, _)| *f == field) {
+                Some(pos) => new_fields.remove(pos).1,
+                None => vec![],
+            };
+            let ok = if old_children.len() == new_children.len() {
+                old_children
+                    .iter()
+                    .zip(new_children.iter())
+                    .all(|(o, n)| self.diff_node(gc, o, n, Some(field)))
+            } else {
+                self.diff_list(gc, field, &old_children, &new_children)
+            };
+            if !ok {
+                return false;
+            }
+        }
+        // Any remaining fields were empty lists in `old`, so there is nowhere to insert into.
+        new_fields.is_empty()
+    }
+
+    /// Produce the edits turning the list `old` into `new`.
+    fn diff_list(
+        &mut self,
+        gc: &'gc GCLock,
+        field: NodeField,
+        old: &[&'gc Node<'gc>],
+        new: &[&'gc Node<'gc>],
+    ) -> bool {
+        let anchors = match common_nodes(old, new) {
+            Some(anchors) => anchors,
+            None => return false,
+        };
+        let (mut old_pos, mut new_pos) = (0, 0);
+        for (old_end, new_end) in anchors
+            .into_iter()
+            .chain(std::iter::once((old.len(), new.len())))
+        {
+            if !self.diff_run(gc, field, old, old_pos..old_end, &new[new_pos..new_end]) {
+                return false;
+            }
+            old_pos = old_end + 1;
+            new_pos = new_end + 1;
+        }
+        true
+    }
+
+    /// Produce the edits replacing the elements `old[run]` with `new`.
+    /// The elements surrounding the run are unchanged.
+    fn diff_run(
+        &mut self,
+        gc: &'gc GCLock,
+        field: NodeField,
+        old: &[&'gc Node<'gc>],
+        run: std::ops::Range<usize>,
+        new: &[&'gc Node<'gc>],
+    ) -> bool {
+        let statements = is_statement_list(field);
+        if run.len() == new.len() {
+            return old[run]
+                .iter()
+                .zip(new.iter())
+                .all(|(o, n)| self.diff_node(gc, o, n, Some(field)));
+        }
+
+        if new.is_empty() {
+            if statements {
+                return old[run].iter().all(|o| self.delete_lines(o));
+            }
+            // Delete the separator following the run, or if it is at the end of the list,
+            // the one preceding it.
+            let (start, end) = if run.end < old.len() {
+                (self.start(old[run.start]), self.start(old[run.end]))
+            } else if run.start > 0 {
+                (self.end(old[run.start - 1]), self.end(old[run.end - 1]))
+            } else {
+                (self.start(old[run.start]), self.end(old[run.end - 1]))
+            };
+            return match (start, end) {
+                (Some(start), Some(end)) => {
+                    self.edits.push(TextEdit {
+                        start,
+                        end,
+                        text: String::new(),
+                    });
+                    true
+                }
+                _ => false,
+            };
+        }
+
+        if run.is_empty() {
+            // Insert next to a surviving neighbor.
+            if run.start > 0 {
+                let prev = old[run.start - 1];
+                let (pos, indent) = match self.end(prev) {
+                    Some(pos) if statements => (self.insertion_point(pos), self.line_indent(prev)),
+                    Some(pos) => (pos, self.line_indent(prev)),
+                    None => return false,
+                };
+                let separator = separator(statements, &indent);
+                let text = self.join(gc, field, new, &separator, &indent);
+                self.edits.push(TextEdit {
+                    start: pos,
+                    end: pos,
+                    text: separator + &text,
+                });
+            } else if run.start < old.len() {
+                let next = old[run.start];
+                let (pos, indent) = match self.start(next) {
+                    Some(pos) => (pos, self.line_indent(next)),
+                    None => return false,
+                };
+                let separator = separator(statements, &indent);
+                let text = self.join(gc, field, new, &separator, &indent);
+                self.edits.push(TextEdit {
+                    start: pos,
+                    end: pos,
+                    text: text + &separator,
+                });
+            } else {
+                return false;
+            }
+            return true;
+        }
+
+        // Replace the whole run.
+        let (start, end) = match (self.start(old[run.start]), self.end(old[run.end - 1])) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return false,
+        };
+        let indent = self.indent_at(start).to_string();
+        let text = self.join(gc, field, new, &separator(statements, &indent), &indent);
+        self.edits.push(TextEdit { start, end, text });
+        true
+    }
+
+    /// Delete the statement `node`, along with its line if nothing else is on it.
+    fn delete_lines(&mut self, node: &'gc Node<'gc>) -> bool {
+        let (mut start, mut end) = match self.byte_range(node.range()) {
+            Some(range) => range,
+            None => return false,
+        };
+        let bytes = self.source.as_bytes();
+        let line_start = self.line_start(start);
+        let line_end = bytes[end..]
+            .iter()
+            .position(|&c| c == b'\n')
+            .map_or(bytes.len(), |pos| end + pos + 1);
+        let blank = |s: &[u8]| s.iter().all(|c| c.is_ascii_whitespace());
+        if blank(&bytes[line_start..start]) && blank(&bytes[end..line_end]) {
+            start = line_start;
+            end = line_end;
+        }
+        self.edits.push(TextEdit {
+            start,
+            end,
+            text: String::new(),
+        });
+        true
+    }
+
+    /// Text for the list elements `nodes` joined with `separator`.
+    fn join(
+        &self,
+        gc: &'gc GCLock,
+        field: NodeField,
+        nodes: &[&'gc Node<'gc>],
+        separator: &str,
+        indent: &str,
+    ) -> String {
+        nodes
+            .iter()
+            .map(|node| self.text(gc, node, Some(field), indent))
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    /// Text for `node` placed in `field` at a position indented by `indent`.
+    /// Nodes from the original tree keep their source text.
+    fn text(
+        &self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        field: Option<NodeField>,
+        indent: &str,
+    ) -> String {
+        let mut text = match self.byte_range(node.range()) {
+            Some((start, end)) if self.is_original(node) => self.source[start..end].to_string(),
+            _ => self.generate(gc, node, indent),
+        };
+        // Declarations in the head of a `for` loop aren't terminated.
+        let in_for_head = matches!(field, Some(NodeField::init) | Some(NodeField::left));
+        if instanceof(node.variant(), NodeVariant::Statement)
+            && !in_for_head
+            && !text.ends_with(';')
+            && gen_js::needs_semicolon(gc, node)
+        {
+            text.push(';');
+        }
+        text
+    }
+
+    /// Generate the text for `node`, indenting every line after the first with `indent`.
+    fn generate(&self, gc: &'gc GCLock, node: &'gc Node<'gc>, indent: &str) -> String {
+        let mut out = vec![];
+        gen_js::generate_fragment(&mut out, gc, node, self.opt.clone())
+            .expect("writing to a Vec cannot fail");
+        let text = String::from_utf8(out).expect("generated JS must be valid UTF-8");
+        let mut result = String::with_capacity(text.len());
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                result.push('\n');
+                if !line.is_empty() {
+                    result.push_str(indent);
+                }
+            }
+            result.push_str(line);
+        }
+        result
+    }
+
+    fn is_original(&self, node: &'gc Node<'gc>) -> bool {
+        self.original.contains(&NodePtr::from_node(node))
+    }
+
+    /// Byte offsets of the start and the end (exclusive) of `range` in the source.
+    fn byte_range(&self, range: &SourceRange) -> Option<(usize, usize)> {
+        if range.file != self.file {
+            return None;
+        }
+        let start = self.lines.offset(range.start)?;
+        // The end of a range is the location of its last character.
+        let end = self.lines.offset(range.end)?;
+        let end = end + self.source.get(end..)?.chars().next()?.len_utf8();
+        if start <= end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+
+    fn start(&self, node: &'gc Node<'gc>) -> Option<usize> {
+        self.byte_range(node.range()).map(|range| range.0)
+    }
+
+    fn end(&self, node: &'gc Node<'gc>) -> Option<usize> {
+        self.byte_range(node.range()).map(|range| range.1)
+    }
+
+    /// Where to insert a statement after a statement ending at `offset`:
+    /// the end of its line if there is only a line comment after it.
+    fn insertion_point(&self, offset: usize) -> usize {
+        let line_end = self.source[offset..]
+            .find('\n')
+            .map_or(self.source.len(), |pos| offset + pos);
+        let rest = self.source[offset..line_end].trim();
+        if rest.is_empty() || rest.starts_with("//") {
+            line_end
+        } else {
+            offset
+        }
+    }
+
+    /// Offset of the start of the line containing `offset`.
+    fn line_start(&self, offset: usize) -> usize {
+        self.lines
+            .line_start(self.lines.loc(offset).line)
+            .expect("offset must be in a line")
+    }
+
+    /// The leading whitespace of the line containing `offset`.
+    fn indent_at(&self, offset: usize) -> &str {
+        let line = &self.source[self.line_start(offset)..];
+        let len = line
+            .bytes()
+            .take_while(|&c| c == b' ' || c == b'\t')
+            .count();
+        &line[..len]
+    }
+
+    /// The leading whitespace of the line `node` starts on.
+    fn line_indent(&self, node: &'gc Node<'gc>) -> String {
+        self.start(node)
+            .map_or(String::new(), |start| self.indent_at(start).to_string())
+    }
+}
+
+/// Separator between list elements.
+fn separator(statements: bool, indent: &str) -> String {
+    if statements {
+        format!("\n{}", indent)
+    } else {
+        ", ".to_string()
+    }
+}
+
+/// Maximum number of cells in the table used to align lists.
+const MAX_ALIGN_CELLS: usize = 1 << 22;
+
+/// Indices of the longest common subsequence of the nodes in `old` and `new`, compared by
+/// identity, as pairs of `(old_index, new_index)`.
+/// Return `None` if the lists are too large to align.
+fn common_nodes<'gc>(
+    old: &[&'gc Node<'gc>],
+    new: &[&'gc Node<'gc>],
+) -> Option<Vec<(usize, usize)>> {
+    let same = |i: usize, j: usize| std::ptr::eq(old[i], new[j]);
+    let prefix = (0..old.len().min(new.len()))
+        .take_while(|&i| same(i, i))
+        .count();
+    let suffix = (0..(old.len() - prefix).min(new.len() - prefix))
+        .take_while(|&i| same(old.len() - 1 - i, new.len() - 1 - i))
+        .count();
+    let n = old.len() - prefix - suffix;
+    let m = new.len() - prefix - suffix;
+    if (n + 1) * (m + 1) > MAX_ALIGN_CELLS {
+        return None;
+    }
+
+    // lcs[i][j] is the length of the common subsequence of the middle parts
+    // of `old[i..]` and `new[j..]`.
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if same(prefix + i, prefix + j) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut result: Vec<(usize, usize)> = (0..prefix).map(|i| (i, i)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if same(prefix + i, prefix + j) {
+            result.push((prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result.extend((0..suffix).map(|i| (old.len() - suffix + i, new.len() - suffix + i)));
+    Some(result)
+}
@@ -0,0 +1,204 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Comments in JS source and their attachment to AST nodes.
+//!
+//! The parser returns the comments of a file separately from the AST
+//! (see [`ParsedJS::comments`](crate::hparser::ParsedJS::comments)).
+//! [`CommentMap::attach`] associates every comment with the node closest to it,
+//! so that [`gen_js`](crate::gen_js) can emit it again.
+//!
+//! Comments are looked up by the kind and range of a node, so they stay attached
+//! to nodes which passes rebuild without changing their range.
+
+use std::collections::HashMap;
+
+use crate::ast::*;
+
+/// The syntax of a comment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CommentKind {
+    /// Comment that begins with "//".
+    Line,
+    /// Comment that is delimited by "/*" and "*/".
+    Block,
+    /// Comment that begins with "#!" at the start of the file.
+    Hashbang,
+}
+
+/// A comment in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// Text of the comment without the delimiters.
+    pub text: String,
+    /// Range of the comment, including the delimiters.
+    pub range: SourceRange,
+}
+
+impl Comment {
+    /// Whether the comment must be kept when minifying:
+    /// a `/*! ... */` comment or a comment containing `@license`.
+    pub fn is_legal(&self) -> bool {
+        match self.kind {
+            CommentKind::Hashbang => true,
+            CommentKind::Block if self.text.starts_with('!') => true,
+            _ => self.text.contains("@license"),
+        }
+    }
+}
+
+/// The comments attached to a single node.
+#[derive(Debug, Default)]
+pub struct NodeComments {
+    /// Comments before the node.
+    pub leading: Vec<Comment>,
+    /// Comments after the node, starting on the line it ends on.
+    pub trailing: Vec<Comment>,
+    /// Comments inside the node which aren't next to any of its children,
+    /// e.g. in an empty block or after the last statement of a block.
+    pub inner: Vec<Comment>,
+}
+
+/// Identifies a node by its kind and range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct NodeKey {
+    name: &'static str,
+    file: SourceId,
+    start: (u32, u32),
+    end: (u32, u32),
+}
+
+impl NodeKey {
+    fn new(node: &Node) -> NodeKey {
+        let range = node.range();
+        NodeKey {
+            name: node.name(),
+            file: range.file,
+            start: start(range),
+            end: end(range),
+        }
+    }
+}
+
+fn start(range: &SourceRange) -> (u32, u32) {
+    (range.start.line, range.start.col)
+}
+
+fn end(range: &SourceRange) -> (u32, u32) {
+    (range.end.line, range.end.col)
+}
+
+/// Whether comments after the last child of `node` on their own line can be
+/// emitted inside `node`.
+pub(crate) fn holds_inner_comments(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::Program(_) | Node::Module(_) | Node::BlockStatement(_)
+    )
+}
+
+/// Where a comment is attached relative to a node.
+enum Placement<'gc> {
+    Leading(&'gc Node<'gc>),
+    Trailing(&'gc Node<'gc>),
+    Inner(&'gc Node<'gc>),
+}
+
+/// Collect the direct children of a node.
+struct ChildCollector<'gc> {
+    children: Vec<&'gc Node<'gc>>,
+}
+
+impl<'gc> Visitor<'gc> for ChildCollector<'gc> {
+    fn call(&mut self, _gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        self.children.push(node);
+    }
+}
+
+/// The comments of a file, attached to the nodes of its AST.
+#[derive(Debug, Default)]
+pub struct CommentMap {
+    nodes: HashMap<NodeKey, NodeComments>,
+}
+
+impl CommentMap {
+    /// Attach `comments`, sorted by position, to the nodes of the AST rooted at `root`.
+    ///
+    /// A comment inside a node is attached to one of its descendants if possible.
+    /// Otherwise it trails the preceding sibling if it starts on the line that sibling
+    /// ends on, and leads the following sibling, if any.
+    pub fn attach<'gc>(
+        gc: &'gc GCLock,
+        root: &'gc Node<'gc>,
+        comments: Vec<Comment>,
+    ) -> CommentMap {
+        let mut map = CommentMap::default();
+        map.attach_to(gc, root, comments);
+        map
+    }
+
+    /// Return the comments attached to `node`, if any.
+    pub fn get(&self, node: &Node) -> Option<&NodeComments> {
+        self.nodes.get(&NodeKey::new(node))
+    }
+
+    /// Return true if no comment is attached to any node.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn entry(&mut self, node: &Node) -> &mut NodeComments {
+        self.nodes.entry(NodeKey::new(node)).or_default()
+    }
+
+    /// Attach `comments`, all of which are inside `node`, to `node` or its descendants.
+    fn attach_to<'gc>(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, comments: Vec<Comment>) {
+        if comments.is_empty() {
+            return;
+        }
+        let mut collector = ChildCollector { children: vec![] };
+        node.visit_children(gc, &mut collector);
+        let mut children = collector.children;
+        children.sort_by_key(|child| start(child.range()));
+
+        // Comments inside each child.
+        let mut nested: Vec<Vec<Comment>> = vec![vec![]; children.len()];
+        for comment in comments {
+            let comment_start = start(&comment.range);
+            let comment_end = end(&comment.range);
+            // Index of the first child after the comment.
+            let next = children.partition_point(|child| start(child.range()) <= comment_start);
+            let prev = next.checked_sub(1).map(|i| children[i]);
+            if let Some(prev) = prev {
+                if end(prev.range()) >= comment_end {
+                    nested[next - 1].push(comment);
+                    continue;
+                }
+            }
+            let following = children.get(next).copied();
+            let same_line = matches!(prev, Some(prev) if prev.range().end.line == comment_start.0);
+            let followed_on_line =
+                matches!(following, Some(f) if f.range().start.line <= comment_end.0);
+            let placement = match (prev, following) {
+                (Some(prev), _) if same_line && !followed_on_line => Placement::Trailing(prev),
+                (_, Some(following)) => Placement::Leading(following),
+                (Some(prev), None) if !holds_inner_comments(node) => Placement::Trailing(prev),
+                (_, None) => Placement::Inner(node),
+            };
+            match placement {
+                Placement::Leading(n) => self.entry(n).leading.push(comment),
+                Placement::Trailing(n) => self.entry(n).trailing.push(comment),
+                Placement::Inner(n) => self.entry(n).inner.push(comment),
+            }
+        }
+
+        for (child, comments) in children.into_iter().zip(nested) {
+            self.attach_to(gc, child, comments);
+        }
+    }
+}
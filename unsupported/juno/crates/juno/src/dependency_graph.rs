@@ -0,0 +1,320 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! The graph of the dependencies between modules, as resolved by semantic analysis.
+//! It can be written as JSON, or in the DOT language of Graphviz.
+
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use juno_support::json::JSONEmitter;
+use juno_support::json::Pretty;
+use juno_support::json::emit_loc;
+use juno_support::json::emit_str;
+use juno_support::source_manager::SourceId;
+use juno_support::source_manager::SourceManager;
+use juno_support::source_manager::SourceRange;
+
+use crate::ast::*;
+use crate::resolve_dependency::DependencyKind;
+use crate::sema::SemContext;
+
+/// A `require` call, `import` declaration or `export ... from` declaration.
+#[derive(Debug, Clone)]
+pub struct Dependency {
+    /// The module containing the dependency.
+    pub from: SourceId,
+    /// The module the dependency was resolved to, `None` if it couldn't be resolved.
+    pub to: Option<SourceId>,
+    /// The specifier of the required module, e.g. `./foo`.
+    pub specifier: String,
+    pub kind: DependencyKind,
+    /// The range of the call or declaration.
+    pub range: SourceRange,
+}
+
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    /// The analyzed modules and the modules they depend on, sorted by ID.
+    pub modules: Vec<SourceId>,
+    /// The dependencies of the analyzed modules, sorted by module and location.
+    pub dependencies: Vec<Dependency>,
+}
+
+impl DependencyGraph {
+    /// Collect the dependencies in the semantic information of every module of `modules`.
+    pub fn build<'s>(
+        gc: &GCLock,
+        modules: impl IntoIterator<Item = (SourceId, &'s SemContext)>,
+    ) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        for (id, sem) in modules {
+            graph.modules.push(id);
+            let resolved = sem
+                .all_requires()
+                .iter()
+                .map(|(node, &to)| (node, Some(to)));
+            let unresolved = sem
+                .all_unresolved_requires()
+                .iter()
+                .map(|node| (node, None));
+            for (node, to) in resolved.chain(unresolved) {
+                let node = node.node(gc);
+                if let Some((specifier, kind)) = dependency_specifier(gc, node) {
+                    graph.dependencies.push(Dependency {
+                        from: id,
+                        to,
+                        specifier,
+                        kind,
+                        range: *node.range(),
+                    });
+                }
+            }
+        }
+        let targets: Vec<SourceId> = graph.dependencies.iter().filter_map(|dep| dep.to).collect();
+        graph.modules.extend(targets);
+        graph.modules.sort_by_key(|id| id.0);
+        graph.modules.dedup();
+        graph
+            .dependencies
+            .sort_by_key(|dep| (dep.from.0, dep.range.start.line, dep.range.start.col));
+        graph
+    }
+
+    /// Find the cycles of the graph, as the dependencies from every module of the cycle
+    /// to the next one. A cycle is found for every dependency closing a path of
+    /// dependencies from a module back to itself, exploring the modules in order.
+    pub fn cycles(&self) -> Vec<Vec<&Dependency>> {
+        let mut finder = CycleFinder::default();
+        for dep in &self.dependencies {
+            if let Some(to) = dep.to {
+                let edges = finder.edges.entry(dep.from).or_default();
+                if !edges.iter().any(|edge| edge.to == Some(to)) {
+                    edges.push(dep);
+                }
+            }
+        }
+        for &id in &self.modules {
+            if !finder.on_path.contains_key(&id) {
+                finder.visit(id);
+            }
+        }
+        finder.cycles
+    }
+
+    /// Report a warning for every cycle of the graph, with notes for the locations of
+    /// the dependencies which form it.
+    pub fn report_cycles(&self, sm: &SourceManager) {
+        for cycle in self.cycles() {
+            let names: Vec<&str> = cycle
+                .iter()
+                .map(|dep| sm.source_name(dep.from))
+                .chain(std::iter::once(sm.source_name(cycle[0].from)))
+                .collect();
+            sm.warning(
+                cycle[0].range,
+                format!("Import cycle: {}", names.join(" -> ")),
+            );
+            for dep in &cycle[1..] {
+                sm.note(
+                    dep.range,
+                    format!("{} is required from here", sm.source_name(dep.to.unwrap())),
+                );
+            }
+        }
+    }
+
+    /// Write the graph as a JSON object with a `modules` array of `{id, name}` objects,
+    /// and a `dependencies` array of `{from, to, specifier, kind, range}` objects.
+    /// `to` is `null` for unresolved dependencies.
+    pub fn write_json<W: Write>(
+        &self,
+        sm: &SourceManager,
+        out: W,
+        pretty: Pretty,
+    ) -> io::Result<()> {
+        let mut emitter = JSONEmitter::new(out, pretty);
+        emitter.open_dict();
+        emitter.emit_key("modules");
+        emitter.open_array();
+        for &id in &self.modules {
+            emitter.open_dict();
+            emitter.emit_key("id");
+            emitter.emit_number(id.0 as f64);
+            emitter.emit_key("name");
+            emit_str(&mut emitter, sm.source_name(id));
+            emitter.close_dict();
+        }
+        emitter.close_array();
+        emitter.emit_key("dependencies");
+        emitter.open_array();
+        for dep in &self.dependencies {
+            emitter.open_dict();
+            emitter.emit_key("from");
+            emitter.emit_number(dep.from.0 as f64);
+            emitter.emit_key("to");
+            match dep.to {
+                Some(to) => emitter.emit_number(to.0 as f64),
+                None => emitter.emit_null(),
+            }
+            emitter.emit_key("specifier");
+            emit_str(&mut emitter, &dep.specifier);
+            emitter.emit_key("kind");
+            emitter.emit_string(kind_name(dep.kind));
+            emitter.emit_key("range");
+            emitter.open_dict();
+            emitter.emit_key("start");
+            emit_loc(&mut emitter, dep.range.start);
+            emitter.emit_key("end");
+            emit_loc(&mut emitter, dep.range.end);
+            emitter.close_dict();
+            emitter.close_dict();
+        }
+        emitter.close_array();
+        emitter.close_dict();
+        emitter.end()
+    }
+
+    /// Write the graph as a Graphviz digraph.
+    /// Every module is a node labeled with its name, and every dependency an edge
+    /// labeled with its kind and location. Unresolved dependencies are dashed edges to
+    /// a dashed node for each distinct specifier.
+    pub fn write_dot(&self, sm: &SourceManager, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "digraph dependencies {{")?;
+        for &id in &self.modules {
+            writeln!(
+                out,
+                "  m{} [label={}];",
+                id.0,
+                dot_string(sm.source_name(id))
+            )?;
+        }
+        let mut unresolved: HashMap<&str, usize> = HashMap::new();
+        for dep in &self.dependencies {
+            let label = format!(
+                "{} {}:{}",
+                kind_name(dep.kind),
+                dep.range.start.line,
+                dep.range.start.col
+            );
+            match dep.to {
+                Some(to) => writeln!(
+                    out,
+                    "  m{} -> m{} [label={}];",
+                    dep.from.0,
+                    to.0,
+                    dot_string(&label)
+                )?,
+                None => {
+                    let next = unresolved.len();
+                    let index = *unresolved.entry(&dep.specifier).or_insert(next);
+                    if index == next {
+                        writeln!(
+                            out,
+                            "  u{} [label={}, shape=box, style=dashed];",
+                            index,
+                            dot_string(&dep.specifier)
+                        )?;
+                    }
+                    writeln!(
+                        out,
+                        "  m{} -> u{} [label={}, style=dashed];",
+                        dep.from.0,
+                        index,
+                        dot_string(&label)
+                    )?;
+                }
+            }
+        }
+        writeln!(out, "}}")
+    }
+}
+
+/// Depth-first search of the cycles of a graph.
+#[derive(Default)]
+struct CycleFinder<'g> {
+    /// The dependencies on distinct modules of every module.
+    edges: HashMap<SourceId, Vec<&'g Dependency>>,
+    /// Whether every visited module is on the current path.
+    on_path: HashMap<SourceId, bool>,
+    /// The dependencies leading from the first module of the search to the current one.
+    path: Vec<&'g Dependency>,
+    cycles: Vec<Vec<&'g Dependency>>,
+}
+
+impl<'g> CycleFinder<'g> {
+    fn visit(&mut self, id: SourceId) {
+        self.on_path.insert(id, true);
+        let edges = self.edges.get(&id).cloned().unwrap_or_default();
+        for dep in edges {
+            let to = dep.to.unwrap();
+            match self.on_path.get(&to) {
+                Some(true) => {
+                    // The path is simple, so at most one of its dependencies leaves `to`.
+                    let start = self
+                        .path
+                        .iter()
+                        .position(|edge| edge.from == to)
+                        .unwrap_or(self.path.len());
+                    let mut cycle = self.path[start..].to_vec();
+                    cycle.push(dep);
+                    self.cycles.push(cycle);
+                }
+                Some(false) => {}
+                None => {
+                    self.path.push(dep);
+                    self.visit(to);
+                    self.path.pop();
+                }
+            }
+        }
+        self.on_path.insert(id, false);
+    }
+}
+
+/// The specifier and kind of `node` if it is a `require` call or a declaration
+/// importing from another module.
+fn dependency_specifier<'gc>(
+    gc: &'gc GCLock,
+    node: &'gc Node<'gc>,
+) -> Option<(String, DependencyKind)> {
+    let (value, kind) = match node {
+        Node::CallExpression(CallExpression { arguments, .. }) => match arguments.head() {
+            Some(Node::StringLiteral(StringLiteral { value, .. })) => {
+                (value, DependencyKind::Require)
+            }
+            _ => return None,
+        },
+        Node::ImportDeclaration(ImportDeclaration {
+            source: Node::StringLiteral(StringLiteral { value, .. }),
+            ..
+        })
+        | Node::ExportAllDeclaration(ExportAllDeclaration {
+            source: Node::StringLiteral(StringLiteral { value, .. }),
+            ..
+        })
+        | Node::ExportNamedDeclaration(ExportNamedDeclaration {
+            source: Some(Node::StringLiteral(StringLiteral { value, .. })),
+            ..
+        }) => (value, DependencyKind::Import),
+        _ => return None,
+    };
+    Some((String::from_utf16_lossy(gc.str_u16(*value)), kind))
+}
+
+fn kind_name(kind: DependencyKind) -> &'static str {
+    match kind {
+        DependencyKind::Require => "require",
+        DependencyKind::Import => "import",
+    }
+}
+
+/// Quote `s` as a DOT string.
+fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -18,11 +18,18 @@ use sourcemap::SourceMap;
 use sourcemap::SourceMapBuilder;
 
 use crate::ast::*;
+use crate::comments;
+use crate::comments::Comment;
+use crate::comments::CommentKind;
+use crate::comments::CommentMap;
+use crate::layout;
+use crate::layout::DocBuilder;
 use crate::sema::DeclKind;
 use crate::sema::Resolution;
 use crate::sema::SemContext;
 
 /// Options for JS generation.
+#[derive(Clone)]
 pub struct Opt<'s> {
     /// Whether to pretty-print the generated JS.
     pub pretty: Pretty,
@@ -38,6 +45,21 @@ pub struct Opt<'s> {
 
     /// Delimiter to use for string literals.
     pub quote: QuoteChar,
+
+    /// If `Some`, comments to emit around the nodes they are attached to.
+    /// The doc block is then emitted as part of the comments instead of `doc_block`.
+    pub comments: Option<&'s CommentMap>,
+
+    /// Which of the attached comments to emit.
+    pub keep_comments: KeepComments,
+
+    /// If `Some`, lay out the output within a line width, breaking lists and
+    /// expressions which don't fit and preserving blank lines between statements.
+    /// Implies `Pretty::Yes`.
+    pub layout: Option<Layout>,
+
+    /// Whether to embed the contents of the sources in the source map.
+    pub sources_content: bool,
 }
 
 impl Default for Opt<'_> {
@@ -48,6 +70,10 @@ impl Default for Opt<'_> {
             force_async_arrow_space: true,
             doc_block: None,
             quote: QuoteChar::Single,
+            comments: None,
+            keep_comments: KeepComments::All,
+            layout: None,
+            sources_content: false,
         }
     }
 }
@@ -67,6 +93,56 @@ pub enum Pretty {
     Yes,
 }
 
+/// Options for laying out the output within a line width, close to Prettier's formatting.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Layout {
+    /// Width that lines are fit into where possible.
+    pub print_width: usize,
+
+    /// Which lists get a trailing comma when broken over several lines.
+    pub trailing_commas: TrailingCommas,
+
+    /// Whether to terminate statements with semicolons.
+    /// If false, semicolons are only emitted where ASI wouldn't insert them.
+    pub semicolons: bool,
+
+    /// Whether to put spaces between braces and their contents in object literals,
+    /// patterns and import/export specifiers.
+    pub bracket_spacing: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            print_width: 80,
+            trailing_commas: TrailingCommas::All,
+            semicolons: true,
+            bracket_spacing: true,
+        }
+    }
+}
+
+/// Which lists get a trailing comma when broken over several lines.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TrailingCommas {
+    /// No trailing commas.
+    None,
+    /// Trailing commas where ES5 allows them: arrays, objects and specifiers.
+    Es5,
+    /// Trailing commas in every list, including arguments and parameters.
+    All,
+}
+
+/// Which comments to emit when comments are enabled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeepComments {
+    /// Emit every comment.
+    All,
+    /// Only emit comments which must be kept when minifying,
+    /// `/*! ... */` and `@license` comments.
+    Legal,
+}
+
 /// Delimiter to use for string literals.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum QuoteChar {
@@ -86,7 +162,8 @@ impl QuoteChar {
 }
 
 /// Generate JS for `root` and print it to `out`.
-/// FIXME: This currently only returns an empty SourceMap.
+/// Return a source map with a mapping for the first token of every node with a
+/// valid source range, in which identifiers refer to their names.
 pub fn generate(
     out: &mut dyn Write,
     ctx: &mut Context,
@@ -97,6 +174,29 @@ pub fn generate(
     GenJS::gen_root(out, &gc, root.node(&gc), opt)
 }
 
+/// Generate JS for `node` alone, to be spliced into existing source text.
+/// Unlike [`generate`], no doc block or trailing newline is emitted, and of the comments
+/// attached to `node` itself, only the inner ones are emitted.
+pub fn generate_fragment<'gc>(
+    out: &mut dyn Write,
+    gc: &'gc GCLock,
+    node: &'gc Node<'gc>,
+    opt: Opt,
+) -> io::Result<()> {
+    GenJS::gen_fragment(out, gc, node, opt)
+}
+
+/// Whether `node` must be parenthesized when placed where `original` was,
+/// because it binds less tightly than `original`.
+pub fn binds_less_tightly(node: &Node, original: &Node, pretty: Pretty) -> bool {
+    get_precedence(node, pretty).0 < get_precedence(original, pretty).0
+}
+
+/// Whether `node` must be followed by a semicolon when emitted as a statement.
+pub fn needs_semicolon<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> bool {
+    !stmt_skip_semi(gc, Some(node))
+}
+
 /// Associativity direction.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Assoc {
@@ -209,6 +309,41 @@ enum ForceBlock {
     Yes,
 }
 
+/// Kind of a comma separated list, which determines its delimiters and
+/// whether it may have a trailing comma.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ListKind {
+    /// Arguments or parameters in `( )`.
+    Args,
+    /// Elements in `[ ]`.
+    Elements,
+    /// Properties or specifiers in `{ }`.
+    Braces,
+}
+
+/// State of the layout mode, see [`Opt::layout`].
+struct LayoutState {
+    opt: Layout,
+
+    /// Document containing all output so far.
+    doc: DocBuilder,
+
+    /// Source map tokens for the marks in `doc`, in order.
+    /// Their output positions are only known once `doc` has been laid out.
+    tokens: Vec<RawToken>,
+}
+
+impl LayoutState {
+    fn new(opt: Layout) -> LayoutState {
+        LayoutState {
+            opt,
+            doc: DocBuilder::new(),
+            tokens: vec![],
+        }
+    }
+}
+
+#[derive(Clone)]
 pub enum Annotation<'s> {
     No,
     Sem(&'s SemContext),
@@ -238,6 +373,16 @@ struct GenJS<'s, 'w> {
     /// Build a source map as we go along.
     sourcemap: SourceMapBuilder,
 
+    /// Trailing comments waiting to be emitted before the next newline.
+    pending_comments: Vec<&'s Comment>,
+
+    /// Comments which have already been emitted before their statement,
+    /// see [`GenJS::hoist_comments`].
+    hoisted_comments: Vec<&'s Comment>,
+
+    /// If `Some`, output is collected into a document which is laid out at the end.
+    layout: Option<LayoutState>,
+
     /// Some(err) if an error has occurred when writing, else None.
     error: Option<io::Error>,
 }
@@ -261,7 +406,7 @@ macro_rules! out_token {
     }}
 }
 
-impl GenJS<'_, '_> {
+impl<'s> GenJS<'s, '_> {
     /// Generate JS for `root` and flush the output.
     /// If at any point, JS generation resulted in an error, return `Err(err)`,
     /// otherwise return `Ok(())`.
@@ -273,22 +418,39 @@ impl GenJS<'_, '_> {
     ) -> io::Result<SourceMap> {
         let mut gen_js = GenJS {
             out: BufWriter::new(writer),
-            opt,
+            layout: opt.layout.map(LayoutState::new),
+            opt: Opt {
+                pretty: if opt.layout.is_some() {
+                    Pretty::Yes
+                } else {
+                    opt.pretty
+                },
+                ..opt
+            },
             indent_step: 2,
             indent: 0,
             position: SourceLoc { line: 1, col: 1 },
             cur_token: None,
             // FIXME: Pass in file name here.
             sourcemap: SourceMapBuilder::new(None),
+            pending_comments: vec![],
+            hoisted_comments: vec![],
             error: None,
         };
         for i in 0..ctx.sm().num_sources() {
-            gen_js
-                .sourcemap
-                .add_source(ctx.sm().source_name(SourceId(i as u32)));
+            let id = SourceId(i as u32);
+            let src_id = gen_js.sourcemap.add_source(ctx.sm().source_name(id));
+            if gen_js.opt.sources_content {
+                // Drop the null terminator.
+                let buf = ctx.sm().source_buffer(id).as_bytes();
+                let contents = String::from_utf8_lossy(&buf[..buf.len() - 1]);
+                gen_js
+                    .sourcemap
+                    .set_source_contents(src_id, Some(&contents));
+            }
         }
 
-        if let Some(doc_block) = gen_js.opt.doc_block.clone() {
+        if let (Some(doc_block), None) = (gen_js.opt.doc_block.clone(), gen_js.opt.comments) {
             let mut buf = [0u8; 4];
             for c in doc_block.chars() {
                 if c == '\n' {
@@ -303,6 +465,7 @@ impl GenJS<'_, '_> {
         gen_js.force_newline();
 
         gen_js.flush_cur_token();
+        gen_js.finish_layout();
         match gen_js.error {
             None => gen_js
                 .out
@@ -312,6 +475,59 @@ impl GenJS<'_, '_> {
         }
     }
 
+    /// Generate JS for `node` without a doc block or trailing newline and flush the output.
+    fn gen_fragment<'gc>(
+        writer: &mut dyn Write,
+        ctx: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        opt: Opt,
+    ) -> io::Result<()> {
+        let mut gen_js = GenJS {
+            out: BufWriter::new(writer),
+            layout: opt.layout.map(LayoutState::new),
+            opt: Opt {
+                pretty: if opt.layout.is_some() {
+                    Pretty::Yes
+                } else {
+                    opt.pretty
+                },
+                ..opt
+            },
+            indent_step: 2,
+            indent: 0,
+            position: SourceLoc { line: 1, col: 1 },
+            cur_token: None,
+            sourcemap: SourceMapBuilder::new(None),
+            pending_comments: vec![],
+            hoisted_comments: vec![],
+            error: None,
+        };
+
+        // The text replaced by `node` only holds its inner comments, the others are kept
+        // around it, so the comments of `node` itself are emitted here instead of by
+        // visiting it. Comments needing a newline are dropped if `node` is an expression.
+        let statement = instanceof(node.variant(), NodeVariant::Statement);
+        if statement {
+            gen_js.hoist_comments(ctx, node);
+        }
+        if let Some(comments) = gen_js.node_comments(node) {
+            if !comments::holds_inner_comments(node) {
+                gen_js.emit_comments_before(node, &comments.inner, statement);
+            }
+        }
+        gen_js.add_segment(node);
+        gen_js.gen_node(ctx, node, None);
+        if gen_js.flush_comments() {
+            gen_js.force_newline_without_indent();
+        }
+        gen_js.finish_layout();
+
+        match gen_js.error {
+            None => gen_js.out.flush(),
+            Some(err) => Err(err),
+        }
+    }
+
     /// Write to the `out` writer if we haven't seen any errors.
     /// If we have seen any errors, do nothing.
     /// Used via the `out!` macro.
@@ -321,7 +537,9 @@ impl GenJS<'_, '_> {
             let buf = format!("{}", args);
             debug_assert!(buf.is_ascii(), "Output must be ASCII");
             debug_assert!(!buf.contains('\n'), "Output must have no newlines");
-            if let Err(e) = self.out.write_all(buf.as_bytes()) {
+            if let Some(layout) = &mut self.layout {
+                layout.doc.text(&buf);
+            } else if let Err(e) = self.out.write_all(buf.as_bytes()) {
                 self.error = Some(e);
             }
             self.position.col += buf.len() as u32;
@@ -335,7 +553,9 @@ impl GenJS<'_, '_> {
     fn write_char(&mut self, ch: char, dst: &mut [u8]) {
         debug_assert!(ch != '\n', "Output must not contain newlines");
         if self.error.is_none() {
-            if let Err(e) = self.out.write_all(ch.encode_utf8(dst).as_bytes()) {
+            if let Some(layout) = &mut self.layout {
+                layout.doc.text(ch.encode_utf8(dst));
+            } else if let Err(e) = self.out.write_all(ch.encode_utf8(dst).as_bytes()) {
                 self.error = Some(e);
             }
             self.position.col += 1;
@@ -351,7 +571,9 @@ impl GenJS<'_, '_> {
             "Output must not contain newlines"
         );
         if self.error.is_none() {
-            if let Err(e) = self.out.write_all(s.as_bytes()) {
+            if let Some(layout) = &mut self.layout {
+                layout.doc.text(s);
+            } else if let Err(e) = self.out.write_all(s.as_bytes()) {
                 self.error = Some(e);
             }
         }
@@ -366,9 +588,12 @@ impl GenJS<'_, '_> {
 
             Node::Program(Program { metadata: _, body }) => {
                 self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body));
+                // The file ends with a newline, which terminates any line comment.
+                self.gen_inner_comments(node, !body.is_empty());
             }
             Node::Module(Module { metadata: _, body }) => {
                 self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body));
+                self.gen_inner_comments(node, !body.is_empty());
             }
 
             Node::FunctionExpression(FunctionExpression {
@@ -398,12 +623,13 @@ impl GenJS<'_, '_> {
                 } else {
                     out_token!(self, node, "function");
                 }
+                // Prettier separates the keyword from the parameters of anonymous functions.
                 if *generator {
                     out!(self, "*");
-                    if id.is_some() {
+                    if id.is_some() || self.layout.is_some() {
                         self.space(ForceSpace::No);
                     }
-                } else if id.is_some() {
+                } else if id.is_some() || self.layout.is_some() {
                     self.space(ForceSpace::Yes);
                 }
                 if let Some(id) = id {
@@ -465,6 +691,7 @@ impl GenJS<'_, '_> {
                         })
                     )
                     && (*expression || self.opt.pretty == Pretty::No)
+                    && self.layout.is_none()
                 {
                     if need_sep {
                         out!(self, " ");
@@ -475,14 +702,10 @@ impl GenJS<'_, '_> {
                         Some(Path::new(node, NodeField::params)),
                     );
                 } else {
-                    out!(self, "(");
-                    for (i, param) in params.iter().enumerate() {
-                        if i > 0 {
-                            self.comma();
-                        }
-                        param.visit(ctx, self, Some(Path::new(node, NodeField::params)));
-                    }
-                    out!(self, ")");
+                    let params: Vec<&Node> = params.iter().collect();
+                    self.print_list(ListKind::Args, &params, false, false, |gen_js, param| {
+                        param.visit(ctx, gen_js, Some(Path::new(node, NodeField::params)))
+                    });
                 }
                 if return_type.is_some() || predicate.is_some() {
                     out!(self, ":");
@@ -630,13 +853,18 @@ impl GenJS<'_, '_> {
             Node::EmptyStatement(_) => {}
 
             Node::BlockStatement(BlockStatement { metadata: _, body }) => {
-                if body.is_empty() {
+                if body.is_empty() && self.inner_comments(node).is_empty() {
                     out!(self, "{{}}");
                 } else {
                     out!(self, "{{");
                     self.inc_indent();
                     self.newline();
                     self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body));
+                    if self.gen_inner_comments(node, !body.is_empty())
+                        && self.opt.pretty == Pretty::No
+                    {
+                        self.force_newline_without_indent();
+                    }
                     self.dec_indent();
                     self.newline();
                     out!(self, "}}");
@@ -899,29 +1127,27 @@ impl GenJS<'_, '_> {
                 elements,
                 trailing_comma,
             }) => {
-                out_token!(self, node, "[");
-                for (i, elem) in elements.iter().enumerate() {
-                    if i > 0 {
-                        self.comma();
-                    }
-                    match elem {
+                self.add_segment(node);
+                let elements: Vec<&Node> = elements.iter().collect();
+                self.print_list(
+                    ListKind::Elements,
+                    &elements,
+                    false,
+                    *trailing_comma,
+                    |gen_js, elem| match elem {
                         Node::SpreadElement(_) => {
-                            elem.visit(ctx, self, Some(Path::new(node, NodeField::elements)));
+                            elem.visit(ctx, gen_js, Some(Path::new(node, NodeField::elements)));
                         }
                         Node::Empty(_) => {}
                         _ => {
-                            self.print_comma_expression(
+                            gen_js.print_comma_expression(
                                 ctx,
                                 elem,
                                 Path::new(node, NodeField::elements),
                             );
                         }
-                    }
-                }
-                if *trailing_comma {
-                    self.comma();
-                }
-                out!(self, "]");
+                    },
+                );
             }
 
             Node::SpreadElement(SpreadElement {
@@ -952,19 +1178,7 @@ impl GenJS<'_, '_> {
                         Some(Path::new(node, NodeField::type_arguments)),
                     );
                 }
-                out!(self, "(");
-                for (i, arg) in arguments.iter().enumerate() {
-                    if i > 0 {
-                        self.comma();
-                    }
-                    self.print_child(
-                        ctx,
-                        Some(arg),
-                        Path::new(node, NodeField::arguments),
-                        ChildPos::Anywhere,
-                    );
-                }
-                out!(self, ")");
+                self.print_args(ctx, arguments, node);
             }
             Node::YieldExpression(YieldExpression {
                 metadata: _,
@@ -1032,19 +1246,7 @@ impl GenJS<'_, '_> {
                         Some(Path::new(node, NodeField::type_arguments)),
                     );
                 }
-                out!(self, "(");
-                for (i, arg) in arguments.iter().enumerate() {
-                    if i > 0 {
-                        self.comma();
-                    }
-                    self.print_child(
-                        ctx,
-                        Some(arg),
-                        Path::new(node, NodeField::arguments),
-                        ChildPos::Anywhere,
-                    );
-                }
-                out!(self, ")");
+                self.print_args(ctx, arguments, node);
             }
             Node::OptionalCallExpression(OptionalCallExpression {
                 metadata: _,
@@ -1066,19 +1268,10 @@ impl GenJS<'_, '_> {
                         Some(Path::new(node, NodeField::type_arguments)),
                     );
                 }
-                out!(self, "{}(", if *optional { "?." } else { "" });
-                for (i, arg) in arguments.iter().enumerate() {
-                    if i > 0 {
-                        self.comma();
-                    }
-                    self.print_child(
-                        ctx,
-                        Some(arg),
-                        Path::new(node, NodeField::arguments),
-                        ChildPos::Anywhere,
-                    );
+                if *optional {
+                    out!(self, "?.");
                 }
-                out!(self, ")");
+                self.print_args(ctx, arguments, node);
             }
 
             Node::AssignmentExpression(AssignmentExpression {
@@ -1232,6 +1425,7 @@ impl GenJS<'_, '_> {
                 operator,
             }) => {
                 let ident = operator.as_str().chars().next().unwrap().is_alphabetic();
+                let outermost = self.begin_binary_group(path);
                 self.print_child(
                     ctx,
                     Some(*left),
@@ -1244,7 +1438,7 @@ impl GenJS<'_, '_> {
                     ForceSpace::No
                 });
                 out!(self, "{}", operator.as_str());
-                self.space(if ident {
+                self.line_or_space(if ident {
                     ForceSpace::Yes
                 } else {
                     ForceSpace::No
@@ -1255,6 +1449,9 @@ impl GenJS<'_, '_> {
                     Path::new(node, NodeField::right),
                     ChildPos::Right,
                 );
+                if outermost {
+                    self.end_binary_group();
+                }
             }
 
             Node::Directive(Directive { metadata: _, value }) => {
@@ -1270,13 +1467,15 @@ impl GenJS<'_, '_> {
                 consequent,
                 alternate,
             }) => {
+                self.begin_group();
                 self.print_child(
                     ctx,
                     Some(*test),
                     Path::new(node, NodeField::test),
                     ChildPos::Left,
                 );
-                self.space(ForceSpace::No);
+                self.inc_indent();
+                self.line_or_space(ForceSpace::No);
                 out!(self, "?");
                 self.space(ForceSpace::No);
                 self.print_child(
@@ -1285,7 +1484,7 @@ impl GenJS<'_, '_> {
                     Path::new(node, NodeField::consequent),
                     ChildPos::Anywhere,
                 );
-                self.space(ForceSpace::No);
+                self.line_or_space(ForceSpace::No);
                 out!(self, ":");
                 self.space(ForceSpace::No);
                 self.print_child(
@@ -1294,6 +1493,8 @@ impl GenJS<'_, '_> {
                     Path::new(node, NodeField::alternate),
                     ChildPos::Right,
                 );
+                self.dec_indent();
+                self.end_group(false);
             }
 
             Node::Identifier(Identifier {
@@ -1302,8 +1503,9 @@ impl GenJS<'_, '_> {
                 type_annotation,
                 optional,
             }) => {
-                self.add_segment(node);
-                self.write_utf8(ctx.str(*name).as_ref());
+                let name = ctx.str(*name);
+                self.add_named_segment(node, Some(name));
+                self.write_utf8(name);
                 self.annotate_identifier(ctx, node);
                 if *optional {
                     out!(self, "?");
@@ -1403,7 +1605,7 @@ impl GenJS<'_, '_> {
                         let mut buf = [0u8; 4];
                         for char in ctx.str(*raw).chars() {
                             if char == '\n' {
-                                self.force_newline_without_indent();
+                                self.literal_newline();
                                 continue;
                             }
                             self.write_char(char, &mut buf);
@@ -1533,6 +1735,7 @@ impl GenJS<'_, '_> {
                 right,
                 operator,
             }) => {
+                let outermost = self.begin_binary_group(path);
                 self.print_child(
                     ctx,
                     Some(*left),
@@ -1541,13 +1744,16 @@ impl GenJS<'_, '_> {
                 );
                 self.space(ForceSpace::No);
                 out!(self, "{}", operator.as_str());
-                self.space(ForceSpace::No);
+                self.line_or_space(ForceSpace::No);
                 self.print_child(
                     ctx,
                     Some(*right),
                     Path::new(node, NodeField::right),
                     ChildPos::Right,
                 );
+                if outermost {
+                    self.end_binary_group();
+                }
             }
 
             Node::ClassExpression(ClassExpression {
@@ -1622,13 +1828,20 @@ impl GenJS<'_, '_> {
                     out!(self, "{{");
                     self.inc_indent();
                     self.newline();
+                    let mut prev: Option<&Node> = None;
                     for prop in body.iter() {
+                        if let Some(prev) = prev {
+                            self.newline();
+                            if self.blank_line_between(prev, prop) {
+                                self.newline();
+                            }
+                        }
                         prop.visit(ctx, self, Some(Path::new(node, NodeField::body)));
-                        self.newline();
+                        prev = Some(prop);
                     }
-                    out!(self, "}}");
                     self.dec_indent();
                     self.newline();
+                    out!(self, "}}");
                 }
             }
             Node::ClassProperty(ClassProperty {
@@ -1804,22 +2017,26 @@ impl GenJS<'_, '_> {
                 if *import_kind != ImportKind::Value {
                     out!(self, "{} ", import_kind.as_str());
                 }
-                let mut has_named_specs = false;
-                for (i, spec) in specifiers.iter().enumerate() {
+                // Default and namespace specifiers precede the named ones.
+                let (named, unnamed): (Vec<&Node>, Vec<&Node>) = specifiers
+                    .iter()
+                    .partition(|spec| matches!(spec, Node::ImportSpecifier(_)));
+                for (i, spec) in unnamed.iter().enumerate() {
                     if i > 0 {
                         self.comma();
                     }
-                    if let Node::ImportSpecifier(_) = spec {
-                        if !has_named_specs {
-                            has_named_specs = true;
-                            out!(self, "{{");
-                        }
-                    }
                     spec.visit(ctx, self, Some(Path::new(node, NodeField::specifiers)));
                 }
+                if !named.is_empty() {
+                    if !unnamed.is_empty() {
+                        self.comma();
+                    }
+                    self.print_list(ListKind::Braces, &named, false, false, |gen_js, spec| {
+                        spec.visit(ctx, gen_js, Some(Path::new(node, NodeField::specifiers)))
+                    });
+                }
                 if !specifiers.is_empty() {
-                    if has_named_specs {
-                        out!(self, "}}");
+                    if !named.is_empty() {
                         self.space(ForceSpace::No);
                     } else {
                         out!(self, " ");
@@ -1854,8 +2071,10 @@ impl GenJS<'_, '_> {
                     out!(self, "{} ", import_kind.as_str());
                 }
                 imported.visit(ctx, self, Some(Path::new(node, NodeField::imported)));
-                out!(self, " as ");
-                local.visit(ctx, self, Some(Path::new(node, NodeField::local)));
+                if self.layout.is_none() || !same_identifier(imported, local) {
+                    out!(self, " as ");
+                    local.visit(ctx, self, Some(Path::new(node, NodeField::local)));
+                }
             }
             Node::ImportDefaultSpecifier(ImportDefaultSpecifier { metadata: _, local }) => {
                 local.visit(ctx, self, Some(Path::new(node, NodeField::local)));
@@ -1889,14 +2108,16 @@ impl GenJS<'_, '_> {
                     if *export_kind != ExportKind::Value {
                         out!(self, "{} ", export_kind.as_str());
                     }
-                    out!(self, "{{");
-                    for (i, spec) in specifiers.iter().enumerate() {
-                        if i > 0 {
-                            self.comma();
-                        }
-                        spec.visit(ctx, self, Some(Path::new(node, NodeField::specifiers)));
-                    }
-                    out!(self, "}}");
+                    let specifiers: Vec<&Node> = specifiers.iter().collect();
+                    self.print_list(
+                        ListKind::Braces,
+                        &specifiers,
+                        false,
+                        false,
+                        |gen_js, spec| {
+                            spec.visit(ctx, gen_js, Some(Path::new(node, NodeField::specifiers)))
+                        },
+                    );
                     if let Some(source) = source {
                         out!(self, " from ");
                         source.visit(ctx, self, Some(Path::new(node, NodeField::source)));
@@ -1909,8 +2130,10 @@ impl GenJS<'_, '_> {
                 local,
             }) => {
                 local.visit(ctx, self, Some(Path::new(node, NodeField::local)));
-                out!(self, " as ");
-                exported.visit(ctx, self, Some(Path::new(node, NodeField::exported)));
+                if self.layout.is_none() || !same_identifier(local, exported) {
+                    out!(self, " as ");
+                    exported.visit(ctx, self, Some(Path::new(node, NodeField::exported)));
+                }
             }
             Node::ExportNamespaceSpecifier(ExportNamespaceSpecifier {
                 metadata: _,
@@ -1960,14 +2183,16 @@ impl GenJS<'_, '_> {
                 elements,
                 type_annotation,
             }) => {
-                out!(self, "[");
-                for (i, elem) in elements.iter().enumerate() {
-                    if i > 0 {
-                        self.comma();
-                    }
-                    elem.visit(ctx, self, Some(Path::new(node, NodeField::elements)));
-                }
-                out!(self, "]");
+                let elements: Vec<&Node> = elements.iter().collect();
+                self.print_list(
+                    ListKind::Elements,
+                    &elements,
+                    false,
+                    false,
+                    |gen_js, elem| {
+                        elem.visit(ctx, gen_js, Some(Path::new(node, NodeField::elements)))
+                    },
+                );
                 if let Some(type_annotation) = type_annotation {
                     out!(self, ":");
                     self.space(ForceSpace::No);
@@ -2093,7 +2318,7 @@ impl GenJS<'_, '_> {
                 let mut buf = [0u8; 4];
                 for char in ctx.str(*raw).chars() {
                     if char == '\n' {
-                        self.force_newline_without_indent();
+                        self.literal_newline();
                         continue;
                     }
                     self.write_char(char, &mut buf);
@@ -2107,7 +2332,7 @@ impl GenJS<'_, '_> {
                 let mut buf = [0u8; 4];
                 for char in ctx.str(*raw).chars() {
                     if char == '\n' {
-                        self.force_newline_without_indent();
+                        self.literal_newline();
                         continue;
                     }
                     self.write_char(char, &mut buf);
@@ -3228,7 +3453,9 @@ impl GenJS<'_, '_> {
     }
 
     /// Print a newline and indent.
+    /// Pending trailing comments are emitted before the newline.
     fn force_newline(&mut self) {
+        self.flush_comments();
         self.force_newline_without_indent();
         out!(self, "{:indent$}", "", indent = self.indent as usize);
     }
@@ -3236,7 +3463,9 @@ impl GenJS<'_, '_> {
     /// Print a newline without any indent after.
     fn force_newline_without_indent(&mut self) {
         if self.error.is_none() {
-            if let Err(e) = self.out.write(&[b'\n']) {
+            if let Some(layout) = &mut self.layout {
+                layout.doc.hard_line();
+            } else if let Err(e) = self.out.write(&[b'\n']) {
                 self.error = Some(e);
             }
         }
@@ -3244,6 +3473,488 @@ impl GenJS<'_, '_> {
         self.position.col = 1;
     }
 
+    /// Print a newline which is part of literal text, such as a template literal,
+    /// so it must be printed as is in layout mode.
+    fn literal_newline(&mut self) {
+        if let (None, Some(layout)) = (&self.error, &mut self.layout) {
+            layout.doc.literal_line();
+            self.position.line += 1;
+            self.position.col = 1;
+        } else {
+            self.force_newline_without_indent();
+        }
+    }
+
+    /// Print a newline after a line comment, with indent if pretty.
+    fn comment_newline(&mut self) {
+        match self.opt.pretty {
+            Pretty::Yes => self.force_newline(),
+            Pretty::No => self.force_newline_without_indent(),
+        }
+    }
+
+    /// Print a space as with [`GenJS::space`], which may be broken into a newline
+    /// in layout mode.
+    fn line_or_space(&mut self, force: ForceSpace) {
+        if self.layout.is_some() {
+            self.line(" ");
+        } else {
+            self.space(force);
+        }
+    }
+
+    /// In layout mode, add a line break which is printed as `flat` unless the enclosing
+    /// group is broken. Otherwise print `flat`.
+    fn line(&mut self, flat: &'static str) {
+        match &mut self.layout {
+            Some(layout) => layout.doc.line(flat, self.indent),
+            None => out!(self, "{}", flat),
+        }
+    }
+
+    /// In layout mode, add `text` which is only printed if the enclosing group is broken.
+    fn if_break(&mut self, text: &'static str) {
+        if let Some(layout) = &mut self.layout {
+            layout.doc.if_break(text);
+        }
+    }
+
+    /// In layout mode, begin a group of line breaks which are either all taken or none.
+    fn begin_group(&mut self) {
+        if let Some(layout) = &mut self.layout {
+            layout.doc.begin_group();
+        }
+    }
+
+    /// In layout mode, end the group begun by the matching [`GenJS::begin_group`],
+    /// forcing its lines to break if `force_break` is true.
+    fn end_group(&mut self, force_break: bool) {
+        if let Some(layout) = &mut self.layout {
+            layout.doc.end_group(force_break);
+        }
+    }
+
+    /// In layout mode, begin the group of a chain of binary or logical expressions
+    /// if the expression at `path` is the outermost one.
+    /// Return true if the group must be ended with [`GenJS::end_binary_group`].
+    fn begin_binary_group(&mut self, path: Option<Path>) -> bool {
+        if self.layout.is_none()
+            || matches!(
+                path,
+                Some(Path {
+                    parent: Node::BinaryExpression(_) | Node::LogicalExpression(_),
+                    ..
+                })
+            )
+        {
+            return false;
+        }
+        self.begin_group();
+        self.inc_indent();
+        true
+    }
+
+    fn end_binary_group(&mut self) {
+        self.dec_indent();
+        self.end_group(false);
+    }
+
+    /// Whether statements are terminated with semicolons.
+    fn semicolons(&self) -> bool {
+        match &self.layout {
+            Some(layout) => layout.opt.semicolons,
+            None => true,
+        }
+    }
+
+    /// Whether to keep a blank line between the consecutive statements or members
+    /// `prev` and `next`, which is done in layout mode if there was one in the source.
+    fn blank_line_between(&self, prev: &Node, next: &Node) -> bool {
+        if self.layout.is_none() {
+            return false;
+        }
+        let prev = prev.range();
+        let mut next_line = next.range().start.line;
+        if let Some(comment) = self.node_comments(next).and_then(|c| c.leading.first()) {
+            next_line = next_line.min(comment.range.start.line);
+        }
+        prev.file == next.range().file && prev.end.line != 0 && next_line > prev.end.line + 1
+    }
+
+    /// Print the comma separated `items` of a list of `kind` with `print`.
+    /// In layout mode, the items are put on their own lines if they don't fit on one,
+    /// which is forced if `force_break` is true.
+    /// If `trailing_comma` is true, a trailing comma is always printed, e.g. after a hole
+    /// at the end of an array.
+    fn print_list<'gc, F>(
+        &mut self,
+        kind: ListKind,
+        items: &[&'gc Node<'gc>],
+        force_break: bool,
+        trailing_comma: bool,
+        mut print: F,
+    ) where
+        F: FnMut(&mut Self, &'gc Node<'gc>),
+    {
+        let (open, close) = match kind {
+            ListKind::Args => ("(", ")"),
+            ListKind::Elements => ("[", "]"),
+            ListKind::Braces => ("{", "}"),
+        };
+        out!(self, "{}", open);
+        let layout = match &self.layout {
+            Some(layout) if !items.is_empty() => layout.opt,
+            _ => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.comma();
+                    }
+                    print(self, item);
+                }
+                if trailing_comma {
+                    self.comma();
+                }
+                out!(self, "{}", close);
+                return;
+            }
+        };
+
+        let spacing = if kind == ListKind::Braces && layout.bracket_spacing {
+            " "
+        } else {
+            ""
+        };
+        // Nothing may follow a rest element, not even a comma.
+        let allow_trailing = !matches!(items.last(), Some(Node::RestElement(_)))
+            && match kind {
+                ListKind::Args => layout.trailing_commas == TrailingCommas::All,
+                _ => layout.trailing_commas != TrailingCommas::None,
+            };
+        self.begin_group();
+        self.inc_indent();
+        self.line(spacing);
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                out!(self, ",");
+                self.line(" ");
+            }
+            print(self, item);
+        }
+        if trailing_comma {
+            out!(self, ",");
+        } else if allow_trailing {
+            self.if_break(",");
+        }
+        self.dec_indent();
+        self.line(spacing);
+        self.end_group(force_break);
+        out!(self, "{}", close);
+    }
+
+    /// Print the `arguments` of the call or `new` expression `node`.
+    /// In layout mode, a function or literal which is the last argument is hugged
+    /// by the parentheses and breaks on its own, as in Prettier.
+    fn print_args<'gc>(
+        &mut self,
+        ctx: &'gc GCLock,
+        arguments: &'gc NodeList<'gc>,
+        node: &'gc Node<'gc>,
+    ) {
+        let args: Vec<&Node> = arguments.iter().collect();
+        let huggable = |arg: &Node| match arg {
+            Node::FunctionExpression(_) | Node::ArrowFunctionExpression(_) => true,
+            Node::ObjectExpression(ObjectExpression { properties, .. }) => !properties.is_empty(),
+            Node::ArrayExpression(ArrayExpression { elements, .. }) => !elements.is_empty(),
+            _ => false,
+        };
+        let hug = self.layout.is_some()
+            && matches!(args.last(), Some(last) if huggable(last))
+            && args.iter().filter(|arg| huggable(arg)).count() == 1;
+        let print = |gen_js: &mut Self, arg| {
+            gen_js.print_child(
+                ctx,
+                Some(arg),
+                Path::new(node, NodeField::arguments),
+                ChildPos::Anywhere,
+            )
+        };
+        if hug {
+            out!(self, "(");
+            for (i, arg) in args.into_iter().enumerate() {
+                if i > 0 {
+                    self.comma();
+                }
+                print(self, arg);
+            }
+            out!(self, ")");
+        } else {
+            self.print_list(ListKind::Args, &args, false, false, print);
+        }
+    }
+
+    /// Whether the expression statement `stmt` starts with a token which would continue
+    /// the previous statement if it wasn't terminated with a semicolon.
+    fn starts_with_asi_hazard<'gc>(&self, ctx: &'gc GCLock, stmt: &'gc Node<'gc>) -> bool {
+        match stmt {
+            Node::ExpressionStatement(ExpressionStatement { expression, .. }) => self
+                .expr_starts_with_asi_hazard(
+                    ctx,
+                    expression,
+                    Path::new(stmt, NodeField::expression),
+                    ChildPos::Anywhere,
+                ),
+            _ => false,
+        }
+    }
+
+    /// Whether `expr`, at `child_pos` in `path`, starts with `(`, `[`, `` ` ``, `+`, `-`,
+    /// `/` or `<`.
+    fn expr_starts_with_asi_hazard<'gc>(
+        &self,
+        ctx: &'gc GCLock,
+        expr: &'gc Node<'gc>,
+        path: Path<'gc>,
+        child_pos: ChildPos,
+    ) -> bool {
+        if self.need_parens(ctx, path, expr, child_pos) == NeedParens::Yes {
+            return true;
+        }
+        let (child, field) = match expr {
+            Node::ArrayExpression(_)
+            | Node::ArrayPattern(_)
+            | Node::TemplateLiteral(_)
+            | Node::RegExpLiteral(_)
+            | Node::JSXElement(_)
+            | Node::JSXFragment(_) => return true,
+            // Parameters are always parenthesized in layout mode.
+            Node::ArrowFunctionExpression(ArrowFunctionExpression { is_async, .. }) => {
+                return !*is_async;
+            }
+            Node::UnaryExpression(UnaryExpression { operator, .. }) => {
+                return matches!(
+                    operator,
+                    UnaryExpressionOperator::Plus | UnaryExpressionOperator::Minus
+                );
+            }
+            Node::UpdateExpression(UpdateExpression {
+                prefix, argument, ..
+            }) => {
+                if *prefix {
+                    return true;
+                }
+                (*argument, NodeField::argument)
+            }
+            Node::CallExpression(CallExpression { callee, .. })
+            | Node::OptionalCallExpression(OptionalCallExpression { callee, .. }) => {
+                (*callee, NodeField::callee)
+            }
+            Node::MemberExpression(MemberExpression { object, .. })
+            | Node::OptionalMemberExpression(OptionalMemberExpression { object, .. }) => {
+                (*object, NodeField::object)
+            }
+            Node::TaggedTemplateExpression(TaggedTemplateExpression { tag, .. }) => {
+                (*tag, NodeField::tag)
+            }
+            Node::BinaryExpression(BinaryExpression { left, .. })
+            | Node::LogicalExpression(LogicalExpression { left, .. })
+            | Node::AssignmentExpression(AssignmentExpression { left, .. }) => {
+                (*left, NodeField::left)
+            }
+            Node::ConditionalExpression(ConditionalExpression { test, .. }) => {
+                (*test, NodeField::test)
+            }
+            Node::SequenceExpression(SequenceExpression { expressions, .. }) => {
+                match expressions.head() {
+                    Some(first) => (first, NodeField::expressions),
+                    None => return false,
+                }
+            }
+            _ => return false,
+        };
+        self.expr_starts_with_asi_hazard(ctx, child, Path::new(expr, field), ChildPos::Left)
+    }
+
+    /// Return the comments attached to `node`, if comments are enabled.
+    fn node_comments(&self, node: &Node) -> Option<&'s comments::NodeComments> {
+        self.opt.comments.and_then(|map| map.get(node))
+    }
+
+    /// Whether `comment` should be emitted.
+    fn keep_comment(&self, comment: &Comment) -> bool {
+        match self.opt.keep_comments {
+            KeepComments::All => true,
+            KeepComments::Legal => comment.is_legal(),
+        }
+    }
+
+    /// Return the inner comments of `node` which should be emitted.
+    fn inner_comments(&self, node: &Node) -> Vec<&'s Comment> {
+        match self.node_comments(node) {
+            Some(comments) => comments
+                .inner
+                .iter()
+                .filter(|c| self.keep_comment(c))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Emit `comment` along with its delimiters.
+    fn emit_comment(&mut self, comment: &Comment) {
+        match comment.kind {
+            CommentKind::Line => {
+                out!(self, "//");
+                self.write_utf8(&comment.text);
+            }
+            CommentKind::Hashbang => {
+                out!(self, "#!");
+                self.write_utf8(&comment.text);
+            }
+            CommentKind::Block => {
+                out!(self, "/*");
+                for (i, line) in comment.text.split('\n').enumerate() {
+                    if i > 0 {
+                        self.force_newline_without_indent();
+                    }
+                    self.write_utf8(line);
+                }
+                out!(self, "*/");
+            }
+        }
+    }
+
+    /// Emit the comments preceding `node`.
+    /// Inner comments of nodes which have no place for them are emitted here as well.
+    fn gen_leading_comments(&mut self, node: &Node, path: Option<Path>) {
+        // Line comments can only be emitted as such where a newline can follow them.
+        let line_start = match path {
+            Some(path) => starts_line(path),
+            None => true,
+        };
+        if let Some(comments) = self.node_comments(node) {
+            self.emit_comments_before(node, &comments.leading, line_start);
+            if !comments::holds_inner_comments(node) {
+                self.emit_comments_before(node, &comments.inner, line_start);
+            }
+        }
+    }
+
+    /// Emit `comments` before `node`, which starts a line if `line_start` is true.
+    /// Comments which need a newline are only emitted at the start of a line,
+    /// elsewhere they have been moved by [`GenJS::hoist_comments`] or are dropped.
+    fn emit_comments_before(&mut self, node: &Node, comments: &'s [Comment], line_start: bool) {
+        for comment in comments {
+            if !self.keep_comment(comment)
+                || (!line_start && needs_newline(comment))
+                || self
+                    .hoisted_comments
+                    .iter()
+                    .any(|c| std::ptr::eq(*c, comment))
+            {
+                continue;
+            }
+            if line_start && self.flush_comments() {
+                self.comment_newline();
+            }
+            match comment.kind {
+                CommentKind::Line | CommentKind::Hashbang if line_start => {
+                    self.emit_comment(comment);
+                    self.comment_newline();
+                }
+                CommentKind::Line | CommentKind::Hashbang => {
+                    out!(self, "/*");
+                    self.write_utf8(&comment.text);
+                    out!(self, "*/");
+                    self.space(ForceSpace::No);
+                }
+                CommentKind::Block => {
+                    self.emit_comment(comment);
+                    if line_start
+                        && self.opt.pretty == Pretty::Yes
+                        && comment.range.end.line < node.range().start.line
+                    {
+                        self.force_newline();
+                    } else {
+                        self.space(ForceSpace::No);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Emit the comments within `node`, which starts a line, that would need a newline
+    /// where they are, before `node`.
+    /// A newline in the middle of a statement may insert a semicolon, e.g. after
+    /// `return`, so these comments are moved before the statement instead.
+    fn hoist_comments<'gc>(&mut self, ctx: &'gc GCLock, node: &'gc Node<'gc>) {
+        let comments = match self.opt.comments {
+            Some(comments) => comments,
+            None => return,
+        };
+        let mut finder = MultilineCommentFinder {
+            comments,
+            found: vec![],
+        };
+        node.visit_children(ctx, &mut finder);
+        for comment in finder.found {
+            if !self.keep_comment(comment) {
+                continue;
+            }
+            if self.flush_comments() {
+                self.comment_newline();
+            }
+            self.emit_comment(comment);
+            self.comment_newline();
+            self.hoisted_comments.push(comment);
+        }
+    }
+
+    /// Queue the comments following `node` to be emitted before the next newline.
+    fn gen_trailing_comments(&mut self, node: &Node) {
+        if let Some(comments) = self.node_comments(node) {
+            for comment in comments.trailing.iter() {
+                if self.keep_comment(comment) {
+                    self.pending_comments.push(comment);
+                }
+            }
+        }
+    }
+
+    /// Emit the inner comments of `node` on their own lines,
+    /// preceded by a newline if `separate` is true.
+    /// Return true if the last one is a line comment, so a newline must follow.
+    fn gen_inner_comments(&mut self, node: &Node, separate: bool) -> bool {
+        let mut need_newline = false;
+        for (i, comment) in self.inner_comments(node).into_iter().enumerate() {
+            if separate || i > 0 {
+                self.newline();
+            }
+            if need_newline && self.opt.pretty == Pretty::No {
+                self.force_newline_without_indent();
+            }
+            self.emit_comment(comment);
+            need_newline = comment.kind != CommentKind::Block;
+        }
+        need_newline
+    }
+
+    /// Emit the pending trailing comments.
+    /// Return true if the last one is a line comment, so a newline must follow.
+    fn flush_comments(&mut self) -> bool {
+        let pending = std::mem::take(&mut self.pending_comments);
+        let mut need_newline = false;
+        for comment in pending {
+            if need_newline {
+                self.comment_newline();
+            } else {
+                self.space(ForceSpace::No);
+            }
+            self.emit_comment(comment);
+            need_newline = comment.kind != CommentKind::Block;
+        }
+        need_newline
+    }
+
     /// Print the child of a `path` node at the position `child_pos`.
     fn print_child<'gc>(
         &mut self,
@@ -3350,14 +4061,21 @@ impl GenJS<'_, '_> {
     }
 
     fn visit_props<'gc>(&mut self, ctx: &'gc GCLock, props: &'gc NodeList<'gc>, path: Path<'gc>) {
-        out!(self, "{{");
-        for (i, prop) in props.iter().enumerate() {
-            if i > 0 {
-                self.comma();
-            }
-            prop.visit(ctx, self, Some(path));
-        }
-        out!(self, "}}");
+        // Like Prettier, keep objects expanded if the first property was on a new line.
+        let object = path.parent.range();
+        let force_break = matches!(
+            props.head(),
+            Some(first) if first.range().file == object.file
+                && first.range().start.line > object.start.line
+        );
+        let props: Vec<&Node> = props.iter().collect();
+        self.print_list(
+            ListKind::Braces,
+            &props,
+            force_break,
+            false,
+            |gen_js, prop| prop.visit(ctx, gen_js, Some(path)),
+        );
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -3374,14 +4092,10 @@ impl GenJS<'_, '_> {
         if let Some(type_parameters) = type_parameters {
             type_parameters.visit(ctx, self, Some(Path::new(node, NodeField::type_parameters)));
         }
-        out!(self, "(");
-        for (i, param) in params.iter().enumerate() {
-            if i > 0 {
-                self.comma();
-            }
-            param.visit(ctx, self, Some(Path::new(node, NodeField::param)));
-        }
-        out!(self, ")");
+        let params: Vec<&Node> = params.iter().collect();
+        self.print_list(ListKind::Args, &params, false, false, |gen_js, param| {
+            param.visit(ctx, gen_js, Some(Path::new(node, NodeField::param)))
+        });
         if return_type.is_some() || predicate.is_some() {
             out!(self, ":");
         }
@@ -3528,20 +4242,9 @@ impl GenJS<'_, '_> {
         force_block: ForceBlock,
         path: Path<'gc>,
     ) -> bool {
-        if let Node::BlockStatement(BlockStatement { metadata: _, body }) = &node {
-            if body.is_empty() {
-                self.space(ForceSpace::No);
-                out!(self, "{{}}");
-                return true;
-            }
+        if let Node::BlockStatement(_) = &node {
             self.space(ForceSpace::No);
-            out!(self, "{{");
-            self.inc_indent();
-            self.newline();
-            self.visit_stmt_list(ctx, body, Path::new(node, NodeField::body));
-            self.dec_indent();
-            self.newline();
-            out!(self, "}}");
+            node.visit(ctx, self, Some(path));
             return true;
         }
         if force_block == ForceBlock::Yes {
@@ -3565,11 +4268,16 @@ impl GenJS<'_, '_> {
     }
 
     fn visit_stmt_list<'gc>(&mut self, ctx: &'gc GCLock, list: &NodeList<'gc>, path: Path<'gc>) {
-        for (i, stmt) in list.iter().enumerate() {
-            if i > 0 {
+        let mut prev: Option<&Node> = None;
+        for stmt in list.iter() {
+            if let Some(prev) = prev {
                 self.newline();
+                if self.blank_line_between(prev, stmt) {
+                    self.newline();
+                }
             }
             self.visit_stmt_in_block(ctx, stmt, path);
+            prev = Some(stmt);
         }
     }
 
@@ -3579,104 +4287,22 @@ impl GenJS<'_, '_> {
         stmt: &'gc Node<'gc>,
         path: Path<'gc>,
     ) {
+        if !self.semicolons() && self.starts_with_asi_hazard(ctx, stmt) {
+            out!(self, ";");
+        }
         stmt.visit(ctx, self, Some(path));
-        if !stmt_skip_semi(ctx, Some(stmt)) {
+        if !stmt_skip_semi(ctx, Some(stmt)) && self.semicolons() {
             out!(self, ";");
         }
+        // There are no newlines to emit trailing comments before in compact mode.
+        if self.opt.pretty == Pretty::No && self.flush_comments() {
+            self.force_newline_without_indent();
+        }
     }
 
     /// Return the precedence and associativity of `node`.
     fn get_precedence(&self, node: &Node<'_>) -> (precedence::Precedence, Assoc) {
-        // Precedence order taken from
-        // https://github.com/facebook/flow/blob/master/src/parser_utils/output/js_layout_generator.ml
-        use precedence::*;
-        match &node {
-            Node::Identifier(_)
-            | Node::NullLiteral(_)
-            | Node::BooleanLiteral(_)
-            | Node::StringLiteral(_)
-            | Node::NumericLiteral(_)
-            | Node::RegExpLiteral(_)
-            | Node::ThisExpression(_)
-            | Node::Super(_)
-            | Node::ArrayExpression(_)
-            | Node::ObjectExpression(_)
-            | Node::ObjectPattern(_)
-            | Node::FunctionExpression(_)
-            | Node::ClassExpression(_)
-            | Node::TemplateLiteral(_)
-            | Node::JSXElement(_)
-            | Node::JSXFragment(_)
-            | Node::TypeCastExpression(_) => (PRIMARY, Assoc::Ltr),
-            Node::MemberExpression(_)
-            | Node::OptionalMemberExpression(_)
-            | Node::MetaProperty(_)
-            | Node::CallExpression(_)
-            | Node::OptionalCallExpression(_) => (MEMBER, Assoc::Ltr),
-            Node::NewExpression(NewExpression {
-                metadata: _,
-                arguments,
-                ..
-            }) => {
-                // `new foo()` has higher precedence than `new foo`. In pretty mode we
-                // always append the `()`, but otherwise we must check the number of args.
-                if self.opt.pretty == Pretty::Yes || !arguments.is_empty() {
-                    (MEMBER, Assoc::Ltr)
-                } else {
-                    (NEW_NO_ARGS, Assoc::Ltr)
-                }
-            }
-            Node::TaggedTemplateExpression(_) | Node::ImportExpression(_) => {
-                (TAGGED_TEMPLATE, Assoc::Ltr)
-            }
-            Node::UpdateExpression(UpdateExpression {
-                metadata: _,
-                prefix,
-                ..
-            }) => {
-                if *prefix {
-                    (POST_UPDATE, Assoc::Ltr)
-                } else {
-                    (UNARY, Assoc::Rtl)
-                }
-            }
-            Node::UnaryExpression(_) => (UNARY, Assoc::Rtl),
-            Node::BinaryExpression(BinaryExpression {
-                metadata: _,
-                operator,
-                ..
-            }) => (get_binary_precedence(*operator), Assoc::Ltr),
-            Node::LogicalExpression(LogicalExpression {
-                metadata: _,
-                operator,
-                ..
-            }) => (get_logical_precedence(*operator), Assoc::Ltr),
-            Node::ConditionalExpression(_) => (COND, Assoc::Rtl),
-            Node::AssignmentExpression(_) => (ASSIGN, Assoc::Rtl),
-            Node::ArrowFunctionExpression(_) => (ARROW, Assoc::Ltr),
-            Node::YieldExpression(_) => (YIELD, Assoc::Ltr),
-            Node::SequenceExpression(_) => (SEQ, Assoc::Rtl),
-
-            Node::ExistsTypeAnnotation(_)
-            | Node::EmptyTypeAnnotation(_)
-            | Node::StringTypeAnnotation(_)
-            | Node::BigIntTypeAnnotation(_)
-            | Node::NumberTypeAnnotation(_)
-            | Node::StringLiteralTypeAnnotation(_)
-            | Node::NumberLiteralTypeAnnotation(_)
-            | Node::BooleanTypeAnnotation(_)
-            | Node::BooleanLiteralTypeAnnotation(_)
-            | Node::NullLiteralTypeAnnotation(_)
-            | Node::SymbolTypeAnnotation(_)
-            | Node::AnyTypeAnnotation(_)
-            | Node::MixedTypeAnnotation(_)
-            | Node::VoidTypeAnnotation(_) => (PRIMARY, Assoc::Ltr),
-            Node::NullableTypeAnnotation(_) => (UNARY, Assoc::Ltr),
-            Node::UnionTypeAnnotation(_) => (UNION_TYPE, Assoc::Ltr),
-            Node::IntersectionTypeAnnotation(_) => (INTERSECTION_TYPE, Assoc::Ltr),
-
-            _ => (ALWAYS_PAREN, Assoc::Ltr),
-        }
+        get_precedence(node, self.opt.pretty)
     }
 
     /// Return whether pathheses are needed around the `child` node,
@@ -3924,19 +4550,70 @@ impl GenJS<'_, '_> {
 
     /// Adds the current location as a segment pointing to the start of `node`.
     fn add_segment(&mut self, node: &Node) {
+        self.add_named_segment(node, None);
+    }
+
+    /// Adds the current location as a segment pointing to the start of `node`,
+    /// which refers to `name` if it isn't `None`.
+    /// Nodes without a valid source range are skipped.
+    /// A segment replaces the previous one if no output was written in between,
+    /// so the innermost node starting at a location is the one it maps to.
+    fn add_named_segment(&mut self, node: &Node, name: Option<&str>) {
+        let range = node.range();
+        if !range.file.is_valid() || range.start.line == 0 {
+            return;
+        }
         // Convert from 1-indexed to 0-indexed as expected by source map.
-        // Use `wrapping_sub` in case the line/col are invalid (0) to ensure
-        // the overflow goes to `u32::MAX`.
-        let new_token = Some(RawToken {
-            dst_line: self.position.line.wrapping_sub(1),
-            dst_col: self.position.col.wrapping_sub(1),
-            src_line: node.range().start.line.wrapping_sub(1),
-            src_col: node.range().start.col.wrapping_sub(1),
-            src_id: 0,
-            name_id: !0,
-        });
-        self.flush_cur_token();
-        self.cur_token = new_token;
+        let new_token = RawToken {
+            dst_line: self.position.line - 1,
+            dst_col: self.position.col - 1,
+            src_line: range.start.line - 1,
+            src_col: range.start.col - 1,
+            src_id: range.file.0,
+            name_id: match name {
+                Some(name) => self.sourcemap.add_name(name),
+                None => !0,
+            },
+        };
+        if let Some(layout) = &mut self.layout {
+            // The output position is filled in by `finish_layout`.
+            if layout.doc.ends_with_mark() {
+                *layout.tokens.last_mut().unwrap() = new_token;
+            } else {
+                layout.doc.mark();
+                layout.tokens.push(new_token);
+            }
+            return;
+        }
+        if !matches!(self.cur_token, Some(cur)
+            if (cur.dst_line, cur.dst_col) == (new_token.dst_line, new_token.dst_col))
+        {
+            self.flush_cur_token();
+        }
+        self.cur_token = Some(new_token);
+    }
+
+    /// In layout mode, lay out the output and write it, along with the source map
+    /// tokens for its marks.
+    fn finish_layout(&mut self) {
+        let LayoutState { opt, doc, tokens } = match self.layout.take() {
+            Some(layout) => layout,
+            None => return,
+        };
+        let (text, marks) = layout::print(&doc.finish(), opt.print_width);
+        for (token, (line, col)) in tokens.iter().zip(marks) {
+            self.cur_token = Some(RawToken {
+                dst_line: line,
+                dst_col: col,
+                ..*token
+            });
+            self.flush_cur_token();
+        }
+        if self.error.is_none() {
+            if let Err(e) = self.out.write_all(text.as_bytes()) {
+                self.error = Some(e);
+            }
+        }
     }
 
     /// Add the `cur_token` to the sourcemap and set `cur_token` to `None`.
@@ -3998,10 +4675,83 @@ impl GenJS<'_, '_> {
 
 impl<'gc> Visitor<'gc> for GenJS<'_, '_> {
     fn call(&mut self, ctx: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        let line_start = match path {
+            Some(path) => starts_line(path),
+            None => true,
+        };
+        if line_start {
+            self.hoist_comments(ctx, node);
+        }
+        self.gen_leading_comments(node, path);
+        self.add_segment(node);
         self.gen_node(ctx, node, path);
+        self.gen_trailing_comments(node);
+    }
+}
+
+/// Find the comments preceding nodes which don't start a line, which can't be emitted
+/// in place without a newline.
+/// Nodes starting a line are skipped, their comments are handled when emitting them.
+struct MultilineCommentFinder<'s> {
+    comments: &'s CommentMap,
+    found: Vec<&'s Comment>,
+}
+
+impl<'gc> Visitor<'gc> for MultilineCommentFinder<'_> {
+    fn call(&mut self, ctx: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        if matches!(path, Some(path) if starts_line(path)) {
+            return;
+        }
+        if let Some(comments) = self.comments.get(node) {
+            let inner: &[Comment] = if comments::holds_inner_comments(node) {
+                &[]
+            } else {
+                &comments.inner
+            };
+            self.found.extend(
+                comments
+                    .leading
+                    .iter()
+                    .chain(inner)
+                    .filter(|c| needs_newline(c)),
+            );
+        }
+        node.visit_children(ctx, self);
+    }
+}
+
+/// Whether `comment` can't be emitted without a newline: a line comment which can't be
+/// turned into a block comment, or a block comment spanning multiple lines.
+fn needs_newline(comment: &Comment) -> bool {
+    match comment.kind {
+        CommentKind::Line | CommentKind::Hashbang => comment.text.contains("*/"),
+        CommentKind::Block => comment.text.contains('\n'),
     }
 }
 
+/// Whether `a` and `b` are identifiers with the same name.
+fn same_identifier(a: &Node, b: &Node) -> bool {
+    match (a, b) {
+        (
+            Node::Identifier(Identifier { name: a, .. }),
+            Node::Identifier(Identifier { name: b, .. }),
+        ) => a == b,
+        _ => false,
+    }
+}
+
+/// Whether a node in `path` starts a line, so it may be preceded by a newline.
+fn starts_line(path: Path) -> bool {
+    matches!(
+        (path.parent, path.field),
+        (Node::Program(_), _)
+            | (Node::Module(_), _)
+            | (Node::BlockStatement(_), NodeField::body)
+            | (Node::ClassBody(_), NodeField::body)
+            | (Node::SwitchCase(_), NodeField::consequent)
+    )
+}
+
 fn is_unary_op(node: &Node, op: UnaryExpressionOperator) -> bool {
     match node {
         Node::UnaryExpression(UnaryExpression {
@@ -4089,6 +4839,101 @@ fn check_nullish(node: &Node) -> bool {
     )
 }
 
+/// Return the precedence and associativity of `node`.
+/// `pretty` affects whether `new` expressions are printed with an argument list.
+fn get_precedence(node: &Node<'_>, pretty: Pretty) -> (precedence::Precedence, Assoc) {
+    // Precedence order taken from
+    // https://github.com/facebook/flow/blob/master/src/parser_utils/output/js_layout_generator.ml
+    use precedence::*;
+    match &node {
+        Node::Identifier(_)
+        | Node::NullLiteral(_)
+        | Node::BooleanLiteral(_)
+        | Node::StringLiteral(_)
+        | Node::NumericLiteral(_)
+        | Node::RegExpLiteral(_)
+        | Node::ThisExpression(_)
+        | Node::Super(_)
+        | Node::ArrayExpression(_)
+        | Node::ObjectExpression(_)
+        | Node::ObjectPattern(_)
+        | Node::FunctionExpression(_)
+        | Node::ClassExpression(_)
+        | Node::TemplateLiteral(_)
+        | Node::JSXElement(_)
+        | Node::JSXFragment(_)
+        | Node::TypeCastExpression(_) => (PRIMARY, Assoc::Ltr),
+        Node::MemberExpression(_)
+        | Node::OptionalMemberExpression(_)
+        | Node::MetaProperty(_)
+        | Node::CallExpression(_)
+        | Node::OptionalCallExpression(_) => (MEMBER, Assoc::Ltr),
+        Node::NewExpression(NewExpression {
+            metadata: _,
+            arguments,
+            ..
+        }) => {
+            // `new foo()` has higher precedence than `new foo`. In pretty mode we
+            // always append the `()`, but otherwise we must check the number of args.
+            if pretty == Pretty::Yes || !arguments.is_empty() {
+                (MEMBER, Assoc::Ltr)
+            } else {
+                (NEW_NO_ARGS, Assoc::Ltr)
+            }
+        }
+        Node::TaggedTemplateExpression(_) | Node::ImportExpression(_) => {
+            (TAGGED_TEMPLATE, Assoc::Ltr)
+        }
+        Node::UpdateExpression(UpdateExpression {
+            metadata: _,
+            prefix,
+            ..
+        }) => {
+            if *prefix {
+                (POST_UPDATE, Assoc::Ltr)
+            } else {
+                (UNARY, Assoc::Rtl)
+            }
+        }
+        Node::UnaryExpression(_) => (UNARY, Assoc::Rtl),
+        Node::BinaryExpression(BinaryExpression {
+            metadata: _,
+            operator,
+            ..
+        }) => (get_binary_precedence(*operator), Assoc::Ltr),
+        Node::LogicalExpression(LogicalExpression {
+            metadata: _,
+            operator,
+            ..
+        }) => (get_logical_precedence(*operator), Assoc::Ltr),
+        Node::ConditionalExpression(_) => (COND, Assoc::Rtl),
+        Node::AssignmentExpression(_) => (ASSIGN, Assoc::Rtl),
+        Node::ArrowFunctionExpression(_) => (ARROW, Assoc::Ltr),
+        Node::YieldExpression(_) => (YIELD, Assoc::Ltr),
+        Node::SequenceExpression(_) => (SEQ, Assoc::Rtl),
+
+        Node::ExistsTypeAnnotation(_)
+        | Node::EmptyTypeAnnotation(_)
+        | Node::StringTypeAnnotation(_)
+        | Node::BigIntTypeAnnotation(_)
+        | Node::NumberTypeAnnotation(_)
+        | Node::StringLiteralTypeAnnotation(_)
+        | Node::NumberLiteralTypeAnnotation(_)
+        | Node::BooleanTypeAnnotation(_)
+        | Node::BooleanLiteralTypeAnnotation(_)
+        | Node::NullLiteralTypeAnnotation(_)
+        | Node::SymbolTypeAnnotation(_)
+        | Node::AnyTypeAnnotation(_)
+        | Node::MixedTypeAnnotation(_)
+        | Node::VoidTypeAnnotation(_) => (PRIMARY, Assoc::Ltr),
+        Node::NullableTypeAnnotation(_) => (UNARY, Assoc::Ltr),
+        Node::UnionTypeAnnotation(_) => (UNION_TYPE, Assoc::Ltr),
+        Node::IntersectionTypeAnnotation(_) => (INTERSECTION_TYPE, Assoc::Ltr),
+
+        _ => (ALWAYS_PAREN, Assoc::Ltr),
+    }
+}
+
 /// Whether to skip the semicolon at the end of `node`.
 /// Block statements don't need semicolons at the end, but other statements which contain
 /// statements don't need them either.
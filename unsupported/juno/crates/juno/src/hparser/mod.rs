@@ -12,17 +12,21 @@ use std::fmt::Formatter;
 
 use convert::Converter;
 use generated_cvt::cvt_node_ptr;
+use hermes::parser::CommentKind;
+use hermes::parser::DiagKind;
 use hermes::parser::HermesParser;
 pub use hermes::parser::MagicCommentKind;
 use hermes::parser::NodePtr;
 pub use hermes::parser::ParserDialect;
 pub use hermes::parser::ParserFlags;
 use hermes::utf::utf8_with_surrogates_to_string_lossy;
-use juno_support::source_manager::SourceId;
 use juno_support::NullTerminatedBuf;
+use juno_support::source_manager::SourceId;
+use juno_support::source_manager::SourceManager;
 use thiserror::Error;
 
 use crate::ast;
+use crate::comments;
 
 pub struct ParsedJS<'a> {
     parser: HermesParser<'a>,
@@ -53,6 +57,52 @@ impl<'parser> ParsedJS<'parser> {
         self.parser.magic_comment(kind)
     }
 
+    /// Return the comments in the source, in order. Comments are only recorded when
+    /// [`ParserFlags::store_comments`] is set.
+    pub fn comments(&self, file_id: SourceId) -> Vec<comments::Comment> {
+        let mut cvt = Converter::new(&self.parser, file_id);
+        self.parser
+            .comments()
+            .iter()
+            .map(|comment| comments::Comment {
+                kind: match comment.kind {
+                    CommentKind::Line => comments::CommentKind::Line,
+                    CommentKind::Block => comments::CommentKind::Block,
+                    CommentKind::Hashbang => comments::CommentKind::Hashbang,
+                },
+                text: utf8_with_surrogates_to_string_lossy(comment.get_string().as_slice()),
+                range: ast::SourceRange {
+                    file: file_id,
+                    start: cvt.cvt_smloc(comment.source_range.start),
+                    end: cvt.cvt_smloc(comment.source_range.end.pred()),
+                },
+            })
+            .collect()
+    }
+
+    /// Report every error, warning and note of the parser to `sm`, in order.
+    /// Remarks are reported as notes.
+    pub fn report_messages(&self, sm: &SourceManager, file_id: SourceId) {
+        let mut cvt = Converter::new(&self.parser, file_id);
+        for msg in self.parser.messages() {
+            let loc = if msg.loc.is_valid() {
+                cvt.cvt_smloc(msg.loc)
+            } else {
+                ast::SourceLoc {
+                    line: msg.coord.line,
+                    col: msg.coord.offset + 1,
+                }
+            };
+            let range = ast::SourceRange::from_loc(file_id, loc);
+            let text = utf8_with_surrogates_to_string_lossy(msg.message.as_slice());
+            match msg.diag_kind {
+                DiagKind::Error => sm.error(range, text),
+                DiagKind::Warning => sm.warning(range, text),
+                DiagKind::Remark | DiagKind::Note => sm.note(range, text),
+            }
+        }
+    }
+
     /// This function is a temporary hack returning the first error.
     /// It returns (line, column, error_message) of the first error.
     pub fn first_error(&self) -> Option<(ast::SourceLoc, String)> {
@@ -112,7 +162,8 @@ impl std::fmt::Display for ParseError {
 }
 
 /// This is a simple function that is intended to be used mostly for testing.
-/// It automatically imports the source string into the source manager.
+/// It automatically imports the source string into the source manager, and reports
+/// all the messages of the parser to it.
 /// When there are errors, it returns only the first error.
 pub fn parse_with_flags(
     flags: ParserFlags,
@@ -124,6 +175,7 @@ pub fn parse_with_flags(
         .add_source("<input>", NullTerminatedBuf::from_str_check(source));
     let buf = ctx.sm().source_buffer_rc(file_id);
     let parsed = ParsedJS::parse(flags, &buf);
+    parsed.report_messages(ctx.sm(), file_id);
     let gc = ast::GCLock::new(ctx);
     if let Some(ast) = parsed.to_ast(&gc, file_id) {
         Ok(ast::NodeRc::from_node(&gc, ast))
@@ -139,7 +191,8 @@ pub fn parse_with_flags(
 }
 
 /// This is a simple function that is intended to be used mostly for testing.
-/// It automatically imports the source string into the source manager.
+/// It automatically imports the source string into the source manager, and reports
+/// all the messages of the parser to it.
 /// When there are errors, it returns only the first error.
 pub fn parse(ctx: &mut ast::Context, source: &str) -> Result<ast::NodeRc, ParseError> {
     parse_with_flags(Default::default(), source, ctx)
@@ -147,6 +200,8 @@ pub fn parse(ctx: &mut ast::Context, source: &str) -> Result<ast::NodeRc, ParseE
 
 #[cfg(test)]
 mod tests {
+    use juno_support::diagnostics::CollectingHandler;
+
     use super::*;
 
     #[test]
@@ -154,4 +209,21 @@ mod tests {
         let mut ctx = ast::Context::new();
         parse(&mut ctx, "function foo(p1) { var x = (10 + p1); }").expect("Parse failed");
     }
+
+    #[test]
+    fn report_all_errors() {
+        let mut ctx = ast::Context::new();
+        let handler = CollectingHandler::new();
+        ctx.sm_mut().set_handler(Box::new(handler.clone()));
+        let err = parse(&mut ctx, "'use strict';\n010;\n011;\n").unwrap_err();
+        assert_eq!(2, err.loc.line);
+        let lines: Vec<u32> = handler
+            .take()
+            .iter()
+            .filter(|diagnostic| diagnostic.kind == juno_support::diagnostics::DiagKind::Error)
+            .map(|diagnostic| diagnostic.range.start.line)
+            .collect();
+        assert_eq!(vec![2, 3], lines);
+        assert_eq!(2, ctx.sm().num_errors());
+    }
 }
@@ -0,0 +1,303 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Wadler-style document algebra for laying out text within a line width.
+//!
+//! A [`Doc`] is text interspersed with optional line breaks. The breaks in a
+//! [`Doc::Group`] are either all taken or all printed flat, and they are only taken
+//! if the group doesn't fit in the rest of the line.
+//! Groups are laid out from the outside in, so inner groups stay flat when possible.
+
+/// Whether the lines of a group are printed flat or broken.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// A document to lay out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Doc {
+    /// Text without newlines.
+    Text(String),
+    /// A line break printed as `flat` if the enclosing group fits on the line,
+    /// otherwise as a newline followed by `indent` spaces.
+    Line { flat: &'static str, indent: usize },
+    /// A newline which is always printed. Breaks all enclosing groups.
+    HardLine,
+    /// A newline within literal text, such as a template literal, which is always
+    /// printed as is: the spaces before it are kept, no indent follows it, and it
+    /// doesn't break the enclosing groups.
+    LiteralLine,
+    /// Text which is only printed if the enclosing group is broken.
+    IfBreak(&'static str),
+    /// Documents whose lines are broken together.
+    /// `broken` groups always break their lines.
+    Group { docs: Vec<Doc>, broken: bool },
+    /// Position to report in the output of [`print`].
+    Mark,
+}
+
+/// Incrementally builds a [`Doc`].
+#[derive(Debug)]
+pub struct DocBuilder {
+    /// Contents of the groups which haven't been ended yet, outermost first.
+    groups: Vec<Vec<Doc>>,
+}
+
+impl Default for DocBuilder {
+    fn default() -> Self {
+        DocBuilder {
+            groups: vec![vec![]],
+        }
+    }
+}
+
+impl DocBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn push(&mut self, doc: Doc) {
+        self.groups
+            .last_mut()
+            .expect("root group is never ended")
+            .push(doc);
+    }
+
+    /// Append `text`, which must not contain newlines.
+    pub fn text(&mut self, text: &str) {
+        debug_assert!(!text.contains('\n'), "Text must not contain newlines");
+        match self.groups.last_mut().and_then(|docs| docs.last_mut()) {
+            Some(Doc::Text(last)) => last.push_str(text),
+            _ => self.push(Doc::Text(text.to_string())),
+        }
+    }
+
+    /// Append a line break which is printed as `flat` when the group isn't broken.
+    pub fn line(&mut self, flat: &'static str, indent: usize) {
+        self.push(Doc::Line { flat, indent });
+    }
+
+    /// Append a newline which is always printed.
+    pub fn hard_line(&mut self) {
+        self.push(Doc::HardLine);
+    }
+
+    /// Append a newline within literal text.
+    pub fn literal_line(&mut self) {
+        self.push(Doc::LiteralLine);
+    }
+
+    /// Append text which is only printed when the group is broken.
+    pub fn if_break(&mut self, text: &'static str) {
+        self.push(Doc::IfBreak(text));
+    }
+
+    /// Append a mark, see [`print`].
+    pub fn mark(&mut self) {
+        self.push(Doc::Mark);
+    }
+
+    /// Whether nothing has been appended since the last mark, so that another mark
+    /// would be at the same position.
+    pub fn ends_with_mark(&self) -> bool {
+        matches!(
+            self.groups.last().and_then(|docs| docs.last()),
+            Some(Doc::Mark)
+        )
+    }
+
+    /// Start a group, which is ended by the matching [`DocBuilder::end_group`].
+    pub fn begin_group(&mut self) {
+        self.groups.push(vec![]);
+    }
+
+    /// End the innermost group, forcing its lines to break if `force_break` is true.
+    pub fn end_group(&mut self, force_break: bool) {
+        assert!(self.groups.len() > 1, "end_group without begin_group");
+        let docs = self.groups.pop().unwrap();
+        let broken = force_break
+            || docs
+                .iter()
+                .any(|doc| matches!(doc, Doc::HardLine | Doc::Group { broken: true, .. }));
+        self.push(Doc::Group { docs, broken });
+    }
+
+    /// Return the document built so far. All groups must have been ended.
+    pub fn finish(mut self) -> Doc {
+        assert!(self.groups.len() == 1, "unterminated group");
+        Doc::Group {
+            docs: self.groups.pop().unwrap(),
+            broken: true,
+        }
+    }
+}
+
+/// Lay out `doc` within `width` columns where possible.
+/// Return the text, without trailing whitespace on any line except those ended by
+/// a [`Doc::LiteralLine`], and the 0-based (line, column) of every [`Doc::Mark`],
+/// in order.
+pub fn print(doc: &Doc, width: usize) -> (String, Vec<(u32, u32)>) {
+    let mut out = String::new();
+    let mut marks = vec![];
+    let mut line: u32 = 0;
+    let mut col: usize = 0;
+    let mut stack: Vec<(Mode, &Doc)> = vec![(Mode::Break, doc)];
+    while let Some((mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                out.push_str(text);
+                col += text.chars().count();
+            }
+            Doc::Line { flat, indent } => {
+                if mode == Mode::Flat {
+                    out.push_str(flat);
+                    col += flat.len();
+                } else {
+                    newline(&mut out);
+                    out.push_str(&" ".repeat(*indent));
+                    line += 1;
+                    col = *indent;
+                }
+            }
+            Doc::HardLine => {
+                newline(&mut out);
+                line += 1;
+                col = 0;
+            }
+            Doc::LiteralLine => {
+                out.push('\n');
+                line += 1;
+                col = 0;
+            }
+            Doc::IfBreak(text) => {
+                if mode == Mode::Break {
+                    out.push_str(text);
+                    col += text.chars().count();
+                }
+            }
+            Doc::Group { docs, broken } => {
+                let mode = if *broken {
+                    Mode::Break
+                } else if mode == Mode::Flat || fits(docs, &stack, width as isize - col as isize) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc)));
+            }
+            Doc::Mark => marks.push((line, col as u32)),
+        }
+    }
+    (out, marks)
+}
+
+/// End the current line of `out`, dropping its trailing spaces.
+fn newline(out: &mut String) {
+    let len = out.trim_end_matches(' ').len();
+    out.truncate(len);
+    out.push('\n');
+}
+
+/// Whether `docs` printed flat, followed by `rest` up to its next line break,
+/// fits in `remaining` columns.
+fn fits(docs: &[Doc], rest: &[(Mode, &Doc)], mut remaining: isize) -> bool {
+    let mut stack: Vec<(Mode, &Doc)> = docs.iter().rev().map(|doc| (Mode::Flat, doc)).collect();
+    let mut rest = rest.iter().rev();
+    while remaining >= 0 {
+        let (mode, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some(next) => *next,
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line { flat, .. } => {
+                if mode == Mode::Break {
+                    return true;
+                }
+                remaining -= flat.len() as isize;
+            }
+            Doc::HardLine | Doc::LiteralLine => return true,
+            Doc::IfBreak(text) => {
+                if mode == Mode::Break {
+                    remaining -= text.chars().count() as isize;
+                }
+            }
+            Doc::Group { docs, broken } => {
+                let mode = if *broken { Mode::Break } else { mode };
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc)));
+            }
+            Doc::Mark => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build `[a, b, c]` as a group which breaks its elements onto separate lines.
+    fn list(builder: &mut DocBuilder, items: &[&str]) {
+        builder.text("[");
+        builder.begin_group();
+        builder.line("", 2);
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                builder.text(",");
+                builder.line(" ", 2);
+            }
+            builder.text(item);
+        }
+        builder.if_break(",");
+        builder.line("", 0);
+        builder.end_group(false);
+        builder.text("]");
+    }
+
+    #[test]
+    fn test_fits() {
+        let mut builder = DocBuilder::new();
+        list(&mut builder, &["aaa", "bbb"]);
+        let doc = builder.finish();
+        assert_eq!(print(&doc, 10).0, "[aaa, bbb]");
+        assert_eq!(print(&doc, 9).0, "[\n  aaa,\n  bbb,\n]");
+    }
+
+    #[test]
+    fn test_hard_line() {
+        let mut builder = DocBuilder::new();
+        builder.begin_group();
+        builder.text("a");
+        builder.line(" ", 2);
+        builder.text("b  ");
+        builder.hard_line();
+        builder.mark();
+        builder.text("c");
+        builder.end_group(false);
+        let (text, marks) = print(&builder.finish(), 80);
+        assert_eq!(text, "a\n  b\nc");
+        assert_eq!(marks, vec![(2, 0)]);
+    }
+
+    #[test]
+    fn test_literal_line() {
+        let mut builder = DocBuilder::new();
+        builder.begin_group();
+        builder.text("a");
+        builder.line(" ", 2);
+        builder.text("b  ");
+        builder.literal_line();
+        builder.text(" c");
+        builder.end_group(false);
+        assert_eq!(print(&builder.finish(), 80).0, "a b  \n c");
+    }
+}
@@ -7,8 +7,13 @@
 
 pub use juno_ast as ast;
 
+pub mod bundle;
+pub mod codemod;
+pub mod comments;
+pub mod dependency_graph;
 pub mod gen_js;
 pub mod hparser;
+pub mod layout;
 pub mod resolve_dependency;
 pub mod sema;
 pub mod sourcemap;
@@ -5,13 +5,22 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fs::File;
 use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
+use std::rc::Rc;
 
+use juno_support::NullTerminatedBuf;
+use juno_support::source_manager::LineTable;
 use juno_support::source_manager::SourceId;
+use juno_support::source_manager::SourceLoc;
 use juno_support::source_manager::SourceManager;
+use juno_support::source_manager::SourceRange;
+use serde_json::Map;
+use serde_json::Value;
 
 use crate::ast::GCLock;
 
@@ -32,6 +41,30 @@ pub trait DependencyResolver {
         path: &str,
         kind: DependencyKind,
     ) -> Option<SourceId>;
+
+    /// Add the files which were read from disk while resolving dependencies to `sm`,
+    /// returning their IDs.
+    ///
+    /// `resolve_dependency` may return the ID of such a file before it is added:
+    /// pending files are given the next free IDs of the SourceManager of `lock`, in the
+    /// order they were read. So once a pending ID has been returned, `load_pending` must
+    /// be called with that same SourceManager before any other source is added to it.
+    /// Resolvers panic if that isn't the case, rather than return the wrong files.
+    fn load_pending(&self, _sm: &mut SourceManager) -> Vec<SourceId> {
+        Vec::new()
+    }
+
+    /// The locations where `resolve_dependency` looks for `path` from `file`, to explain
+    /// why it couldn't be resolved.
+    fn searched_locations(
+        &self,
+        _lock: &GCLock,
+        _file: SourceId,
+        _path: &str,
+        _kind: DependencyKind,
+    ) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 #[derive(Debug, Default)]
@@ -75,10 +108,7 @@ impl DependencyResolver for DefaultResolver {
     ) -> Option<SourceId> {
         if path.starts_with('.') {
             // Relative path resolution
-            let mut buf: PathBuf = lock.sm().source_name(file).into();
-            buf.pop();
-            buf.push(path);
-            let mut filename = remove_dots(buf).display().to_string();
+            let mut filename = relative_name(lock.sm(), file, path);
             lock.sm().lookup_name(&filename).or_else(|| {
                 filename += ".js";
                 lock.sm().lookup_name(&filename)
@@ -87,6 +117,617 @@ impl DependencyResolver for DefaultResolver {
             self.module_names.get(path).copied()
         }
     }
+
+    fn searched_locations(
+        &self,
+        lock: &GCLock,
+        file: SourceId,
+        path: &str,
+        _kind: DependencyKind,
+    ) -> Vec<String> {
+        if path.starts_with('.') {
+            let filename = relative_name(lock.sm(), file, path);
+            vec![filename.clone(), filename + ".js"]
+        } else {
+            vec![format!("inputs named {}", path)]
+        }
+    }
+}
+
+/// The name of the source at `path` relative to the directory of `file`.
+fn relative_name(sm: &SourceManager, file: SourceId, path: &str) -> String {
+    let mut buf: PathBuf = sm.source_name(file).into();
+    buf.pop();
+    buf.push(path);
+    remove_dots(buf).display().to_string()
+}
+
+/// Options of [`NodeResolver`].
+#[derive(Debug, Clone)]
+pub struct NodeResolverOptions {
+    /// Extensions tried, in order, when a path doesn't name a file.
+    pub extensions: Vec<String>,
+    /// Conditions matched by the `exports` and `imports` of packages, along with
+    /// `import` or `require`, depending on the kind of the dependency, and `default`.
+    /// As in Node, the first matching condition in the package wins.
+    pub conditions: Vec<String>,
+}
+
+impl Default for NodeResolverOptions {
+    fn default() -> Self {
+        NodeResolverOptions {
+            extensions: vec![".js".into(), ".mjs".into(), ".cjs".into(), ".jsx".into()],
+            conditions: Vec::new(),
+        }
+    }
+}
+
+/// Resolves dependencies like Node.js: relative and absolute paths are resolved as a
+/// file or directory, `#name` through the `imports` of the enclosing package, and
+/// other specifiers as packages in the `node_modules` of the enclosing directories.
+///
+/// Files which aren't registered with the SourceManager are read from disk and given
+/// the next free IDs. They must be added by [`DependencyResolver::load_pending`] before
+/// any other source, see its documentation.
+#[derive(Debug, Default)]
+pub struct NodeResolver {
+    opts: NodeResolverOptions,
+    /// Parsed `package.json` of each directory that was searched, `None` if the
+    /// directory doesn't contain a valid one.
+    packages: RefCell<HashMap<PathBuf, Option<Rc<Value>>>>,
+    /// Files read from disk which haven't been added to the SourceManager yet, with
+    /// the IDs they were given.
+    pending: RefCell<Vec<(SourceId, String, NullTerminatedBuf)>>,
+    /// Every location that was searched, while collecting them.
+    searched: RefCell<Option<Vec<String>>>,
+}
+
+impl NodeResolver {
+    pub fn new(opts: NodeResolverOptions) -> Self {
+        NodeResolver {
+            opts,
+            ..Default::default()
+        }
+    }
+
+    /// Record that `location` was searched, if the locations are being collected.
+    fn search(&self, location: impl FnOnce() -> String) {
+        if let Some(searched) = self.searched.borrow_mut().as_mut() {
+            let location = location();
+            if !searched.contains(&location) {
+                searched.push(location);
+            }
+        }
+    }
+
+    /// Find the source named by `path` exactly, reading it from disk if necessary.
+    fn find_file(&self, sm: &SourceManager, path: &Path) -> Option<SourceId> {
+        let name = path.display().to_string();
+        self.search(|| name.clone());
+        if let Some(id) = sm.lookup_name(&name) {
+            return Some(id);
+        }
+        let mut pending = self.pending.borrow_mut();
+        check_pending(sm, &pending);
+        if let Some((id, _, _)) = pending.iter().find(|(_, n, _)| *n == name) {
+            return Some(*id);
+        }
+        if !path.is_file() {
+            return None;
+        }
+        let buf = File::open(path)
+            .and_then(|mut file| NullTerminatedBuf::from_file(&mut file))
+            .ok()?;
+        let id = SourceId((sm.num_sources() + pending.len()) as u32);
+        pending.push((id, name, buf));
+        Some(id)
+    }
+
+    /// Resolve `path` as a file, trying every extension.
+    fn resolve_file(&self, sm: &SourceManager, path: &Path) -> Option<SourceId> {
+        self.find_file(sm, path).or_else(|| {
+            self.opts.extensions.iter().find_map(|ext| {
+                let mut name = path.as_os_str().to_owned();
+                name.push(ext);
+                self.find_file(sm, Path::new(&name))
+            })
+        })
+    }
+
+    /// Resolve the `index` file of directory `dir`.
+    fn resolve_index(&self, sm: &SourceManager, dir: &Path) -> Option<SourceId> {
+        self.opts
+            .extensions
+            .iter()
+            .find_map(|ext| self.find_file(sm, &dir.join(format!("index{}", ext))))
+    }
+
+    /// Resolve directory `dir` through the `module` or `main` field of its
+    /// `package.json`, falling back to its `index` file.
+    fn resolve_directory(
+        &self,
+        sm: &SourceManager,
+        dir: &Path,
+        kind: DependencyKind,
+    ) -> Option<SourceId> {
+        let fields: &[&str] = match kind {
+            DependencyKind::Import => &["module", "main"],
+            DependencyKind::Require => &["main"],
+        };
+        if let Some(package) = self.package(dir) {
+            for field in fields {
+                if let Some(main) = package.get(field).and_then(Value::as_str) {
+                    let path = remove_dots(dir.join(main));
+                    let id = self
+                        .resolve_file(sm, &path)
+                        .or_else(|| self.resolve_index(sm, &path));
+                    if id.is_some() {
+                        return id;
+                    }
+                }
+            }
+        }
+        self.resolve_index(sm, dir)
+    }
+
+    /// Resolve `path` as a file or a directory.
+    fn resolve_path(
+        &self,
+        sm: &SourceManager,
+        path: &Path,
+        kind: DependencyKind,
+    ) -> Option<SourceId> {
+        self.resolve_file(sm, path)
+            .or_else(|| self.resolve_directory(sm, path, kind))
+    }
+
+    /// Resolve `specifier`, which names a package and optionally a path in it, from
+    /// the `node_modules` of `dir` and its ancestors.
+    fn resolve_package(
+        &self,
+        sm: &SourceManager,
+        dir: &Path,
+        specifier: &str,
+        kind: DependencyKind,
+    ) -> Option<SourceId> {
+        let (name, subpath) = split_package_specifier(specifier)?;
+        for parent in dir.ancestors() {
+            if parent.ends_with("node_modules") {
+                continue;
+            }
+            let package_dir = parent.join("node_modules").join(name);
+            if let Some(exports) = self
+                .package(&package_dir)
+                .and_then(|p| p.get("exports").cloned())
+            {
+                // The exports are the only entry points of the package.
+                return self.resolve_exports(sm, &package_dir, &subpath, &exports, kind);
+            }
+            let id = self.resolve_path(sm, &remove_dots(package_dir.join(&subpath)), kind);
+            if id.is_some() {
+                return id;
+            }
+        }
+        None
+    }
+
+    /// Resolve `subpath` of the package in `package_dir` through its `exports`.
+    fn resolve_exports(
+        &self,
+        sm: &SourceManager,
+        package_dir: &Path,
+        subpath: &str,
+        exports: &Value,
+        kind: DependencyKind,
+    ) -> Option<SourceId> {
+        self.search(|| format!("exports of {}", package_dir.join("package.json").display()));
+        match exports {
+            Value::Object(map) if map.keys().any(|key| key.starts_with('.')) => {
+                let (target, star) = match_subpath(map, subpath)?;
+                self.resolve_target(sm, package_dir, target, star, kind, false)
+            }
+            // Conditions or targets of the main entry point.
+            _ if subpath == "." => self.resolve_target(sm, package_dir, exports, None, kind, false),
+            _ => None,
+        }
+    }
+
+    /// Resolve `specifier`, which starts with `#`, through the `imports` of the
+    /// package enclosing `dir`.
+    fn resolve_imports(
+        &self,
+        sm: &SourceManager,
+        dir: &Path,
+        specifier: &str,
+        kind: DependencyKind,
+    ) -> Option<SourceId> {
+        for package_dir in dir.ancestors() {
+            if let Some(package) = self.package(package_dir) {
+                self.search(|| {
+                    format!("imports of {}", package_dir.join("package.json").display())
+                });
+                let imports = match package.get("imports") {
+                    Some(Value::Object(imports)) => imports,
+                    _ => return None,
+                };
+                let (target, star) = match_subpath(imports, specifier)?;
+                return self.resolve_target(sm, package_dir, target, star, kind, true);
+            }
+        }
+        None
+    }
+
+    /// Resolve the `target` of an `exports` or `imports` entry of the package in
+    /// `package_dir`, replacing every `*` with `star`.
+    /// Targets of `imports` can also name other packages.
+    fn resolve_target(
+        &self,
+        sm: &SourceManager,
+        package_dir: &Path,
+        target: &Value,
+        star: Option<&str>,
+        kind: DependencyKind,
+        imports: bool,
+    ) -> Option<SourceId> {
+        match target {
+            Value::String(target) => {
+                let target = match star {
+                    Some(star) => target.replace('*', star),
+                    None => target.clone(),
+                };
+                if target.starts_with("./") {
+                    self.find_file(sm, &remove_dots(package_dir.join(&target)))
+                } else if imports && !is_path(&target) {
+                    self.resolve_package(sm, package_dir, &target, kind)
+                } else {
+                    None
+                }
+            }
+            Value::Array(targets) => targets.iter().find_map(|target| {
+                self.resolve_target(sm, package_dir, target, star, kind, imports)
+            }),
+            Value::Object(conditions) => {
+                let kind_condition = match kind {
+                    DependencyKind::Import => "import",
+                    DependencyKind::Require => "require",
+                };
+                // The first active condition in the order of package.json wins.
+                conditions
+                    .iter()
+                    .filter(|(condition, _)| {
+                        *condition == kind_condition
+                            || *condition == "default"
+                            || self.opts.conditions.iter().any(|c| c == *condition)
+                    })
+                    .find_map(|(_, target)| {
+                        self.resolve_target(sm, package_dir, target, star, kind, imports)
+                    })
+            }
+            _ => None,
+        }
+    }
+
+    /// The `package.json` in `dir`, if there is a valid one.
+    fn package(&self, dir: &Path) -> Option<Rc<Value>> {
+        self.packages
+            .borrow_mut()
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let text = std::fs::read_to_string(dir.join("package.json")).ok()?;
+                match serde_json::from_str(&text) {
+                    Ok(package @ Value::Object(_)) => Some(Rc::new(package)),
+                    _ => None,
+                }
+            })
+            .clone()
+    }
+}
+
+impl DependencyResolver for NodeResolver {
+    fn resolve_dependency(
+        &self,
+        lock: &GCLock,
+        file: SourceId,
+        path: &str,
+        kind: DependencyKind,
+    ) -> Option<SourceId> {
+        let sm = lock.sm();
+        let mut dir: PathBuf = sm.source_name(file).into();
+        dir.pop();
+        if path.starts_with('#') {
+            self.resolve_imports(sm, &dir, path, kind)
+        } else if is_path(path) {
+            self.resolve_path(sm, &remove_dots(dir.join(path)), kind)
+        } else {
+            self.resolve_package(sm, &dir, path, kind)
+        }
+    }
+
+    fn searched_locations(
+        &self,
+        lock: &GCLock,
+        file: SourceId,
+        path: &str,
+        kind: DependencyKind,
+    ) -> Vec<String> {
+        *self.searched.borrow_mut() = Some(Vec::new());
+        self.resolve_dependency(lock, file, path, kind);
+        self.searched.take().unwrap_or_default()
+    }
+
+    fn load_pending(&self, sm: &mut SourceManager) -> Vec<SourceId> {
+        let pending = self.pending.take();
+        check_pending(sm, &pending);
+        pending
+            .into_iter()
+            .map(|(id, name, buf)| {
+                let added = sm.add_source(name, buf);
+                debug_assert_eq!(added, id);
+                added
+            })
+            .collect()
+    }
+}
+
+/// Check that the IDs given to the `pending` files are still the next free IDs of `sm`.
+fn check_pending(sm: &SourceManager, pending: &[(SourceId, String, NullTerminatedBuf)]) {
+    if let Some((first, name, _)) = pending.first() {
+        assert_eq!(
+            first.0 as usize,
+            sm.num_sources(),
+            "Sources were added before the pending file {} was loaded",
+            name
+        );
+    }
+}
+
+/// Options of [`HasteResolver`].
+#[derive(Debug, Clone, Default)]
+pub struct HasteResolverOptions {
+    /// Platforms whose files are preferred, in order, to the files for any platform.
+    /// e.g. `ios` and `native` select `Foo.ios.js`, then `Foo.native.js`, then `Foo.js`.
+    pub platforms: Vec<String>,
+    /// Names of modules mapped to the names of their sources, in addition to the names
+    /// declared by `@providesModule`.
+    pub name_map: HashMap<String, String>,
+    /// Specifiers replaced by other specifiers before resolving them.
+    pub aliases: HashMap<String, String>,
+}
+
+/// Platforms recognized in the names of files, in addition to the configured ones.
+const HASTE_PLATFORMS: &[&str] = &["ios", "android", "native"];
+
+/// Resolves dependencies on Haste module names, which are declared by `@providesModule`
+/// in the doc block of a file or by the name map, and relative paths. Files for other
+/// platforms than the configured ones are ignored.
+#[derive(Debug, Default)]
+pub struct HasteResolver {
+    opts: HasteResolverOptions,
+    /// The sources of every module name, with the platforms they are for.
+    modules: HashMap<String, Vec<(Option<String>, SourceId)>>,
+}
+
+impl HasteResolver {
+    /// Find the module names of every source of `sm`, reporting an error for every
+    /// name which is declared more than once for the same platform.
+    pub fn new(sm: &SourceManager, opts: HasteResolverOptions) -> Self {
+        let mut resolver = HasteResolver {
+            opts,
+            modules: HashMap::new(),
+        };
+        let mut declared = HashMap::new();
+        let mut name_map: Vec<(&String, &String)> = resolver.opts.name_map.iter().collect();
+        name_map.sort();
+        for (name, source) in name_map {
+            if let Some(id) = sm.lookup_name(source) {
+                let loc = SourceLoc { line: 1, col: 1 };
+                declared
+                    .entry(name.clone())
+                    .or_insert_with(Vec::new)
+                    .push((id, loc));
+            }
+        }
+        for i in 0..sm.num_sources() {
+            let id = SourceId(i as u32);
+            let buf = sm.source_buffer(id).as_bytes();
+            if let Some((name, offset)) = provides_module(buf) {
+                let loc = LineTable::new(buf).loc(offset);
+                declared
+                    .entry(name)
+                    .or_insert_with(Vec::new)
+                    .push((id, loc));
+            }
+        }
+
+        let mut names: Vec<(String, Vec<(SourceId, SourceLoc)>)> = declared.into_iter().collect();
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, sources) in names {
+            let mut entries: Vec<(Option<String>, SourceId, SourceLoc)> = vec![];
+            for (id, loc) in sources {
+                let platform = resolver.platform_of(sm.source_name(id));
+                match entries.iter().find(|(p, _, _)| *p == platform) {
+                    Some(&(_, prev_id, _)) if prev_id == id => {}
+                    Some(&(_, prev_id, prev_loc)) => {
+                        sm.error(
+                            SourceRange::from_loc(id, loc),
+                            format!("Duplicate module name '{}'", name),
+                        );
+                        sm.note(
+                            SourceRange::from_loc(prev_id, prev_loc),
+                            format!("'{}' was previously declared here", name),
+                        );
+                    }
+                    None => entries.push((platform, id, loc)),
+                }
+            }
+            resolver.modules.insert(
+                name,
+                entries
+                    .into_iter()
+                    .map(|(platform, id, _)| (platform, id))
+                    .collect(),
+            );
+        }
+        resolver
+    }
+
+    /// The platform of the file `name`, from the extension before the last one.
+    fn platform_of(&self, name: &str) -> Option<String> {
+        let stem = Path::new(name).file_stem()?;
+        let platform = Path::new(stem).extension()?.to_str()?;
+        if HASTE_PLATFORMS.contains(&platform) || self.opts.platforms.iter().any(|p| p == platform)
+        {
+            Some(platform.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// The suffixes of the names of files to try, in order, before their extension.
+    fn platform_suffixes(&self) -> impl Iterator<Item = String> + '_ {
+        self.opts
+            .platforms
+            .iter()
+            .map(|platform| format!(".{}", platform))
+            .chain(std::iter::once(String::new()))
+    }
+}
+
+impl DependencyResolver for HasteResolver {
+    fn resolve_dependency(
+        &self,
+        lock: &GCLock,
+        file: SourceId,
+        path: &str,
+        _kind: DependencyKind,
+    ) -> Option<SourceId> {
+        let path = self.opts.aliases.get(path).map_or(path, String::as_str);
+        if path.starts_with('.') {
+            let filename = relative_name(lock.sm(), file, path);
+            lock.sm().lookup_name(&filename).or_else(|| {
+                self.platform_suffixes()
+                    .find_map(|suffix| lock.sm().lookup_name(format!("{}{}.js", filename, suffix)))
+            })
+        } else {
+            let sources = self.modules.get(path)?;
+            self.opts
+                .platforms
+                .iter()
+                .map(Some)
+                .chain(std::iter::once(None))
+                .find_map(|platform| {
+                    sources
+                        .iter()
+                        .find(|(p, _)| p.as_ref() == platform)
+                        .map(|&(_, id)| id)
+                })
+        }
+    }
+
+    fn searched_locations(
+        &self,
+        lock: &GCLock,
+        file: SourceId,
+        path: &str,
+        _kind: DependencyKind,
+    ) -> Vec<String> {
+        let path = self.opts.aliases.get(path).map_or(path, String::as_str);
+        if path.starts_with('.') {
+            let filename = relative_name(lock.sm(), file, path);
+            std::iter::once(filename.clone())
+                .chain(
+                    self.platform_suffixes()
+                        .map(|suffix| format!("{}{}.js", filename, suffix)),
+                )
+                .collect()
+        } else {
+            vec![format!("Haste module {}", path)]
+        }
+    }
+}
+
+/// Find the module name declared by `@providesModule` in the comments before any code
+/// in `buf`, with the byte offset of the tag.
+fn provides_module(buf: &[u8]) -> Option<(String, usize)> {
+    const TAG: &[u8] = b"@providesModule";
+    let mut pos = 0;
+    loop {
+        while matches!(buf.get(pos), Some(c) if c.is_ascii_whitespace()) {
+            pos += 1;
+        }
+        let rest = &buf[pos..];
+        if rest.starts_with(b"//") {
+            pos += rest.iter().position(|&c| c == b'\n')?;
+        } else if rest.starts_with(b"/*") {
+            let len = rest.windows(2).position(|w| w == b"*/")?;
+            let comment = &rest[..len];
+            let tag = match comment.windows(TAG.len()).position(|w| w == TAG) {
+                Some(tag) => tag,
+                None => {
+                    pos += len + 2;
+                    continue;
+                }
+            };
+            let name: String = comment[tag + TAG.len()..]
+                .iter()
+                .skip_while(|c| c.is_ascii_whitespace())
+                .take_while(|c| !c.is_ascii_whitespace() && **c != b'*')
+                .map(|&c| c as char)
+                .collect();
+            return (!name.is_empty()).then_some((name, pos + tag));
+        } else {
+            return None;
+        }
+    }
+}
+
+/// Whether `specifier` is a relative or absolute path rather than a package name.
+fn is_path(specifier: &str) -> bool {
+    specifier == "."
+        || specifier == ".."
+        || specifier.starts_with("./")
+        || specifier.starts_with("../")
+        || specifier.starts_with('/')
+}
+
+/// Split `specifier` into the name of a package, which may be scoped, and the path in
+/// the package, as `.` or `./path`.
+fn split_package_specifier(specifier: &str) -> Option<(&str, String)> {
+    let name_len = if specifier.starts_with('@') {
+        let scope_len = specifier.find('/')?;
+        specifier[scope_len + 1..]
+            .find('/')
+            .map_or(specifier.len(), |len| scope_len + 1 + len)
+    } else {
+        specifier.find('/').unwrap_or(specifier.len())
+    };
+    let (name, path) = specifier.split_at(name_len);
+    if name.is_empty() || name.ends_with('/') {
+        return None;
+    }
+    Some((name, format!(".{}", path)))
+}
+
+/// Find the entry of `map`, the `exports` or `imports` of a package, which matches
+/// `key`. Keys can contain a single `*`, in which case the entry with the longest
+/// prefix before the `*` matches, and the matched string is returned with it.
+fn match_subpath<'a>(
+    map: &'a Map<String, Value>,
+    key: &'a str,
+) -> Option<(&'a Value, Option<&'a str>)> {
+    if let Some(target) = map.get(key) {
+        if !key.contains('*') {
+            return Some((target, None));
+        }
+    }
+    map.iter()
+        .filter_map(|(pattern, target)| {
+            let (prefix, suffix) = pattern.split_once('*')?;
+            let star = key.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            (key.len() >= pattern.len()).then_some((prefix.len(), target, star))
+        })
+        .max_by_key(|&(prefix_len, _, _)| prefix_len)
+        .map(|(_, target, star)| (target, Some(star)))
 }
 
 /// Remove all non-leading `.` and `..` from the path, treating `..` as a parent directory.
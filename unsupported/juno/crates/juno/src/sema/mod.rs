@@ -15,6 +15,7 @@
 mod decl_collector;
 mod keywords;
 mod known_globals;
+mod rename;
 mod resolver;
 mod sem_context;
 
@@ -0,0 +1,123 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Renaming of declarations, using the identifier resolution of a [`SemContext`].
+
+use std::collections::HashMap;
+
+use juno_support::atom_table::Atom;
+
+use crate::ast::*;
+
+use super::DeclId;
+use super::Resolution;
+use super::SemContext;
+
+impl SemContext {
+    /// Return `root` with every identifier resolved to one of the declarations in `names`,
+    /// including the declaring identifiers, renamed to the new name of the declaration.
+    /// `root` must be the AST (or a part of it) that `self` was created for.
+    ///
+    /// Shorthand properties whose value is renamed are expanded, so that object literals
+    /// and patterns keep their keys.
+    /// Renaming doesn't check that the new names don't conflict with other declarations,
+    /// which is up to the caller.
+    pub fn rename<'gc>(
+        &self,
+        gc: &'gc GCLock,
+        root: &'gc Node<'gc>,
+        names: &HashMap<DeclId, Atom>,
+    ) -> &'gc Node<'gc> {
+        if names.is_empty() {
+            return root;
+        }
+        let mut renamer = Renamer { sem: self, names };
+        root.visit_mut(gc, &mut renamer, None).unwrap_or(root)
+    }
+}
+
+struct Renamer<'a> {
+    sem: &'a SemContext,
+    names: &'a HashMap<DeclId, Atom>,
+}
+
+impl Renamer<'_> {
+    /// The new name of the identifier `node`, if it is renamed.
+    fn new_name<'gc>(&self, gc: &'gc GCLock, node: &'gc Node<'gc>) -> Option<Atom> {
+        match self.sem.ident_decl(&NodeRc::from_node(gc, node)) {
+            Some(Resolution::Decl(decl)) => self.names.get(&decl).copied(),
+            _ => None,
+        }
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for Renamer<'_> {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        match node {
+            Node::Identifier(_) if is_property_name(path) => TransformResult::Unchanged,
+            Node::Identifier(id) => match self.new_name(gc, node) {
+                Some(name) if name != id.name => {
+                    let mut builder = builder::Identifier::from_node(id);
+                    builder.name(name);
+                    TransformResult::Changed(builder.build_forced(gc))
+                }
+                _ => TransformResult::Unchanged,
+            },
+            Node::Property(
+                prop @ Property {
+                    shorthand: true,
+                    value,
+                    ..
+                },
+            ) => {
+                // The value of a shorthand pattern property may have a default.
+                let id = match value {
+                    Node::AssignmentPattern(AssignmentPattern { left, .. }) => left,
+                    _ => value,
+                };
+                match (id, self.new_name(gc, id)) {
+                    (Node::Identifier(Identifier { name, .. }), Some(new_name))
+                        if new_name != *name =>
+                    {
+                        let mut builder = builder::Property::from_node(prop);
+                        builder.shorthand(false);
+                        node.replace_with_new(builder::Builder::Property(builder), gc, self)
+                    }
+                    _ => node.visit_children_mut(gc, self),
+                }
+            }
+            _ => node.visit_children_mut(gc, self),
+        }
+    }
+}
+
+/// Whether an identifier at `path` is the name of a property or of an export, rather than
+/// a reference to a declaration.
+/// Such names may be the same node as the local binding, e.g. in shorthand properties.
+fn is_property_name(path: Option<Path>) -> bool {
+    matches!(
+        path,
+        Some(Path {
+            parent: Node::Property(Property {
+                computed: false,
+                ..
+            }),
+            field: NodeField::key,
+        }) | Some(Path {
+            parent: Node::ImportSpecifier(_),
+            field: NodeField::imported,
+        }) | Some(Path {
+            parent: Node::ExportSpecifier(_),
+            field: NodeField::exported,
+        })
+    )
+}
@@ -10,6 +10,8 @@ use std::collections::HashSet;
 use std::rc::Rc;
 
 use juno_support::atom_table::Atom;
+use juno_support::diagnostics::Fix;
+use juno_support::diagnostics::TextEdit;
 use juno_support::source_manager::SourceId;
 use juno_support::source_manager::SourceRange;
 use juno_support::ScopedHashMap;
@@ -469,6 +471,7 @@ impl<'gc> Resolver<'gc, '_> {
                 // Create the module scope as a function.
                 pself.in_new_function(lock, node, |pself| {
                     pself.in_new_scope(lock, node, |pself| {
+                        pself.sem.set_module_scope(pself.current_scope.unwrap());
                         // Search for "use strict".
                         if find_use_strict(lock, &node_cast!(Node::Module, node).body).is_some() {
                             pself
@@ -669,9 +672,10 @@ impl<'gc> Resolver<'gc, '_> {
                         && !pself.function_strict_mode()
                         && vd.kind == ast::VariableDeclarationKind::Var)
                     {
-                        lock.sm().error(
+                        lock.sm().error_with_fixes(
                             *init.range(),
                             "for-in/for-of variable declaration may not be initialized",
+                            Self::remove_initializer_fix(lock, declarator.id, init),
                         );
                     }
                 }
@@ -1062,6 +1066,51 @@ impl<'gc> Resolver<'gc, '_> {
         true
     }
 
+    /// Fix removing the initializer `init` of the declarator of `id`, from the end of `id`.
+    fn remove_initializer_fix(lock: &GCLock, id: &Node, init: &Node) -> Vec<Fix> {
+        let file = id.range().file;
+        let table = lock.sm().line_table(file);
+        match (table.offset(id.range().end), table.offset(init.range().end)) {
+            (Some(start), Some(end)) => vec![Fix::new(
+                "remove the initializer",
+                file,
+                vec![TextEdit {
+                    start: start + 1,
+                    end: end + 1,
+                    text: String::new(),
+                }],
+            )],
+            _ => vec![],
+        }
+    }
+
+    /// Fix replacing the `=` between `left` and `right` of an assignment with `===`,
+    /// when the assignment is likely a mistyped comparison.
+    fn comparison_fix(lock: &GCLock, left: &Node, right: &Node) -> Vec<Fix> {
+        let file = left.range().file;
+        let table = lock.sm().line_table(file);
+        let between = table
+            .offset(left.range().end)
+            .zip(table.offset(right.range().start));
+        let buf = lock.sm().source_buffer(file).as_bytes();
+        let eq = between.and_then(|(end, start)| {
+            let pos = buf.get(end + 1..start)?.iter().position(|&c| c == b'=')?;
+            Some(end + 1 + pos)
+        });
+        match eq {
+            Some(eq) => vec![Fix::new(
+                "use '===' to compare",
+                file,
+                vec![TextEdit {
+                    start: eq,
+                    end: eq + 1,
+                    text: "===".to_string(),
+                }],
+            )],
+            None => vec![],
+        }
+    }
+
     /// Ensure that the specified node is a valid target for an assignment, in
     /// other words it is an l-value, a Pattern (checked recursively) or an Empty
     /// (used by elision).
@@ -1229,6 +1278,34 @@ impl<'gc> Resolver<'gc, '_> {
         }
     }
 
+    /// Record and report that `target`, the dependency of `node`, couldn't be resolved,
+    /// listing the locations searched by `dependency_resolver`.
+    fn report_unresolved(
+        &mut self,
+        lock: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        target: &str,
+        kind: DependencyKind,
+        dependency_resolver: &dyn DependencyResolver,
+    ) {
+        self.sem
+            .add_unresolved_require(NodeRc::from_node(lock, node));
+        let msg = match kind {
+            DependencyKind::Import => format!("Unable to resolve import for {}", target),
+            DependencyKind::Require => format!("Unable to resolve require for {}", target),
+        };
+        if lock.ctx().error_unresolved {
+            lock.sm().error(*node.range(), msg);
+        } else {
+            lock.sm().warning(*node.range(), msg);
+        }
+        let searched = dependency_resolver.searched_locations(lock, self.file_id, target, kind);
+        if !searched.is_empty() {
+            lock.sm()
+                .note(*node.range(), format!("Searched: {}", searched.join(", ")));
+        }
+    }
+
     /// Mark the current scope and every one of its ancestor scopes as users of local `eval()`.
     /// Set the `can_rename` flag on every variable in the scopes to `false`.
     fn register_local_eval(&mut self) {
@@ -1300,7 +1377,21 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
             Node::Identifier(ident) => self.visit_identifier(lock, ident, node, path.unwrap()),
 
             Node::AssignmentExpression(asgn) => {
-                self.validate_assignment_target(lock, asgn.left);
+                // Assigning to an expression which isn't a reference, rather than to
+                // a reserved identifier, is likely a mistyped comparison.
+                if asgn.operator == ast::AssignmentExpressionOperator::Assign
+                    && !asgn.left.is_pattern()
+                    && !node_isa!(Node::Identifier, asgn.left)
+                    && !self.is_lvalue(asgn.left)
+                {
+                    lock.sm().error_with_fixes(
+                        *asgn.left.range(),
+                        "invalid assignment left-hand side",
+                        Self::comparison_fix(lock, asgn.left, asgn.right),
+                    );
+                } else {
+                    self.validate_assignment_target(lock, asgn.left);
+                }
                 node.visit_children(lock, self);
             }
 
@@ -1468,13 +1559,21 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
             Node::ImportDeclaration(ast::ImportDeclaration {
                 source: Node::StringLiteral(ast::StringLiteral { value, .. }),
                 ..
+            })
+            | Node::ExportNamedDeclaration(ast::ExportNamedDeclaration {
+                source: Some(Node::StringLiteral(ast::StringLiteral { value, .. })),
+                ..
+            })
+            | Node::ExportAllDeclaration(ast::ExportAllDeclaration {
+                source: Node::StringLiteral(ast::StringLiteral { value, .. }),
+                ..
             }) => {
                 node.visit_children(lock, self);
                 if let ResolverMode::Module {
                     dependency_resolver,
                 } = self.mode
                 {
-                    // Resolve `import`.
+                    // Resolve `import` and `export ... from`.
                     let target = String::from_utf16_lossy(lock.str_u16(*value));
                     match dependency_resolver.resolve_dependency(
                         lock,
@@ -1485,12 +1584,13 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
                         Some(file_id) => {
                             self.sem.add_require(NodeRc::from_node(lock, node), file_id);
                         }
-                        None => {
-                            lock.sm().warning(
-                                *node.range(),
-                                format!("Unable to resolve import for {}", target),
-                            );
-                        }
+                        None => self.report_unresolved(
+                            lock,
+                            node,
+                            &target,
+                            DependencyKind::Import,
+                            dependency_resolver,
+                        ),
                     }
                 }
             }
@@ -1539,12 +1639,13 @@ impl<'gc> Visitor<'gc> for Resolver<'gc, '_> {
                                 Some(file_id) => {
                                     self.sem.add_require(NodeRc::from_node(lock, node), file_id);
                                 }
-                                None => {
-                                    lock.sm().warning(
-                                        *node.range(),
-                                        format!("Unable to resolve require for {}", target),
-                                    );
-                                }
+                                None => self.report_unresolved(
+                                    lock,
+                                    node,
+                                    &target,
+                                    DependencyKind::Require,
+                                    dependency_resolver,
+                                ),
                             }
                         }
                     }
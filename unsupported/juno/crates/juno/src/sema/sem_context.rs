@@ -277,8 +277,13 @@ pub struct SemContext {
     /// Lexical scopes associated with AST nodes. Usually BlockStatement, but
     /// occasionally others.
     node_scopes: HashMap<NodeRc, LexicalScopeId>,
-    /// Resolved `require` calls.
+    /// Resolved `require` calls, `import` declarations and `export ... from` declarations.
     requires: HashMap<NodeRc, SourceId>,
+    /// `require` calls, `import` declarations and `export ... from` declarations whose
+    /// dependency couldn't be resolved.
+    unresolved_requires: Vec<NodeRc>,
+    /// The scope of the top-level declarations, if this is the context of a module.
+    module_scope: Option<LexicalScopeId>,
 }
 
 impl SemContext {
@@ -380,6 +385,15 @@ impl SemContext {
         self.node_scopes.get(&node).copied()
     }
 
+    pub(super) fn set_module_scope(&mut self, scope: LexicalScopeId) {
+        self.module_scope = Some(scope);
+    }
+    /// Return the scope of the top-level declarations of the module, which is kept
+    /// even if the `Module` node itself is rebuilt.
+    pub fn module_scope(&self) -> Option<LexicalScopeId> {
+        self.module_scope
+    }
+
     pub fn all_decls(&self) -> &[Decl] {
         self.decls.as_slice()
     }
@@ -416,6 +430,13 @@ impl SemContext {
     pub fn add_require(&mut self, call: NodeRc, file_id: SourceId) {
         self.requires.insert(call, file_id);
     }
+    /// Dependencies which couldn't be resolved, in the order they were found.
+    pub fn all_unresolved_requires(&self) -> &[NodeRc] {
+        &self.unresolved_requires
+    }
+    pub fn add_unresolved_require(&mut self, call: NodeRc) {
+        self.unresolved_requires.push(call);
+    }
 
     /// Return the id of the global scope in the context. This may seem
     /// redundant, since the ID is constant. The idea here that the global scope
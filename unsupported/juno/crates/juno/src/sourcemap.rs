@@ -5,45 +5,266 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+//! Reading, writing and composing source maps.
+//!
+//! [`SourceMap`] doesn't retain the `ignoreList` of a source map, so it is kept
+//! alongside it in [`FullSourceMap`].
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io::Write;
+
+use serde_json::Value;
+use sourcemap::DecodedMap;
 use sourcemap::SourceMap;
 use sourcemap::SourceMapBuilder;
 
-/// Return a merged version of the `input` and `output` source maps.
-/// Currently this only supports a single input source map.
-/// The function works by iterating through every `Token` in `output` and attempting
-/// to look it up in `input` (use the `lookup_token` method to get the closest token near it).
-/// If `input` does not contain the token, use the `output` token.
-/// Currently the only metadata retained from the `input` map is `names` and `sources`.
-/// TODO: Add support for other sourcemap metadata fields such as `metadata` and `sourcesContent`.
-pub fn merge_sourcemaps(input: &SourceMap, output: &SourceMap) -> SourceMap {
-    let mut merged = SourceMapBuilder::new(output.get_file());
+/// A source map together with its list of sources to ignore when debugging.
+#[derive(Debug, Clone)]
+pub struct FullSourceMap {
+    pub map: SourceMap,
+
+    /// Indices of the sources of `map` in its `ignoreList`, in increasing order.
+    pub ignore_list: Vec<u32>,
+}
+
+impl From<SourceMap> for FullSourceMap {
+    fn from(map: SourceMap) -> Self {
+        FullSourceMap {
+            map,
+            ignore_list: vec![],
+        }
+    }
+}
+
+impl FullSourceMap {
+    /// Parse a regular, indexed or Hermes source map.
+    /// Indexed source maps are flattened, so all of their sections must be embedded.
+    pub fn from_slice(data: &[u8]) -> sourcemap::Result<FullSourceMap> {
+        let map = match sourcemap::decode_slice(data)? {
+            DecodedMap::Regular(map) => map,
+            DecodedMap::Index(index) => index.flatten()?,
+            DecodedMap::Hermes(hermes) => (*hermes).clone(),
+        };
+        // The JSON has already been validated by the decoder.
+        let ignored = match serde_json::from_slice::<Value>(data) {
+            Ok(json) => ignored_sources(&json),
+            Err(_) => HashSet::new(),
+        };
+        let ignore_list = (0..map.get_source_count())
+            .filter(|&i| matches!(map.get_source(i), Some(src) if ignored.contains(src)))
+            .collect();
+        Ok(FullSourceMap { map, ignore_list })
+    }
 
-    for name in input.names() {
-        merged.add_name(name);
+    /// Whether the source `src_id` is in the ignore list.
+    pub fn is_ignored(&self, src_id: u32) -> bool {
+        self.ignore_list.binary_search(&src_id).is_ok()
     }
 
-    let mut output_src_id = 0;
-    for src in input.sources() {
-        output_src_id = merged.add_source(src) + 1;
+    /// Serialize the source map as JSON to `w`.
+    /// The ignore list is written as both `ignoreList` and the older `x_google_ignoreList`.
+    pub fn to_writer<W: Write>(&self, w: W) -> sourcemap::Result<()> {
+        if self.ignore_list.is_empty() {
+            return self.map.to_writer(w);
+        }
+        let mut buf: Vec<u8> = vec![];
+        self.map.to_writer(&mut buf)?;
+        let mut json: Value = serde_json::from_slice(&buf)?;
+        if let Value::Object(fields) = &mut json {
+            let list = Value::from(self.ignore_list.clone());
+            fields.insert("ignoreList".into(), list.clone());
+            fields.insert("x_google_ignoreList".into(), list);
+        }
+        serde_json::to_writer(w, &json)?;
+        Ok(())
+    }
+}
+
+/// Return the names of the sources in the ignore list of the JSON source map `json`,
+/// including those of the sections of an indexed map.
+fn ignored_sources(json: &Value) -> HashSet<String> {
+    let mut result = HashSet::new();
+    if let Some(sections) = json.get("sections").and_then(Value::as_array) {
+        for section in sections {
+            if let Some(map) = section.get("map") {
+                result.extend(ignored_sources(map));
+            }
+        }
+        return result;
+    }
+    let sources = match json.get("sources").and_then(Value::as_array) {
+        Some(sources) => sources,
+        None => return result,
+    };
+    let list = json
+        .get("ignoreList")
+        .or_else(|| json.get("x_google_ignoreList"))
+        .and_then(Value::as_array);
+    for index in list.into_iter().flatten() {
+        let src = index
+            .as_u64()
+            .and_then(|i| sources.get(i as usize))
+            .and_then(Value::as_str);
+        if let Some(src) = src {
+            result.insert(src.to_string());
+        }
     }
-    for output_token in output.tokens() {
-        let (merged_token, src_id) =
-            match input.lookup_token(output_token.get_src_line(), output_token.get_src_col()) {
-                Some(input_token) => (input_token, input_token.get_src_id()),
-                None => (output_token, output_src_id),
+    result
+}
+
+/// Builds a source map whose sources are deduplicated by name.
+struct Composer {
+    builder: SourceMapBuilder,
+    /// Map from source name to its ID in `builder`.
+    sources: HashMap<String, u32>,
+    ignore_list: Vec<u32>,
+}
+
+impl Composer {
+    fn new(file: Option<&str>) -> Composer {
+        Composer {
+            builder: SourceMapBuilder::new(file),
+            sources: HashMap::new(),
+            ignore_list: vec![],
+        }
+    }
+
+    /// Return the ID of the source `src_id` of `map` in the result,
+    /// adding it with its contents if it hasn't been seen before.
+    fn add_source(&mut self, map: &FullSourceMap, src_id: u32) -> Option<u32> {
+        let name = map.map.get_source(src_id)?;
+        if let Some(&id) = self.sources.get(name) {
+            return Some(id);
+        }
+        let id = self.builder.add_source(name);
+        self.builder
+            .set_source_contents(id, map.map.get_source_contents(src_id));
+        if map.is_ignored(src_id) {
+            self.ignore_list.push(id);
+        }
+        self.sources.insert(name.to_string(), id);
+        Some(id)
+    }
+
+    fn finish(self) -> FullSourceMap {
+        let mut ignore_list = self.ignore_list;
+        ignore_list.sort_unstable();
+        FullSourceMap {
+            map: self.builder.into_sourcemap(),
+            ignore_list,
+        }
+    }
+}
+
+/// Compose `output`, which maps generated code to its sources, with `inputs`, which
+/// maps the source with each index in `output` to its own sources, if it has a map.
+///
+/// Every mapping of `output` into a source with an input map is traced back
+/// through that map. If the input map has no mapping on the line of the position,
+/// the generated code doesn't come from any original source and is left unmapped.
+/// Sources without an input map are kept as they are.
+/// The `file` of `output` is kept, and the sources in the result keep their contents
+/// and whether they are ignored.
+pub fn compose_sourcemaps(
+    output: &FullSourceMap,
+    inputs: &[Option<&FullSourceMap>],
+) -> FullSourceMap {
+    let mut composer = Composer::new(output.map.get_file());
+
+    for token in output.map.tokens() {
+        let (dst_line, dst_col) = token.get_dst();
+        let src_id = token.get_src_id();
+        let input = if token.get_source().is_some() {
+            inputs.get(src_id as usize).copied().flatten()
+        } else {
+            None
+        };
+        let (map, mapped) = match input {
+            None => (output, Some(token)),
+            Some(input) => (
+                input,
+                input
+                    .map
+                    .lookup_token(token.get_src_line(), token.get_src_col())
+                    .filter(|t| t.get_dst_line() == token.get_src_line()),
+            ),
+        };
+        let source = match mapped {
+            Some(mapped) if mapped.get_source().is_some() => {
+                composer.add_source(map, mapped.get_src_id())
+            }
+            _ => None,
+        };
+        match (mapped, source) {
+            (Some(mapped), Some(source)) => {
+                // Prefer the name in the original source.
+                let name = mapped.get_name().or_else(|| token.get_name());
+                let name_id = name.map(|name| composer.builder.add_name(name));
+                composer.builder.add_raw(
+                    dst_line,
+                    dst_col,
+                    mapped.get_src_line(),
+                    mapped.get_src_col(),
+                    Some(source),
+                    name_id,
+                );
+            }
+            _ => {
+                composer
+                    .builder
+                    .add_raw(dst_line, dst_col, 0, 0, None, None);
+            }
+        }
+    }
+
+    composer.finish()
+}
+
+/// Concatenate the maps of consecutive parts of a generated file into a map of the
+/// whole file named `file`. Each section is the line the part starts on, counting from 0,
+/// and the map of the part.
+pub fn concat_sourcemaps(file: Option<&str>, sections: &[(u32, &FullSourceMap)]) -> FullSourceMap {
+    let mut composer = Composer::new(file);
+    for &(line, section) in sections {
+        for token in section.map.tokens() {
+            let source = if token.get_source().is_some() {
+                composer.add_source(section, token.get_src_id())
+            } else {
+                None
             };
+            let name_id = source
+                .and(token.get_name())
+                .map(|name| composer.builder.add_name(name));
+            composer.builder.add_raw(
+                token.get_dst_line() + line,
+                token.get_dst_col(),
+                token.get_src_line(),
+                token.get_src_col(),
+                source,
+                name_id,
+            );
+        }
+    }
+    composer.finish()
+}
+
+/// Compose a chain of source maps, each of which maps the output of a step
+/// of a pipeline back to its input, given in the order the steps ran.
+/// The first map maps to the original sources, and every later map has the
+/// output of the previous step as its only source.
+pub fn compose_chain(chain: &[FullSourceMap]) -> Option<FullSourceMap> {
+    let (first, rest) = chain.split_first()?;
+    Some(rest.iter().fold(first.clone(), |acc, map| {
+        let inputs = vec![Some(&acc); map.map.get_source_count() as usize];
+        compose_sourcemaps(map, &inputs)
+    }))
+}
 
-        merged.add_raw(
-            output_token.get_dst_line(),
-            output_token.get_dst_col(),
-            merged_token.get_src_line(),
-            merged_token.get_src_col(),
-            Some(src_id),
-            match merged_token.get_name() {
-                Some(_) => Some(merged_token.get_name_id()),
-                _ => None,
-            },
-        );
-    }
-    merged.into_sourcemap()
+/// Return a merged version of the `input` and `output` source maps,
+/// where `input` is the map of every source of `output`.
+pub fn merge_sourcemaps(input: &SourceMap, output: &SourceMap) -> SourceMap {
+    let input = FullSourceMap::from(input.clone());
+    let inputs = vec![Some(&input); output.get_source_count() as usize];
+    compose_sourcemaps(&FullSourceMap::from(output.clone()), &inputs).map
 }
@@ -0,0 +1,459 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::io;
+
+use juno::ast::*;
+use juno::bundle::*;
+use juno::comments::CommentMap;
+use juno::gen_js;
+use juno::hparser;
+use juno::resolve_dependency::DefaultResolver;
+use juno::sema;
+use juno::sema::SemContext;
+use juno_support::NullTerminatedBuf;
+use juno_support::source_manager::SourceId;
+
+type BundleFn = for<'s> fn(
+    &mut Context,
+    SourceId,
+    &HashMap<SourceId, BundleModule<'s>>,
+    gen_js::Opt<'s>,
+) -> io::Result<Bundle>;
+
+/// Parse every `(name, source)` of `files` as a module and bundle the first one.
+fn bundle_files(files: &[(&str, &str)]) -> (Bundle, Vec<SourceId>) {
+    bundle_files_with(files, bundle)
+}
+
+/// Parse every `(name, source)` of `files` as a module and bundle the first one
+/// with scope hoisting.
+fn hoist_files(files: &[(&str, &str)]) -> String {
+    bundle_files_with(files, bundle_scope_hoisted).0.code
+}
+
+fn bundle_files_with(files: &[(&str, &str)], bundle_fn: BundleFn) -> (Bundle, Vec<SourceId>) {
+    let mut ctx = Context::new();
+    let (ids, analyzed) = parse_files(&mut ctx, files);
+    let modules: HashMap<SourceId, BundleModule> = ids
+        .iter()
+        .zip(&analyzed)
+        .map(|(&id, module)| {
+            (
+                id,
+                BundleModule {
+                    ast: module.ast.clone(),
+                    sem: &module.sem,
+                    source_map: None,
+                    comments: None,
+                },
+            )
+        })
+        .collect();
+    let result = bundle_fn(&mut ctx, ids[0], &modules, Default::default()).unwrap();
+    (result, ids)
+}
+
+/// Parse every `(name, source)` of `files` as a module, remove the code that the first
+/// one doesn't need, assuming that the modules named in `pure` have no side effects,
+/// and bundle it with scope hoisting.
+fn shake_files(files: &[(&str, &str)], pure: &[&str]) -> String {
+    let mut ctx = Context::new();
+    let (ids, analyzed) = parse_files(&mut ctx, files);
+    let modules: HashMap<SourceId, BundleModule> = ids
+        .iter()
+        .zip(&analyzed)
+        .map(|(&id, module)| {
+            (
+                id,
+                BundleModule {
+                    ast: module.ast.clone(),
+                    sem: &module.sem,
+                    source_map: None,
+                    comments: Some(&module.comments),
+                },
+            )
+        })
+        .collect();
+    let pure: Vec<SourceId> = ids
+        .iter()
+        .zip(files)
+        .filter(|(_, (name, _))| pure.contains(name))
+        .map(|(&id, _)| id)
+        .collect();
+    let shaken = tree_shake(&mut ctx, ids[0], &modules, |id| !pure.contains(&id));
+    let modules: HashMap<SourceId, BundleModule> = shaken
+        .into_iter()
+        .map(|(id, ast)| {
+            (
+                id,
+                BundleModule {
+                    ast,
+                    comments: None,
+                    ..modules[&id]
+                },
+            )
+        })
+        .collect();
+    bundle_scope_hoisted(&mut ctx, ids[0], &modules, Default::default())
+        .unwrap()
+        .code
+}
+
+/// A parsed module with its semantic information and comments.
+struct AnalyzedModule {
+    ast: NodeRc,
+    sem: SemContext,
+    comments: CommentMap,
+}
+
+/// Parse every `(name, source)` of `files` as a module.
+fn parse_files(ctx: &mut Context, files: &[(&str, &str)]) -> (Vec<SourceId>, Vec<AnalyzedModule>) {
+    let ids: Vec<SourceId> = files
+        .iter()
+        .map(|(name, src)| {
+            ctx.sm_mut()
+                .add_source(*name, NullTerminatedBuf::from_str_check(src))
+        })
+        .collect();
+    let resolver = DefaultResolver::new(ctx.sm());
+    let mut analyzed = vec![];
+    for &id in &ids {
+        let buf = ctx.sm().source_buffer_rc(id);
+        let parsed = hparser::ParsedJS::parse(
+            hparser::ParserFlags {
+                store_comments: true,
+                ..Default::default()
+            },
+            &buf,
+        );
+        let gc = GCLock::new(ctx);
+        let program = parsed.to_ast(&gc, id).unwrap();
+        let module = builder::Module::build_template(
+            &gc,
+            template::Module {
+                metadata: TemplateMetadata {
+                    phantom: Default::default(),
+                    range: *program.range(),
+                },
+                body: node_cast!(Node::Program, program).body,
+            },
+        );
+        let sem = sema::resolve_module(&gc, module, id, &resolver);
+        let comments = CommentMap::attach(&gc, module, parsed.comments(id));
+        analyzed.push(AnalyzedModule {
+            ast: NodeRc::from_node(&gc, module),
+            sem,
+            comments,
+        });
+    }
+    (ids, analyzed)
+}
+
+#[test]
+fn test_bundle_require() {
+    let (result, ids) = bundle_files(&[
+        ("/app/a.js", "const b = require('./b');\nprint(b.x);\n"),
+        ("/app/b.js", "exports.x = require('fs');\n"),
+        ("/app/unused.js", "print('unused');\n"),
+    ]);
+    assert_eq!(result.graph.modules, vec![ids[0], ids[1]]);
+    assert_eq!(result.graph.deps[&ids[0]], vec![ids[1]]);
+    let code = &result.code;
+    assert!(code.starts_with("var __d, __r;\n"));
+    assert!(code.contains(
+        "__d(function (global, require, module, exports) {\n\
+         const b = require(1);\n\
+         print(b.x);\n\
+         }, 0, [1], \"/app/a.js\");\n"
+    ));
+    // Unresolved modules are required by name, with the `require` of the host.
+    assert!(code.contains("exports.x = require('fs');\n}, 1, [], \"/app/b.js\");\n"));
+    assert!(code.contains(
+        "if (typeof id === 'string' && typeof require === 'function') {\n        return require(id);"
+    ));
+    assert!(!code.contains("unused"));
+    assert!(code.ends_with("__r(0);\n"));
+}
+
+#[test]
+fn test_bundle_imports() {
+    let (result, ids) = bundle_files(&[
+        (
+            "/app/a.js",
+            "import def, {x as y} from './b';\n\
+             import * as ns from './c';\n\
+             import cjs from './c';\n\
+             print(def, y(), ns, cjs, {y});\n",
+        ),
+        (
+            "/app/b.js",
+            "export default function () {}\nexport function x() {}\n",
+        ),
+        ("/app/c.js", "module.exports = 1;\n"),
+    ]);
+    assert_eq!(result.graph.modules, ids);
+    let code = &result.code;
+    // Dependencies run first, in order.
+    assert!(code.contains("'use strict';\nrequire(1);\nrequire(2);\nrequire(2);\n"));
+    // References read the live exports, and CommonJS modules are their own default export.
+    assert!(code.contains(
+        "print(require(1).default, (0, require(1).x)(), require(2), require(2), {y: require(1).x});"
+    ));
+    // Exports are getters.
+    assert!(code.contains("require.defineExports(exports, {"));
+    assert!(code.contains("return _default;"));
+    assert!(code.contains("return x;"));
+    assert!(code.contains("function _default()"));
+    assert!(code.contains("module.exports = 1;\n}, 2, [], \"/app/c.js\");"));
+}
+
+#[test]
+fn test_bundle_reexports() {
+    let (result, ids) = bundle_files(&[
+        (
+            "/app/a.js",
+            "export * from './b';\nexport {y as z} from './b';\nconst w = 1;\nexport {w};\n",
+        ),
+        ("/app/b.js", "export const y = 1, _default = 2;\n"),
+    ]);
+    assert_eq!(result.graph.deps[&ids[0]], vec![ids[1]]);
+    let code = &result.code;
+    assert!(code.contains("require.exportAll(exports, 1);\nrequire(1);\n"));
+    assert!(code.contains("return require(1).y;"));
+    assert!(code.contains("return w;"));
+    assert!(code.contains("return _default;"));
+}
+
+#[test]
+fn test_bundle_sourcemap() {
+    let (result, _) = bundle_files(&[
+        ("/app/a.js", "import {x} from './b';\nprint(x);\n"),
+        ("/app/b.js", "export const x = 1;\n"),
+    ]);
+    let map = &result.source_map.map;
+    let sources: Vec<&str> = map.sources().collect();
+    assert_eq!(sources, vec!["/app/a.js", "/app/b.js"]);
+
+    let lines: Vec<&str> = result.code.lines().collect();
+    let print = lines
+        .iter()
+        .position(|line| *line == "print(require(1).x);")
+        .unwrap() as u32;
+    let token = map.lookup_token(print, 0).unwrap();
+    assert_eq!(token.get_source(), Some("/app/a.js"));
+    assert_eq!(token.get_src(), (1, 0));
+    let token = map.lookup_token(print, 6).unwrap();
+    assert_eq!(token.get_source(), Some("/app/a.js"));
+    assert_eq!(token.get_src(), (1, 6));
+
+    let export = lines
+        .iter()
+        .position(|line| *line == "const x = 1;")
+        .unwrap() as u32;
+    let token = map.lookup_token(export, 6).unwrap();
+    assert_eq!(token.get_source(), Some("/app/b.js"));
+    assert_eq!(token.get_src(), (0, 13));
+}
+
+#[test]
+fn test_scope_hoist_conflicts() {
+    let code = hoist_files(&[
+        (
+            "/app/a.js",
+            "import {x, f} from './b';\nconst y = 1;\nprint(x, f(), y);\n",
+        ),
+        (
+            "/app/b.js",
+            "const y = 2;\nexport const x = y;\nexport function f() {}\n",
+        ),
+    ]);
+    // Dependencies come first and keep their names.
+    assert!(code.starts_with("const y = 2;\nconst x = y;\nfunction f() {}\n"));
+    assert!(code.ends_with("const y$1 = 1;\nprint(x, f(), y$1);\n"));
+    assert!(!code.contains("__d"));
+}
+
+#[test]
+fn test_scope_hoist_shadowing() {
+    let code = hoist_files(&[
+        (
+            "/app/a.js",
+            "import {x as z} from './b';\nfunction f(x) {\n  return x;\n}\nprint(f(z), z);\n",
+        ),
+        ("/app/b.js", "export const x = 2;\n"),
+    ]);
+    // `x` would be shadowed by the parameter of `f`.
+    assert!(code.starts_with("const x$1 = 2;\n"));
+    assert!(code.contains("return x;"));
+    assert!(code.ends_with("print(f(x$1), x$1);\n"));
+}
+
+#[test]
+fn test_scope_hoist_cycle() {
+    let code = hoist_files(&[
+        (
+            "/app/a.js",
+            "import {count as n, inc} from './b';\ninc();\nprint(n);\n",
+        ),
+        (
+            "/app/b.js",
+            "import './a';\nexport let count = 0;\nexport function inc() {\n  count++;\n}\n",
+        ),
+    ]);
+    // Imported bindings are live references to the exporting declarations.
+    assert!(code.starts_with("let count = 0;\n"));
+    assert!(code.ends_with("inc();\nprint(count);\n"));
+    assert!(!code.contains("import"));
+}
+
+#[test]
+fn test_scope_hoist_interop() {
+    let code = hoist_files(&[
+        (
+            "/app/a.js",
+            "import * as ns from './b';\n\
+             import def from './b';\n\
+             import cjs from './c';\n\
+             import {readFile} from 'fs';\n\
+             export {def as main};\n\
+             print(ns, {def}, cjs, readFile);\n",
+        ),
+        ("/app/b.js", "export default 1;\nexport const y = 2;\n"),
+        ("/app/c.js", "module.exports = 3;\n"),
+    ]);
+    // External imports go first, then the registry with the CommonJS modules.
+    assert!(code.starts_with("import {readFile} from 'fs';\nvar __d, __r;\n"));
+    assert!(code.contains("module.exports = 3;\n}, 2, [], \"/app/c.js\");\n"));
+    assert!(code.contains("var b_default = 1;\n"));
+    assert!(code.contains("var b = Object.freeze({"));
+    assert!(code.contains("return b_default;"));
+    assert!(code.contains("var cjs = __r(2);\n"));
+    assert!(code.contains("print(b, {def: b_default}, cjs, readFile);\n"));
+    assert!(code.ends_with("export {b_default as main};\n"));
+}
+
+#[test]
+fn test_tree_shake_unused_exports() {
+    let code = shake_files(
+        &[
+            ("/app/a.js", "import {used} from './b';\nprint(used());\n"),
+            (
+                "/app/b.js",
+                "const helper = 1;\n\
+                 const other = 2;\n\
+                 export function used() {\n  return helper;\n}\n\
+                 export function unused() {\n  return other;\n}\n",
+            ),
+        ],
+        &[],
+    );
+    assert_eq!(
+        code,
+        "const helper = 1;\nfunction used() {\n  return helper;\n}\nprint(used());\n"
+    );
+}
+
+#[test]
+fn test_tree_shake_side_effects() {
+    let code = shake_files(
+        &[
+            (
+                "/app/a.js",
+                "import {x} from './b';\nimport './c';\nimport {y} from './d';\nprint(1);\n",
+            ),
+            ("/app/b.js", "print('b');\nexport const x = 1;\n"),
+            ("/app/c.js", "print('c');\n"),
+            ("/app/d.js", "export const y = 2;\nprint('d');\n"),
+        ],
+        &["/app/c.js", "/app/d.js"],
+    );
+    // Modules with side effects are kept even if none of their exports are used.
+    assert!(code.starts_with("print('b');\n"));
+    assert!(!code.contains("x = 1"));
+    // Unused modules without side effects are removed.
+    assert!(!code.contains("'c'"));
+    assert!(!code.contains("'d'"));
+    assert!(code.ends_with("print(1);\n"));
+}
+
+#[test]
+fn test_tree_shake_pure_annotations() {
+    let code = shake_files(
+        &[
+            ("/app/a.js", "import {a} from './b';\nprint(a);\n"),
+            (
+                "/app/b.js",
+                "export const a = 1;\n\
+                 export const b = /*#__PURE__*/ make();\n\
+                 export const c = make();\n\
+                 /*#__PURE__*/ make();\n\
+                 const d = /*@__PURE__*/ new Thing(make());\n\
+                 class E {\n  static e = make();\n}\n\
+                 class F {}\n",
+            ),
+        ],
+        &[],
+    );
+    assert!(!code.contains("const b"));
+    // Unused exports with side effects are still run.
+    assert!(code.contains("const c = make();\n"));
+    // Pure calls with arguments which have side effects are kept.
+    assert!(code.contains("const d = new Thing(make());\n"));
+    assert!(code.contains("class E"));
+    assert!(!code.contains("class F"));
+    assert_eq!(code.matches("make()").count(), 3);
+}
+
+#[test]
+fn test_tree_shake_reexports() {
+    let code = shake_files(
+        &[
+            (
+                "/app/a.js",
+                "import {x} from './b';\nimport * as ns from './c';\nprint(x, ns);\n",
+            ),
+            (
+                "/app/b.js",
+                "export * from './d';\nexport {z} from './e';\n",
+            ),
+            ("/app/c.js", "export const k = 4;\nexport default 5;\n"),
+            ("/app/d.js", "export const x = 1;\nexport const w = 2;\n"),
+            ("/app/e.js", "export const z = 3;\n"),
+        ],
+        &["/app/e.js"],
+    );
+    assert!(code.contains("const x = 1;\n"));
+    assert!(!code.contains("const w"));
+    assert!(!code.contains("const z"));
+    // All the exports of a namespace object are used.
+    assert!(code.contains("const k = 4;\n"));
+    assert!(code.contains("var c_default = 5;\n"));
+}
+
+#[test]
+fn test_package_side_effects() {
+    let dir = std::env::temp_dir().join(format!("juno-side-effects-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    std::fs::write(
+        dir.join("package.json"),
+        r#"{"sideEffects": ["./src/polyfill.js", "*.css", "lib/**/init-?.js"]}"#,
+    )
+    .unwrap();
+    assert!(package_side_effects(&dir.join("src/polyfill.js")));
+    assert!(!package_side_effects(&dir.join("src/index.js")));
+    assert!(package_side_effects(&dir.join("src/style/main.css")));
+    assert!(package_side_effects(&dir.join("lib/init-a.js")));
+    assert!(package_side_effects(&dir.join("lib/a/b/init-b.js")));
+    assert!(!package_side_effects(&dir.join("lib/a/init-ab.js")));
+
+    // The closest package.json applies.
+    std::fs::write(dir.join("lib/package.json"), r#"{"sideEffects": false}"#).unwrap();
+    assert!(!package_side_effects(&dir.join("lib/init-a.js")));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
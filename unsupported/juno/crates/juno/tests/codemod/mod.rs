@@ -0,0 +1,231 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use juno::ast::*;
+use juno::codemod::*;
+use juno::comments::CommentMap;
+use juno::gen_js;
+use juno::hparser;
+use juno_support::NullTerminatedBuf;
+
+/// Parse `src` with its comments, transform it with `pass` and return the rewritten source.
+fn rewrite<V>(src: &str, pass: &mut V) -> String
+where
+    V: for<'gc> VisitorMut<'gc>,
+{
+    let mut ctx = Context::new();
+    let file_id = ctx
+        .sm_mut()
+        .add_source("<input>", NullTerminatedBuf::from_str_check(src));
+    let buf = ctx.sm().source_buffer_rc(file_id);
+    let parsed = hparser::ParsedJS::parse(
+        hparser::ParserFlags {
+            store_comments: true,
+            ..Default::default()
+        },
+        &buf,
+    );
+    let gc = GCLock::new(&mut ctx);
+    let original = parsed.to_ast(&gc, file_id).unwrap();
+    let comments = CommentMap::attach(&gc, original, parsed.comments(file_id));
+    let transformed = original.visit_mut(&gc, pass, None).unwrap();
+    let edits = compute_edits(
+        &gc,
+        src,
+        original,
+        transformed,
+        gen_js::Opt {
+            comments: Some(&comments),
+            ..Default::default()
+        },
+    );
+    apply_edits(src, &edits)
+}
+
+/// Replace every numeric literal `1` with `2`.
+struct OneToTwo {}
+
+impl<'gc> VisitorMut<'gc> for OneToTwo {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        if let Node::NumericLiteral(lit @ NumericLiteral { value, .. }) = node {
+            if *value == 1.0 {
+                let mut builder = builder::NumericLiteral::from_node(lit);
+                builder.value(2.0);
+                return node.replace_with_new(builder.into(), gc, self);
+            }
+        }
+        node.visit_children_mut(gc, self)
+    }
+}
+
+/// Remove calls to `debug` and duplicate calls to `log`.
+struct Statements {}
+
+impl<'gc> VisitorMut<'gc> for Statements {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        if let Node::ExpressionStatement(ExpressionStatement {
+            expression:
+                Node::CallExpression(CallExpression {
+                    callee: Node::Identifier(Identifier { name, .. }),
+                    ..
+                }),
+            ..
+        }) = node
+        {
+            match gc.str(*name) {
+                "debug" => return TransformResult::Removed,
+                "log" => return TransformResult::Expanded(vec![node, node]),
+                _ => {}
+            }
+        }
+        node.visit_children_mut(gc, self)
+    }
+}
+
+/// Replace `inc(x)` with `x + 1`.
+struct Inc {}
+
+impl<'gc> VisitorMut<'gc> for Inc {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock,
+        node: &'gc Node<'gc>,
+        _path: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        if let Node::CallExpression(CallExpression {
+            callee: Node::Identifier(Identifier { name, .. }),
+            arguments,
+            ..
+        }) = node
+        {
+            if gc.str(*name) == "inc" && arguments.len() == 1 {
+                return TransformResult::Changed(builder::BinaryExpression::build_template(
+                    gc,
+                    template::BinaryExpression {
+                        metadata: (*node.range()).into(),
+                        left: arguments.head().unwrap(),
+                        right: builder::NumericLiteral::build_template(
+                            gc,
+                            template::NumericLiteral {
+                                metadata: Default::default(),
+                                value: 1.0,
+                            },
+                        ),
+                        operator: BinaryExpressionOperator::Plus,
+                    },
+                ));
+            }
+        }
+        node.visit_children_mut(gc, self)
+    }
+}
+
+#[test]
+fn test_unchanged() {
+    let src = "/* a */ foo( 3 ,4 ) ;\n";
+    let mut ctx = Context::new();
+    let ast = hparser::parse(&mut ctx, src).unwrap();
+    let gc = GCLock::new(&mut ctx);
+    let node = ast.node(&gc);
+    assert_eq!(
+        compute_edits(&gc, src, node, node, Default::default()),
+        vec![]
+    );
+}
+
+#[test]
+fn test_literals() {
+    assert_eq!(
+        rewrite(
+            "// Header.\nlet x = 1; /* keep */\nfoo(  1 ,3,\n    1);\n",
+            &mut OneToTwo {}
+        ),
+        "// Header.\nlet x = 2; /* keep */\nfoo(  2 ,3,\n    2);\n",
+    );
+}
+
+#[test]
+fn test_statements() {
+    assert_eq!(
+        rewrite(
+            "function f() {\n  a();\n  debug(1);\n  log(2); // log\n  b();\n}\n",
+            &mut Statements {}
+        ),
+        "function f() {\n  a();\n  log(2); // log\n  log(2);\n  b();\n}\n",
+    );
+    assert_eq!(
+        rewrite("a(); debug(); b();\n", &mut Statements {}),
+        "a();  b();\n",
+    );
+}
+
+#[test]
+fn test_parens() {
+    assert_eq!(
+        rewrite("y = 2 * inc(x); // x\nz = inc(x);\n", &mut Inc {}),
+        "y = 2 * (x + 1); // x\nz = (x + 1);\n",
+    );
+}
+
+#[test]
+fn test_comments() {
+    // Comments inside regenerated nodes are kept, the ones around them aren't repeated.
+    assert_eq!(
+        rewrite("y = /* y */ inc(/* x */ x); // z\n", &mut Inc {}),
+        "y = /* y */ /* x */ x + 1; // z\n",
+    );
+    assert_eq!(
+        rewrite("foo(/* one */ 1, // one\n    3);\n", &mut OneToTwo {}),
+        "foo(/* one */ 2, // one\n    3);\n",
+    );
+}
+
+#[test]
+fn test_unified_diff() {
+    assert_eq!(unified_diff("a.js", "b.js", "x\n", "x\n"), "");
+    assert_eq!(
+        unified_diff("a.js", "b.js", "a\nb\nc\n", "a\nB\nc\n"),
+        "--- a.js\n+++ b.js\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n",
+    );
+    assert_eq!(
+        unified_diff("a.js", "a.js", "a", "b"),
+        "--- a.js\n+++ a.js\n@@ -1 +1 @@\n-a\n\\ No newline at end of file\n+b\n\\ No newline at end of file\n",
+    );
+    assert_eq!(
+        unified_diff("a.js", "a.js", "", "x\n"),
+        "--- a.js\n+++ a.js\n@@ -0,0 +1 @@\n+x\n",
+    );
+
+    // Changes far apart are reported in separate hunks.
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let new = "X\n2\n3\n4\n5\n6\n7\n8\n9\nY\n";
+    assert_eq!(
+        unified_diff("a.js", "a.js", old, new),
+        "--- a.js\n+++ a.js\n\
+         @@ -1,4 +1,4 @@\n-1\n+X\n 2\n 3\n 4\n\
+         @@ -7,4 +7,4 @@\n 7\n 8\n 9\n-10\n+Y\n",
+    );
+
+    // Large files which are too different are replaced whole, without a search
+    // proportional to their size at every step.
+    let old: String = (0..50000).map(|i| format!("a{}\n", i)).collect();
+    let new: String = (0..50000).map(|i| format!("b{}\n", i)).collect();
+    let diff = unified_diff("a.js", "a.js", &old, &new);
+    assert!(diff.starts_with("--- a.js\n+++ a.js\n@@ -1,50000 +1,50000 @@\n-a0\n"));
+    assert!(diff.contains("\n-a49999\n+b0\n"));
+    assert!(diff.ends_with("\n+b49999\n"));
+}
@@ -0,0 +1,199 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use juno::ast::*;
+use juno::dependency_graph::DependencyGraph;
+use juno::hparser;
+use juno::resolve_dependency::DefaultResolver;
+use juno::resolve_dependency::DependencyKind;
+use juno::sema;
+use juno::sema::SemContext;
+use juno_support::NullTerminatedBuf;
+use juno_support::source_manager::SourceId;
+use serde_json::json;
+
+/// Parse and analyze every `(name, source)` of `files` as a module, and build the
+/// graph of their dependencies.
+fn graph_files(ctx: &mut Context, files: &[(&str, &str)]) -> DependencyGraph {
+    let ids: Vec<SourceId> = files
+        .iter()
+        .map(|(name, src)| {
+            ctx.sm_mut()
+                .add_source(*name, NullTerminatedBuf::from_str_check(src))
+        })
+        .collect();
+    let resolver = DefaultResolver::new(ctx.sm());
+    let gc = GCLock::new(ctx);
+    let sems: Vec<SemContext> = ids
+        .iter()
+        .map(|&id| {
+            let buf = gc.sm().source_buffer_rc(id);
+            let parsed = hparser::ParsedJS::parse(Default::default(), &buf);
+            let program = parsed.to_ast(&gc, id).unwrap();
+            let module = builder::Module::build_template(
+                &gc,
+                template::Module {
+                    metadata: TemplateMetadata {
+                        phantom: Default::default(),
+                        range: *program.range(),
+                    },
+                    body: node_cast!(Node::Program, program).body,
+                },
+            );
+            sema::resolve_module(&gc, module, id, &resolver)
+        })
+        .collect();
+    DependencyGraph::build(&gc, ids.iter().copied().zip(&sems))
+}
+
+const FILES: &[(&str, &str)] = &[
+    (
+        "/app/a.js",
+        "import {x} from './b';\nconst fs = require('fs');\nexport * from './c';\n",
+    ),
+    ("/app/b.js", "export const x = require('./c');\n"),
+    ("/app/c.js", "export const y = 1;\n"),
+];
+
+#[test]
+fn test_dependency_graph() {
+    let mut ctx = Context::new();
+    let graph = graph_files(&mut ctx, FILES);
+    assert_eq!(graph.modules, vec![SourceId(0), SourceId(1), SourceId(2)]);
+    let deps: Vec<(u32, Option<u32>, &str, DependencyKind)> = graph
+        .dependencies
+        .iter()
+        .map(|dep| {
+            (
+                dep.from.0,
+                dep.to.map(|to| to.0),
+                dep.specifier.as_str(),
+                dep.kind,
+            )
+        })
+        .collect();
+    assert_eq!(
+        deps,
+        vec![
+            (0, Some(1), "./b", DependencyKind::Import),
+            (0, None, "fs", DependencyKind::Require),
+            (0, Some(2), "./c", DependencyKind::Import),
+            (1, Some(2), "./c", DependencyKind::Require),
+        ]
+    );
+    let fs = &graph.dependencies[1];
+    assert_eq!(fs.range.file, SourceId(0));
+    assert_eq!((fs.range.start.line, fs.range.start.col), (2, 12));
+}
+
+#[test]
+fn test_dependency_graph_json() {
+    let mut ctx = Context::new();
+    let graph = graph_files(&mut ctx, &FILES[..2]);
+    let mut out = vec![];
+    graph.write_json(ctx.sm(), &mut out, Pretty::No).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(
+        value["modules"],
+        json!([
+            {"id": 0, "name": "/app/a.js"},
+            {"id": 1, "name": "/app/b.js"},
+        ])
+    );
+    let fs = &value["dependencies"][1];
+    assert_eq!(fs["from"], json!(0));
+    assert_eq!(fs["to"], json!(null));
+    assert_eq!(fs["specifier"], json!("fs"));
+    assert_eq!(fs["kind"], json!("require"));
+    assert_eq!(fs["range"]["start"], json!({"line": 2, "col": 12}));
+    // `./c` isn't one of the modules.
+    assert_eq!(value["dependencies"].as_array().unwrap().len(), 4);
+    assert_eq!(value["dependencies"][2]["to"], json!(null));
+
+    // Names and specifiers are escaped.
+    let mut ctx = Context::new();
+    let graph = graph_files(&mut ctx, &[("C:\\app\\a.js", "require('\"q\"');\n")]);
+    let mut out = vec![];
+    graph.write_json(ctx.sm(), &mut out, Pretty::No).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(value["modules"][0]["name"], json!("C:\\app\\a.js"));
+    assert_eq!(value["dependencies"][0]["specifier"], json!("\"q\""));
+}
+
+#[test]
+fn test_dependency_graph_dot() {
+    let mut ctx = Context::new();
+    let graph = graph_files(
+        &mut ctx,
+        &[
+            ("/app/a.js", "import './b';\nrequire('fs');\n"),
+            ("/app/b.js", "require('fs');\n"),
+        ],
+    );
+    let mut out = vec![];
+    graph.write_dot(ctx.sm(), &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "digraph dependencies {\n\
+         \x20 m0 [label=\"/app/a.js\"];\n\
+         \x20 m1 [label=\"/app/b.js\"];\n\
+         \x20 m0 -> m1 [label=\"import 1:1\"];\n\
+         \x20 u0 [label=\"fs\", shape=box, style=dashed];\n\
+         \x20 m0 -> u0 [label=\"require 2:1\", style=dashed];\n\
+         \x20 m1 -> u0 [label=\"require 1:1\", style=dashed];\n\
+         }\n"
+    );
+}
+
+#[test]
+fn test_dependency_cycles() {
+    let mut ctx = Context::new();
+    let graph = graph_files(
+        &mut ctx,
+        &[
+            ("/app/a.js", "import './b';\n"),
+            ("/app/b.js", "import './c';\n"),
+            (
+                "/app/c.js",
+                "import './a';\nrequire('./b');\nrequire('./b');\n",
+            ),
+            ("/app/d.js", "import './a';\n"),
+        ],
+    );
+    let cycles: Vec<Vec<(u32, u32)>> = graph
+        .cycles()
+        .iter()
+        .map(|cycle| {
+            cycle
+                .iter()
+                .map(|dep| (dep.from.0, dep.to.unwrap().0))
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        cycles,
+        vec![vec![(0, 1), (1, 2), (2, 0)], vec![(1, 2), (2, 1)]]
+    );
+
+    assert_eq!(ctx.sm().num_warnings(), 0);
+    graph.report_cycles(ctx.sm());
+    assert_eq!(ctx.sm().num_warnings(), 2);
+}
+
+#[test]
+fn test_unresolved_errors() {
+    let mut ctx = Context::new();
+    graph_files(&mut ctx, &[("/app/a.js", "require('./missing');\n")]);
+    assert_eq!(ctx.sm().num_errors(), 0);
+    assert_eq!(ctx.sm().num_warnings(), 1);
+
+    let mut ctx = Context::new();
+    ctx.error_unresolved = true;
+    graph_files(&mut ctx, &[("/app/a.js", "require('./missing');\n")]);
+    assert_eq!(ctx.sm().num_errors(), 1);
+    assert_eq!(ctx.sm().num_warnings(), 0);
+}
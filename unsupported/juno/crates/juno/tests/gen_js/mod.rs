@@ -6,9 +6,11 @@
  */
 
 use juno::ast::*;
+use juno::comments::CommentMap;
 use juno::gen_js;
 use juno::hparser;
 use juno::sourcemap::merge_sourcemaps;
+use juno_support::NullTerminatedBuf;
 
 fn do_gen<'ast>(ctx: &mut Context<'ast>, node: &NodeRc, pretty: gen_js::Pretty) -> String {
     use juno::gen_js::*;
@@ -26,6 +28,60 @@ fn do_gen<'ast>(ctx: &mut Context<'ast>, node: &NodeRc, pretty: gen_js::Pretty)
     String::from_utf8(out).expect("Invalid UTF-8 output in test")
 }
 
+/// Generate `src` with its comments attached.
+fn gen_comments(src: &str, pretty: gen_js::Pretty, keep_comments: gen_js::KeepComments) -> String {
+    let mut ctx = Context::new();
+    let file_id = ctx
+        .sm_mut()
+        .add_source("<input>", NullTerminatedBuf::from_str_check(src));
+    let buf = ctx.sm().source_buffer_rc(file_id);
+    let parsed = hparser::ParsedJS::parse(
+        hparser::ParserFlags {
+            store_comments: true,
+            ..Default::default()
+        },
+        &buf,
+    );
+    let (ast, comments) = {
+        let gc = GCLock::new(&mut ctx);
+        let root = parsed.to_ast(&gc, file_id).unwrap();
+        let comments = CommentMap::attach(&gc, root, parsed.comments(file_id));
+        (NodeRc::from_node(&gc, root), comments)
+    };
+    let mut out: Vec<u8> = vec![];
+    gen_js::generate(
+        &mut out,
+        &mut ctx,
+        &ast,
+        gen_js::Opt {
+            pretty,
+            comments: Some(&comments),
+            keep_comments,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    String::from_utf8(out).expect("Invalid UTF-8 output in test")
+}
+
+/// Parse `src` and generate it formatted with `layout`.
+fn gen_layout(src: &str, layout: gen_js::Layout) -> String {
+    let mut ctx = Context::new();
+    let ast = hparser::parse(&mut ctx, src).unwrap();
+    let mut out: Vec<u8> = vec![];
+    gen_js::generate(
+        &mut out,
+        &mut ctx,
+        &ast,
+        gen_js::Opt {
+            layout: Some(layout),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    String::from_utf8(out).expect("Invalid UTF-8 output in test")
+}
+
 fn test_roundtrip_with_flags(flags: hparser::ParserFlags, src1: &str) {
     use juno::ast::*;
 
@@ -644,6 +700,97 @@ fn test_jsx() {
     );
 }
 
+#[test]
+fn test_layout() {
+    use juno::gen_js::{Layout, TrailingCommas};
+    let narrow = Layout {
+        print_width: 20,
+        ..Default::default()
+    };
+
+    let call = "foo(aaaaaaaaaa, bbbbbbbbbb, cccccccccc);";
+    assert_eq!(
+        gen_layout(call, Default::default()),
+        "foo(aaaaaaaaaa, bbbbbbbbbb, cccccccccc);\n"
+    );
+    assert_eq!(
+        gen_layout(call, narrow),
+        "foo(\n  aaaaaaaaaa,\n  bbbbbbbbbb,\n  cccccccccc,\n);\n"
+    );
+    assert_eq!(
+        gen_layout(
+            call,
+            Layout {
+                trailing_commas: TrailingCommas::Es5,
+                ..narrow
+            }
+        ),
+        "foo(\n  aaaaaaaaaa,\n  bbbbbbbbbb,\n  cccccccccc\n);\n"
+    );
+
+    // The last function argument is hugged.
+    assert_eq!(
+        gen_layout("foo(a, function() { return 1; })", narrow),
+        "foo(a, function () {\n  return 1;\n});\n"
+    );
+
+    assert_eq!(
+        gen_layout("xxxxxxxxxx = aaaaaaaaaa + bbbbbbbbbb;", narrow),
+        "xxxxxxxxxx = aaaaaaaaaa +\n  bbbbbbbbbb;\n"
+    );
+
+    // Objects stay expanded if the first property was on its own line.
+    assert_eq!(
+        gen_layout("x = {a: 1, b: 2};", Default::default()),
+        "x = { a: 1, b: 2 };\n"
+    );
+    assert_eq!(
+        gen_layout("x = {\n  a: 1, b: 2};", Default::default()),
+        "x = {\n  a: 1,\n  b: 2,\n};\n"
+    );
+    assert_eq!(
+        gen_layout(
+            "x = {a: 1};",
+            Layout {
+                bracket_spacing: false,
+                ..Default::default()
+            }
+        ),
+        "x = {a: 1};\n"
+    );
+
+    assert_eq!(
+        gen_layout("a();\n\n\nb();\nc();", Default::default()),
+        "a();\n\nb();\nc();\n"
+    );
+    assert_eq!(
+        gen_layout("f(x => x);", Default::default()),
+        "f((x) => x);\n"
+    );
+    assert_eq!(
+        gen_layout("import {a as a, b as c} from 'x';", Default::default()),
+        "import { a, b as c } from 'x';\n"
+    );
+
+    // Newlines in template literals are printed as is and don't break the call.
+    assert_eq!(
+        gen_layout("foo(`a  \n b`, c);", Default::default()),
+        "foo(`a  \n b`, c);\n"
+    );
+}
+
+#[test]
+fn test_layout_no_semicolons() {
+    let layout = gen_js::Layout {
+        semicolons: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        gen_layout("a();\n[1, 2].forEach(f);\nlet x = 1;\n(x => x)();", layout),
+        "a()\n;[1, 2].forEach(f)\nlet x = 1\n;((x) => x)()\n"
+    );
+}
+
 #[test]
 fn test_sourcemap() {
     use juno::gen_js::*;
@@ -658,7 +805,7 @@ fn test_sourcemap() {
   return 1;
 }\n"
     );
-    assert_eq!(sourcemap.get_token_count(), 4);
+    assert_eq!(sourcemap.get_token_count(), 5);
 
     assert_eq!(
         sourcemap.get_token(0).unwrap().get_raw_token(),
@@ -680,12 +827,25 @@ fn test_sourcemap() {
             src_line: 0,
             src_col: 9,
             src_id: 0,
-            name_id: !0,
+            name_id: 0,
         }
     );
+    assert_eq!(sourcemap.get_token(1).unwrap().get_name(), Some("foo"));
 
     assert_eq!(
         sourcemap.get_token(2).unwrap().get_raw_token(),
+        sourcemap::RawToken {
+            dst_line: 0,
+            dst_col: 15,
+            src_line: 0,
+            src_col: 15,
+            src_id: 0,
+            name_id: !0,
+        }
+    );
+
+    assert_eq!(
+        sourcemap.get_token(3).unwrap().get_raw_token(),
         sourcemap::RawToken {
             dst_line: 1,
             dst_col: 2,
@@ -697,7 +857,7 @@ fn test_sourcemap() {
     );
 
     assert_eq!(
-        sourcemap.get_token(3).unwrap().get_raw_token(),
+        sourcemap.get_token(4).unwrap().get_raw_token(),
         sourcemap::RawToken {
             dst_line: 1,
             dst_col: 9,
@@ -709,6 +869,91 @@ fn test_sourcemap() {
     );
 }
 
+/// Generate `src` with `opt` and check that the source map survives serialization
+/// and maps every identifier in the output to the same identifier in `src`.
+fn check_sourcemap_names(src: &str, opt: gen_js::Opt, num_identifiers: usize) {
+    let mut ctx = Context::new();
+    let ast = hparser::parse(&mut ctx, src).unwrap();
+    let mut out: Vec<u8> = vec![];
+    let generated = gen_js::generate(&mut out, &mut ctx, &ast, opt).unwrap();
+    let output = String::from_utf8(out).expect("Invalid UTF-8 output in test");
+
+    let mut json: Vec<u8> = vec![];
+    generated.to_writer(&mut json).unwrap();
+    let sourcemap = sourcemap::SourceMap::from_slice(&json).unwrap();
+    assert_eq!(sourcemap.get_token_count(), generated.get_token_count());
+
+    let text_at = |text: &'_ str, (line, col): (u32, u32)| -> String {
+        text.lines().nth(line as usize).unwrap()[col as usize..].to_string()
+    };
+    let mut named = 0;
+    for token in sourcemap.tokens() {
+        assert_eq!(token.get_src_id(), 0);
+        // Every mapping must be within the source and output text.
+        let src_text = text_at(src, token.get_src());
+        let dst_text = text_at(&output, token.get_dst());
+        if let Some(name) = token.get_name() {
+            assert!(src_text.starts_with(name), "{} at {:?}", name, token);
+            assert!(dst_text.starts_with(name), "{} at {:?}", name, token);
+            named += 1;
+        }
+    }
+    assert_eq!(named, num_identifiers);
+}
+
+#[test]
+fn test_sourcemap_roundtrip() {
+    let src = "function add(first, second) {
+  return first + second;
+}
+
+const total = add(1, 2);
+console.log(total);
+";
+    check_sourcemap_names(src, gen_js::Opt::new(), 10);
+    check_sourcemap_names(
+        src,
+        gen_js::Opt {
+            pretty: gen_js::Pretty::No,
+            ..Default::default()
+        },
+        10,
+    );
+    check_sourcemap_names(
+        src,
+        gen_js::Opt {
+            layout: Some(gen_js::Layout {
+                print_width: 20,
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+        10,
+    );
+}
+
+#[test]
+fn test_sourcemap_sources_content() {
+    let src = "let x = 1;\n";
+    let mut ctx = Context::new();
+    let ast = hparser::parse(&mut ctx, src).unwrap();
+    let mut out: Vec<u8> = vec![];
+    let sourcemap = gen_js::generate(&mut out, &mut ctx, &ast, gen_js::Opt::new()).unwrap();
+    assert_eq!(sourcemap.get_source_contents(0), None);
+
+    let sourcemap = gen_js::generate(
+        &mut out,
+        &mut ctx,
+        &ast,
+        gen_js::Opt {
+            sources_content: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(sourcemap.get_source_contents(0), Some(src));
+}
+
 #[test]
 fn test_sourcemap_merged() {
     use juno::gen_js::*;
@@ -743,3 +988,52 @@ fn test_sourcemap_merged() {
     assert_eq!(input_token.get_source().unwrap(), "test.ts");
     assert_eq!(input_token.get_src(), (1, 17));
 }
+
+#[test]
+fn test_comments() {
+    use gen_js::KeepComments;
+    use gen_js::Pretty;
+    let src = "// Header.
+/* Block */
+let x = 1; // x
+function f() {
+  // Only a comment.
+}
+/*! Legal */
+foo(/* arg */ 2);
+// End.
+";
+    assert_eq!(gen_comments(src, Pretty::Yes, KeepComments::All), src);
+    assert_eq!(
+        gen_comments(src, Pretty::No, KeepComments::All),
+        "// Header.\n/* Block */let x=1;// x\nfunction f(){// Only a comment.\n}\
+         /*! Legal */foo(/* arg */2);// End.\n"
+    );
+    assert_eq!(
+        gen_comments(src, Pretty::No, KeepComments::Legal),
+        "let x=1;function f(){}/*! Legal */foo(2);\n"
+    );
+
+    // Line comments are turned into block comments where a newline could change the meaning.
+    assert_eq!(
+        gen_comments(
+            "function g() {\n  return ( // why\n    1);\n}\n",
+            Pretty::No,
+            KeepComments::All
+        ),
+        "function g(){return /* why*/1;}\n"
+    );
+    // Comments which need a newline are moved before their statement instead.
+    assert_eq!(
+        gen_comments(
+            "function g() {\n  return ( // a */ b\n    1);\n}\n",
+            Pretty::Yes,
+            KeepComments::All
+        ),
+        "function g() {\n  // a */ b\n  return 1;\n}\n"
+    );
+    assert_eq!(
+        gen_comments("throw (/* a\n b */ x);\n", Pretty::No, KeepComments::All),
+        "/* a\n b */\nthrow x;\n"
+    );
+}
@@ -8,4 +8,9 @@
 #[macro_use]
 mod ast;
 
+mod bundle;
+mod codemod;
+mod dependency_graph;
 mod gen_js;
+mod resolve;
+mod sourcemap;
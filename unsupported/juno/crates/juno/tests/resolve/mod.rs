@@ -0,0 +1,454 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use juno::ast::*;
+use juno::resolve_dependency::*;
+use juno_support::NullTerminatedBuf;
+use juno_support::source_manager::SourceId;
+
+/// Write every `(path, contents)` of `files` to a new temporary directory `name`.
+fn write_tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    for (path, contents) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+/// Register an empty source named `path`.
+fn add_file(ctx: &mut Context, path: &Path) -> SourceId {
+    ctx.sm_mut().add_source(
+        path.display().to_string(),
+        NullTerminatedBuf::from_str_check(""),
+    )
+}
+
+/// Resolve `specifier` from `file`, and return the path of the result relative to `dir`.
+fn resolve(
+    ctx: &mut Context,
+    resolver: &NodeResolver,
+    dir: &Path,
+    file: SourceId,
+    specifier: &str,
+    kind: DependencyKind,
+) -> Option<String> {
+    let id = resolver.resolve_dependency(&GCLock::new(ctx), file, specifier, kind)?;
+    resolver.load_pending(ctx.sm_mut());
+    let name = Path::new(ctx.sm().source_name(id));
+    Some(name.strip_prefix(dir).unwrap().display().to_string())
+}
+
+#[test]
+fn test_node_resolve_paths() {
+    let dir = write_tree(
+        "juno-resolve-paths",
+        &[
+            ("src/util.js", "export const x = 1;"),
+            ("src/lib/index.mjs", "export const y = 2;"),
+            ("src/types.ts", "export type T = number;"),
+        ],
+    );
+    let mut ctx = Context::new();
+    let main = add_file(&mut ctx, &dir.join("src/main.js"));
+    let resolver = NodeResolver::new(Default::default());
+    let resolve_main = |ctx: &mut Context, specifier| {
+        resolve(
+            ctx,
+            &resolver,
+            &dir,
+            main,
+            specifier,
+            DependencyKind::Import,
+        )
+    };
+
+    assert_eq!(resolve_main(&mut ctx, "./util").unwrap(), "src/util.js");
+    assert_eq!(resolve_main(&mut ctx, "./util.js").unwrap(), "src/util.js");
+    assert_eq!(
+        resolve_main(&mut ctx, "../src/lib").unwrap(),
+        "src/lib/index.mjs"
+    );
+    assert_eq!(resolve_main(&mut ctx, "./types"), None);
+    assert_eq!(resolve_main(&mut ctx, "./missing"), None);
+    let missing = dir.join("src/missing").display().to_string();
+    assert_eq!(
+        resolver.searched_locations(
+            &GCLock::new(&mut ctx),
+            main,
+            "./missing",
+            DependencyKind::Import
+        ),
+        [
+            "",
+            ".js",
+            ".mjs",
+            ".cjs",
+            ".jsx",
+            "/index.js",
+            "/index.mjs",
+            "/index.cjs",
+            "/index.jsx"
+        ]
+        .iter()
+        .map(|suffix| format!("{}{}", missing, suffix))
+        .collect::<Vec<_>>()
+    );
+
+    // Files are only loaded once.
+    assert_eq!(ctx.sm().num_sources(), 3);
+    assert_eq!(resolve_main(&mut ctx, "./util.js").unwrap(), "src/util.js");
+    assert_eq!(ctx.sm().num_sources(), 3);
+
+    let resolver = NodeResolver::new(NodeResolverOptions {
+        extensions: vec![".ts".into()],
+        ..Default::default()
+    });
+    assert_eq!(
+        resolve(
+            &mut ctx,
+            &resolver,
+            &dir,
+            main,
+            "./types",
+            DependencyKind::Import
+        )
+        .unwrap(),
+        "src/types.ts"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_node_resolve_packages() {
+    let dir = write_tree(
+        "juno-resolve-packages",
+        &[
+            (
+                "node_modules/a/package.json",
+                r#"{"main": "dist/a", "module": "esm/a.js"}"#,
+            ),
+            ("node_modules/a/dist/a.js", "exports.a = 1;"),
+            ("node_modules/a/esm/a.js", "export const a = 1;"),
+            (
+                "node_modules/@scope/b/package.json",
+                r#"{"exports": {
+                    ".": {"require": "./b.cjs", "import": "./b.mjs"},
+                    "./features/*": "./src/features/*.js",
+                    "./features/internal/*": null
+                }}"#,
+            ),
+            ("node_modules/@scope/b/b.cjs", "exports.b = 1;"),
+            ("node_modules/@scope/b/b.mjs", "export const b = 1;"),
+            (
+                "node_modules/@scope/b/src/features/x.js",
+                "export const x = 1;",
+            ),
+            (
+                "node_modules/@scope/b/src/features/internal/y.js",
+                "export const y = 1;",
+            ),
+        ],
+    );
+    let mut ctx = Context::new();
+    let main = add_file(&mut ctx, &dir.join("src/main.js"));
+    let resolver = NodeResolver::new(Default::default());
+    let resolve_main =
+        |ctx: &mut Context, specifier, kind| resolve(ctx, &resolver, &dir, main, specifier, kind);
+    use DependencyKind::*;
+
+    assert_eq!(
+        resolve_main(&mut ctx, "a", Import).unwrap(),
+        "node_modules/a/esm/a.js"
+    );
+    assert_eq!(
+        resolve_main(&mut ctx, "a", Require).unwrap(),
+        "node_modules/a/dist/a.js"
+    );
+    assert_eq!(
+        resolve_main(&mut ctx, "a/dist/a", Import).unwrap(),
+        "node_modules/a/dist/a.js"
+    );
+    assert_eq!(
+        resolve_main(&mut ctx, "@scope/b", Import).unwrap(),
+        "node_modules/@scope/b/b.mjs"
+    );
+    assert_eq!(
+        resolve_main(&mut ctx, "@scope/b", Require).unwrap(),
+        "node_modules/@scope/b/b.cjs"
+    );
+    assert_eq!(
+        resolve_main(&mut ctx, "@scope/b/features/x", Import).unwrap(),
+        "node_modules/@scope/b/src/features/x.js"
+    );
+    // Only the exports of a package can be imported.
+    assert_eq!(
+        resolve_main(&mut ctx, "@scope/b/features/internal/y", Import),
+        None
+    );
+    let searched = resolver.searched_locations(
+        &GCLock::new(&mut ctx),
+        main,
+        "@scope/b/features/internal/y",
+        Import,
+    );
+    // The exports of the package end the search, after `src/node_modules`.
+    assert_eq!(
+        searched.last().unwrap(),
+        &format!(
+            "exports of {}",
+            dir.join("node_modules/@scope/b/package.json").display()
+        )
+    );
+    assert_eq!(resolve_main(&mut ctx, "@scope/b/b.mjs", Import), None);
+    assert_eq!(resolve_main(&mut ctx, "@scope", Import), None);
+    assert_eq!(resolve_main(&mut ctx, "c", Import), None);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_node_resolve_imports() {
+    let dir = write_tree(
+        "juno-resolve-imports",
+        &[
+            (
+                "package.json",
+                r##"{"imports": {
+                    "#dep": {"custom": "./src/custom.js", "default": "a"},
+                    "#first": {"custom": "./src/custom.js", "import": "./src/utils/str.js"},
+                    "#default": {"default": "./src/utils/str.js", "custom": "./src/custom.js"},
+                    "#utils/*": "./src/utils/*.js"
+                }}"##,
+            ),
+            ("src/custom.js", "export const custom = 1;"),
+            ("src/utils/str.js", "export const str = 1;"),
+            ("node_modules/a/index.js", "export const a = 1;"),
+        ],
+    );
+    let mut ctx = Context::new();
+    let main = add_file(&mut ctx, &dir.join("src/main.js"));
+    let resolver = NodeResolver::new(Default::default());
+    assert_eq!(
+        resolve(
+            &mut ctx,
+            &resolver,
+            &dir,
+            main,
+            "#dep",
+            DependencyKind::Import
+        )
+        .unwrap(),
+        "node_modules/a/index.js"
+    );
+    assert_eq!(
+        resolve(
+            &mut ctx,
+            &resolver,
+            &dir,
+            main,
+            "#utils/str",
+            DependencyKind::Import
+        )
+        .unwrap(),
+        "src/utils/str.js"
+    );
+    assert_eq!(
+        resolve(
+            &mut ctx,
+            &resolver,
+            &dir,
+            main,
+            "#other",
+            DependencyKind::Import
+        ),
+        None
+    );
+
+    let resolver = NodeResolver::new(NodeResolverOptions {
+        conditions: vec!["custom".into()],
+        ..Default::default()
+    });
+    assert_eq!(
+        resolve(
+            &mut ctx,
+            &resolver,
+            &dir,
+            main,
+            "#dep",
+            DependencyKind::Require
+        )
+        .unwrap(),
+        "src/custom.js"
+    );
+    // The first matching condition in the package wins, whatever the options are.
+    assert_eq!(
+        resolve(
+            &mut ctx,
+            &resolver,
+            &dir,
+            main,
+            "#first",
+            DependencyKind::Import
+        )
+        .unwrap(),
+        "src/custom.js"
+    );
+    assert_eq!(
+        resolve(
+            &mut ctx,
+            &resolver,
+            &dir,
+            main,
+            "#default",
+            DependencyKind::Require
+        )
+        .unwrap(),
+        "src/utils/str.js"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "Sources were added before the pending file")]
+fn test_node_resolve_pending() {
+    let dir = write_tree(
+        "juno-resolve-pending",
+        &[("util.js", "export const x = 1;")],
+    );
+    let mut ctx = Context::new();
+    let main = add_file(&mut ctx, &dir.join("main.js"));
+    let resolver = NodeResolver::new(Default::default());
+    let util = resolver.resolve_dependency(
+        &GCLock::new(&mut ctx),
+        main,
+        "./util",
+        DependencyKind::Import,
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+    // The ID of the pending file now names another source.
+    assert_eq!(util, Some(add_file(&mut ctx, &dir.join("other.js"))));
+    resolver.load_pending(ctx.sm_mut());
+}
+
+/// Register every `(name, source)` of `files`.
+fn add_sources(ctx: &mut Context, files: &[(&str, &str)]) -> Vec<SourceId> {
+    files
+        .iter()
+        .map(|(name, src)| {
+            ctx.sm_mut()
+                .add_source(*name, NullTerminatedBuf::from_str_check(src))
+        })
+        .collect()
+}
+
+/// Resolve `specifier` from `file` and return the name of the result.
+fn resolve_name(
+    ctx: &mut Context,
+    resolver: &dyn DependencyResolver,
+    file: SourceId,
+    specifier: &str,
+) -> Option<String> {
+    let id =
+        resolver.resolve_dependency(&GCLock::new(ctx), file, specifier, DependencyKind::Import)?;
+    Some(ctx.sm().source_name(id).to_string())
+}
+
+#[test]
+fn test_haste_resolve() {
+    let mut ctx = Context::new();
+    let ids = add_sources(
+        &mut ctx,
+        &[
+            ("/app/main.js", ""),
+            ("/app/Button.js", "/**\n * @providesModule Button\n */\n"),
+            ("/app/Button.ios.js", "/** @providesModule Button */"),
+            (
+                "/app/Button.android.js",
+                "// @flow\n/* Copyright */\n/**\n * @providesModule Button\n */",
+            ),
+            ("/app/lib/Text.js", "/* @providesModule Text */"),
+            ("/app/Image.js", ""),
+            ("/app/Image.native.js", ""),
+            ("/app/Late.js", "require('x');\n/* @providesModule Late */"),
+        ],
+    );
+    let main = ids[0];
+    let platforms = |platforms: &[&str]| HasteResolverOptions {
+        platforms: platforms.iter().map(|p| p.to_string()).collect(),
+        name_map: [("Picture".to_string(), "/app/Image.js".to_string())].into(),
+        aliases: [("Label".to_string(), "Text".to_string())].into(),
+    };
+
+    let resolver = HasteResolver::new(ctx.sm(), platforms(&["ios", "native"]));
+    assert_eq!(ctx.sm().num_errors(), 0);
+    let mut resolve = |specifier| resolve_name(&mut ctx, &resolver, main, specifier);
+    assert_eq!(resolve("Button").unwrap(), "/app/Button.ios.js");
+    assert_eq!(resolve("Text").unwrap(), "/app/lib/Text.js");
+    assert_eq!(resolve("Label").unwrap(), "/app/lib/Text.js");
+    assert_eq!(resolve("Picture").unwrap(), "/app/Image.js");
+    assert_eq!(resolve("./Image").unwrap(), "/app/Image.native.js");
+    assert_eq!(resolve("./Image.js").unwrap(), "/app/Image.js");
+    assert_eq!(resolve("./lib/Text").unwrap(), "/app/lib/Text.js");
+    // Only the comments before the code can declare the name.
+    assert_eq!(resolve("Late"), None);
+
+    let resolver = HasteResolver::new(ctx.sm(), platforms(&["android"]));
+    assert_eq!(
+        resolve_name(&mut ctx, &resolver, main, "Button").unwrap(),
+        "/app/Button.android.js"
+    );
+    assert_eq!(
+        resolve_name(&mut ctx, &resolver, main, "./Image").unwrap(),
+        "/app/Image.js"
+    );
+    let resolver = HasteResolver::new(ctx.sm(), platforms(&[]));
+    assert_eq!(
+        resolve_name(&mut ctx, &resolver, main, "Button").unwrap(),
+        "/app/Button.js"
+    );
+}
+
+#[test]
+fn test_haste_duplicates() {
+    let mut ctx = Context::new();
+    add_sources(
+        &mut ctx,
+        &[
+            ("/app/a/Dup.js", "/* @providesModule Dup */"),
+            ("/app/b/Dup.js", "/* @providesModule Dup */"),
+            ("/app/b/Dup.ios.js", "/* @providesModule Dup */"),
+            ("/app/Mapped.js", "/* @providesModule Mapped */"),
+        ],
+    );
+    let resolver = HasteResolver::new(
+        ctx.sm(),
+        HasteResolverOptions {
+            // Mapping a file to the name it declares isn't a duplicate.
+            name_map: [
+                ("Mapped".to_string(), "/app/Mapped.js".to_string()),
+                ("Dup".to_string(), "/app/Mapped.js".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        },
+    );
+    // `Dup` is declared three times for any platform.
+    assert_eq!(ctx.sm().num_errors(), 2);
+    let main = SourceId(0);
+    assert_eq!(
+        resolve_name(&mut ctx, &resolver, main, "Dup").unwrap(),
+        "/app/Mapped.js"
+    );
+    assert_eq!(
+        resolve_name(&mut ctx, &resolver, main, "Mapped").unwrap(),
+        "/app/Mapped.js"
+    );
+}
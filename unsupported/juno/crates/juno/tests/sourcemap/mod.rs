@@ -0,0 +1,200 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use juno::sourcemap::*;
+use sourcemap::SourceMap;
+use sourcemap::SourceMapBuilder;
+
+/// Output position, source and source position, and name of a token.
+type Mapping<'a> = ((u32, u32), Option<(&'a str, (u32, u32))>, Option<&'a str>);
+
+/// Return the source, source position and name of every token of `map`, by output position.
+fn mappings(map: &SourceMap) -> Vec<Mapping> {
+    map.tokens()
+        .map(|t| {
+            (
+                t.get_dst(),
+                t.get_source().map(|src| (src, t.get_src())),
+                t.get_name(),
+            )
+        })
+        .collect()
+}
+
+/// Serialize `map` and parse it again.
+fn roundtrip(map: &FullSourceMap) -> FullSourceMap {
+    let mut json: Vec<u8> = vec![];
+    map.to_writer(&mut json).unwrap();
+    FullSourceMap::from_slice(&json).unwrap()
+}
+
+#[test]
+fn test_compose_multiple_sources() {
+    // a.ts -> a.js
+    let mut builder = SourceMapBuilder::new(Some("a.js"));
+    let a_ts = builder.add_source("a.ts");
+    builder.set_source_contents(a_ts, Some("let x: number = 1;"));
+    let x = builder.add_name("x");
+    builder.add_raw(0, 0, 5, 2, Some(a_ts), None);
+    builder.add_raw(0, 4, 5, 6, Some(a_ts), Some(x));
+    let input = FullSourceMap {
+        map: builder.into_sourcemap(),
+        ignore_list: vec![a_ts],
+    };
+
+    // a.js, b.js -> out.js
+    let mut builder = SourceMapBuilder::new(Some("out.js"));
+    let a_js = builder.add_source("a.js");
+    let b_js = builder.add_source("b.js");
+    builder.set_source_contents(b_js, Some("b();"));
+    let y = builder.add_name("y");
+    builder.add_raw(0, 0, 0, 0, Some(a_js), None);
+    builder.add_raw(0, 10, 0, 4, Some(a_js), Some(y));
+    builder.add_raw(1, 0, 3, 1, Some(b_js), None);
+    // a.js has no mapping on this line.
+    builder.add_raw(2, 0, 7, 0, Some(a_js), None);
+    let output = FullSourceMap::from(builder.into_sourcemap());
+
+    let composed = roundtrip(&compose_sourcemaps(&output, &[Some(&input), None]));
+    let map = &composed.map;
+    assert_eq!(map.get_file(), Some("out.js"));
+    assert_eq!(map.sources().collect::<Vec<_>>(), vec!["a.ts", "b.js"]);
+    assert_eq!(map.get_source_contents(0), Some("let x: number = 1;"));
+    assert_eq!(map.get_source_contents(1), Some("b();"));
+    assert_eq!(composed.ignore_list, vec![0]);
+    assert_eq!(
+        mappings(map),
+        vec![
+            ((0, 0), Some(("a.ts", (5, 2))), None),
+            ((0, 10), Some(("a.ts", (5, 6))), Some("x")),
+            ((1, 0), Some(("b.js", (3, 1))), None),
+            ((2, 0), None, None),
+        ]
+    );
+}
+
+#[test]
+fn test_compose_chain() {
+    // a.js -> b.js
+    let mut builder = SourceMapBuilder::new(Some("b.js"));
+    let a_js = builder.add_source("a.js");
+    builder.add_raw(0, 0, 0, 0, Some(a_js), None);
+    builder.add_raw(0, 4, 1, 0, Some(a_js), None);
+    let first = FullSourceMap::from(builder.into_sourcemap());
+
+    // b.js -> c.js
+    let mut builder = SourceMapBuilder::new(Some("c.js"));
+    let b_js = builder.add_source("b.js");
+    builder.add_raw(2, 0, 0, 4, Some(b_js), None);
+    let second = FullSourceMap::from(builder.into_sourcemap());
+
+    assert!(compose_chain(&[]).is_none());
+    let composed = compose_chain(&[first, second]).unwrap();
+    assert_eq!(composed.map.get_file(), Some("c.js"));
+    assert_eq!(
+        mappings(&composed.map),
+        vec![((2, 0), Some(("a.js", (1, 0))), None)]
+    );
+}
+
+#[test]
+fn test_index_map() {
+    let map = FullSourceMap::from_slice(
+        br#"{
+            "version": 3,
+            "file": "min.js",
+            "sections": [
+                {
+                    "offset": {"line": 0, "column": 0},
+                    "map": {
+                        "version": 3,
+                        "sources": ["x.js"],
+                        "sourcesContent": ["x"],
+                        "names": [],
+                        "mappings": "AAAA",
+                        "ignoreList": [0]
+                    }
+                },
+                {
+                    "offset": {"line": 1, "column": 0},
+                    "map": {
+                        "version": 3,
+                        "sources": ["y.js"],
+                        "names": [],
+                        "mappings": "AAAA"
+                    }
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(map.map.sources().collect::<Vec<_>>(), vec!["x.js", "y.js"]);
+    assert_eq!(map.ignore_list, vec![0]);
+    assert_eq!(
+        mappings(&map.map),
+        vec![
+            ((0, 0), Some(("x.js", (0, 0))), None),
+            ((1, 0), Some(("y.js", (0, 0))), None),
+        ]
+    );
+}
+
+#[test]
+fn test_ignore_list() {
+    let json = br#"{
+        "version": 3,
+        "sources": ["a.js", "node_modules/b.js"],
+        "names": [],
+        "mappings": "AAAA,ECAA",
+        "x_google_ignoreList": [1]
+    }"#;
+    let map = FullSourceMap::from_slice(json).unwrap();
+    assert_eq!(map.ignore_list, vec![1]);
+    assert!(map.is_ignored(1));
+    assert!(!map.is_ignored(0));
+
+    let mut out: Vec<u8> = vec![];
+    map.to_writer(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains(r#""ignoreList":[1]"#), "{}", out);
+    assert!(out.contains(r#""x_google_ignoreList":[1]"#), "{}", out);
+    assert_eq!(roundtrip(&map).ignore_list, vec![1]);
+}
+
+#[test]
+fn test_concat() {
+    let mut builder = SourceMapBuilder::new(None);
+    let a = builder.add_source("a.js");
+    let name = builder.add_name("f");
+    builder.add_raw(0, 0, 3, 4, Some(a), Some(name));
+    builder.add_raw(1, 2, 0, 0, None, None);
+    let first = FullSourceMap {
+        map: builder.into_sourcemap(),
+        ignore_list: vec![0],
+    };
+
+    let mut builder = SourceMapBuilder::new(None);
+    let b = builder.add_source("b.js");
+    let a = builder.add_source("a.js");
+    builder.add_raw(0, 0, 1, 0, Some(b), None);
+    builder.add_raw(0, 5, 7, 0, Some(a), None);
+    let second = FullSourceMap::from(builder.into_sourcemap());
+
+    let map = concat_sourcemaps(Some("bundle.js"), &[(0, &first), (10, &second)]);
+    assert_eq!(map.map.get_file(), Some("bundle.js"));
+    assert_eq!(map.map.sources().collect::<Vec<_>>(), vec!["a.js", "b.js"]);
+    assert_eq!(map.ignore_list, vec![0]);
+    assert_eq!(
+        mappings(&map.map),
+        vec![
+            ((0, 0), Some(("a.js", (3, 4))), Some("f")),
+            ((1, 2), None, None),
+            ((10, 0), Some(("b.js", (1, 0))), None),
+            ((10, 5), Some(("a.js", (7, 0))), None),
+        ]
+    );
+}
@@ -174,6 +174,10 @@ pub struct Context<'ast> {
 
     /// Whether to warn about undefined variables in strict mode functions.
     pub warn_undefined: bool,
+
+    /// Whether dependencies of modules which can't be resolved are errors instead of
+    /// warnings.
+    pub error_unresolved: bool,
 }
 
 impl Default for Context<'_> {
@@ -202,6 +206,7 @@ impl<'ast> Context<'ast> {
             markbit_marked: true,
             strict_mode: false,
             warn_undefined: false,
+            error_unresolved: false,
         }
     }
 
@@ -719,7 +724,7 @@ impl NodeRc {
     /// # Panics
     ///
     /// Will panic if `gc` is not for the same context as this `NodeRc` was created in.
-    pub fn node<'gc>(&'_ self, gc: &'gc GCLock<'_, '_>) -> &'gc Node {
+    pub fn node<'gc>(&'_ self, gc: &'gc GCLock<'_, '_>) -> &'gc Node<'gc> {
         unsafe {
             assert_eq!(
                 self.counter.as_ref().ctx_id,
@@ -728,7 +733,14 @@ impl NodeRc {
                 self.counter.as_ref().ctx_id,
                 gc.ctx.id
             );
-            &self.entry().as_ref().inner
+            // SAFETY: `entry` points to a live `StorageEntry`, which can't be freed while this
+            // `NodeRc` counts it. The entry belongs to the context of `gc`, as checked above,
+            // and so do the nodes it references, which are reachable from it. Nodes are only
+            // freed by `Context::gc`, which requires `&mut Context` and can't run while `gc`
+            // borrows the context. So the node and the nodes it references are valid for `'gc`,
+            // which is also the lifetime that `GCLock::alloc` gives to the nodes it allocates.
+            let entry = self.entry.as_ptr() as *const StorageEntry<'gc>;
+            &(*entry).inner
         }
     }
 
@@ -242,6 +242,28 @@ macro_rules! gen_nodekind_enum {
                 }
             }
 
+            /// Whether `self` and `other` are the same kind of node and all their non-node fields
+            /// (operators, literal values, names, flags) are equal.
+            /// Child nodes and lists are not compared, but optional children must be present in
+            /// both nodes.
+            #[allow(unused_variables)]
+            pub fn shallow_eq(&self, other: &Node<'gc>) -> bool {
+                match (self, other) {
+                    $(
+                        (
+                            Node::$kind($kind {
+                                $($($field,)*)?
+                                ..
+                            }),
+                            Node::$kind(other),
+                        ) => {
+                            true $($( && NodeChild::shallow_eq(*$field, other.$field) )*)?
+                        }
+                    ),*
+                    _ => false,
+                }
+            }
+
             #[inline]
             pub fn range(&self) -> &SourceRange {
                 match self {
@@ -374,6 +396,7 @@ macro_rules! gen_nodekind_enum {
                 NodeMetadata,
                 NodeString,
                 PropertyKind,
+                SourceRange,
                 TransformResult,
                 UnaryExpressionOperator,
                 UpdateExpressionOperator,
@@ -395,6 +418,27 @@ macro_rules! gen_nodekind_enum {
                         )*
                     }
                 }
+
+                /// Set the source range of the node being built.
+                pub fn range(&mut self, range: SourceRange) {
+                    match self {
+                        $(
+                        Self::$kind(builder) => {
+                            builder.is_changed = true;
+                            builder.inner.metadata.range = range;
+                        }
+                        )*
+                    }
+                }
+
+                /// Return the new node, even if nothing was changed.
+                pub fn build_forced(self, gc: &'a GCLock) -> &'a Node<'a> {
+                    match self {
+                        $(
+                        Self::$kind(builder) => builder.build_forced(gc),
+                        )*
+                    }
+                }
             }
 
             $(
@@ -57,6 +57,7 @@ pub use node_child::NodeMetadata;
 pub use node_child::NodeString;
 pub use node_child::TemplateMetadata;
 pub use node_enums::*;
+pub use validate::instanceof;
 pub use validate::validate_tree;
 pub use validate::validate_tree_pure;
 pub use validate::TreeValidationError;
@@ -261,6 +261,11 @@ where
     /// (for `Out`) are unstable.
     fn duplicate(self) -> Self::Out;
 
+    /// Compare this child with the corresponding child of another node of the same kind.
+    /// Values are compared directly, while `Node` references and lists are considered equal
+    /// so that callers can compare them separately.
+    fn shallow_eq(self, other: Self) -> bool;
+
     /// If this NodeChild is a list, visit the elements and call `cb` with each `NodeListElement`
     /// in this AST node only (non-recursive).
     fn mark_list<CB: Fn(&NodeListElement)>(self, _lock: &'gc GCLock, _cb: CB) {}
@@ -271,36 +276,54 @@ impl NodeChild<'_> for f64 {
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for bool {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for NodeLabel {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for NodeString {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl<'gc> NodeChild<'gc> for &NodeString {
     type Out = NodeString;
     fn duplicate(self) -> Self::Out {
         *self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for &Option<NodeString> {
     type Out = Option<NodeString>;
     fn duplicate(self) -> Self::Out {
         *self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 
 impl<'gc, T: NodeChild<'gc> + NodeChild<'gc, Out = T>> NodeChild<'gc> for Option<T> {
@@ -338,6 +361,14 @@ impl<'gc, T: NodeChild<'gc> + NodeChild<'gc, Out = T>> NodeChild<'gc> for Option
     fn duplicate(self) -> Self::Out {
         self.map(|inner| inner.duplicate())
     }
+
+    fn shallow_eq(self, other: Self) -> bool {
+        match (self, other) {
+            (Some(a), Some(b)) => a.shallow_eq(b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
 }
 
 impl<'gc> NodeChild<'gc> for &Option<&'gc Node<'gc>> {
@@ -375,6 +406,10 @@ impl<'gc> NodeChild<'gc> for &Option<&'gc Node<'gc>> {
     fn duplicate(self) -> Self::Out {
         *self
     }
+
+    fn shallow_eq(self, other: Self) -> bool {
+        self.is_some() == other.is_some()
+    }
 }
 
 impl<'gc> NodeChild<'gc> for &'gc Node<'gc> {
@@ -416,6 +451,10 @@ impl<'gc> NodeChild<'gc> for &'gc Node<'gc> {
     fn duplicate(self) -> Self::Out {
         self
     }
+
+    fn shallow_eq(self, _other: Self) -> bool {
+        true
+    }
 }
 
 impl<'gc> NodeChild<'gc> for NodeList<'gc> {
@@ -479,6 +518,10 @@ impl<'gc> NodeChild<'gc> for NodeList<'gc> {
         NodeList { head: self.head }
     }
 
+    fn shallow_eq(self, _other: Self) -> bool {
+        true
+    }
+
     fn mark_list<CB: Fn(&NodeListElement)>(self, _lock: &'gc GCLock, cb: CB) {
         let mut cur = self.head;
         while !cur.is_null() {
@@ -136,58 +136,88 @@ impl NodeChild<'_> for UnaryExpressionOperator {
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for BinaryExpressionOperator {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for LogicalExpressionOperator {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for UpdateExpressionOperator {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for AssignmentExpressionOperator {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for VariableDeclarationKind {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for PropertyKind {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for MethodDefinitionKind {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for ImportKind {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
 impl NodeChild<'_> for ExportKind {
     type Out = Self;
     fn duplicate(self) -> Self::Out {
         self
     }
+    fn shallow_eq(self, other: Self) -> bool {
+        self == other
+    }
 }
@@ -154,7 +154,7 @@ impl<'gc> ValidChild<'gc> for NodeList<'gc> {
 }
 
 /// Return whether `subtype` contains `supertype` in its parent chain.
-fn instanceof(subtype: NodeVariant, supertype: NodeVariant) -> bool {
+pub fn instanceof(subtype: NodeVariant, supertype: NodeVariant) -> bool {
     let mut cur = subtype;
     loop {
         if cur == supertype {
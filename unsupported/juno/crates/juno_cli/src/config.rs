@@ -0,0 +1,106 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::anyhow;
+use command_line::CommandLine;
+use serde_json::Value;
+
+/// Name of the configuration file of a project.
+pub const CONFIG_FILE_NAME: &str = "juno.json";
+
+/// How the value of a key of the configuration file seeds its option.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Kind {
+    /// A string, boolean or number, or an array of them for list options.
+    Value,
+    /// A path relative to the directory of the configuration file.
+    Path,
+    /// A boolean, true specifies the flag.
+    Flag,
+}
+
+/// The keys of the configuration file, and the options they seed.
+static KEYS: &[(&str, &str, Kind)] = &[
+    ("dialect", "dialect", Kind::Value),
+    ("jsx", "jsx", Kind::Value),
+    ("strict-mode", "strict-mode", Kind::Value),
+    ("strip-flow", "strip-flow", Kind::Value),
+    ("optimize", "O", Kind::Flag),
+    ("rewrite", "rewrite", Kind::Value),
+    ("resolver", "resolver", Kind::Value),
+    ("resolve-extension", "resolve-extension", Kind::Value),
+    ("condition", "condition", Kind::Value),
+    ("platform", "platform", Kind::Value),
+    ("out-dir", "out-dir", Kind::Path),
+    ("sourcemap", "sourcemap", Kind::Value),
+    ("sources-content", "sources-content", Kind::Value),
+    ("base-url", "base-url", Kind::Value),
+    ("input-source-map", "input-source-map", Kind::Value),
+];
+
+/// Find the configuration file for `input`, in its directory or the closest
+/// ancestor containing one. Standard input is looked up from the current
+/// directory.
+pub fn find_config(input: &Path) -> Option<PathBuf> {
+    let start = if input == Path::new("-") {
+        std::env::current_dir().ok()?
+    } else {
+        std::fs::canonicalize(input).ok()?.parent()?.to_path_buf()
+    };
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Seed the options of `cl` with the values in the configuration file at
+/// `path`, so that the command line overrides them.
+pub fn load_config(cl: &CommandLine, path: &Path) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    seed_options(cl, &text, path.parent().unwrap_or_else(|| Path::new("")))
+        .with_context(|| format!("Invalid configuration file {}", path.display()))
+}
+
+/// Seed the options of `cl` with the JSON configuration in `text`, resolving
+/// relative paths against `dir`.
+fn seed_options(cl: &CommandLine, text: &str, dir: &Path) -> anyhow::Result<()> {
+    let config: Value = serde_json::from_str(text)?;
+    let config = config
+        .as_object()
+        .ok_or_else(|| anyhow!("expected an object"))?;
+    for (key, value) in config {
+        let (_, name, kind) = KEYS
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .ok_or_else(|| anyhow!("unknown key '{}'", key))?;
+        let values = match value {
+            Value::Array(values) => values.as_slice(),
+            _ => std::slice::from_ref(value),
+        };
+        for value in values {
+            let seeded = match (kind, value) {
+                (Kind::Flag, Value::Bool(false)) => continue,
+                (Kind::Flag, Value::Bool(true)) => cl.seed(name, None),
+                (Kind::Path, Value::String(s)) => {
+                    cl.seed(name, Some(&dir.join(s).to_string_lossy()))
+                }
+                (Kind::Value, Value::String(s)) => cl.seed(name, Some(s)),
+                (Kind::Value, Value::Bool(_) | Value::Number(_)) => {
+                    cl.seed(name, Some(&value.to_string()))
+                }
+                _ => return Err(anyhow!("invalid value {} for '{}'", value, key)),
+            };
+            seeded.map_err(|e| anyhow!("'{}': {}", key, e))?;
+        }
+    }
+    Ok(())
+}
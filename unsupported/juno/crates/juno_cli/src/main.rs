@@ -5,45 +5,80 @@
  * LICENSE file in the root directory of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fs::File;
+use std::io::IsTerminal;
 use std::io::Write;
 use std::ops::DerefMut;
+use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::exit;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 
-use anyhow::ensure;
 use anyhow::Context;
 use anyhow::Error;
+use anyhow::bail;
+use anyhow::ensure;
 use command_line::CommandLine;
+use command_line::CommandLineIntent;
 use command_line::Hidden;
 use command_line::Opt;
 use command_line::OptDesc;
 use juno::ast;
+use juno::ast::NodeRc;
 use juno::ast::node_cast;
 use juno::ast::validate_tree;
-use juno::ast::NodeRc;
-use juno::ast::SourceRange;
+use juno::bundle;
+use juno::codemod;
+use juno::comments::CommentMap;
+use juno::dependency_graph::DependencyGraph;
 use juno::gen_js;
 use juno::hparser;
 use juno::hparser::MagicCommentKind;
 use juno::hparser::ParsedJS;
 use juno::hparser::ParserDialect;
-use juno::resolve_dependency;
+use juno::resolve_dependency::DefaultResolver;
+use juno::resolve_dependency::DependencyResolver;
+use juno::resolve_dependency::HasteResolver;
+use juno::resolve_dependency::HasteResolverOptions;
+use juno::resolve_dependency::NodeResolver;
+use juno::resolve_dependency::NodeResolverOptions;
 use juno::sema;
 use juno::sema::SemContext;
-use juno::sourcemap::merge_sourcemaps;
+use juno::sourcemap::FullSourceMap;
+use juno::sourcemap::compose_sourcemaps;
 use juno_pass::PassManager;
-use juno_support::fetchurl;
-use juno_support::source_manager::SourceId;
+use juno_pass::RewritePass;
 use juno_support::HeapSize;
 use juno_support::NullTerminatedBuf;
 use juno_support::Timer;
-use sourcemap::SourceMap;
+use juno_support::diagnostics;
+use juno_support::diagnostics::JsonLinesHandler;
+use juno_support::diagnostics::SarifHandler;
+use juno_support::diagnostics::TerminalHandler;
+use juno_support::fetchurl;
+use juno_support::source_manager::SourceId;
+use juno_support::source_manager::SourceManager;
 use url::Url;
+use watch::Poller;
+
+mod config;
+mod watch;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Command {
+    /// Generate the output selected by the options.
+    Build,
+    /// Only report the diagnostics of the inputs.
+    Lint,
+    /// Dump the graph of the dependencies between the modules.
+    Deps,
+}
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 enum Gen {
@@ -55,6 +90,64 @@ enum Gen {
     Js,
     /// Generate JavaScript source with annotations about variable resolution.
     ResolvedJs,
+    /// Dump the graph of the dependencies between the modules.
+    Deps,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum DepsFormat {
+    /// A JSON object with the modules and the dependencies.
+    Json,
+    /// A Graphviz digraph.
+    Dot,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Codemod {
+    /// Generate the output selected by `Gen`.
+    No,
+    /// Rewrite the changed parts of every input file.
+    InPlace,
+    /// Print a unified diff for every changed input file.
+    Diff,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Comments {
+    /// Drop all comments.
+    None,
+    /// Keep every comment.
+    All,
+    /// Keep only `/*! ... */` and `@license` comments.
+    Legal,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Resolver {
+    /// Resolve relative paths and the names of the other inputs.
+    Default,
+    /// Resolve like Node.js, loading files on demand.
+    Node,
+    /// Resolve Haste module names and relative paths.
+    Haste,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Color {
+    /// Color the diagnostics if stderr is a terminal.
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum DiagnosticsFormat {
+    /// Human readable diagnostics with source snippets.
+    Text,
+    /// A JSON object per diagnostic, on its own line.
+    Json,
+    /// A SARIF log with all the diagnostics, written at the end.
+    Sarif,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -64,12 +157,18 @@ enum InputSourceMap {
 }
 
 struct Options {
+    /// The subcommand, which implies some options.
+    command: Opt<Command>,
+
     /// Enable pretty printing.
     pretty: Opt<bool>,
 
     /// Select what to emit.
     gen: Opt<Gen>,
 
+    /// Format of the dependency graph.
+    deps_format: Opt<DepsFormat>,
+
     /// Perform AST validation.
     validate_ast: Opt<bool>,
 
@@ -79,16 +178,31 @@ struct Options {
     /// Input file to parse.
     input_path: Opt<PathBuf>,
 
+    /// Configuration file seeding the options, instead of the one found from the first input.
+    config: Opt<Option<PathBuf>>,
+
     /// Path to output to.
     /// Defaults to `-`, which is `stdout`.
     // #[structopt(long = "out", short = "o", default_value = "-", parse(from_os_str))]
     output_path: Opt<PathBuf>,
 
+    /// Directory to compile every input into a file of, instead of `output_path`.
+    out_dir: Opt<Option<PathBuf>>,
+
+    /// Number of threads compiling into `out_dir`, 0 for one per CPU.
+    jobs: Opt<usize>,
+
+    /// Keep running, and compile the inputs again when they or their dependencies change.
+    watch: Opt<bool>,
+
     /// Whether to output a source map.
     /// The source map will be merged with an input source map if provided.
     /// Can only be used when generating JS.
     sourcemap: Opt<bool>,
 
+    /// Whether to embed the contents of the sources in the source map.
+    sources_content: Opt<bool>,
+
     /// Base URL to prepend to relative URLs.
     base_url: Opt<Option<Url>>,
 
@@ -101,6 +215,33 @@ struct Options {
     /// Whether to run strip flow types.
     strip_flow: Opt<bool>,
 
+    /// Rewrite rules to apply, see [`juno_pass::RewriteRule`].
+    rewrite: Opt<String>,
+
+    /// Whether to rewrite the input files instead of generating output.
+    codemod: Opt<Codemod>,
+
+    /// Whether to bundle the first input file and the modules it depends on.
+    bundle: Opt<bool>,
+
+    /// Whether to hoist the ES modules of a bundle into its top-level scope.
+    scope_hoist: Opt<bool>,
+
+    /// Whether to remove the unused exports of the modules of a bundle.
+    tree_shake: Opt<bool>,
+
+    /// How to resolve the dependencies of the modules of a bundle.
+    resolver: Opt<Resolver>,
+
+    /// Extensions tried by the Node.js resolver.
+    resolve_extensions: Opt<String>,
+
+    /// Conditions matched by the Node.js resolver in package exports and imports.
+    conditions: Opt<String>,
+
+    /// Platforms preferred by the Haste resolver.
+    platforms: Opt<String>,
+
     /// Whether to force a space after the `async` keyword in arrow functions.
     force_async_arrow_space: Opt<bool>,
 
@@ -108,6 +249,24 @@ struct Options {
     /// The doc block contains every comment prior to the first non-directive token in the file.
     emit_doc_block: Opt<bool>,
 
+    /// Which comments to preserve when generating JS.
+    comments: Opt<Comments>,
+
+    /// Whether to lay out the generated JS within `print_width`, like Prettier.
+    format: Opt<bool>,
+
+    /// Line width to fit the formatted output in.
+    print_width: Opt<usize>,
+
+    /// Which lists get trailing commas in formatted output.
+    trailing_commas: Opt<gen_js::TrailingCommas>,
+
+    /// Whether formatted output terminates statements with semicolons.
+    semicolons: Opt<bool>,
+
+    /// Whether formatted output has spaces inside the braces of objects.
+    bracket_spacing: Opt<bool>,
+
     /// Whether to use double quotes on string literals.
     double_quote_strings: Opt<bool>,
 
@@ -126,6 +285,24 @@ struct Options {
     /// Warn about undefined variables in strict mode functions.
     warn_undefined: Opt<bool>,
 
+    /// Report dependencies which can't be resolved as errors.
+    error_unresolved: Opt<bool>,
+
+    /// Warn about cycles of dependencies between modules.
+    check_cycles: Opt<bool>,
+
+    /// Whether to color the diagnostics.
+    color: Opt<Color>,
+
+    /// Format of the diagnostics.
+    diagnostics_format: Opt<DiagnosticsFormat>,
+
+    /// Number of errors after which no more diagnostics are reported, 0 for no limit.
+    error_limit: Opt<usize>,
+
+    /// Apply the fixes of the diagnostics to the input files.
+    apply_fixes: Opt<bool>,
+
     /// Measure and print times.
     xtime: Opt<bool>,
 
@@ -139,6 +316,21 @@ impl Options {
         let output_cat = cl.add_category("Output Options", None);
 
         Options {
+            command: Opt::new_subcommand(
+                cl,
+                OptDesc {
+                    values: Some(&[
+                        ("build", Command::Build, "Compile the inputs (default)."),
+                        (
+                            "lint",
+                            Command::Lint,
+                            "Only report diagnostics, including undefined variables.",
+                        ),
+                        ("deps", Command::Deps, "Dump the dependency graph."),
+                    ]),
+                    ..Default::default()
+                },
+            ),
             pretty: Opt::new_bool(
                 cl,
                 OptDesc {
@@ -162,6 +354,20 @@ impl Options {
                             Gen::ResolvedJs,
                             "Generate resolution information.",
                         ),
+                        ("gen-deps", Gen::Deps, "Dump the dependency graph."),
+                    ]),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            deps_format: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("deps-format"),
+                    desc: Some("Format of the dependency graph (default: json)."),
+                    values: Some(&[
+                        ("json", DepsFormat::Json, "JSON modules and dependencies"),
+                        ("dot", DepsFormat::Dot, "Graphviz digraph"),
                     ]),
                     category: output_cat,
                     ..Default::default()
@@ -194,6 +400,19 @@ impl Options {
                     ..Default::default()
                 },
             ),
+            config: Opt::<Option<PathBuf>>::new_optional(
+                cl,
+                OptDesc {
+                    long: Some("config"),
+                    desc: Some(
+                        "Read default options from this file instead of the juno.json \
+                        found in the directory of the first input or its ancestors.",
+                    ),
+                    value_desc: Some("path"),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
             output_path: Opt::<PathBuf>::new(
                 cl,
                 OptDesc {
@@ -206,6 +425,40 @@ impl Options {
                     ..Default::default()
                 },
             ),
+            out_dir: Opt::<Option<PathBuf>>::new_optional(
+                cl,
+                OptDesc {
+                    long: Some("out-dir"),
+                    desc: Some("Compile every input into its own file in this directory."),
+                    value_desc: Some("directory"),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            jobs: Opt::new(
+                cl,
+                OptDesc {
+                    long: Some("jobs"),
+                    short: Some("j"),
+                    desc: Some(
+                        "Number of threads compiling into --out-dir (default: one per CPU).",
+                    ),
+                    value_desc: Some("threads"),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            watch: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("watch"),
+                    desc: Some(
+                        "Keep running, and compile the inputs again when they or their dependencies change.",
+                    ),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
             sourcemap: Opt::new_bool(
                 cl,
                 OptDesc {
@@ -215,6 +468,15 @@ impl Options {
                     ..Default::default()
                 },
             ),
+            sources_content: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("sources-content"),
+                    desc: Some("Embed the contents of the sources in the source map."),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
             base_url: Opt::<Option<Url>>::new_optional(
                 cl,
                 OptDesc {
@@ -262,6 +524,136 @@ impl Options {
                     ..Default::default()
                 },
             ),
+            rewrite: Opt::<String>::new_list(
+                cl,
+                OptDesc {
+                    long: Some("rewrite"),
+                    desc: Some("Apply a rewrite rule of the form 'pattern => replacement'."),
+                    value_desc: Some("rule"),
+                    ..Default::default()
+                },
+            ),
+            codemod: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("codemod"),
+                    desc: Some(
+                        "Apply the passes to the input files, regenerating only the changed code.",
+                    ),
+                    values: Some(&[
+                        (
+                            "in-place",
+                            Codemod::InPlace,
+                            "Rewrite the input files in place.",
+                        ),
+                        (
+                            "diff",
+                            Codemod::Diff,
+                            "Output a unified diff for every changed file.",
+                        ),
+                    ]),
+                    init: Some(Codemod::No),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            bundle: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("bundle"),
+                    desc: Some(
+                        "Bundle the first input file and the modules it depends on \
+                        into a single script.",
+                    ),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            scope_hoist: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("scope-hoist"),
+                    desc: Some(
+                        "Concatenate the ES modules of a bundle into a single scope \
+                        instead of wrapping every module in a function.",
+                    ),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            tree_shake: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("tree-shake"),
+                    desc: Some(
+                        "Remove the exports of a bundle that no module uses, \
+                        and the code that only they need.",
+                    ),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            resolver: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("resolver"),
+                    desc: Some("How to resolve dependencies when bundling (default: default)."),
+                    values: Some(&[
+                        (
+                            "default",
+                            Resolver::Default,
+                            "Resolve relative paths and the names of the inputs",
+                        ),
+                        (
+                            "node",
+                            Resolver::Node,
+                            "Resolve like Node.js, loading files on demand",
+                        ),
+                        (
+                            "haste",
+                            Resolver::Haste,
+                            "Resolve @providesModule names and relative paths",
+                        ),
+                    ]),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            resolve_extensions: Opt::new_list(
+                cl,
+                OptDesc {
+                    long: Some("resolve-extension"),
+                    desc: Some(
+                        "Extension tried by the Node.js resolver \
+                        (default: .js, .mjs, .cjs, .jsx).",
+                    ),
+                    value_desc: Some("extension"),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            conditions: Opt::new_list(
+                cl,
+                OptDesc {
+                    long: Some("condition"),
+                    desc: Some("Condition matched by the Node.js resolver in package exports."),
+                    value_desc: Some("name"),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
+            platforms: Opt::new_list(
+                cl,
+                OptDesc {
+                    long: Some("platform"),
+                    desc: Some(
+                        "Platform preferred by the Haste resolver, e.g. ios for .ios.js files.",
+                    ),
+                    value_desc: Some("name"),
+                    category: input_cat,
+                    ..Default::default()
+                },
+            ),
             force_async_arrow_space: Opt::new_bool(
                 cl,
                 OptDesc {
@@ -280,6 +672,92 @@ impl Options {
                     ..Default::default()
                 },
             ),
+            comments: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("comments"),
+                    desc: Some("Comments to preserve when generating JS."),
+                    values: Some(&[
+                        ("none", Comments::None, "Drop all comments."),
+                        ("all", Comments::All, "Keep all comments."),
+                        (
+                            "legal",
+                            Comments::Legal,
+                            "Keep only /*! */ and @license comments.",
+                        ),
+                    ]),
+                    init: Some(Comments::None),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            format: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("format"),
+                    desc: Some("Format the generated JS like Prettier."),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            print_width: Opt::new(
+                cl,
+                OptDesc {
+                    long: Some("print-width"),
+                    desc: Some("Line width of formatted output (default: 80)."),
+                    value_desc: Some("columns"),
+                    init: Some(80),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            trailing_commas: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("trailing-commas"),
+                    desc: Some("Trailing commas in broken lists of formatted output."),
+                    values: Some(&[
+                        (
+                            "all",
+                            gen_js::TrailingCommas::All,
+                            "Wherever possible, including arguments.",
+                        ),
+                        (
+                            "es5",
+                            gen_js::TrailingCommas::Es5,
+                            "Where valid in ES5: arrays and objects.",
+                        ),
+                        ("none", gen_js::TrailingCommas::None, "No trailing commas."),
+                    ]),
+                    init: Some(gen_js::TrailingCommas::All),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            semicolons: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("semicolons"),
+                    desc: Some(
+                        "Terminate statements with semicolons in formatted output (default: on).",
+                    ),
+                    init: Some(true),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
+            bracket_spacing: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("bracket-spacing"),
+                    desc: Some(
+                        "Print spaces inside object braces in formatted output (default: on).",
+                    ),
+                    init: Some(true),
+                    category: output_cat,
+                    ..Default::default()
+                },
+            ),
             double_quote_strings: Opt::new_bool(
                 cl,
                 OptDesc {
@@ -349,30 +827,122 @@ impl Options {
                     ..Default::default()
                 },
             ),
-            xtime: Opt::new_bool(
+            error_unresolved: Opt::new_bool(
                 cl,
                 OptDesc {
-                    long: Some("Xtime"),
-                    desc: Some("Measure and print times."),
-                    hidden: Hidden::Yes,
+                    long: Some("error-unresolved"),
+                    desc: Some("Report dependencies which can't be resolved as errors."),
                     ..Default::default()
                 },
             ),
-            xmem: Opt::new_bool(
+            check_cycles: Opt::new_bool(
                 cl,
                 OptDesc {
-                    long: Some("Xmem"),
-                    desc: Some("Measure and print memory usage."),
-                    hidden: Hidden::Yes,
+                    long: Some("check-cycles"),
+                    desc: Some("Warn about cycles of dependencies between modules."),
                     ..Default::default()
                 },
             ),
-        }
-    }
-
-    /// Ensure the arguments are valid.
-    /// Return `Err` if there are any conflicts.
+            color: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("color"),
+                    desc: Some("Whether to color the diagnostics."),
+                    values: Some(&[
+                        ("auto", Color::Auto, "Color if stderr is a terminal."),
+                        ("always", Color::Always, "Always color."),
+                        ("never", Color::Never, "Never color."),
+                    ]),
+                    init: Some(Color::Auto),
+                    env: Some("JUNO_COLOR"),
+                    ..Default::default()
+                },
+            ),
+            diagnostics_format: Opt::new_enum(
+                cl,
+                OptDesc {
+                    long: Some("diagnostics-format"),
+                    desc: Some("Format of the diagnostics printed to stderr."),
+                    values: Some(&[
+                        (
+                            "text",
+                            DiagnosticsFormat::Text,
+                            "Human readable, with source snippets.",
+                        ),
+                        ("json", DiagnosticsFormat::Json, "One JSON object per line."),
+                        (
+                            "sarif",
+                            DiagnosticsFormat::Sarif,
+                            "A SARIF log, written at the end.",
+                        ),
+                    ]),
+                    init: Some(DiagnosticsFormat::Text),
+                    env: Some("JUNO_DIAGNOSTICS_FORMAT"),
+                    ..Default::default()
+                },
+            ),
+            error_limit: Opt::new(
+                cl,
+                OptDesc {
+                    long: Some("error-limit"),
+                    desc: Some(
+                        "Stop reporting diagnostics after this many errors, 0 for no limit (default: 20).",
+                    ),
+                    value_desc: Some("errors"),
+                    init: Some(20),
+                    env: Some("JUNO_ERROR_LIMIT"),
+                    ..Default::default()
+                },
+            ),
+            apply_fixes: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("apply-fixes"),
+                    desc: Some("Apply the fixes of the diagnostics to the input files."),
+                    ..Default::default()
+                },
+            ),
+            xtime: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("Xtime"),
+                    desc: Some("Measure and print times."),
+                    hidden: Hidden::Yes,
+                    ..Default::default()
+                },
+            ),
+            xmem: Opt::new_bool(
+                cl,
+                OptDesc {
+                    long: Some("Xmem"),
+                    desc: Some("Measure and print memory usage."),
+                    hidden: Hidden::Yes,
+                    ..Default::default()
+                },
+            ),
+        }
+    }
+
+    /// Ensure the arguments are valid.
+    /// Return `Err` if there are any conflicts.
     fn validate(&self) -> anyhow::Result<()> {
+        match *self.command {
+            Command::Build => {}
+            Command::Lint => ensure!(
+                self.gen.occurrences() == 0
+                    && !*self.sourcemap
+                    && !*self.bundle
+                    && *self.codemod == Codemod::No
+                    && !*self.run
+                    && self.out_dir.is_none()
+                    && !*self.watch,
+                "The lint subcommand doesn't generate output"
+            ),
+            Command::Deps => ensure!(
+                *self.gen == Gen::Deps,
+                "The deps subcommand generates the dependency graph"
+            ),
+        }
         if *self.sourcemap {
             ensure!(
                 *self.output_path != Path::new("-"),
@@ -380,8 +950,256 @@ impl Options {
             );
             ensure!(*self.gen == Gen::Js, "Source map requires JS output");
         }
+        ensure!(
+            *self.sourcemap || !*self.sources_content,
+            "Sources content requires a source map",
+        );
+        if *self.codemod != Codemod::No {
+            ensure!(!*self.sourcemap, "Codemod doesn't generate source maps");
+            ensure!(!*self.run, "Codemod can't run the AST");
+        }
+        if *self.bundle {
+            ensure!(*self.sema, "Bundling requires semantic analysis");
+            ensure!(*self.gen == Gen::Js, "Bundling requires JS output");
+            ensure!(*self.codemod == Codemod::No, "Codemod can't bundle");
+            ensure!(!*self.run, "Bundling can't run the AST");
+        }
+        if *self.scope_hoist {
+            ensure!(*self.bundle, "Scope hoisting requires bundling");
+        }
+        if *self.tree_shake {
+            ensure!(*self.bundle, "Tree shaking requires bundling");
+        }
+        if *self.gen == Gen::Deps {
+            ensure!(
+                *self.sema,
+                "The dependency graph requires semantic analysis"
+            );
+            ensure!(
+                *self.codemod == Codemod::No,
+                "Codemod can't generate the dependency graph"
+            );
+            ensure!(!*self.run, "The dependency graph can't run the AST");
+        } else {
+            ensure!(
+                *self.deps_format == DepsFormat::Json,
+                "Dependency graph format requires the dependency graph"
+            );
+        }
+        if *self.check_cycles {
+            ensure!(
+                *self.bundle || *self.gen == Gen::Deps || *self.command == Command::Lint,
+                "Only bundling, the dependency graph and linting check cycles"
+            );
+        }
+        if *self.resolver != Resolver::Default {
+            ensure!(
+                *self.bundle || *self.gen == Gen::Deps,
+                "Only bundling and the dependency graph resolve dependencies"
+            );
+        }
+        if *self.resolver != Resolver::Node {
+            ensure!(
+                self.resolve_extensions.values().is_empty() && self.conditions.values().is_empty(),
+                "Extensions and conditions require the Node.js resolver",
+            );
+        }
+        if *self.resolver != Resolver::Haste {
+            ensure!(
+                self.platforms.values().is_empty(),
+                "Platforms require the Haste resolver",
+            );
+        }
+        if self.out_dir.is_some() {
+            ensure!(
+                *self.output_path == Path::new("-"),
+                "--out-dir and --out are exclusive"
+            );
+            ensure!(!*self.bundle, "--out-dir can't bundle");
+            ensure!(*self.codemod == Codemod::No, "Codemod can't use --out-dir");
+            ensure!(!*self.run, "--out-dir can't run the AST");
+            ensure!(
+                matches!(*self.gen, Gen::Js | Gen::ResolvedJs | Gen::Ast),
+                "--out-dir requires JS or AST output"
+            );
+            ensure!(
+                self.input_path.values().iter().all(|p| p != Path::new("-")),
+                "--out-dir can't compile stdin"
+            );
+            let mut outputs = HashMap::<PathBuf, &Path>::new();
+            for input in self.input_path.values() {
+                let output = match out_dir_path(input) {
+                    Some(output) => output,
+                    None => bail!(
+                        "--out-dir can't compile {}, which goes up with '..'",
+                        input.display()
+                    ),
+                };
+                if let Some(other) = outputs.insert(output, input) {
+                    bail!(
+                        "{} and {} would be compiled into the same file of --out-dir",
+                        other.display(),
+                        input.display()
+                    );
+                }
+            }
+        } else {
+            ensure!(*self.jobs == 0, "--jobs requires --out-dir");
+        }
+        if *self.watch {
+            ensure!(
+                *self.bundle || self.out_dir.is_some(),
+                "--watch requires --bundle or --out-dir"
+            );
+            ensure!(
+                self.out_dir.is_some() || *self.output_path != Path::new("-"),
+                "--watch requires --out or --out-dir"
+            );
+            ensure!(
+                self.input_path.values().iter().all(|p| p != Path::new("-")),
+                "--watch can't watch stdin"
+            );
+        }
+        if *self.diagnostics_format == DiagnosticsFormat::Sarif {
+            ensure!(
+                self.out_dir.is_none() && !*self.watch,
+                "SARIF diagnostics can't be used with --out-dir or --watch"
+            );
+        }
+        if *self.apply_fixes {
+            ensure!(!*self.watch, "--apply-fixes can't be used with --watch");
+            ensure!(
+                *self.codemod != Codemod::InPlace,
+                "--apply-fixes and in place codemods are exclusive"
+            );
+            ensure!(
+                self.input_path.values().iter().all(|p| p != Path::new("-")),
+                "--apply-fixes can't rewrite stdin"
+            );
+        }
+        if *self.codemod == Codemod::InPlace {
+            ensure!(
+                self.input_path.values().iter().all(|p| p != Path::new("-")),
+                "Codemod can't rewrite stdin in place",
+            );
+        }
         Ok(())
     }
+
+    /// Copy the values of the options, which can be shared with other threads.
+    fn settings(&self) -> Settings {
+        Settings {
+            command: *self.command,
+            pretty: *self.pretty,
+            gen: *self.gen,
+            deps_format: *self.deps_format,
+            validate_ast: *self.validate_ast,
+            sema: *self.sema,
+            input_path: self.input_path.values().clone(),
+            output_path: (*self.output_path).clone(),
+            out_dir: (*self.out_dir).clone(),
+            jobs: *self.jobs,
+            watch: *self.watch,
+            sourcemap: *self.sourcemap,
+            sources_content: *self.sources_content,
+            base_url: (*self.base_url).clone(),
+            input_source_map: *self.input_source_map,
+            optimize: *self.optimize,
+            strip_flow: *self.strip_flow,
+            rewrite: self.rewrite.values().clone(),
+            codemod: *self.codemod,
+            bundle: *self.bundle,
+            scope_hoist: *self.scope_hoist,
+            tree_shake: *self.tree_shake,
+            resolver: *self.resolver,
+            resolve_extensions: self.resolve_extensions.values().clone(),
+            conditions: self.conditions.values().clone(),
+            platforms: self.platforms.values().clone(),
+            force_async_arrow_space: *self.force_async_arrow_space,
+            emit_doc_block: *self.emit_doc_block,
+            comments: *self.comments,
+            format: *self.format,
+            print_width: *self.print_width,
+            trailing_commas: *self.trailing_commas,
+            semicolons: *self.semicolons,
+            bracket_spacing: *self.bracket_spacing,
+            double_quote_strings: *self.double_quote_strings,
+            run: *self.run,
+            dialect: *self.dialect,
+            jsx: *self.jsx,
+            strict_mode: *self.strict_mode,
+            warn_undefined: *self.warn_undefined,
+            error_unresolved: *self.error_unresolved,
+            check_cycles: *self.check_cycles,
+            color: *self.color,
+            diagnostics_format: *self.diagnostics_format,
+            error_limit: *self.error_limit,
+            apply_fixes: *self.apply_fixes,
+            xtime: *self.xtime,
+            xmem: *self.xmem,
+        }
+    }
+}
+
+/// The values of [`Options`], see their documentation. Unlike the options, which are
+/// bound to the command line, they can be shared with the threads compiling in parallel.
+struct Settings {
+    command: Command,
+    pretty: bool,
+    gen: Gen,
+    deps_format: DepsFormat,
+    validate_ast: bool,
+    sema: bool,
+    input_path: Vec<PathBuf>,
+    output_path: PathBuf,
+    out_dir: Option<PathBuf>,
+    jobs: usize,
+    watch: bool,
+    sourcemap: bool,
+    sources_content: bool,
+    base_url: Option<Url>,
+    input_source_map: InputSourceMap,
+    optimize: bool,
+    strip_flow: bool,
+    rewrite: Vec<String>,
+    codemod: Codemod,
+    bundle: bool,
+    scope_hoist: bool,
+    tree_shake: bool,
+    resolver: Resolver,
+    resolve_extensions: Vec<String>,
+    conditions: Vec<String>,
+    platforms: Vec<String>,
+    force_async_arrow_space: bool,
+    emit_doc_block: bool,
+    comments: Comments,
+    format: bool,
+    print_width: usize,
+    trailing_commas: gen_js::TrailingCommas,
+    semicolons: bool,
+    bracket_spacing: bool,
+    double_quote_strings: bool,
+    run: bool,
+    dialect: ParserDialect,
+    jsx: bool,
+    strict_mode: bool,
+    warn_undefined: bool,
+    error_unresolved: bool,
+    check_cycles: bool,
+    color: Color,
+    diagnostics_format: DiagnosticsFormat,
+    error_limit: usize,
+    apply_fixes: bool,
+    xtime: bool,
+    xmem: bool,
+}
+
+impl Settings {
+    /// Whether to attach comments to the AST: if they are preserved, if tree shaking
+    /// needs them for pure annotations, or if codemods regenerate code containing them.
+    fn attach_comments(&self) -> bool {
+        self.comments != Comments::None || self.tree_shake || self.codemod != Codemod::No
+    }
 }
 
 /// Read the specified file or stdin into a null terminated buffer.
@@ -400,7 +1218,7 @@ fn read_file_or_stdin(input: &Path) -> anyhow::Result<NullTerminatedBuf> {
 fn parse_magic_url(
     parsed: &ParsedJS,
     kind: MagicCommentKind,
-    opt: &Options,
+    opt: &Settings,
 ) -> Result<Option<Url>, Error> {
     parsed
         .magic_comment(kind)
@@ -412,11 +1230,11 @@ fn parse_magic_url(
 /// Consume the URL and fetch and parse the source map from it.
 /// The URL is consumed to ensure that it is freed immediately after it is no
 /// longer needed. Data URLs can potentially contain megabytes of data.
-fn load_source_map(url: Url) -> anyhow::Result<SourceMap> {
+fn load_source_map(url: Url) -> anyhow::Result<FullSourceMap> {
     fetchurl::fetch_url(&url, Default::default())
         .with_context(|| format!("Source map: {}", &url))
         .and_then(|data| {
-            SourceMap::from_slice(data.as_ref())
+            FullSourceMap::from_slice(data.as_ref())
                 .with_context(|| format!("Source map: {}: error parsing", &url))
         })
 }
@@ -438,45 +1256,121 @@ fn script_to_module<'gc>(
     )
 }
 
+/// Run the passes selected by the options on `ast` and return the transformed AST.
+fn run_passes(opt: &Settings, ctx: &mut ast::Context, ast: NodeRc) -> anyhow::Result<NodeRc> {
+    let ast = if opt.strip_flow {
+        PassManager::strip_flow().run(ctx, ast)
+    } else {
+        ast
+    };
+
+    let ast = if !opt.rewrite.is_empty() {
+        let rules: Vec<&str> = opt.rewrite.iter().map(|s| s.as_str()).collect();
+        let mut pm = PassManager::new();
+        pm.add_pass(Box::new(RewritePass::parse(ctx, &rules)?));
+        pm.run(ctx, ast)
+    } else {
+        ast
+    };
+
+    Ok(if opt.optimize {
+        PassManager::standard().run(ctx, ast)
+    } else {
+        ast
+    })
+}
+
+/// Options for generating JS, shared by full generation and codemods.
+fn gen_js_opt<'s>(
+    opt: &Settings,
+    annotation: gen_js::Annotation<'s>,
+    doc_block: Option<Rc<String>>,
+    comments: Option<&'s CommentMap>,
+) -> gen_js::Opt<'s> {
+    gen_js::Opt {
+        pretty: if opt.pretty {
+            gen_js::Pretty::Yes
+        } else {
+            gen_js::Pretty::No
+        },
+        annotation,
+        force_async_arrow_space: opt.force_async_arrow_space,
+        doc_block,
+        quote: if opt.double_quote_strings {
+            gen_js::QuoteChar::Double
+        } else {
+            gen_js::QuoteChar::Single
+        },
+        comments,
+        keep_comments: if opt.comments == Comments::Legal {
+            gen_js::KeepComments::Legal
+        } else {
+            gen_js::KeepComments::All
+        },
+        layout: if opt.format {
+            Some(gen_js::Layout {
+                print_width: opt.print_width,
+                trailing_commas: opt.trailing_commas,
+                semicolons: opt.semicolons,
+                bracket_spacing: opt.bracket_spacing,
+            })
+        } else {
+            None
+        },
+        sources_content: opt.sources_content,
+    }
+}
+
+/// Open `output_path` for writing, or stdout if it is `-`.
+fn create_output(output_path: &Path) -> anyhow::Result<Box<dyn Write>> {
+    Ok(if output_path == Path::new("-") {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(File::create(output_path).with_context(|| output_path.display().to_string())?)
+    })
+}
+
+/// Write `map` next to the output at `output_path` and link to it from `out`.
+fn write_source_map(
+    out: &mut dyn Write,
+    output_path: &Path,
+    map: &FullSourceMap,
+) -> anyhow::Result<()> {
+    // Workaround because `PathBuf` doesn't have a way to append an extension,
+    // only to replace the existing one.
+    let mut path = output_path.to_path_buf().into_os_string();
+    path.push(".map");
+    let sourcemap_file = File::create(PathBuf::from(&path))?;
+    map.to_writer(sourcemap_file)?;
+    write!(out, "\n//# sourceMappingURL={}", path.to_str().unwrap())?;
+    Ok(())
+}
+
 /// Generate the specified output, if any.
 /// Returns whether any output was generated.
 fn gen_output(
-    opt: &Options,
+    opt: &Settings,
     ctx: &mut ast::Context,
     sem: Option<&SemContext>,
     js_module: &ParsedJSModule,
+    output_path: &Path,
 ) -> anyhow::Result<bool> {
-    let output_path = &*opt.output_path;
-    let mut out: Box<dyn Write> = if output_path == Path::new("-") {
-        Box::new(std::io::stdout())
-    } else {
-        Box::new(File::create(output_path).with_context(|| output_path.display().to_string())?)
-    };
+    let mut out = create_output(output_path)?;
 
-    let final_ast = if *opt.strip_flow {
-        PassManager::strip_flow().run(ctx, js_module.ast.clone())
-    } else {
-        js_module.ast.clone()
-    };
+    let final_ast = run_passes(opt, ctx, js_module.ast.clone())?;
 
-    let final_ast = if *opt.optimize {
-        PassManager::standard().run(ctx, final_ast)
-    } else {
-        final_ast
-    };
-
-    if *opt.run {
+    if opt.run {
         juno_eval::run(&final_ast);
         return Ok(true);
     }
 
-    match *opt.gen {
+    match opt.gen {
         Gen::Ast => {
             ast::dump_json(
                 out,
                 ctx,
                 &final_ast,
-                if !*opt.pretty {
+                if !opt.pretty {
                     ast::Pretty::No
                 } else {
                     ast::Pretty::Yes
@@ -489,37 +1383,30 @@ fn gen_output(
                 out.deref_mut(),
                 ctx,
                 &final_ast,
-                gen_js::Opt {
-                    pretty: if *opt.pretty {
-                        gen_js::Pretty::Yes
-                    } else {
-                        gen_js::Pretty::No
-                    },
-                    annotation: match sem {
-                        Some(sem) if *opt.gen == Gen::ResolvedJs => gen_js::Annotation::Sem(sem),
+                gen_js_opt(
+                    opt,
+                    match sem {
+                        Some(sem) if opt.gen == Gen::ResolvedJs => gen_js::Annotation::Sem(sem),
                         _ => gen_js::Annotation::No,
                     },
-                    force_async_arrow_space: *opt.force_async_arrow_space,
-                    doc_block: js_module.doc_block.clone(),
-                    quote: if *opt.double_quote_strings {
-                        gen_js::QuoteChar::Double
-                    } else {
-                        gen_js::QuoteChar::Single
-                    },
-                },
+                    js_module.doc_block.clone(),
+                    js_module.comments.as_ref(),
+                ),
             )?;
-            if *opt.sourcemap {
-                // Workaround because `PathBuf` doesn't have a way to append an extension,
-                // only to replace the existing one.
-                let mut path = output_path.clone().into_os_string();
-                path.push(".map");
-                let sourcemap_file = File::create(PathBuf::from(&path))?;
+            if opt.sourcemap {
+                let generated_map = FullSourceMap::from(generated_map);
                 let merged_map = match &js_module.source_map {
                     None => generated_map,
-                    Some(input_map) => merge_sourcemaps(input_map, &generated_map),
+                    Some(input_map) => {
+                        // Only the source of this module has an input map.
+                        let mut inputs = vec![None; generated_map.map.get_source_count() as usize];
+                        if let Some(input) = inputs.get_mut(js_module.id.0 as usize) {
+                            *input = Some(input_map);
+                        }
+                        compose_sourcemaps(&generated_map, &inputs)
+                    }
                 };
-                merged_map.to_writer(sourcemap_file)?;
-                write!(out, "\n//# sourceMappingURL={}", path.to_str().unwrap())?;
+                write_source_map(out.deref_mut(), output_path, &merged_map)?;
             }
             Ok(true)
         }
@@ -529,7 +1416,276 @@ fn gen_output(
             }
             Ok(true)
         }
+        Gen::Deps => unreachable!("The dependency graph is generated by gen_deps"),
+    }
+}
+
+/// Create the resolver of the dependencies of modules selected by `opt`.
+fn dependency_resolver(opt: &Settings, sm: &SourceManager) -> Box<dyn DependencyResolver> {
+    match opt.resolver {
+        Resolver::Default => Box::new(DefaultResolver::new(sm)),
+        Resolver::Node => {
+            let mut node_opts = NodeResolverOptions {
+                conditions: opt.conditions.clone(),
+                ..Default::default()
+            };
+            if !opt.resolve_extensions.is_empty() {
+                node_opts.extensions = opt.resolve_extensions.clone();
+            }
+            Box::new(NodeResolver::new(node_opts))
+        }
+        Resolver::Haste => Box::new(HasteResolver::new(
+            sm,
+            HasteResolverOptions {
+                platforms: opt.platforms.clone(),
+                ..Default::default()
+            },
+        )),
+    }
+}
+
+/// The transformed AST and semantic information of the analyzed modules.
+type Analyzed = HashMap<SourceId, (NodeRc, SemContext)>;
+
+/// Sort the parsed modules in the order of their IDs.
+fn sorted_modules(js_modules: HashMap<SourceId, ParsedJSModule>) -> Vec<ParsedJSModule> {
+    let mut js_modules: Vec<ParsedJSModule> = js_modules.into_values().collect();
+    js_modules.sort_by_key(|module| module.id.0);
+    js_modules
+}
+
+/// Run the passes and semantic analysis on every module which isn't in `analyzed` yet,
+/// in order, and on the files loaded to resolve their dependencies, which are appended
+/// to `js_modules`.
+/// Return whether there were no errors.
+fn analyze_modules(
+    opt: &Settings,
+    ctx: &mut ast::Context,
+    js_modules: &mut Vec<ParsedJSModule>,
+    analyzed: &mut Analyzed,
+    timer: &mut Timer,
+) -> anyhow::Result<bool> {
+    let resolver = dependency_resolver(opt, ctx.sm());
+    let mut i = 0;
+    while i < js_modules.len() {
+        let module = &js_modules[i];
+        i += 1;
+        if analyzed.contains_key(&module.id) {
+            continue;
+        }
+        let ast = run_passes(opt, ctx, module.ast.clone())?;
+        let lock = ast::GCLock::new(ctx);
+        let sem = sema::resolve_module(&lock, ast.node(&lock), module.id, resolver.as_ref());
+        drop(lock);
+        analyzed.insert(module.id, (ast, sem));
+
+        // Parse the files that were loaded to resolve the dependencies of the module.
+        for id in resolver.load_pending(ctx.sm_mut()) {
+            match parse_module(opt, ctx, id, true, timer)? {
+                Some(module) => js_modules.push(module),
+                None => return Ok(false),
+            }
+        }
+    }
+    if opt.check_cycles {
+        let lock = ast::GCLock::new(ctx);
+        DependencyGraph::build(
+            &lock,
+            js_modules
+                .iter()
+                .map(|module| (module.id, &analyzed[&module.id].1)),
+        )
+        .report_cycles(lock.sm());
     }
+    print_counts(opt, ctx.sm());
+    Ok(ctx.sm().num_errors() == 0)
+}
+
+/// Print the number of errors and warnings, if there are any and the diagnostics are
+/// human readable.
+fn print_counts(opt: &Settings, sm: &SourceManager) {
+    if opt.diagnostics_format == DiagnosticsFormat::Text
+        && (sm.num_errors() != 0 || sm.num_warnings() != 0)
+    {
+        eprintln!(
+            "{} error(s), {} warning(s)",
+            sm.num_errors(),
+            sm.num_warnings()
+        );
+    }
+}
+
+/// Write the graph of the dependencies between all the modules to the output.
+fn gen_deps(
+    opt: &Settings,
+    ctx: &mut ast::Context,
+    js_modules: HashMap<SourceId, ParsedJSModule>,
+    timer: &mut Timer,
+) -> anyhow::Result<TransformStatus> {
+    let mut js_modules = sorted_modules(js_modules);
+    let mut analyzed = Analyzed::new();
+    if !analyze_modules(opt, ctx, &mut js_modules, &mut analyzed, timer)? {
+        return Ok(TransformStatus::Error);
+    }
+    let lock = ast::GCLock::new(ctx);
+    let graph = DependencyGraph::build(
+        &lock,
+        js_modules
+            .iter()
+            .map(|module| (module.id, &analyzed[&module.id].1)),
+    );
+    let mut out = create_output(&opt.output_path)?;
+    match opt.deps_format {
+        DepsFormat::Json => {
+            let pretty = if opt.pretty {
+                ast::Pretty::Yes
+            } else {
+                ast::Pretty::No
+            };
+            graph.write_json(lock.sm(), out, pretty)?;
+        }
+        DepsFormat::Dot => graph.write_dot(lock.sm(), out.deref_mut())?,
+    }
+    Ok(TransformStatus::Success)
+}
+
+/// Run the passes and semantic analysis on every module, only reporting the diagnostics.
+fn lint(
+    opt: &Settings,
+    ctx: &mut ast::Context,
+    js_modules: HashMap<SourceId, ParsedJSModule>,
+    timer: &mut Timer,
+) -> anyhow::Result<TransformStatus> {
+    let mut js_modules = sorted_modules(js_modules);
+    let mut analyzed = Analyzed::new();
+    if !analyze_modules(opt, ctx, &mut js_modules, &mut analyzed, timer)? {
+        return Ok(TransformStatus::Error);
+    }
+    Ok(TransformStatus::Success)
+}
+
+/// Run the passes and semantic analysis on every module which isn't in `analyzed` yet,
+/// then bundle `entry` and the modules it depends on into the output.
+fn gen_bundle(
+    opt: &Settings,
+    ctx: &mut ast::Context,
+    js_modules: &mut Vec<ParsedJSModule>,
+    analyzed: &mut Analyzed,
+    entry: SourceId,
+    timer: &mut Timer,
+) -> anyhow::Result<TransformStatus> {
+    if !analyze_modules(opt, ctx, js_modules, analyzed, timer)? {
+        return Ok(TransformStatus::Error);
+    }
+
+    let mut modules: HashMap<SourceId, bundle::BundleModule> = js_modules
+        .iter()
+        .map(|module| {
+            let (ast, sem) = &analyzed[&module.id];
+            (
+                module.id,
+                bundle::BundleModule {
+                    ast: ast.clone(),
+                    sem,
+                    source_map: module.source_map.as_ref(),
+                    comments: module.comments.as_ref(),
+                },
+            )
+        })
+        .collect();
+    if opt.tree_shake {
+        let side_effects: HashMap<SourceId, bool> = modules
+            .keys()
+            .map(|&id| {
+                let path = Path::new(ctx.sm().source_name(id));
+                (id, bundle::package_side_effects(path))
+            })
+            .collect();
+        let shaken = bundle::tree_shake(ctx, entry, &modules, |id| side_effects[&id]);
+        modules = shaken
+            .into_iter()
+            .map(|(id, ast)| {
+                (
+                    id,
+                    bundle::BundleModule {
+                        ast,
+                        ..modules[&id]
+                    },
+                )
+            })
+            .collect();
+    }
+    // Comments are only attached for tree shaking if they aren't preserved.
+    if opt.comments == Comments::None {
+        for module in modules.values_mut() {
+            module.comments = None;
+        }
+    }
+    let gen_opt = gen_js_opt(opt, gen_js::Annotation::No, None, None);
+    let result = if opt.scope_hoist {
+        bundle::bundle_scope_hoisted(ctx, entry, &modules, gen_opt)?
+    } else {
+        bundle::bundle(ctx, entry, &modules, gen_opt)?
+    };
+
+    let output_path = &opt.output_path;
+    let mut out = create_output(output_path)?;
+    out.write_all(result.code.as_bytes())?;
+    if opt.sourcemap {
+        write_source_map(out.deref_mut(), output_path, &result.source_map)?;
+    }
+    Ok(TransformStatus::Success)
+}
+
+/// Apply the passes to every module and regenerate the changed parts of its source,
+/// either rewriting the file in place or printing a unified diff.
+fn run_codemod(
+    opt: &Settings,
+    ctx: &mut ast::Context,
+    js_modules: HashMap<SourceId, ParsedJSModule>,
+) -> anyhow::Result<()> {
+    let mut modules: Vec<ParsedJSModule> = js_modules.into_values().collect();
+    modules.sort_by_key(|module| module.id.0);
+
+    let output_path = &opt.output_path;
+    let mut out: Box<dyn Write> = if output_path == Path::new("-") {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(File::create(output_path).with_context(|| output_path.display().to_string())?)
+    };
+
+    for module in modules {
+        let transformed = run_passes(opt, ctx, module.ast.clone())?;
+
+        let lock = ast::GCLock::new(ctx);
+        let name = lock.sm().source_name(module.id).to_string();
+        let buf = lock.sm().source_buffer_rc(module.id);
+        // Drop the null terminator.
+        let bytes = &buf.as_bytes()[..buf.len() - 1];
+        let source = std::str::from_utf8(bytes).with_context(|| name.clone())?;
+
+        let edits = codemod::compute_edits(
+            &lock,
+            source,
+            module.ast.node(&lock),
+            transformed.node(&lock),
+            gen_js_opt(opt, gen_js::Annotation::No, None, module.comments.as_ref()),
+        );
+        let result = codemod::apply_edits(source, &edits);
+        if result == source {
+            continue;
+        }
+
+        match opt.codemod {
+            Codemod::InPlace => std::fs::write(&name, result).with_context(|| name.clone())?,
+            Codemod::Diff => {
+                let diff = codemod::unified_diff(&name, &name, source, &result);
+                out.write_all(diff.as_bytes())?;
+            }
+            Codemod::No => unreachable!("not in codemod mode"),
+        }
+    }
+    Ok(())
 }
 
 /// TransformStatus indicates whether there were parse or validation errors
@@ -553,113 +1709,508 @@ struct ParsedJSModule {
     id: SourceId,
     /// AST node, may be either `Program` or `Module`.
     ast: NodeRc,
-    source_map: Option<SourceMap>,
+    source_map: Option<FullSourceMap>,
     /// Doc block for the file if it exists.
     doc_block: Option<Rc<String>>,
+    /// Comments attached to the AST, see [`Settings::attach_comments`].
+    comments: Option<CommentMap>,
 }
 
-fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
-    opt.validate()?;
+/// Parse the registered source `file_id`, converting it to a module if `as_module`.
+/// Return `None` if there were errors.
+fn parse_module(
+    opt: &Settings,
+    ctx: &mut ast::Context,
+    file_id: SourceId,
+    as_module: bool,
+    timer: &mut Timer,
+) -> anyhow::Result<Option<ParsedJSModule>> {
+    let buf = ctx.sm().source_buffer_rc(file_id);
 
-    let mut ctx = ast::Context::new();
+    // Parse.
+    let parsed = hparser::ParsedJS::parse(
+        hparser::ParserFlags {
+            strict_mode: ctx.strict_mode(),
+            enable_jsx: opt.jsx,
+            dialect: opt.dialect,
+            store_doc_block: opt.emit_doc_block,
+            store_comments: opt.attach_comments(),
+        },
+        &buf,
+    );
+    timer.mark("Parse");
+    parsed.report_messages(ctx.sm(), file_id);
+    if parsed.has_errors() {
+        return Ok(None);
+    }
+
+    // Extract the optional source mapping URL.
+    let sm_url = if opt.input_source_map != InputSourceMap::Ignore {
+        parse_magic_url(&parsed, MagicCommentKind::SourceMappingUrl, opt)?
+    } else {
+        None
+    };
+
+    let (ast, comments) = {
+        // Convert to Juno AST.
+        let lock = ast::GCLock::new(ctx);
+        let root = match parsed.to_ast(&lock, file_id) {
+            None => return Ok(None),
+            Some(program) => {
+                if as_module {
+                    script_to_module(&lock, node_cast!(ast::Node::Program, program))
+                } else {
+                    program
+                }
+            }
+        };
+        let comments = if opt.attach_comments() {
+            Some(CommentMap::attach(&lock, root, parsed.comments(file_id)))
+        } else {
+            None
+        };
+        (NodeRc::from_node(&lock, root), comments)
+    };
+    let doc_block = parsed.get_doc_block().map(|s| Rc::new(s.to_string()));
+    // We don't need the original parser anymore.
+    drop(parsed);
+    timer.mark("Cvt");
+
+    if opt.validate_ast {
+        validate_tree(ctx, &ast).with_context(|| ctx.sm().source_name(file_id).to_string())?;
+        timer.mark("Validate AST");
+    }
+
+    // Fetch and parse the source map before we generate the output.
+    let source_map = sm_url.map(load_source_map).transpose()?;
+
+    Ok(Some(ParsedJSModule {
+        id: file_id,
+        ast,
+        source_map,
+        doc_block,
+        comments,
+    }))
+}
 
-    // Propagate flags.
-    if *opt.strict_mode {
+/// Create a context with the flags of `opt`.
+fn new_context(opt: &Settings) -> ast::Context {
+    let mut ctx = ast::Context::new();
+    if opt.strict_mode {
         ctx.enable_strict_mode();
     }
-    ctx.warn_undefined = *opt.warn_undefined;
+    ctx.warn_undefined = opt.warn_undefined;
+    ctx.error_unresolved = opt.error_unresolved;
+    let sm = ctx.sm_mut();
+    sm.set_handler(match opt.diagnostics_format {
+        DiagnosticsFormat::Text => Box::new(TerminalHandler::new(match opt.color {
+            Color::Auto => std::io::stderr().is_terminal(),
+            Color::Always => true,
+            Color::Never => false,
+        })),
+        DiagnosticsFormat::Json => Box::new(JsonLinesHandler::new()),
+        DiagnosticsFormat::Sarif => Box::new(SarifHandler::new(if opt.pretty {
+            ast::Pretty::Yes
+        } else {
+            ast::Pretty::No
+        })),
+    });
+    sm.set_error_limit(opt.error_limit);
+    if opt.apply_fixes {
+        sm.collect_fixes();
+    }
+    ctx
+}
+
+/// Apply the fixes collected by `sm` to their files.
+/// Fixes which conflict with a previously reported fix are skipped.
+fn apply_fixes(opt: &Settings, sm: &SourceManager) -> anyhow::Result<()> {
+    let fixes = sm.take_fixes();
+    let mut by_file = BTreeMap::<u32, Vec<&[diagnostics::TextEdit]>>::new();
+    for fix in &fixes {
+        by_file.entry(fix.file.0).or_default().push(&fix.edits);
+    }
+    for (file, edits) in by_file {
+        let file = SourceId(file);
+        let name = sm.source_name(file);
+        let buf = sm.source_buffer(file);
+        // Drop the null terminator.
+        let source = std::str::from_utf8(&buf.as_bytes()[..buf.len() - 1])
+            .with_context(|| name.to_string())?;
+        let total = edits.len();
+        let (result, applied) = diagnostics::apply_fixes(source, edits);
+        if applied != 0 {
+            std::fs::write(name, result).with_context(|| name.to_string())?;
+        }
+        if opt.diagnostics_format == DiagnosticsFormat::Text {
+            sm.output(format!(
+                "{}: applied {} of {} fix(es)",
+                name, applied, total
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Return the path in `--out-dir` of the file compiled from `input`, which is `input`
+/// relative to the current directory or the root.
+/// Return `None` if `input` goes up with `..`, so that it can't be compiled outside of
+/// `--out-dir` or into the file of another input.
+fn out_dir_path(input: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in input.components() {
+        match component {
+            Component::Normal(name) => relative.push(name),
+            Component::ParentDir => return None,
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    Some(relative)
+}
+
+/// Compile the script `input` into its own file in `out_dir`, see [`out_dir_path`].
+fn compile_file(
+    opt: &Settings,
+    ctx: &mut ast::Context,
+    input: &Path,
+    out_dir: &Path,
+    timer: &mut Timer,
+) -> anyhow::Result<TransformStatus> {
+    let file_id = ctx
+        .sm_mut()
+        .add_source(input.display().to_string(), read_file_or_stdin(input)?);
+    let js_module = match parse_module(opt, ctx, file_id, false, timer)? {
+        Some(js_module) => js_module,
+        None => return Ok(TransformStatus::Error),
+    };
+    let sem = if opt.sema {
+        let lock = ast::GCLock::new(ctx);
+        let sem = sema::resolve_program(&lock, file_id, js_module.ast.node(&lock));
+        if lock.sm().num_errors() != 0 {
+            return Ok(TransformStatus::Error);
+        }
+        timer.mark("Sema");
+        Some(sem)
+    } else {
+        None
+    };
+
+    let output_path = out_dir.join(out_dir_path(input).expect("inputs are validated"));
+    if let Some(dir) = output_path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| dir.display().to_string())?;
+    }
+    if gen_output(opt, ctx, sem.as_ref(), &js_module, &output_path)? {
+        timer.mark("Gen");
+    }
+    Ok(TransformStatus::Success)
+}
+
+/// The result of compiling one of the inputs of [`compile_all`].
+struct Compiled {
+    /// Index of the input.
+    index: usize,
+    /// The buffered diagnostics.
+    diagnostics: Vec<String>,
+    /// Times of the compilation of the input.
+    timer: Timer,
+    status: anyhow::Result<TransformStatus>,
+}
+
+/// Compile every input into its own file in `out_dir` on a pool of threads.
+/// Contexts aren't `Send`, so every file is compiled in a context of the thread which
+/// compiles it. The diagnostics are buffered, and printed for all the inputs at the end,
+/// each input with its own SARIF log if that is the format.
+fn compile_all(
+    opt: &Settings,
+    inputs: &[PathBuf],
+    out_dir: &Path,
+) -> anyhow::Result<TransformStatus> {
+    let jobs = match opt.jobs {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        jobs => jobs,
+    };
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Compiled> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.min(inputs.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let input = match inputs.get(index) {
+                            Some(input) => input,
+                            None => break,
+                        };
+                        let mut ctx = new_context(opt);
+                        ctx.sm_mut().buffer_diagnostics();
+                        let mut timer = Timer::new();
+                        let status = compile_file(opt, &mut ctx, input, out_dir, &mut timer);
+                        ctx.sm().finish_diagnostics();
+                        let status = status.and_then(|status| {
+                            if opt.apply_fixes {
+                                apply_fixes(opt, ctx.sm())?;
+                            }
+                            Ok(status)
+                        });
+                        results.push(Compiled {
+                            index,
+                            diagnostics: ctx.sm().take_buffered(),
+                            timer,
+                            status,
+                        });
+                    }
+                    results
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Worker panicked"))
+            .collect()
+    });
+    results.sort_by_key(|compiled| compiled.index);
+
+    let mut failed = 0;
+    for Compiled {
+        index,
+        diagnostics,
+        timer,
+        status,
+    } in results
+    {
+        for diagnostic in diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        if opt.xtime {
+            print!("{}:\n{:#}", inputs[index].display(), timer);
+        }
+        match status {
+            Ok(TransformStatus::Success) => {}
+            Ok(TransformStatus::Error) => failed += 1,
+            Err(e) => {
+                eprintln!("{}: {:#}", inputs[index].display(), e);
+                failed += 1;
+            }
+        }
+    }
+    if failed != 0 {
+        eprintln!("{} of {} file(s) failed", failed, inputs.len());
+        return Ok(TransformStatus::Error);
+    }
+    Ok(TransformStatus::Success)
+}
+
+/// Compile every input into `out_dir`, then compile the inputs again whenever they change.
+/// Errors are reported without stopping, the inputs are compiled again after they change.
+fn watch_all(opt: &Settings, out_dir: &Path) -> anyhow::Result<TransformStatus> {
+    let inputs = &opt.input_path;
+    let mut poller = Poller::new();
+    for input in inputs {
+        poller.watch(input);
+    }
+    if let Err(e) = compile_all(opt, inputs, out_dir) {
+        eprintln!("{:#}", e);
+    }
+    loop {
+        let changed = poller.wait();
+        eprintln!("Rebuilding {} changed file(s)", changed.len());
+        if let Err(e) = compile_all(opt, &changed, out_dir) {
+            eprintln!("{:#}", e);
+        }
+    }
+}
+
+/// Read the registered source `id` again from its file and parse it as a module.
+fn reload_module(
+    opt: &Settings,
+    ctx: &mut ast::Context,
+    id: SourceId,
+    timer: &mut Timer,
+) -> anyhow::Result<Option<ParsedJSModule>> {
+    let buf = read_file_or_stdin(Path::new(ctx.sm().source_name(id)))?;
+    ctx.sm_mut().replace_source(id, buf);
+    parse_module(opt, ctx, id, true, timer)
+}
+
+/// Bundle the inputs, then bundle them again whenever any of them, or the files loaded
+/// to resolve their dependencies, change.
+/// Only the changed files are parsed and analyzed again, the others are kept in memory.
+/// Errors are reported without stopping, the files which failed are parsed again and
+/// the modules bundled again after the next change.
+fn watch_bundle(
+    opt: &Settings,
+    ctx: &mut ast::Context,
+    timer: &mut Timer,
+) -> anyhow::Result<TransformStatus> {
+    // The inputs are read when they are parsed, like the files which changed.
+    let mut entry = None;
+    for path in &opt.input_path {
+        let id = ctx.sm_mut().add_source(
+            path.display().to_string(),
+            NullTerminatedBuf::from_str_copy(""),
+        );
+        entry.get_or_insert(id);
+    }
+    let entry = entry.expect("at least one input is required");
+
+    let mut js_modules = Vec::<ParsedJSModule>::new();
+    let mut analyzed = Analyzed::new();
+    let mut poller = Poller::new();
+    loop {
+        // Parse every source without a module, because it changed, or it was never
+        // parsed successfully.
+        let mut parsed = true;
+        for id in (0..ctx.sm().num_sources() as u32).map(SourceId) {
+            if js_modules.iter().any(|module| module.id == id) {
+                continue;
+            }
+            match reload_module(opt, ctx, id, timer) {
+                Ok(Some(module)) => js_modules.push(module),
+                Ok(None) => parsed = false,
+                Err(e) => {
+                    eprintln!("{:#}", e);
+                    parsed = false;
+                }
+            }
+        }
+        js_modules.sort_by_key(|module| module.id.0);
+        if parsed {
+            if let Err(e) = gen_bundle(opt, ctx, &mut js_modules, &mut analyzed, entry, timer) {
+                eprintln!("{:#}", e);
+            }
+        }
+        ctx.sm().finish_diagnostics();
+        for id in 0..ctx.sm().num_sources() as u32 {
+            poller.watch(Path::new(ctx.sm().source_name(SourceId(id))));
+        }
+
+        let changed = poller.wait();
+        eprintln!("Rebuilding after {} file(s) changed", changed.len());
+        ctx.sm_mut().reset_counts();
+        for path in changed {
+            let id = ctx
+                .sm()
+                .lookup_name(path.to_string_lossy())
+                .expect("only sources are watched");
+            js_modules.retain(|module| module.id != id);
+            analyzed.remove(&id);
+        }
+    }
+}
+
+fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
+    opt.validate()?;
+    let opt = &opt.settings();
+
+    if let Some(out_dir) = &opt.out_dir {
+        return if opt.watch {
+            watch_all(opt, out_dir)
+        } else {
+            compile_all(opt, &opt.input_path, out_dir)
+        };
+    }
+
+    let mut ctx = new_context(opt);
 
     // Start measuring time.
     let mut timer = Timer::new();
 
+    let status = transform(opt, &mut ctx, &mut timer);
+    ctx.sm().finish_diagnostics();
+    if opt.apply_fixes {
+        apply_fixes(opt, ctx.sm())?;
+    }
+    if let TransformStatus::Error = status? {
+        return Ok(TransformStatus::Error);
+    }
+
+    // Optionally print memory usage.
+    if opt.xmem {
+        println!("Context size:  {} MB", ctx.heap_size() / 1_000_000);
+        println!("Storage size:  {} MB", ctx.storage_size() / 1_000_000);
+        println!("# nodes:       {}", ctx.num_nodes());
+    }
+
+    // Drop the AST. We are doing it explicitly just to measure the time.
+    drop(ctx);
+    timer.mark("Drop");
+
+    // Optionally print elapsed times.
+    if opt.xtime {
+        print!("{:#}", timer);
+    }
+
+    Ok(TransformStatus::Success)
+}
+
+/// Parse the inputs and generate the output selected by `opt`.
+fn transform(
+    opt: &Settings,
+    ctx: &mut ast::Context,
+    timer: &mut Timer,
+) -> anyhow::Result<TransformStatus> {
+    // Only bundles are watched here, `--out-dir` is watched by `watch_all`.
+    if opt.watch {
+        return watch_bundle(opt, ctx, timer);
+    }
+
     // Read the input into memory.
-    let input_paths = opt.input_path.values();
+    let input_paths = &opt.input_path;
 
     let mut js_modules = HashMap::<SourceId, ParsedJSModule>::new();
+    // The first input, which is the entry point of a bundle.
+    let mut entry = None;
 
     for path in input_paths {
         let input = path.as_path();
         let file_id = ctx
             .sm_mut()
             .add_source(input.display().to_string(), read_file_or_stdin(input)?);
-        let buf = ctx.sm().source_buffer_rc(file_id);
-
-        // Parse.
-        let parsed = hparser::ParsedJS::parse(
-            hparser::ParserFlags {
-                strict_mode: ctx.strict_mode(),
-                enable_jsx: *opt.jsx,
-                dialect: *opt.dialect,
-                store_doc_block: *opt.emit_doc_block,
-            },
-            &buf,
-        );
-        timer.mark("Parse");
-        if let Some(e) = parsed.first_error() {
-            ctx.sm().error(SourceRange::from_loc(file_id, e.0), e.1);
-            return Ok(TransformStatus::Error);
-        }
+        entry.get_or_insert(file_id);
 
-        // Extract the optional source mapping URL.
-        let sm_url = if *opt.input_source_map != InputSourceMap::Ignore {
-            parse_magic_url(&parsed, MagicCommentKind::SourceMappingUrl, opt)?
-        } else {
-            None
-        };
-
-        let ast = {
-            // Convert to Juno AST.
-            let lock = ast::GCLock::new(&mut ctx);
-            match parsed.to_ast(&lock, file_id) {
-                None => return Ok(TransformStatus::Error),
-                Some(program) => {
-                    if input_paths.len() > 1 {
-                        NodeRc::from_node(
-                            &lock,
-                            script_to_module(&lock, node_cast!(ast::Node::Program, program)),
-                        )
-                    } else {
-                        NodeRc::from_node(&lock, program)
-                    }
-                }
+        // Codemods must preserve the original structure of the files.
+        let as_module = (input_paths.len() > 1
+            || opt.bundle
+            || opt.gen == Gen::Deps
+            || opt.command == Command::Lint)
+            && opt.codemod == Codemod::No;
+        match parse_module(opt, ctx, file_id, as_module, timer)? {
+            Some(module) => {
+                js_modules.insert(file_id, module);
             }
-        };
-        let doc_block = parsed.get_doc_block().map(|s| Rc::new(s.to_string()));
-        // We don't need the original parser anymore.
-        drop(parsed);
-        timer.mark("Cvt");
-
-        if *opt.validate_ast {
-            validate_tree(&mut ctx, &ast).with_context(|| input.display().to_string())?;
-            timer.mark("Validate AST");
-        }
-
-        // Fetch and parse the source map before we generate the output.
-        let source_map = sm_url.map(load_source_map).transpose()?;
-
-        js_modules.insert(
-            file_id,
-            ParsedJSModule {
-                id: file_id,
-                ast,
-                source_map,
-                doc_block,
-            },
-        );
+            None => return Ok(TransformStatus::Error),
+        }
     }
 
-    if js_modules.len() == 1 {
+    if opt.command == Command::Lint {
+        if let TransformStatus::Error = lint(opt, ctx, js_modules, timer)? {
+            return Ok(TransformStatus::Error);
+        }
+        timer.mark("Lint");
+    } else if opt.codemod != Codemod::No {
+        run_codemod(opt, ctx, js_modules)?;
+        timer.mark("Codemod");
+    } else if opt.gen == Gen::Deps {
+        if let TransformStatus::Error = gen_deps(opt, ctx, js_modules, timer)? {
+            return Ok(TransformStatus::Error);
+        }
+        timer.mark("Deps");
+    } else if opt.bundle {
+        let entry = entry.expect("at least one input is required");
+        let mut js_modules = sorted_modules(js_modules);
+        let mut analyzed = Analyzed::new();
+        if let TransformStatus::Error =
+            gen_bundle(opt, ctx, &mut js_modules, &mut analyzed, entry, timer)?
+        {
+            return Ok(TransformStatus::Error);
+        }
+        timer.mark("Bundle");
+    } else if js_modules.len() == 1 {
         let js_module = js_modules.into_values().next().unwrap();
-        let sem = if *opt.sema {
-            let lock = ast::GCLock::new(&mut ctx);
+        let sem = if opt.sema {
+            let lock = ast::GCLock::new(ctx);
             let sem = sema::resolve_program(&lock, js_module.id, js_module.ast.node(&lock));
-            if lock.sm().num_errors() != 0 || lock.sm().num_warnings() != 0 {
-                eprintln!(
-                    "{} error(s), {} warning(s)",
-                    lock.sm().num_errors(),
-                    lock.sm().num_warnings()
-                );
-            }
+            print_counts(opt, lock.sm());
             if lock.sm().num_errors() != 0 {
                 return Ok(TransformStatus::Error);
             }
@@ -671,19 +2222,19 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
         };
 
         // Generate output.
-        if gen_output(opt, &mut ctx, sem.as_ref(), &js_module)? {
+        if gen_output(opt, ctx, sem.as_ref(), &js_module, &opt.output_path)? {
             timer.mark("Gen");
         }
     } else {
         // Show information about semantic resolution for all modules if requested.
-        if *opt.sema {
+        if opt.sema {
             println!("{} modules", js_modules.len());
             let mut sems = Vec::new();
-            let resolver = resolve_dependency::DefaultResolver::new(ctx.sm());
+            let resolver = DefaultResolver::new(ctx.sm());
             for module in js_modules.into_values() {
                 let sem;
                 {
-                    let lock = ast::GCLock::new(&mut ctx);
+                    let lock = ast::GCLock::new(ctx);
                     sem = sema::resolve_module(&lock, module.ast.node(&lock), module.id, &resolver);
 
                     let source_name = lock.sm().source_name(module.id);
@@ -698,7 +2249,7 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
                     }
                 }
                 // Generate output.
-                if gen_output(opt, &mut ctx, Some(&sem), &module)? {
+                if gen_output(opt, ctx, Some(&sem), &module, &opt.output_path)? {
                     timer.mark("Gen");
                 }
                 sems.push(sem);
@@ -710,29 +2261,57 @@ fn run(opt: &Options) -> anyhow::Result<TransformStatus> {
         }
     }
 
-    // Optionally print memory usage.
-    if *opt.xmem {
-        println!("Context size:  {} MB", ctx.heap_size() / 1_000_000);
-        println!("Storage size:  {} MB", ctx.storage_size() / 1_000_000);
-        println!("# nodes:       {}", ctx.num_nodes());
-    }
-
-    // Drop the AST. We are doing it explicitly just to measure the time.
-    drop(ctx);
-    timer.mark("Drop");
+    Ok(TransformStatus::Success)
+}
 
-    // Optionally print elapsed times.
-    if *opt.xtime {
-        print!("{:#}", timer);
+/// Find the configuration file and the subcommand selected by the command line
+/// `args`, if they are valid.
+fn find_seeds(args: &[String]) -> Option<(Option<PathBuf>, Command)> {
+    // The inputs are needed to find the file, so parse the arguments once
+    // without seeding.
+    let mut cl = CommandLine::new("A JavaScript compiler");
+    let opt = Options::new(&mut cl);
+    match cl.parse(args) {
+        Ok(CommandLineIntent::Normal) => {
+            let config = match &*opt.config {
+                Some(path) => Some(path.clone()),
+                None => config::find_config(&opt.input_path[0]),
+            };
+            Some((config, *opt.command))
+        }
+        _ => None,
     }
-
-    Ok(TransformStatus::Success)
 }
 
-fn main() {
+/// Parse the options from the command line, seeding them from the
+/// configuration file and the subcommand.
+fn parse_options() -> Options {
+    let args: Vec<String> = std::env::args().collect();
     let mut cl = CommandLine::new("A JavaScript compiler");
     let opt = Options::new(&mut cl);
+    if let Some((config, command)) = find_seeds(&args) {
+        if let Some(path) = config {
+            if let Err(e) = config::load_config(&cl, &path) {
+                eprintln!("{:#}", e);
+                exit(1);
+            }
+        }
+        // The options implied by the subcommand can still be overridden.
+        let implied = match command {
+            Command::Build => None,
+            Command::Lint => Some("warn-undefined"),
+            Command::Deps => Some("gen-deps"),
+        };
+        if let Some(name) = implied {
+            cl.seed(name, None).expect("implied option must exist");
+        }
+    }
     cl.parse_env_args();
+    opt
+}
+
+fn main() {
+    let opt = parse_options();
 
     match run(&opt) {
         Ok(TransformStatus::Success) => {}
@@ -0,0 +1,62 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// Interval between two polls of the watched files.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Detects changes to files by polling their modification times, which works on
+/// every platform.
+#[derive(Debug, Default)]
+pub struct Poller {
+    /// Modification time of every watched file, `None` if it can't be read, e.g. because
+    /// the file was removed.
+    mtimes: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl Poller {
+    pub fn new() -> Poller {
+        Default::default()
+    }
+
+    /// Start watching `path`, if it isn't watched yet.
+    pub fn watch(&mut self, path: &Path) {
+        if !self.mtimes.contains_key(path) {
+            self.mtimes.insert(path.to_path_buf(), mtime(path));
+        }
+    }
+
+    /// Block until some of the watched files change, and return them sorted.
+    pub fn wait(&mut self) -> Vec<PathBuf> {
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let mut changed = vec![];
+            for (path, time) in &mut self.mtimes {
+                let new_time = mtime(path);
+                if new_time != *time {
+                    *time = new_time;
+                    changed.push(path.clone());
+                }
+            }
+            if !changed.is_empty() {
+                changed.sort();
+                return changed;
+            }
+        }
+    }
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -15,3 +15,9 @@ pub use manager::PassManager;
 
 mod passes;
 pub use passes::*;
+
+mod rewrite;
+pub use rewrite::Condition;
+pub use rewrite::RewritePass;
+pub use rewrite::RewriteRule;
+pub use rewrite::RuleError;
@@ -0,0 +1,607 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Declarative rewrite rules for writing simple passes.
+//!
+//! A rule is written as JS source of the form
+//! ```text
+//! PATTERN => REPLACEMENT [where $x is CONDITION, ...]
+//! ```
+//! `PATTERN` and `REPLACEMENT` must each be a single expression or statement.
+//! If both are expressions the rule applies to expressions, otherwise it applies to statements.
+//! Identifiers starting with `$` are metavariables: in the pattern they match any node and bind
+//! it, and in the replacement they are substituted with the node they were bound to.
+//! A metavariable which is used more than once in the pattern only matches if all the nodes it
+//! would bind are structurally equal. `$_` matches any node without binding it.
+//! Identifiers starting with `$` are written with another `$` in front, e.g. `$$` is `$`.
+//!
+//! For example, the [`crate::add_negative`] pass can be written as
+//! ```text
+//! $x + -$y => $x - $y
+//! ```
+//! and a rule which is only valid when evaluating an operand has no side effects as
+//! ```text
+//! ($x, $y) => $y where $x is pure
+//! ```
+//!
+//! Supported conditions are `pure`, `literal` and `identifier`.
+//!
+//! Rules are applied top-down: when a node matches, it is replaced and then the children of the
+//! replacement are visited, so a replacement is never matched against the rule that produced it.
+//! The metavariables in the replacement refer to the matched nodes themselves, everything else
+//! in the replacement is freshly allocated with the source range of the matched node.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+
+use juno::ast::*;
+use juno::hparser;
+use juno::hparser::ParseError;
+use juno::hparser::ParsedJS;
+use juno_support::NullTerminatedBuf;
+
+use crate::Pass;
+
+/// Condition which a metavariable must satisfy for a rule to apply.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// Evaluating the node has no side effects.
+    Pure,
+    /// The node is a literal.
+    Literal,
+    /// The node is an identifier.
+    Identifier,
+}
+
+impl Condition {
+    fn parse(name: &str) -> Option<Condition> {
+        match name {
+            "pure" => Some(Condition::Pure),
+            "literal" => Some(Condition::Literal),
+            "identifier" => Some(Condition::Identifier),
+            _ => None,
+        }
+    }
+
+    fn check(self, node: &Node) -> bool {
+        match self {
+            Condition::Pure => is_pure(node),
+            Condition::Literal => matches!(
+                node,
+                Node::NullLiteral(_)
+                    | Node::BooleanLiteral(_)
+                    | Node::StringLiteral(_)
+                    | Node::NumericLiteral(_)
+                    | Node::BigIntLiteral(_)
+                    | Node::RegExpLiteral(_)
+            ),
+            Condition::Identifier => matches!(node, Node::Identifier(_)),
+        }
+    }
+}
+
+/// Error in the text of a rewrite rule.
+#[derive(Debug)]
+pub enum RuleError {
+    /// No `=>` separating the pattern from the replacement.
+    MissingArrow,
+    /// The pattern or the replacement failed to parse.
+    Parse(ParseError),
+    /// The pattern or the replacement is not a single expression or statement.
+    NotSingle(String),
+    /// A metavariable in the replacement or a condition doesn't occur in the pattern.
+    Unbound(String),
+    /// A condition isn't of the form `$x is CONDITION`.
+    InvalidCondition(String),
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::MissingArrow => {
+                write!(f, "rule must be of the form 'pattern => replacement'")
+            }
+            RuleError::Parse(err) => write!(f, "{}", err),
+            RuleError::NotSingle(src) => {
+                write!(
+                    f,
+                    "'{}' must be a single expression or statement",
+                    src.trim()
+                )
+            }
+            RuleError::Unbound(name) => write!(f, "metavariable '{}' is not bound", name),
+            RuleError::InvalidCondition(cond) => write!(f, "invalid condition '{}'", cond.trim()),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+/// A single rewrite rule, parsed into the `Context` it will be applied in.
+pub struct RewriteRule {
+    /// Expression or statement to match.
+    pattern: NodeRc,
+    /// Template used to build the replacement.
+    replacement: NodeRc,
+    /// Conditions on the metavariables bound by `pattern`.
+    conditions: Vec<(NodeLabel, Condition)>,
+}
+
+impl RewriteRule {
+    /// Parse `rule` into `ctx`.
+    /// The resulting rule may only be applied to ASTs in `ctx`.
+    pub fn parse(ctx: &mut Context, rule: &str) -> Result<RewriteRule, RuleError> {
+        let (rule, conditions) = split_conditions(rule);
+
+        // Arrow functions may appear on either side, so use the first `=>`
+        // which leaves a valid pattern on the left.
+        let arrows = rule.match_indices("=>").map(|(i, _)| i).collect::<Vec<_>>();
+        let split = arrows
+            .iter()
+            .copied()
+            .find(|&i| {
+                let buf = NullTerminatedBuf::from_str_copy(&rule[..i]);
+                let parsed = ParsedJS::parse(Default::default(), &buf);
+                !parsed.has_errors()
+            })
+            .or_else(|| arrows.first().copied())
+            .ok_or(RuleError::MissingArrow)?;
+
+        let pattern = parse_template(ctx, &rule[..split])?;
+        let replacement = parse_template(ctx, &rule[split + 2..])?;
+
+        let gc = GCLock::new(ctx);
+        let (pattern_node, replacement_node) = match (
+            expression(pattern.node(&gc)),
+            expression(replacement.node(&gc)),
+        ) {
+            (Some(pattern), Some(replacement)) => (pattern, replacement),
+            _ => (pattern.node(&gc), replacement.node(&gc)),
+        };
+
+        let bound = collect_metavars(&gc, pattern_node);
+        let wildcard = gc.atom("$_");
+        for name in collect_metavars(&gc, replacement_node) {
+            if name == wildcard || !bound.contains(&name) {
+                return Err(RuleError::Unbound(gc.str(name).to_string()));
+            }
+        }
+
+        let mut parsed_conditions = vec![];
+        if let Some(conditions) = conditions {
+            for cond in conditions.split(',') {
+                let words = cond.split_whitespace().collect::<Vec<_>>();
+                let (name, condition) = match words.as_slice() {
+                    [name, "is", condition] if name.starts_with('$') => (
+                        gc.atom(*name),
+                        Condition::parse(condition)
+                            .ok_or_else(|| RuleError::InvalidCondition(cond.to_string()))?,
+                    ),
+                    _ => return Err(RuleError::InvalidCondition(cond.to_string())),
+                };
+                if name == wildcard || !bound.contains(&name) {
+                    return Err(RuleError::Unbound(gc.str(name).to_string()));
+                }
+                parsed_conditions.push((name, condition));
+            }
+        }
+
+        Ok(RewriteRule {
+            pattern: NodeRc::from_node(&gc, pattern_node),
+            replacement: NodeRc::from_node(&gc, replacement_node),
+            conditions: parsed_conditions,
+        })
+    }
+
+    /// Attempt to apply the rule to `node`, returning the replacement if it matched.
+    pub fn apply<'gc>(&self, gc: &'gc GCLock, node: &'gc Node<'gc>) -> Option<&'gc Node<'gc>> {
+        let mut matcher = Matcher {
+            wildcard: gc.atom("$_"),
+            bindings: HashMap::new(),
+        };
+        if !matcher.matches(gc, self.pattern.node(gc), node) {
+            return None;
+        }
+        for (name, condition) in &self.conditions {
+            if !condition.check(matcher.bindings[name]) {
+                return None;
+            }
+        }
+        let mut instantiate = Instantiate {
+            bindings: &matcher.bindings,
+            range: *node.range(),
+        };
+        match instantiate.call(gc, self.replacement.node(gc), None) {
+            TransformResult::Changed(new_node) => Some(new_node),
+            _ => unreachable!("instantiation always allocates a new node"),
+        }
+    }
+}
+
+/// Pass which applies a list of rewrite rules.
+/// At every node, the first rule which matches is applied.
+pub struct RewritePass {
+    rules: Vec<RewriteRule>,
+}
+
+impl RewritePass {
+    pub fn new(rules: Vec<RewriteRule>) -> Self {
+        Self { rules }
+    }
+
+    /// Parse each of `rules` into `ctx` and create a pass applying them.
+    pub fn parse(ctx: &mut Context, rules: &[&str]) -> Result<Self, RuleError> {
+        let rules = rules
+            .iter()
+            .map(|rule| RewriteRule::parse(ctx, rule))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(rules))
+    }
+}
+
+impl Pass for RewritePass {
+    fn name(&self) -> &'static str {
+        "Rewrite"
+    }
+    fn description(&self) -> &'static str {
+        "Applies declarative rewrite rules"
+    }
+    fn run<'gc>(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        VisitorMut::call(self, gc, node, None)
+    }
+}
+
+impl<'gc> VisitorMut<'gc> for RewritePass {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+        _parent: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        for rule in &self.rules {
+            if let Some(replacement) = rule.apply(gc, node) {
+                return node.replace_with_existing(replacement, gc, self);
+            }
+        }
+        node.visit_children_mut(gc, self)
+    }
+}
+
+/// Split the trailing `where ...` clause off `rule`.
+/// The clause starts at the last `where` token outside of brackets, so a `where` in a string,
+/// template literal, regular expression or comment doesn't start it.
+fn split_conditions(rule: &str) -> (&str, Option<&str>) {
+    let mut chars = rule.char_indices().peekable();
+    let mut last = None;
+    let mut depth = 0;
+    // Whether a `/` starts a regular expression instead of being a division.
+    let mut regexp_allowed = true;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                skip_until(&mut chars, c);
+                regexp_allowed = false;
+            }
+            '/' if matches!(chars.peek(), Some((_, '/'))) => {
+                while matches!(chars.next(), Some((_, c)) if c != '\n') {}
+            }
+            '/' if matches!(chars.peek(), Some((_, '*'))) => {
+                chars.next();
+                let mut prev = ' ';
+                for (_, c) in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            '/' if regexp_allowed => {
+                skip_until(&mut chars, '/');
+                regexp_allowed = false;
+            }
+            '(' | '[' | '{' => {
+                depth += 1;
+                regexp_allowed = true;
+            }
+            ')' | ']' | '}' => {
+                depth -= 1;
+                regexp_allowed = false;
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let mut end = i + c.len_utf8();
+                while let Some(&(j, c)) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '$') {
+                        break;
+                    }
+                    chars.next();
+                    end = j + c.len_utf8();
+                }
+                if depth == 0 && &rule[i..end] == "where" {
+                    last = Some((i, end));
+                }
+                regexp_allowed = false;
+            }
+            c if c.is_whitespace() => {}
+            _ => regexp_allowed = true,
+        }
+    }
+    match last {
+        Some((start, end)) => (&rule[..start], Some(&rule[end..])),
+        None => (rule, None),
+    }
+}
+
+/// Advance `chars` past the next `end` which isn't escaped with a backslash, or inside
+/// a character class if it ends a regular expression.
+fn skip_until(chars: &mut impl Iterator<Item = (usize, char)>, end: char) {
+    let mut in_class = false;
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' if end == '/' => in_class = true,
+            ']' if end == '/' => in_class = false,
+            c if c == end && !in_class => return,
+            _ => {}
+        }
+    }
+}
+
+/// Parse one side of a rule, which must be a single statement.
+fn parse_template(ctx: &mut Context, src: &str) -> Result<NodeRc, RuleError> {
+    let program = hparser::parse(ctx, src).map_err(RuleError::Parse)?;
+    let gc = GCLock::new(ctx);
+    let body = match program.node(&gc) {
+        Node::Program(Program { body, .. }) => *body,
+        _ => unreachable!("parser must return a Program"),
+    };
+    match (body.head(), body.len()) {
+        (Some(stmt), 1) => Ok(NodeRc::from_node(&gc, stmt)),
+        _ => Err(RuleError::NotSingle(src.to_string())),
+    }
+}
+
+/// Return the expression in `stmt` if it is an expression statement.
+/// When both sides of a rule are expression statements, the rule applies to expressions.
+fn expression<'gc>(stmt: &'gc Node<'gc>) -> Option<&'gc Node<'gc>> {
+    match stmt {
+        Node::ExpressionStatement(ExpressionStatement {
+            expression,
+            directive: None,
+            ..
+        }) => Some(expression),
+        _ => None,
+    }
+}
+
+/// Return the name of `node` if it is a metavariable.
+fn metavar(gc: &GCLock, node: &Node) -> Option<NodeLabel> {
+    match node {
+        Node::Identifier(Identifier { name, .. })
+            if gc.str(*name).starts_with('$') && !gc.str(*name).starts_with("$$") =>
+        {
+            Some(*name)
+        }
+        _ => None,
+    }
+}
+
+/// Return the name of the identifier `node` without its escaping `$`, if it is an escaped
+/// identifier starting with `$`.
+fn escaped<'gc>(gc: &'gc GCLock, node: &Node) -> Option<&'gc str> {
+    match node {
+        Node::Identifier(Identifier { name, .. }) if gc.str(*name).starts_with("$$") => {
+            Some(&gc.str(*name)[1..])
+        }
+        _ => None,
+    }
+}
+
+/// Collect all the metavariables in the subtree rooted at the node.
+struct MetavarCollector {
+    names: Vec<NodeLabel>,
+}
+
+impl<'gc> Visitor<'gc> for MetavarCollector {
+    fn call(&mut self, gc: &'gc GCLock, node: &'gc Node<'gc>, _path: Option<Path<'gc>>) {
+        if let Some(name) = metavar(gc, node) {
+            self.names.push(name);
+        }
+        node.visit_children(gc, self);
+    }
+}
+
+fn collect_metavars<'gc>(gc: &'gc GCLock, node: &'gc Node<'gc>) -> Vec<NodeLabel> {
+    let mut collector = MetavarCollector { names: vec![] };
+    collector.call(gc, node, None);
+    collector.names
+}
+
+/// Collect the direct children of a node along with the fields they are in.
+struct ChildCollector<'gc> {
+    children: Vec<(NodeField, &'gc Node<'gc>)>,
+}
+
+impl<'gc> Visitor<'gc> for ChildCollector<'gc> {
+    fn call(&mut self, _gc: &'gc GCLock, node: &'gc Node<'gc>, path: Option<Path<'gc>>) {
+        self.children
+            .push((path.expect("children must have a parent").field, node));
+    }
+}
+
+/// Pair up the children of `a` and `b`.
+/// Return `None` if they don't have the same number of children in each field.
+fn zip_children<'gc>(
+    gc: &'gc GCLock,
+    a: &'gc Node<'gc>,
+    b: &'gc Node<'gc>,
+) -> Option<Vec<(&'gc Node<'gc>, &'gc Node<'gc>)>> {
+    let mut a_children = ChildCollector { children: vec![] };
+    a.visit_children(gc, &mut a_children);
+    let mut b_children = ChildCollector { children: vec![] };
+    b.visit_children(gc, &mut b_children);
+    if a_children.children.len() != b_children.children.len() {
+        return None;
+    }
+    a_children
+        .children
+        .into_iter()
+        .zip(b_children.children)
+        .map(|((a_field, a), (b_field, b))| {
+            if a_field == b_field {
+                Some((a, b))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Whether `a` and `b` are structurally equal, ignoring source ranges.
+fn structurally_equal<'gc>(gc: &'gc GCLock, a: &'gc Node<'gc>, b: &'gc Node<'gc>) -> bool {
+    a.shallow_eq(b)
+        && match zip_children(gc, a, b) {
+            Some(children) => children
+                .into_iter()
+                .all(|(a, b)| structurally_equal(gc, a, b)),
+            None => false,
+        }
+}
+
+struct Matcher<'gc> {
+    /// Name of the metavariable which matches without binding.
+    wildcard: NodeLabel,
+    /// Nodes bound to each metavariable so far.
+    bindings: HashMap<NodeLabel, &'gc Node<'gc>>,
+}
+
+impl<'gc> Matcher<'gc> {
+    fn matches(&mut self, gc: &'gc GCLock, pattern: &'gc Node<'gc>, node: &'gc Node<'gc>) -> bool {
+        if let Some(name) = metavar(gc, pattern) {
+            if name == self.wildcard {
+                return true;
+            }
+            return match self.bindings.entry(name) {
+                Entry::Occupied(bound) => structurally_equal(gc, bound.get(), node),
+                Entry::Vacant(entry) => {
+                    entry.insert(node);
+                    true
+                }
+            };
+        }
+        if let Some(name) = escaped(gc, pattern) {
+            return matches!(node, Node::Identifier(id) if gc.str(id.name) == name);
+        }
+        pattern.shallow_eq(node)
+            && match zip_children(gc, pattern, node) {
+                Some(children) => children
+                    .into_iter()
+                    .all(|(pattern, node)| self.matches(gc, pattern, node)),
+                None => false,
+            }
+    }
+}
+
+/// Copy the replacement template, substituting metavariables with their bindings.
+struct Instantiate<'gc, 'b> {
+    bindings: &'b HashMap<NodeLabel, &'gc Node<'gc>>,
+    /// Range given to all the newly allocated nodes.
+    range: SourceRange,
+}
+
+impl<'gc> VisitorMut<'gc> for Instantiate<'gc, '_> {
+    fn call(
+        &mut self,
+        gc: &'gc GCLock<'_, '_>,
+        node: &'gc Node<'gc>,
+        _parent: Option<Path<'gc>>,
+    ) -> TransformResult<&'gc Node<'gc>> {
+        if let Node::Identifier(id) = node {
+            if let Some(bound) = self.bindings.get(&id.name) {
+                return TransformResult::Changed(bound);
+            }
+            if let Some(name) = escaped(gc, node) {
+                let mut builder = builder::Identifier::from_node(id);
+                builder.name(gc.atom(name));
+                let mut builder = builder::Builder::Identifier(builder);
+                builder.range(self.range);
+                return node.replace_with_new(builder, gc, self);
+            }
+        }
+        let mut builder = builder::Builder::from_node(node);
+        builder.range(self.range);
+        node.replace_with_new(builder, gc, self)
+    }
+}
+
+/// Whether evaluating `node` is free of side effects, assuming that reading variables
+/// and implicit conversions of operands are not observable.
+fn is_pure(node: &Node) -> bool {
+    match node {
+        Node::NullLiteral(_)
+        | Node::BooleanLiteral(_)
+        | Node::StringLiteral(_)
+        | Node::NumericLiteral(_)
+        | Node::BigIntLiteral(_)
+        | Node::RegExpLiteral(_)
+        | Node::Identifier(_)
+        | Node::ThisExpression(_)
+        | Node::FunctionExpression(_)
+        | Node::ArrowFunctionExpression(_)
+        | Node::Empty(_) => true,
+        Node::UnaryExpression(UnaryExpression {
+            operator, argument, ..
+        }) => *operator != UnaryExpressionOperator::Delete && is_pure(argument),
+        Node::BinaryExpression(BinaryExpression {
+            left,
+            right,
+            operator,
+            ..
+        }) => {
+            // `in` and `instanceof` throw on non-object right operands.
+            !matches!(
+                operator,
+                BinaryExpressionOperator::In | BinaryExpressionOperator::Instanceof
+            ) && is_pure(left)
+                && is_pure(right)
+        }
+        Node::LogicalExpression(LogicalExpression { left, right, .. }) => {
+            is_pure(left) && is_pure(right)
+        }
+        Node::ConditionalExpression(ConditionalExpression {
+            test,
+            consequent,
+            alternate,
+            ..
+        }) => is_pure(test) && is_pure(consequent) && is_pure(alternate),
+        Node::SequenceExpression(SequenceExpression { expressions, .. }) => {
+            expressions.iter().all(is_pure)
+        }
+        Node::TemplateLiteral(TemplateLiteral { expressions, .. }) => {
+            expressions.iter().all(is_pure)
+        }
+        Node::ArrayExpression(ArrayExpression { elements, .. }) => elements.iter().all(is_pure),
+        Node::ObjectExpression(ObjectExpression { properties, .. }) => {
+            properties.iter().all(|prop| match prop {
+                Node::Property(Property {
+                    key,
+                    value,
+                    computed,
+                    ..
+                }) => (!computed || is_pure(key)) && is_pure(value),
+                _ => false,
+            })
+        }
+        _ => false,
+    }
+}
@@ -7,3 +7,4 @@
 
 #[macro_use]
 mod strip_flow;
+mod rewrite;
@@ -0,0 +1,136 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+use juno::ast;
+use juno::ast::NodeRc;
+use juno::gen_js;
+use juno::hparser;
+use juno_pass::PassManager;
+use juno_pass::RewritePass;
+use juno_pass::RewriteRule;
+use juno_pass::RuleError;
+
+#[test]
+fn add_negative() {
+    assert_rewrite(&["$x + -$y => $x - $y"], "a + -b;", "a - b;");
+    assert_rewrite(
+        &["$x + -$y => $x - $y"],
+        "a + -(b + -c); a + b;",
+        "a - (b - c); a + b;",
+    );
+}
+
+#[test]
+fn repeated_metavar() {
+    assert_rewrite(&["$x - $x => 0"], "a.b - a.b; a - b;", "0; a - b;");
+}
+
+#[test]
+fn wildcard() {
+    assert_rewrite(&["[$_, $x] => $x"], "[f(), g()];", "g();");
+}
+
+#[test]
+fn conditions() {
+    assert_rewrite(
+        &["($x, $y) => $y where $x is pure"],
+        "(a + 1, b); (f(), b);",
+        "b; (f(), b);",
+    );
+    assert_rewrite(
+        &["typeof $x == $y => typeof $x === $y where $y is literal"],
+        "typeof a == 'number'; typeof a == b;",
+        "typeof a === 'number'; typeof a == b;",
+    );
+}
+
+#[test]
+fn where_in_rule() {
+    assert_rewrite(
+        &["$x + ' where ' => $x + / where /.source where $x is identifier"],
+        "a + ' where '; f() + ' where ';",
+        "a + / where /.source; f() + ' where ';",
+    );
+    assert_rewrite(&["f(where) => g(where)"], "f(where);", "g(where);");
+}
+
+#[test]
+fn escaped_dollar() {
+    assert_rewrite(
+        &["$$($x) => $$$.wrap($x)"],
+        "$(a); $$(a); f(a);",
+        "$$.wrap(a); $$(a); f(a);",
+    );
+}
+
+#[test]
+fn statements() {
+    assert_rewrite(
+        &["if (true) $s; else $_; => $s;"],
+        "if (true) a(); else b();",
+        "a();",
+    );
+    assert_rewrite(&["$x; => if ($x) {}"], "a(); b;", "if (a()) {} if (b) {}");
+}
+
+#[test]
+fn arrow_in_rule() {
+    assert_rewrite(&["(() => $x)() => $x"], "(() => a)();", "a;");
+}
+
+#[test]
+fn first_rule_wins() {
+    assert_rewrite(
+        &["$x + 0 => $x", "$x + $y => $y + $x"],
+        "a + 0; a + b;",
+        "a; b + a;",
+    );
+}
+
+#[test]
+fn errors() {
+    let mut ctx = ast::Context::new();
+    assert!(matches!(
+        RewriteRule::parse(&mut ctx, "a + b"),
+        Err(RuleError::MissingArrow)
+    ));
+    assert!(matches!(
+        RewriteRule::parse(&mut ctx, "$x => $y"),
+        Err(RuleError::Unbound(name)) if name == "$y"
+    ));
+    assert!(matches!(
+        RewriteRule::parse(&mut ctx, "$x => a; b;"),
+        Err(RuleError::NotSingle(_))
+    ));
+    assert!(matches!(
+        RewriteRule::parse(&mut ctx, "$x + => 1"),
+        Err(RuleError::Parse(_))
+    ));
+    assert!(matches!(
+        RewriteRule::parse(&mut ctx, "$x => $x where $x is odd"),
+        Err(RuleError::InvalidCondition(_))
+    ));
+}
+
+fn gen(ctx: &mut ast::Context, node: &NodeRc) -> String {
+    let mut out = vec![];
+    gen_js::generate(&mut out, ctx, node, gen_js::Opt::new()).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn assert_rewrite(rules: &[&str], input: &str, expected: &str) {
+    let mut ctx = ast::Context::new();
+    let ast = hparser::parse(&mut ctx, input).unwrap();
+    let mut pm = PassManager::new();
+    pm.add_pass(Box::new(RewritePass::parse(&mut ctx, rules).unwrap()));
+    let ast = pm.run(&mut ctx, ast);
+
+    let mut ctx_expected = ast::Context::new();
+    let ast_expected = hparser::parse(&mut ctx_expected, expected).unwrap();
+
+    assert_eq!(gen(&mut ctx_expected, &ast_expected), gen(&mut ctx, &ast));
+}
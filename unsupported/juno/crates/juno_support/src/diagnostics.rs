@@ -0,0 +1,646 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+//! Consumers of the diagnostics reported to a [`SourceManager`].
+
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::io::Write;
+use std::rc::Rc;
+
+use crate::json::JSONEmitter;
+use crate::json::Pretty;
+use crate::json::emit_loc;
+use crate::json::emit_str;
+use crate::source_manager::SourceId;
+use crate::source_manager::SourceLoc;
+use crate::source_manager::SourceManager;
+use crate::source_manager::SourceRange;
+
+/// The kind of a reported diagnostic.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DiagKind {
+    Error,
+    Warning,
+    Note,
+}
+
+impl DiagKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            DiagKind::Error => "error",
+            DiagKind::Warning => "warning",
+            DiagKind::Note => "note",
+        }
+    }
+
+    /// ANSI escape sequence of the color of the kind.
+    fn color(self) -> &'static str {
+        match self {
+            DiagKind::Error => "\x1b[1;31m",
+            DiagKind::Warning => "\x1b[1;35m",
+            DiagKind::Note => "\x1b[1;30m",
+        }
+    }
+}
+
+const BOLD: &str = "\x1b[1m";
+const GREEN: &str = "\x1b[1;32m";
+const RESET: &str = "\x1b[0m";
+
+/// A diagnostic reported to a [`SourceManager`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagKind,
+    pub range: SourceRange,
+    pub message: String,
+    /// Fixes which correct the problem, any of which can be applied.
+    pub fixes: Vec<Fix>,
+    /// Identifies the kind of problem, e.g. the name of a lint rule.
+    pub code: Option<String>,
+    /// The severity given by the tool which reported the diagnostic, which can be finer
+    /// than the kind, e.g. "Hint" for a note. A note with a severity is a diagnostic of
+    /// its own rather than a note about the last error or warning.
+    pub severity: Option<String>,
+}
+
+impl Diagnostic {
+    /// A diagnostic of `kind` without a code or severity.
+    pub fn new<S: Into<String>>(
+        kind: DiagKind,
+        range: SourceRange,
+        message: S,
+        fixes: Vec<Fix>,
+    ) -> Diagnostic {
+        Diagnostic {
+            kind,
+            range,
+            message: message.into(),
+            fixes,
+            code: None,
+            severity: None,
+        }
+    }
+}
+
+/// Replacement of the bytes `start..end` of a source with `text`.
+/// An edit with `start == end` inserts `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl TextEdit {
+    /// Replace the text of `range`, or return `None` if `range` isn't in its source.
+    pub fn replace<S: Into<String>>(
+        sm: &SourceManager,
+        range: SourceRange,
+        text: S,
+    ) -> Option<TextEdit> {
+        let table = sm.line_table(range.file);
+        Some(TextEdit {
+            start: table.offset(range.start)?,
+            end: table.offset(range.end)? + 1,
+            text: text.into(),
+        })
+    }
+
+    /// Insert `text` before `loc` in `file`, or return `None` if `loc` isn't in `file`.
+    pub fn insert<S: Into<String>>(
+        sm: &SourceManager,
+        file: SourceId,
+        loc: SourceLoc,
+        text: S,
+    ) -> Option<TextEdit> {
+        let offset = sm.line_table(file).offset(loc)?;
+        Some(TextEdit {
+            start: offset,
+            end: offset,
+            text: text.into(),
+        })
+    }
+}
+
+/// A machine-applicable fix of a diagnostic, made of edits of a single source which
+/// must all be applied together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    /// Description of the fix, e.g. "remove the initializer".
+    pub message: String,
+    pub file: SourceId,
+    pub edits: Vec<TextEdit>,
+}
+
+impl Fix {
+    pub fn new<S: Into<String>>(message: S, file: SourceId, edits: Vec<TextEdit>) -> Fix {
+        Fix {
+            message: message.into(),
+            file,
+            edits,
+        }
+    }
+}
+
+/// Apply `fixes`, each given by its edits, to `source`, in order.
+/// A fix is skipped if any of its edits is outside `source`, splits a character, or
+/// overlaps an edit of the same fix or of a fix which was applied, so conflicting fixes
+/// of the same problem are only applied once.
+/// Return the new source and the number of applied fixes.
+pub fn apply_fixes<'a, I>(source: &str, fixes: I) -> (String, usize)
+where
+    I: IntoIterator<Item = &'a [TextEdit]>,
+{
+    // Two edits conflict if they overlap, or insert at the same position.
+    let conflict = |a: &TextEdit, b: &TextEdit| {
+        (a.start < b.end && b.start < a.end) || (a.start == b.start && a.end == b.end)
+    };
+    let mut applied: Vec<&TextEdit> = Vec::new();
+    let mut count = 0;
+    for edits in fixes {
+        let valid = edits.iter().enumerate().all(|(i, edit)| {
+            edit.start <= edit.end
+                && source.is_char_boundary(edit.start)
+                && source.is_char_boundary(edit.end)
+                && !edits[..i].iter().any(|other| conflict(edit, other))
+                && !applied.iter().any(|other| conflict(edit, other))
+        });
+        if valid {
+            applied.extend(edits);
+            count += 1;
+        }
+    }
+
+    // Insertions come before the replacements which start at the same position.
+    applied.sort_by_key(|edit| (edit.start, edit.end));
+    let mut result = String::with_capacity(source.len());
+    let mut pos = 0;
+    for edit in applied {
+        result.push_str(&source[pos..edit.start]);
+        result.push_str(&edit.text);
+        pos = edit.end;
+    }
+    result.push_str(&source[pos..]);
+    (result, count)
+}
+
+/// Receives the diagnostics reported to a [`SourceManager`].
+/// The source manager counts the diagnostics and applies the error limit before they
+/// reach the handler. Notes without a severity follow the error or warning they are
+/// about.
+pub trait DiagnosticHandler: Debug {
+    /// Handle a diagnostic reported to `sm`.
+    fn handle(&mut self, sm: &SourceManager, diagnostic: Diagnostic);
+
+    /// Called when the error limit is reached, after which no diagnostics are handled.
+    fn error_limit_reached(&mut self, _sm: &SourceManager) {}
+
+    /// Called when no more diagnostics will be reported, e.g. to write a document with
+    /// all of them.
+    fn finish(&mut self, _sm: &SourceManager) {}
+}
+
+/// Prints every diagnostic like clang, followed by the line of the start of its range
+/// with the range underlined.
+#[derive(Debug, Default)]
+pub struct TerminalHandler {
+    /// Whether to use ANSI colors.
+    color: bool,
+}
+
+impl TerminalHandler {
+    pub fn new(color: bool) -> TerminalHandler {
+        TerminalHandler { color }
+    }
+
+    /// The ANSI escape sequence `code` if colors are enabled, otherwise nothing.
+    fn style(&self, code: &'static str) -> &'static str {
+        if self.color { code } else { "" }
+    }
+}
+
+impl DiagnosticHandler for TerminalHandler {
+    fn handle(&mut self, sm: &SourceManager, diagnostic: Diagnostic) {
+        let range = diagnostic.range;
+        let mut text = format!(
+            "{}{}:{}:{}: {}{}: {}{}{}{}",
+            self.style(BOLD),
+            sm.source_name(range.file),
+            range.start.line,
+            range.start.col,
+            self.style(diagnostic.kind.color()),
+            diagnostic.kind.name(),
+            self.style(RESET),
+            self.style(BOLD),
+            diagnostic.message,
+            self.style(RESET)
+        );
+        if let Some((line, underline)) = sm.snippet(range) {
+            text = format!(
+                "{}\n{}\n{}{}{}",
+                text,
+                line,
+                self.style(GREEN),
+                underline,
+                self.style(RESET)
+            );
+        }
+        for fix in &diagnostic.fixes {
+            text = format!(
+                "{}\n{}fix-it:{} {}",
+                text,
+                self.style(GREEN),
+                self.style(RESET),
+                fix.message
+            );
+        }
+        sm.output(text);
+    }
+
+    fn error_limit_reached(&mut self, sm: &SourceManager) {
+        sm.output(format!(
+            "{}fatal error: {}{}too many errors emitted, stopping now{}",
+            self.style(DiagKind::Error.color()),
+            self.style(RESET),
+            self.style(BOLD),
+            self.style(RESET)
+        ));
+    }
+}
+
+/// Collects the diagnostics, for tests and tools embedding the compiler.
+/// Clones share the collected diagnostics, so a clone can be kept to read them after
+/// the handler is passed to the source manager.
+#[derive(Debug, Default, Clone)]
+pub struct CollectingHandler {
+    diagnostics: Rc<RefCell<Vec<Diagnostic>>>,
+}
+
+impl CollectingHandler {
+    pub fn new() -> CollectingHandler {
+        Default::default()
+    }
+
+    /// Take the diagnostics collected so far.
+    pub fn take(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics.borrow_mut())
+    }
+}
+
+impl DiagnosticHandler for CollectingHandler {
+    fn handle(&mut self, _sm: &SourceManager, diagnostic: Diagnostic) {
+        self.diagnostics.borrow_mut().push(diagnostic);
+    }
+}
+
+/// Prints every diagnostic as a JSON object on its own line, with the `kind`, the
+/// `file`, the `range` with 1-based `start` and inclusive `end`, and the `message`.
+/// Diagnostics with fixes also have `fixes`, with the `message` and `edits` of each
+/// fix, whose `range` ends at an exclusive `end`.
+#[derive(Debug, Default)]
+pub struct JsonLinesHandler {}
+
+impl JsonLinesHandler {
+    pub fn new() -> JsonLinesHandler {
+        Default::default()
+    }
+}
+
+impl DiagnosticHandler for JsonLinesHandler {
+    fn handle(&mut self, sm: &SourceManager, diagnostic: Diagnostic) {
+        let mut out = Vec::new();
+        let mut emitter = JSONEmitter::new(&mut out, Pretty::No);
+        emitter.open_dict();
+        emitter.emit_key("kind");
+        emitter.emit_string(diagnostic.kind.name());
+        emitter.emit_key("file");
+        emit_str(&mut emitter, sm.source_name(diagnostic.range.file));
+        emitter.emit_key("range");
+        emitter.open_dict();
+        emitter.emit_key("start");
+        emit_loc(&mut emitter, diagnostic.range.start);
+        emitter.emit_key("end");
+        emit_loc(&mut emitter, diagnostic.range.end);
+        emitter.close_dict();
+        emitter.emit_key("message");
+        emit_str(&mut emitter, &diagnostic.message);
+        if !diagnostic.fixes.is_empty() {
+            emitter.emit_key("fixes");
+            emitter.open_array();
+            for fix in &diagnostic.fixes {
+                let table = sm.line_table(fix.file);
+                emitter.open_dict();
+                emitter.emit_key("message");
+                emit_str(&mut emitter, &fix.message);
+                emitter.emit_key("edits");
+                emitter.open_array();
+                for edit in &fix.edits {
+                    emitter.open_dict();
+                    emitter.emit_key("range");
+                    emitter.open_dict();
+                    emitter.emit_key("start");
+                    emit_loc(&mut emitter, table.loc(edit.start));
+                    emitter.emit_key("end");
+                    emit_loc(&mut emitter, table.loc(edit.end));
+                    emitter.close_dict();
+                    emitter.emit_key("text");
+                    emit_str(&mut emitter, &edit.text);
+                    emitter.close_dict();
+                }
+                emitter.close_array();
+                emitter.close_dict();
+            }
+            emitter.close_array();
+        }
+        emitter.close_dict();
+        emitter.end().expect("Writing to memory can't fail");
+        sm.output(String::from_utf8_lossy(&out).trim_end().to_string());
+    }
+}
+
+/// Writes all the diagnostics as a SARIF 2.1.0 log when finished.
+/// The notes of an error or warning are its related locations, and its fixes are
+/// SARIF fixes. Notes with a severity are results of their own.
+#[derive(Debug)]
+pub struct SarifHandler {
+    pretty: Pretty,
+    /// Every error or warning, with its notes.
+    results: Vec<(Diagnostic, Vec<Diagnostic>)>,
+}
+
+impl SarifHandler {
+    pub fn new(pretty: Pretty) -> SarifHandler {
+        SarifHandler {
+            pretty,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl DiagnosticHandler for SarifHandler {
+    fn handle(&mut self, _sm: &SourceManager, diagnostic: Diagnostic) {
+        match (diagnostic.kind, self.results.last_mut()) {
+            (DiagKind::Note, Some((_, notes))) if diagnostic.severity.is_none() => {
+                notes.push(diagnostic)
+            }
+            _ => self.results.push((diagnostic, Vec::new())),
+        }
+    }
+
+    fn finish(&mut self, sm: &SourceManager) {
+        let mut out = Vec::new();
+        let mut emitter = JSONEmitter::new(&mut out, self.pretty);
+        emitter.open_dict();
+        emitter.emit_key("$schema");
+        emitter.emit_string("https://json.schemastore.org/sarif-2.1.0.json");
+        emitter.emit_key("version");
+        emitter.emit_string("2.1.0");
+        emitter.emit_key("runs");
+        emitter.open_array();
+        emitter.open_dict();
+        emitter.emit_key("tool");
+        emitter.open_dict();
+        emitter.emit_key("driver");
+        emitter.open_dict();
+        emitter.emit_key("name");
+        emitter.emit_string("juno");
+        emitter.close_dict();
+        emitter.close_dict();
+        emitter.emit_key("results");
+        emitter.open_array();
+        for (diagnostic, notes) in std::mem::take(&mut self.results) {
+            emitter.open_dict();
+            emitter.emit_key("level");
+            emitter.emit_string(diagnostic.kind.name());
+            emitter.emit_key("message");
+            emit_sarif_message(&mut emitter, &diagnostic.message);
+            emitter.emit_key("locations");
+            emitter.open_array();
+            emit_sarif_location(&mut emitter, sm, &diagnostic, None);
+            emitter.close_array();
+            if !notes.is_empty() {
+                emitter.emit_key("relatedLocations");
+                emitter.open_array();
+                for (id, note) in notes.iter().enumerate() {
+                    emit_sarif_location(&mut emitter, sm, note, Some(id));
+                }
+                emitter.close_array();
+            }
+            if !diagnostic.fixes.is_empty() {
+                emitter.emit_key("fixes");
+                emitter.open_array();
+                for fix in &diagnostic.fixes {
+                    emit_sarif_fix(&mut emitter, sm, fix);
+                }
+                emitter.close_array();
+            }
+            emitter.close_dict();
+        }
+        emitter.close_array();
+        emitter.close_dict();
+        emitter.close_array();
+        emitter.close_dict();
+        emitter.end().expect("Writing to memory can't fail");
+        sm.output(String::from_utf8_lossy(&out).trim_end().to_string());
+    }
+}
+
+fn emit_sarif_message<W: Write>(emitter: &mut JSONEmitter<W>, message: &str) {
+    emitter.open_dict();
+    emitter.emit_key("text");
+    emit_str(emitter, message);
+    emitter.close_dict();
+}
+
+/// Emit the location of `diagnostic`, with its `id` and message if it is related to a
+/// result. SARIF regions end at an exclusive column.
+fn emit_sarif_location<W: Write>(
+    emitter: &mut JSONEmitter<W>,
+    sm: &SourceManager,
+    diagnostic: &Diagnostic,
+    id: Option<usize>,
+) {
+    let range = diagnostic.range;
+    emitter.open_dict();
+    if let Some(id) = id {
+        emitter.emit_key("id");
+        emitter.emit_number(id as f64);
+        emitter.emit_key("message");
+        emit_sarif_message(emitter, &diagnostic.message);
+    }
+    emitter.emit_key("physicalLocation");
+    emitter.open_dict();
+    emit_sarif_artifact(emitter, sm, range.file);
+    emitter.emit_key("region");
+    let mut end = range.end;
+    end.col += 1;
+    emit_sarif_region(emitter, range.start, end);
+    emitter.close_dict();
+    emitter.close_dict();
+}
+
+fn emit_sarif_artifact<W: Write>(emitter: &mut JSONEmitter<W>, sm: &SourceManager, file: SourceId) {
+    emitter.emit_key("artifactLocation");
+    emitter.open_dict();
+    emitter.emit_key("uri");
+    emit_str(emitter, sm.source_name(file));
+    emitter.close_dict();
+}
+
+/// Emit the region from `start` to the exclusive `end`.
+fn emit_sarif_region<W: Write>(emitter: &mut JSONEmitter<W>, start: SourceLoc, end: SourceLoc) {
+    emitter.open_dict();
+    emitter.emit_key("startLine");
+    emitter.emit_number(start.line as f64);
+    emitter.emit_key("startColumn");
+    emitter.emit_number(start.col as f64);
+    emitter.emit_key("endLine");
+    emitter.emit_number(end.line as f64);
+    emitter.emit_key("endColumn");
+    emitter.emit_number(end.col as f64);
+    emitter.close_dict();
+}
+
+/// Emit `fix` with all its edits as the replacements of a single artifact change.
+fn emit_sarif_fix<W: Write>(emitter: &mut JSONEmitter<W>, sm: &SourceManager, fix: &Fix) {
+    let table = sm.line_table(fix.file);
+    emitter.open_dict();
+    emitter.emit_key("description");
+    emit_sarif_message(emitter, &fix.message);
+    emitter.emit_key("artifactChanges");
+    emitter.open_array();
+    emitter.open_dict();
+    emit_sarif_artifact(emitter, sm, fix.file);
+    emitter.emit_key("replacements");
+    emitter.open_array();
+    for edit in &fix.edits {
+        emitter.open_dict();
+        emitter.emit_key("deletedRegion");
+        emit_sarif_region(emitter, table.loc(edit.start), table.loc(edit.end));
+        emitter.emit_key("insertedContent");
+        emit_sarif_message(emitter, &edit.text);
+        emitter.close_dict();
+    }
+    emitter.close_array();
+    emitter.close_dict();
+    emitter.close_array();
+    emitter.close_dict();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NullTerminatedBuf;
+
+    fn new_sm(handler: Box<dyn DiagnosticHandler>) -> SourceManager {
+        let mut sm = SourceManager::new();
+        sm.add_source("a.js", NullTerminatedBuf::from_str_copy("let x = \"y\";"));
+        sm.buffer_diagnostics();
+        sm.set_handler(handler);
+        sm
+    }
+
+    fn range(start: u32, end: u32) -> SourceRange {
+        SourceRange {
+            file: crate::source_manager::SourceId(0),
+            start: SourceLoc {
+                line: 1,
+                col: start,
+            },
+            end: SourceLoc { line: 1, col: end },
+        }
+    }
+
+    #[test]
+    fn collecting() {
+        let handler = CollectingHandler::new();
+        let sm = new_sm(Box::new(handler.clone()));
+        sm.error(range(5, 5), "bad");
+        sm.note(range(9, 11), "here");
+        assert_eq!(
+            vec![
+                Diagnostic::new(DiagKind::Error, range(5, 5), "bad", vec![]),
+                Diagnostic::new(DiagKind::Note, range(9, 11), "here", vec![]),
+            ],
+            handler.take()
+        );
+        assert!(sm.take_buffered().is_empty());
+        assert_eq!(1, sm.num_errors());
+    }
+
+    #[test]
+    fn json_lines() {
+        let sm = new_sm(Box::new(JsonLinesHandler::new()));
+        sm.warning(range(9, 11), "odd \"y\"");
+        assert_eq!(
+            vec![
+                r#"{"kind":"warning","file":"a.js","range":{"start":{"line":1,"col":9},"end":{"line":1,"col":11}},"message":"odd \"y\""}"#
+            ],
+            sm.take_buffered()
+        );
+    }
+
+    #[test]
+    fn fixes() {
+        let sm = new_sm(Box::new(JsonLinesHandler::new()));
+        let edit = TextEdit::replace(&sm, range(5, 5), "y").unwrap();
+        assert_eq!((4, 5), (edit.start, edit.end));
+        let fix = Fix::new("rename", SourceId(0), vec![edit]);
+        sm.error_with_fixes(range(5, 5), "bad", vec![fix]);
+        assert_eq!(
+            vec![concat!(
+                r#"{"kind":"error","file":"a.js","range":{"start":{"line":1,"col":5},"#,
+                r#""end":{"line":1,"col":5}},"message":"bad","fixes":[{"message":"rename","#,
+                r#""edits":[{"range":{"start":{"line":1,"col":5},"end":{"line":1,"col":6}},"#,
+                r#""text":"y"}]}]}"#
+            )],
+            sm.take_buffered()
+        );
+    }
+
+    #[test]
+    fn apply() {
+        let edit = |start, end, text: &str| TextEdit {
+            start,
+            end,
+            text: text.to_string(),
+        };
+        let rename = [edit(4, 5, "y")];
+        let conflicting = [edit(4, 5, "z")];
+        let quote = [edit(8, 9, "'"), edit(10, 11, "'")];
+        let insert = [edit(0, 0, "export ")];
+        let out_of_bounds = [edit(20, 21, "")];
+        let (result, applied) = apply_fixes(
+            "let x = \"y\";",
+            [&rename[..], &conflicting, &quote, &insert, &out_of_bounds],
+        );
+        assert_eq!("export let y = 'y';", result);
+        assert_eq!(3, applied);
+    }
+
+    #[test]
+    fn sarif() {
+        let sm = new_sm(Box::new(SarifHandler::new(Pretty::No)));
+        sm.error(range(5, 5), "bad");
+        sm.note(range(9, 11), "here");
+        assert!(sm.take_buffered().is_empty());
+        sm.finish_diagnostics();
+        assert_eq!(
+            vec![concat!(
+                r#"{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0","#,
+                r#""runs":[{"tool":{"driver":{"name":"juno"}},"results":[{"level":"error","#,
+                r#""message":{"text":"bad"},"locations":[{"physicalLocation":{"#,
+                r#""artifactLocation":{"uri":"a.js"},"region":{"startLine":1,"startColumn":5,"#,
+                r#""endLine":1,"endColumn":6}}}],"relatedLocations":[{"id":0,"#,
+                r#""message":{"text":"here"},"physicalLocation":{"artifactLocation":{"uri":"a.js"},"#,
+                r#""region":{"startLine":1,"startColumn":9,"endLine":1,"endColumn":12}}}]}]}]}"#
+            )],
+            sm.take_buffered()
+        );
+    }
+}
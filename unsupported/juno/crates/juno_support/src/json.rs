@@ -10,6 +10,8 @@ use std::io;
 use std::io::BufWriter;
 use std::io::Write;
 
+use crate::source_manager::SourceLoc;
+
 /// Whether to pretty-print the JSON.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Pretty {
@@ -310,6 +312,33 @@ impl<W: Write> JSONEmitter<W> {
     }
 }
 
+/// Emit `s` as a JSON string, escaping it.
+pub fn emit_str<W: Write>(emitter: &mut JSONEmitter<W>, s: &str) {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c < ' ' => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    emitter.emit_string(&escaped);
+}
+
+/// Emit `loc` as a `{"line", "col"}` dict.
+pub fn emit_loc<W: Write>(emitter: &mut JSONEmitter<W>, loc: SourceLoc) {
+    emitter.open_dict();
+    emitter.emit_key("line");
+    emitter.emit_number(loc.line as f64);
+    emitter.emit_key("col");
+    emitter.emit_number(loc.col as f64);
+    emitter.close_dict();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -332,6 +361,18 @@ mod tests {
         assert_eq!(String::from_utf8(out).unwrap(), "\"\\ud00a\\n\"\n");
     }
 
+    #[test]
+    fn test_str() {
+        let mut out = vec![];
+        let mut emitter = JSONEmitter::new(&mut out, Pretty::No);
+        emit_str(&mut emitter, "C:\\a \"b\"\n");
+        emitter.end().unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\"C:\\\\a \\\"b\\\"\\n\"\n"
+        );
+    }
+
     #[test]
     fn test_dict() {
         let mut out = vec![];
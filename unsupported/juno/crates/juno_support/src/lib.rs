@@ -29,6 +29,7 @@ pub mod opaque_id;
 pub mod atom_table;
 pub mod case;
 pub mod convert;
+pub mod diagnostics;
 pub mod fetchurl;
 pub mod json;
 pub mod source_manager;
@@ -11,6 +11,11 @@ use std::rc::Rc;
 
 use crate::HeapSize;
 use crate::NullTerminatedBuf;
+use crate::diagnostics::DiagKind;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::DiagnosticHandler;
+use crate::diagnostics::Fix;
+use crate::diagnostics::TerminalHandler;
 
 /// A source range within a single JS file.
 ///
@@ -76,6 +81,16 @@ struct Inner {
     num_errors: usize,
     num_warnings: usize,
     num_notes: usize,
+    /// Diagnostics which haven't been printed, if they are buffered.
+    buffered: Option<Vec<String>>,
+    /// Whether the last error or warning wasn't reported, so its notes aren't either.
+    suppressed: bool,
+    /// Line tables of the sources which diagnostics were reported in.
+    line_tables: HashMap<SourceId, Rc<LineTable>>,
+    /// Consumer of the diagnostics, a [`TerminalHandler`] without colors by default.
+    handler: Option<Box<dyn DiagnosticHandler>>,
+    /// Fixes of the reported diagnostics, if they are collected.
+    fixes: Option<Vec<Fix>>,
 }
 
 /// SourceManager owns a collection of source buffers and their names and handles
@@ -84,6 +99,8 @@ struct Inner {
 pub struct SourceManager {
     sources: Vec<(String, Rc<NullTerminatedBuf>)>,
     filenames: HashMap<String, SourceId>,
+    /// Number of errors after which no more diagnostics are reported, 0 for no limit.
+    error_limit: usize,
     inner: UnsafeCell<Inner>,
 }
 
@@ -105,6 +122,13 @@ impl SourceManager {
         id
     }
 
+    /// Replace the buffer of a registered source, e.g. after its file changed.
+    /// Anything derived from the previous buffer is out of date.
+    pub fn replace_source(&mut self, source_id: SourceId, buf: NullTerminatedBuf) {
+        self.sources[source_id.as_usize()].1 = Rc::new(buf);
+        self.inner.get_mut().line_tables.remove(&source_id);
+    }
+
     /// Obtain the number of registered sources.
     pub fn num_sources(&self) -> usize {
         self.sources.len()
@@ -156,43 +180,227 @@ impl SourceManager {
         unsafe { self.inner() }.num_warnings
     }
 
+    /// Reset the number of reported diagnostics, e.g. before analyzing changed sources.
+    pub fn reset_counts(&mut self) {
+        let inner = self.inner.get_mut();
+        inner.num_errors = 0;
+        inner.num_warnings = 0;
+        inner.num_notes = 0;
+    }
+
+    /// Send the diagnostics reported from now on to `handler`.
+    pub fn set_handler(&mut self, handler: Box<dyn DiagnosticHandler>) {
+        self.inner.get_mut().handler = Some(handler);
+    }
+
+    /// Notify the handler that no more diagnostics will be reported.
+    pub fn finish_diagnostics(&self) {
+        self.with_handler(|handler| handler.finish(self));
+    }
+
+    /// Stop reporting diagnostics after `limit` errors, or never if `limit` is 0.
+    /// Errors are still counted after the limit.
+    pub fn set_error_limit(&mut self, limit: usize) {
+        self.error_limit = limit;
+    }
+
+    /// Buffer the output of the diagnostics instead of printing it, until it is taken
+    /// with [`Self::take_buffered`].
+    pub fn buffer_diagnostics(&mut self) {
+        self.inner.get_mut().buffered = Some(Vec::new());
+    }
+
+    /// Take the output of the diagnostics buffered since the last call.
+    pub fn take_buffered(&self) -> Vec<String> {
+        let inner = unsafe { self.inner_mut() };
+        inner
+            .buffered
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Collect the fixes of the diagnostics reported from now on, until they are taken
+    /// with [`Self::take_fixes`]. Fixes are collected even after the error limit.
+    pub fn collect_fixes(&mut self) {
+        self.inner.get_mut().fixes = Some(Vec::new());
+    }
+
+    /// Take the fixes collected since the last call.
+    pub fn take_fixes(&self) -> Vec<Fix> {
+        let inner = unsafe { self.inner_mut() };
+        inner.fixes.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
     /// Report an error at the specified range in the specified source buffer.
     pub fn error<S: Into<String>>(&self, range: SourceRange, msg: S) {
-        let inner = unsafe { self.inner_mut() };
-        inner.num_errors += 1;
-
-        eprintln!(
-            "{}:{}:{}: error: {}",
-            self.source_name(range.file),
-            range.start.line,
-            range.start.col,
-            msg.into()
-        );
+        self.error_with_fixes(range, msg, Vec::new());
+    }
+    /// Report an error with fixes which correct it.
+    pub fn error_with_fixes<S: Into<String>>(&self, range: SourceRange, msg: S, fixes: Vec<Fix>) {
+        self.report(Diagnostic::new(DiagKind::Error, range, msg, fixes));
     }
+    /// Report a note about the last error or warning at the specified range.
     pub fn note<S: Into<String>>(&self, range: SourceRange, msg: S) {
-        let inner = unsafe { self.inner_mut() };
-        inner.num_notes += 1;
-
-        eprintln!(
-            "{}:{}:{}: note: {}",
-            self.source_name(range.file),
-            range.start.line,
-            range.start.col,
-            msg.into()
-        );
+        self.report(Diagnostic::new(DiagKind::Note, range, msg, Vec::new()));
     }
     /// Report a warning at the specified range in the specified source buffer.
     pub fn warning<S: Into<String>>(&self, range: SourceRange, msg: S) {
+        self.warning_with_fixes(range, msg, Vec::new());
+    }
+    /// Report a warning with fixes which correct it.
+    pub fn warning_with_fixes<S: Into<String>>(&self, range: SourceRange, msg: S, fixes: Vec<Fix>) {
+        self.report(Diagnostic::new(DiagKind::Warning, range, msg, fixes));
+    }
+
+    /// Report `diagnostic`, counted according to its kind, and send it to the handler
+    /// unless the error limit was reached.
+    pub fn report(&self, diagnostic: Diagnostic) {
         let inner = unsafe { self.inner_mut() };
-        inner.num_warnings += 1;
-
-        eprintln!(
-            "{}:{}:{}: warning: {}",
-            self.source_name(range.file),
-            range.start.line,
-            range.start.col,
-            msg.into()
+        match diagnostic.kind {
+            DiagKind::Error => inner.num_errors += 1,
+            DiagKind::Warning => inner.num_warnings += 1,
+            DiagKind::Note => inner.num_notes += 1,
+        }
+        if let Some(collected) = &mut inner.fixes {
+            collected.extend(diagnostic.fixes.iter().cloned());
+        }
+        let kind = diagnostic.kind;
+        if kind != DiagKind::Note {
+            inner.suppressed = self.error_limit != 0 && inner.num_errors > self.error_limit;
+            if inner.suppressed {
+                if kind == DiagKind::Error && inner.num_errors == self.error_limit + 1 {
+                    self.with_handler(|handler| handler.error_limit_reached(self));
+                }
+                return;
+            }
+        } else if inner.suppressed {
+            return;
+        }
+        self.with_handler(|handler| handler.handle(self, diagnostic));
+    }
+
+    /// Call `f` with the handler, which is taken out of the inner object meanwhile so
+    /// the handler can use the source manager.
+    fn with_handler(&self, f: impl FnOnce(&mut dyn DiagnosticHandler)) {
+        let mut handler = unsafe { self.inner_mut() }
+            .handler
+            .take()
+            .unwrap_or_else(|| Box::new(TerminalHandler::default()));
+        f(handler.as_mut());
+        unsafe { self.inner_mut() }.handler = Some(handler);
+    }
+
+    /// Print the output of a diagnostic handler to stderr, or buffer it if the
+    /// diagnostics are buffered.
+    pub fn output(&self, diagnostic: String) {
+        match &mut unsafe { self.inner_mut() }.buffered {
+            Some(buffered) => buffered.push(diagnostic),
+            None => eprintln!("{}", diagnostic),
+        }
+    }
+
+    /// The line table of a previously registered source buffer, which is computed once.
+    pub fn line_table(&self, source_id: SourceId) -> Rc<LineTable> {
+        Rc::clone(
+            unsafe { self.inner_mut() }
+                .line_tables
+                .entry(source_id)
+                .or_insert_with(|| {
+                    Rc::new(LineTable::new(self.source_buffer(source_id).as_bytes()))
+                }),
+        )
+    }
+
+    /// The line of the start of `range`, and a line with a caret under the start of
+    /// `range` and tildes under the rest of it on that line.
+    /// Return `None` if `range` isn't in its source.
+    pub fn snippet(&self, range: SourceRange) -> Option<(String, String)> {
+        let buf = self.source_buffer(range.file).as_bytes();
+        let table = self.line_table(range.file);
+        let line_start = table.line_start(range.start.line)?;
+        let line_end = table
+            .line_start(range.start.line + 1)
+            .map_or(table.len, |next| next - 1);
+        let start = table.offset(range.start)?.min(line_end);
+        let end = if range.end.line == range.start.line {
+            table.offset(range.end).unwrap_or(line_end)
+        } else {
+            line_end
+        }
+        .clamp(start, line_end.max(start + 1) - 1);
+
+        // Tabs are kept so the underline is aligned however they are displayed.
+        let is_char_start = |c: &&u8| (**c & 0xC0) != 0x80;
+        let mut underline: String = buf[line_start..start]
+            .iter()
+            .filter(is_char_start)
+            .map(|&c| if c == b'\t' { '\t' } else { ' ' })
+            .collect();
+        underline.push('^');
+        let len = buf[start..=end].iter().filter(is_char_start).count();
+        underline.push_str(&"~".repeat(len.saturating_sub(1)));
+
+        let line = String::from_utf8_lossy(&buf[line_start..line_end]);
+        Some((line.trim_end_matches('\r').to_string(), underline))
+    }
+}
+
+/// Start offsets of the lines in a source buffer, used to convert between [`SourceLoc`]
+/// and byte offsets.
+#[derive(Debug)]
+pub struct LineTable {
+    /// Byte offset of the first character of each line.
+    starts: Vec<usize>,
+    /// Length of the buffer, excluding the null terminator.
+    len: usize,
+}
+
+impl LineTable {
+    pub fn new(buf: &[u8]) -> LineTable {
+        let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+        let mut starts = vec![0];
+        starts.extend(
+            buf[..len]
+                .iter()
+                .enumerate()
+                .filter(|(_, &c)| c == b'\n')
+                .map(|(i, _)| i + 1),
         );
+        LineTable { starts, len }
+    }
+
+    /// Number of lines in the buffer.
+    pub fn num_lines(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Byte offset of the start of the 1-based `line`, or `None` if there is no such line.
+    pub fn line_start(&self, line: u32) -> Option<usize> {
+        self.starts.get((line as usize).checked_sub(1)?).copied()
+    }
+
+    /// Byte offset of `loc`, or `None` if it is outside the buffer.
+    /// The offset of the null terminator is considered to be inside the buffer.
+    pub fn offset(&self, loc: SourceLoc) -> Option<usize> {
+        let offset = self.line_start(loc.line)? + (loc.col as usize).checked_sub(1)?;
+        if offset <= self.len {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+
+    /// Location of the byte at `offset`.
+    pub fn loc(&self, offset: usize) -> SourceLoc {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        SourceLoc {
+            line: line as u32 + 1,
+            col: (offset - self.starts[line]) as u32 + 1,
+        }
     }
 }
 
@@ -223,4 +431,129 @@ mod tests {
         assert_eq!(2, buf1.len());
         assert_eq!(b"a\0", buf1.as_bytes());
     }
+
+    #[test]
+    fn buffered_diagnostics() {
+        let mut sm = SourceManager::new();
+        let id = sm.add_source("buf", NullTerminatedBuf::from_str_copy("a"));
+        sm.buffer_diagnostics();
+
+        let range = SourceRange::from_loc(id, SourceLoc { line: 1, col: 1 });
+        sm.error(range, "bad");
+        sm.warning(range, "odd");
+        assert_eq!(
+            vec!["buf:1:1: error: bad\na\n^", "buf:1:1: warning: odd\na\n^"],
+            sm.take_buffered()
+        );
+        assert!(sm.take_buffered().is_empty());
+        assert_eq!(1, sm.num_errors());
+        assert_eq!(1, sm.num_warnings());
+    }
+
+    #[test]
+    fn snippets() {
+        let mut sm = SourceManager::new();
+        let id = sm.add_source(
+            "a.js",
+            NullTerminatedBuf::from_str_copy("x;\n\tfoo(bar);\n"),
+        );
+        sm.buffer_diagnostics();
+
+        let loc = |line, col| SourceLoc { line, col };
+        sm.error(
+            SourceRange {
+                file: id,
+                start: loc(2, 6),
+                end: loc(2, 8),
+            },
+            "bad",
+        );
+        sm.note(
+            SourceRange {
+                file: id,
+                start: loc(1, 1),
+                end: loc(2, 2),
+            },
+            "here",
+        );
+        sm.warning(SourceRange::from_loc(id, loc(3, 1)), "at the end");
+        assert_eq!(
+            vec![
+                "a.js:2:6: error: bad\n\tfoo(bar);\n\t    ^~~",
+                "a.js:1:1: note: here\nx;\n^~",
+                "a.js:3:1: warning: at the end\n\n^",
+            ],
+            sm.take_buffered()
+        );
+
+        sm.set_handler(Box::new(TerminalHandler::new(true)));
+        sm.warning(SourceRange::from_loc(id, loc(1, 1)), "odd");
+        assert_eq!(
+            vec![
+                "\x1b[1ma.js:1:1: \x1b[1;35mwarning: \x1b[0m\x1b[1modd\x1b[0m\nx;\n\x1b[1;32m^\x1b[0m"
+            ],
+            sm.take_buffered()
+        );
+    }
+
+    #[test]
+    fn error_limit() {
+        let mut sm = SourceManager::new();
+        let id = sm.add_source("a.js", NullTerminatedBuf::from_str_copy("x"));
+        sm.buffer_diagnostics();
+        sm.set_error_limit(2);
+
+        let range = SourceRange::from_loc(id, SourceLoc { line: 1, col: 1 });
+        for _ in 0..3 {
+            sm.error(range, "bad");
+            sm.note(range, "why");
+        }
+        sm.warning(range, "odd");
+        assert_eq!(
+            vec![
+                "a.js:1:1: error: bad\nx\n^",
+                "a.js:1:1: note: why\nx\n^",
+                "a.js:1:1: error: bad\nx\n^",
+                "a.js:1:1: note: why\nx\n^",
+                "fatal error: too many errors emitted, stopping now",
+            ],
+            sm.take_buffered()
+        );
+        assert_eq!(3, sm.num_errors());
+        assert_eq!(1, sm.num_warnings());
+    }
+
+    #[test]
+    fn replace_source() {
+        let mut sm = SourceManager::new();
+        let id = sm.add_source("a.js", NullTerminatedBuf::from_str_copy("old"));
+        let range = SourceRange::from_loc(id, SourceLoc { line: 1, col: 1 });
+        sm.error(range, "bad");
+
+        sm.replace_source(id, NullTerminatedBuf::from_str_copy("new"));
+        sm.reset_counts();
+        assert_eq!(b"new\0", sm.source_buffer(id).as_bytes());
+        assert_eq!("a.js", sm.source_name(id));
+        assert_eq!(1, sm.num_sources());
+        assert_eq!(0, sm.num_errors());
+    }
+
+    #[test]
+    fn line_table() {
+        let buf = NullTerminatedBuf::from_str_copy("ab\nc\n\nd");
+        let table = LineTable::new(buf.as_bytes());
+
+        assert_eq!(4, table.num_lines());
+        assert_eq!(Some(3), table.line_start(2));
+        assert_eq!(None, table.line_start(5));
+
+        assert_eq!(Some(1), table.offset(SourceLoc { line: 1, col: 2 }));
+        assert_eq!(Some(5), table.offset(SourceLoc { line: 3, col: 1 }));
+        assert_eq!(Some(7), table.offset(SourceLoc { line: 4, col: 2 }));
+        assert_eq!(None, table.offset(SourceLoc { line: 4, col: 3 }));
+
+        assert_eq!(SourceLoc { line: 1, col: 3 }, table.loc(2));
+        assert_eq!(SourceLoc { line: 2, col: 1 }, table.loc(3));
+        assert_eq!(SourceLoc { line: 4, col: 1 }, table.loc(6));
+    }
 }
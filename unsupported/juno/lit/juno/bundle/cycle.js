@@ -0,0 +1,18 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --gen-deps --check-cycles %s %S/cycle-dep.js 2>&1 >/dev/null | %FileCheck %s
+
+import './cycle-dep';
+
+// CHECK: {{.*}}cycle.js:10:1: warning: Import cycle: {{.*}}cycle.js -> {{.*}}cycle-dep.js -> {{.*}}cycle.js
+// CHECK-NEXT: import './cycle-dep';
+// CHECK-NEXT: ^{{~*}}
+// CHECK-NEXT: {{.*}}cycle-dep.js:10:1: note: {{.*}}cycle.js is required from here
+// CHECK-NEXT: import './cycle';
+// CHECK-NEXT: ^{{~*}}
+// CHECK-NEXT: 0 error(s), 1 warning(s)
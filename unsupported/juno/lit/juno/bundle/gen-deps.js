@@ -0,0 +1,19 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --gen-deps --deps-format=dot %s %S/dep.js | %FileCheck %s --match-full-lines
+
+import {greet} from './dep';
+const fs = require('fs');
+
+// CHECK:      digraph dependencies {
+// CHECK-NEXT:   m0 [label="{{.*}}gen-deps.js"];
+// CHECK-NEXT:   m1 [label="{{.*}}dep.js"];
+// CHECK-NEXT:   m0 -> m1 [label="import 10:1"];
+// CHECK-NEXT:   u0 [label="fs", shape=box, style=dashed];
+// CHECK-NEXT:   m0 -> u0 [label="require 11:12", style=dashed];
+// CHECK-NEXT: }
@@ -0,0 +1,16 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --bundle --scope-hoist --resolver=haste --platform=ios %s %S/haste/Greeting.js %S/haste/Greeting.ios.js | %FileCheck %s --match-full-lines
+
+import {greeting} from 'Greeting';
+
+print(greeting);
+
+// CHECK:      const greeting = 'hello ios';
+// CHECK-NEXT: print(greeting);
+// CHECK-NOT: {{.}}
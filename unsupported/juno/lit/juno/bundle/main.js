@@ -0,0 +1,28 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --bundle %s %S/dep.js | %FileCheck %s --match-full-lines
+
+import {greet} from './dep';
+
+greet('world');
+
+// CHECK-LABEL: var __d, __r;
+// CHECK:      __d(function (global, require, module, exports) {
+// CHECK-NEXT: 'use strict';
+// CHECK-NEXT: require(1);
+// CHECK-NEXT: (0, require(1).greet)('world');
+// CHECK-NEXT: }, 0, [1], "{{.*}}main.js");
+// CHECK-NEXT: __d(function (global, require, module, exports) {
+// CHECK-NEXT: 'use strict';
+// CHECK-NEXT: require.defineExports(exports, {'greet': function () {
+// CHECK:        return greet;
+// CHECK:      function greet(name) {
+// CHECK-NEXT:   print('hello ' + name);
+// CHECK-NEXT: }
+// CHECK-NEXT: }, 1, [], "{{.*}}dep.js");
+// CHECK-NEXT: __r(0);
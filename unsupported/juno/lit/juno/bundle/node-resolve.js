@@ -0,0 +1,18 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --bundle --scope-hoist --resolver=node %s | %FileCheck %s --match-full-lines
+
+import {greet} from 'greeter';
+
+greet('world');
+
+// CHECK:      function greet(name) {
+// CHECK-NEXT:   print('hello ' + name);
+// CHECK-NEXT: }
+// CHECK-NEXT: greet('world');
+// CHECK-NOT: {{.}}
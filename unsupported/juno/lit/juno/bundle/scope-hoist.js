@@ -0,0 +1,21 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --bundle --scope-hoist %s %S/dep.js | %FileCheck %s --match-full-lines
+
+import {greet as hello} from './dep';
+
+// Conflicts with the parameter of greet.
+const name = 'world';
+hello(name);
+
+// CHECK:      function greet(name) {
+// CHECK-NEXT:   print('hello ' + name);
+// CHECK-NEXT: }
+// CHECK-NEXT: const name$1 = 'world';
+// CHECK-NEXT: greet(name$1);
+// CHECK-NOT: {{.}}
@@ -0,0 +1,18 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --bundle --scope-hoist --tree-shake %s %S/utils.js | %FileCheck %s --match-full-lines
+
+import {square} from './utils';
+
+print(square(3));
+
+// CHECK:      function square(x) {
+// CHECK-NEXT:   return x * x;
+// CHECK-NEXT: }
+// CHECK-NEXT: print(square(3));
+// CHECK-NOT: {{.}}
@@ -0,0 +1,21 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: true
+
+const cache = /*#__PURE__*/ new Map();
+
+export function square(x) {
+  return x * x;
+}
+
+export function memoized(x) {
+  if (!cache.has(x)) {
+    cache.set(x, square(x));
+  }
+  return cache.get(x);
+}
@@ -0,0 +1,47 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --codemod=diff -O %s | %FileCheck %s --match-full-lines
+
+// Only the code changed by the passes is regenerated,
+// everything else is kept as written.
+function foo(x, y) {
+  // Comments are preserved.
+  return true ? x   +   1 : y + 1; // Trailing.
+}
+// Unchanged code.
+function unchanged( a ) { return a ; }
+function bar(x) {
+  if (false) {
+    return x;
+  }
+  return   2;   /* Formatting is preserved. */
+}
+// End of the code.
+
+
+
+
+// CHECK: --- {{.*}}codemod.js
+// CHECK-NEXT: +++ {{.*}}codemod.js
+// CHECK-NEXT: @@ -11,14 +11,11 @@
+// CHECK-NEXT:  // everything else is kept as written.
+// CHECK-NEXT:  function foo(x, y) {
+// CHECK-NEXT:    // Comments are preserved.
+// CHECK-NEXT: -  return true ? x   +   1 : y + 1; // Trailing.
+// CHECK-NEXT: +  return x   +   1; // Trailing.
+// CHECK-NEXT:  }
+// CHECK-NEXT:  // Unchanged code.
+// CHECK-NEXT:  function unchanged( a ) { return a ; }
+// CHECK-NEXT:  function bar(x) {
+// CHECK-NEXT: -  if (false) {
+// CHECK-NEXT: -    return x;
+// CHECK-NEXT: -  }
+// CHECK-NEXT:    return   2;   /* Formatting is preserved. */
+// CHECK-NEXT:  }
+// CHECK-NEXT:  // End of the code.
+// CHECK-NOT: {{.}}
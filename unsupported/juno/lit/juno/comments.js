@@ -0,0 +1,26 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --gen-js --comments=legal %s | %FileCheck %s --match-full-lines
+
+/*! Keep this. */
+/**
+ * @license MIT
+ */
+// Drop this.
+function foo(x) {
+  /* Drop this too. */
+  return x; // And this.
+}
+
+// CHECK-LABEL: /*! Keep this. */
+// CHECK-NEXT: /**
+// CHECK-NEXT:  * @license MIT
+// CHECK-NEXT:  */
+// CHECK-NEXT: function foo(x) {
+// CHECK-NEXT:   return x;
+// CHECK-NEXT: }
@@ -0,0 +1,21 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --gen-js %s | %FileCheck %s
+// RUN: %juno --gen-js --strip-flow=false %s | %FileCheck %s --check-prefix=FLOW
+// RUN: (%juno --gen-js --config=%S/bad.json %s 2>&1 || true) | %FileCheck %s --check-prefix=BAD
+
+function f(x: number): number {
+  return <div>{x}</div>;
+}
+
+// CHECK: function f(x) {
+// CHECK-NEXT: return <div>{x}</div>;
+
+// FLOW: function f(x: number): number {
+
+// BAD: Invalid configuration file {{.*}}bad.json: unknown key 'pretty'
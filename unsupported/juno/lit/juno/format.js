@@ -0,0 +1,25 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno --gen-js --format --print-width=40 --semicolons=0 %s | %FileCheck %s --match-full-lines
+
+function f(aaaaaaaaaaaa, bbbbbbbbbbbb, cccccccccccc) { return aaaaaaaaaaaa; }
+
+
+let x = {a: 1};
+[x].forEach(print);
+
+// CHECK-LABEL: function f(
+// CHECK-NEXT:   aaaaaaaaaaaa,
+// CHECK-NEXT:   bbbbbbbbbbbb,
+// CHECK-NEXT:   cccccccccccc,
+// CHECK-NEXT: ) {
+// CHECK-NEXT:   return aaaaaaaaaaaa
+// CHECK-NEXT: }
+// CHECK-EMPTY:
+// CHECK-NEXT: let x = { a: 1 }
+// CHECK-NEXT: ;[x].forEach(print)
@@ -0,0 +1,26 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: rm -rf %t
+// RUN: (%juno --out-dir %t -j 2 %s %S/out-dir/bad.txt %S/bundle/dep.js 2>&1 || true) | %FileCheck %s
+// RUN: cat %t%s | %FileCheck %s --check-prefix=OUT --match-full-lines
+// RUN: cat %t%S/bundle/dep.js | %FileCheck %s --check-prefix=DEP
+// RUN: (%juno --out-dir %t %S/../juno/empty.js 2>&1 || true) | %FileCheck %s --check-prefix=UP
+// RUN: (%juno --out-dir %t --diagnostics-format=sarif %S/out-dir/bad.txt 2>&1 || true) | %FileCheck %s --check-prefix=SARIF
+
+print('compiled');
+
+// CHECK: {{.*}}bad.txt:1:9: error: {{.*}}
+// CHECK: 1 of 3 file(s) failed
+
+// OUT: print('compiled');
+
+// DEP: function greet(name) {
+
+// UP: --out-dir can't compile {{.*}}empty.js, which goes up with '..'
+
+// SARIF: {"$schema":"https://json.schemastore.org/sarif-2.1.0.json",{{.*}}"results":[{"level":"error",{{.*}}bad.txt{{.*}}
@@ -0,0 +1,15 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: (%juno %s 2>&1 || true) | %FileCheck %s
+
+'use strict';
+var a = 010;
+var b = 011;
+
+// CHECK: {{.*}}parse-errors.js:11:9: error: {{.*}}
+// CHECK: {{.*}}parse-errors.js:12:9: error: {{.*}}
@@ -0,0 +1,20 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: cp %s %t.js && (%juno %t.js --apply-fixes 2>&1 || true) | %FileCheck %s --match-full-lines --check-prefix=DIAG
+// RUN: %FileCheck %s --input-file=%t.js --match-full-lines --check-prefix=FIXED
+
+for (let x = 0 of a) {}
+
+// DIAG: {{.*}}:11:14: error: for-in/for-of variable declaration may not be initialized
+// DIAG-NEXT: for (let x = 0 of a) {}
+// DIAG-NEXT:              ^
+// DIAG-NEXT: fix-it: remove the initializer
+// DIAG-NEXT: 1 error(s), 0 warning(s)
+// DIAG-NEXT: {{.*}}: applied 1 of 1 fix(es)
+
+// FIXED: for (let x of a) {}
@@ -12,20 +12,28 @@ switch (1) {
   continue;
 }
 // CHECK: {{.*}}:12:3: error: 'continue' not within a loop or switch
+// CHECK-NEXT:   continue;
+// CHECK-NEXT:   ^{{~*}}
 
 x: {
   continue;
 }
-// CHECK-NEXT: {{.*}}:17:3: error: 'continue' not within a loop or switch
+// CHECK-NEXT: {{.*}}:19:3: error: 'continue' not within a loop or switch
+// CHECK-NEXT:   continue;
+// CHECK-NEXT:   ^{{~*}}
 
 a: {
   while(1) {
     continue a;
   }
 }
-// CHECK-NEXT: {{.*}}:23:14: error: 'continue' label 'a' is not a loop label
+// CHECK-NEXT: {{.*}}:27:14: error: 'continue' label 'a' is not a loop label
+// CHECK-NEXT:     continue a;
+// CHECK-NEXT:              ^
 
 while(1) {
   continue b;
 }
-// CHECK-NEXT: {{.*}}:29:12: error: label 'b' is not defined
+// CHECK-NEXT: {{.*}}:35:12: error: label 'b' is not defined
+// CHECK-NEXT:   continue b;
+// CHECK-NEXT:            ^
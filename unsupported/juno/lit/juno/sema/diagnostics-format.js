@@ -0,0 +1,16 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: (%juno %s --diagnostics-format=json 2>&1 || true) | %FileCheck %s --match-full-lines
+// RUN: (%juno %s --diagnostics-format=sarif 2>&1 || true) | %FileCheck %s --check-prefix=SARIF
+
+new.target
+
+// CHECK: {"kind":"error","file":"{{.*}}diagnostics-format.js","range":{"start":{"line":11,"col":1},"end":{"line":11,"col":{{[0-9]+}}}},"message":"'new.target' outside of a function"}
+// CHECK-NOT: error(s)
+
+// SARIF: {"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0",{{.*}}"results":[{"level":"error","message":{"text":"'new.target' outside of a function"},{{.*}}"region":{"startLine":11,"startColumn":1,"endLine":11,"endColumn":{{[0-9]+}}}}}]}]}]}
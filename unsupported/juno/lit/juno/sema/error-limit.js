@@ -0,0 +1,21 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: (%juno %s --gen-sema --error-limit=2 2>&1 || true) | %FileCheck %s --match-full-lines
+
+while (1) { continue a; }
+while (1) { continue b; }
+while (1) { continue c; }
+
+// CHECK: {{.*}}:10:22: error: label 'a' is not defined
+// CHECK-NEXT: while (1) { continue a; }
+// CHECK-NEXT:                      ^{{~*}}
+// CHECK-NEXT: {{.*}}:11:22: error: label 'b' is not defined
+// CHECK-NEXT: while (1) { continue b; }
+// CHECK-NEXT:                      ^{{~*}}
+// CHECK-NEXT: fatal error: too many errors emitted, stopping now
+// CHECK-NEXT: 3 error(s), 0 warning(s)
@@ -11,4 +11,6 @@
 arguments = 0;
 
 // CHECK-LABEL: {{.*}}:11:1: error: invalid assignment left-hand side
+// CHECK-NEXT: arguments = 0;
+// CHECK-NEXT: ^{{~*}}
 // CHECK-NEXT: 1 error(s), 0 warning(s)
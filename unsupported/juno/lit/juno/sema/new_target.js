@@ -11,4 +11,6 @@ function foo() { new.target }
 new.target
 
 //CHECK: {{.*}}new_target.js:11:1: error: 'new.target' outside of a function
+//CHECK-NEXT: new.target
+//CHECK-NEXT: ^{{~*}}
 //CHECK-NEXT: 1 error(s), 0 warning(s)
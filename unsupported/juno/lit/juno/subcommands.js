@@ -0,0 +1,28 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under the MIT license found in the
+ * LICENSE file in the root directory of this source tree.
+ */
+
+// RUN: %juno lint %s 2>&1 | %FileCheck %s --check-prefix=LINT
+// RUN: echo "lint %s" > %t.rsp && %juno @%t.rsp 2>&1 | %FileCheck %s --check-prefix=LINT
+// RUN: %juno deps --deps-format=dot %s | %FileCheck %s --check-prefix=DEPS
+// RUN: (%juno deps --gen-js %s 2>&1 || true) | %FileCheck %s --check-prefix=CONFLICT
+// RUN: JUNO_ERROR_LIMIT=many %juno %s 2>&1 | %FileCheck %s --check-prefix=ENV
+// RUN: %juno --completion=bash | %FileCheck %s --check-prefix=BASH
+
+export function f() {
+  return missing;
+}
+
+// LINT: {{.*}}subcommands.js:16:10: warning: identifier 'missing' was not declared in function 'f'
+// LINT: 0 error(s), 1 warning(s)
+
+// DEPS: digraph dependencies {
+
+// CONFLICT: The deps subcommand generates the dependency graph
+
+// ENV: {{.*}}: for the --error-limit option: in environment variable JUNO_ERROR_LIMIT: invalid digit found in string
+
+// BASH: complete -o filenames -F _juno juno